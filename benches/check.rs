@@ -0,0 +1,47 @@
+//! Predicate parsing and evaluation, as run by `envsense check` in prompts
+//! and scripts.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use envsense::check;
+use envsense::engine::DetectionEngine;
+use std::hint::black_box;
+
+mod common;
+
+const PREDICATES: &[&str] = &[
+    "agent",
+    "!ci",
+    "agent.id",
+    "agent.id=cursor",
+    "ci.is_pr",
+    "terminal.color_level=truecolor",
+];
+
+fn parse(c: &mut Criterion) {
+    c.bench_function("check/parse_predicate", |b| {
+        b.iter(|| {
+            for predicate in PREDICATES {
+                black_box(check::parse_predicate(black_box(predicate)).unwrap());
+            }
+        })
+    });
+}
+
+fn evaluate(c: &mut Criterion) {
+    let env = DetectionEngine::with_default_detectors().detect_from_snapshot(&common::snapshot());
+    let registry = check::registry();
+    c.bench_function("check/evaluate", |b| {
+        b.iter(|| {
+            for predicate in PREDICATES {
+                let parsed = check::parse_predicate(predicate).unwrap();
+                black_box(check::evaluate(black_box(&env), parsed, registry));
+            }
+        })
+    });
+    c.bench_function("check/evaluate_predicate", |b| {
+        b.iter(|| check::evaluate_predicate(black_box(&env), "agent.id=cursor").unwrap())
+    });
+}
+
+criterion_group!(benches, parse, evaluate);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+use envsense::detectors::EnvSnapshot;
+use std::collections::HashMap;
+
+/// A typical agent session in CI: Cursor in GitHub Actions on a color
+/// terminal, with the usual unrelated variables around it
+pub fn snapshot() -> EnvSnapshot {
+    let mut vars: HashMap<String, String> = [
+        ("CURSOR_AGENT", "1"),
+        ("TERM_PROGRAM", "vscode"),
+        ("TERM", "xterm-256color"),
+        ("COLORTERM", "truecolor"),
+        ("GITHUB_ACTIONS", "true"),
+        ("GITHUB_EVENT_NAME", "pull_request"),
+        ("GITHUB_REF_NAME", "feature/bench"),
+        ("SHELL", "/bin/zsh"),
+        ("HOME", "/home/runner"),
+        ("LANG", "en_US.UTF-8"),
+        ("ENVSENSE_CONTAINER", "none"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect();
+    // A real environment has dozens of variables no detector looks at
+    for i in 0..60 {
+        vars.insert(format!("UNRELATED_{i}"), "x".repeat(40));
+    }
+    EnvSnapshot::with_mock_tty(vars, true, true, true)
+}
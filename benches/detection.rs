@@ -0,0 +1,31 @@
+//! Full detection: reading the environment, building the engine and running
+//! every detector.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use envsense::detectors::EnvSnapshot;
+use envsense::engine::DetectionEngine;
+use std::hint::black_box;
+
+mod common;
+
+fn snapshot(c: &mut Criterion) {
+    c.bench_function("snapshot/current", |b| b.iter(EnvSnapshot::current));
+
+    let snap = common::snapshot();
+    c.bench_function("snapshot/clone", |b| b.iter(|| black_box(&snap).clone()));
+}
+
+fn detection(c: &mut Criterion) {
+    c.bench_function("engine/with_default_detectors", |b| {
+        b.iter(DetectionEngine::with_default_detectors)
+    });
+
+    let engine = DetectionEngine::with_default_detectors();
+    let snap = common::snapshot();
+    c.bench_function("engine/detect_from_snapshot", |b| {
+        b.iter(|| engine.detect_from_snapshot(black_box(&snap)))
+    });
+}
+
+criterion_group!(benches, snapshot, detection);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+//! Rendering a detection result, as `envsense info` does for prompts and
+//! status bars.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use envsense::engine::DetectionEngine;
+use envsense::human;
+use envsense::report;
+use envsense::theme::Theme;
+use std::hint::black_box;
+
+mod common;
+
+fn render(c: &mut Criterion) {
+    let env = DetectionEngine::with_default_detectors().detect_from_snapshot(&common::snapshot());
+    let theme = Theme::plain();
+    c.bench_function("info/human", |b| {
+        b.iter(|| human::render_human(black_box(&env), None, &theme, false, false).unwrap())
+    });
+    c.bench_function("info/raw", |b| {
+        b.iter(|| human::render_human(black_box(&env), None, &theme, true, false).unwrap())
+    });
+    c.bench_function("info/json", |b| {
+        b.iter(|| serde_json::to_string_pretty(black_box(&env)).unwrap())
+    });
+    c.bench_function("info/porcelain", |b| {
+        b.iter(|| report::render_porcelain(black_box(&env)))
+    });
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+/* Generated by cbindgen from envsense-ffi; do not edit by hand. */
+
+#ifndef ENVSENSE_H
+#define ENVSENSE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// `envsense_check` result: the predicate is false
+#define ENVSENSE_FALSE 0
+
+// `envsense_check` result: the predicate is true
+#define ENVSENSE_TRUE 1
+
+// The argument was NULL or not valid UTF-8
+#define ENVSENSE_ERR_INVALID_ARGUMENT -1
+
+// The predicate could not be parsed or names an unknown field
+#define ENVSENSE_ERR_PARSE -2
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Detect the current environment and return it as JSON
+//
+// The JSON has the `contexts`, `traits`, `evidence`, `confidence`,
+// `diagnostics` and `version` fields of `envsense info --json`. Returns NULL
+// if serialization fails. Free the result with `envsense_string_free`.
+char *envsense_detect_json(void);
+
+// Evaluate a predicate such as `agent.id=cursor` against the current
+// environment
+//
+// Returns `ENVSENSE_TRUE` or `ENVSENSE_FALSE`, or a negative
+// `ENVSENSE_ERR_*` code.
+//
+// # Safety
+//
+// `predicate` must be NULL or point to a NUL-terminated string.
+int envsense_check(const char *predicate);
+
+// Release a string returned by this library; NULL is ignored
+//
+// # Safety
+//
+// `s` must be NULL or a pointer returned by this library that has not been
+// freed yet.
+void envsense_string_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ENVSENSE_H */
@@ -0,0 +1,73 @@
+//! C ABI for envsense, so C, C++ and Go programs can link detection instead of
+//! running the `envsense` binary.
+//!
+//! The header `include/envsense.h` is generated from this file with cbindgen;
+//! `cargo test -p envsense-ffi` fails when it is out of date and
+//! `ENVSENSE_UPDATE_HEADER=1` rewrites it.
+//!
+//! Strings returned by this library are owned by the caller and must be
+//! released with [`envsense_string_free`].
+
+use envsense::prelude::*;
+use std::ffi::{CStr, CString, c_char, c_int};
+
+/// `envsense_check` result: the predicate is false
+pub const ENVSENSE_FALSE: c_int = 0;
+/// `envsense_check` result: the predicate is true
+pub const ENVSENSE_TRUE: c_int = 1;
+/// The argument was NULL or not valid UTF-8
+pub const ENVSENSE_ERR_INVALID_ARGUMENT: c_int = -1;
+/// The predicate could not be parsed or names an unknown field
+pub const ENVSENSE_ERR_PARSE: c_int = -2;
+
+/// Detect the current environment and return it as JSON
+///
+/// The JSON has the `contexts`, `traits`, `evidence`, `confidence`,
+/// `diagnostics` and `version` fields of `envsense info --json`. Returns NULL
+/// if serialization fails. Free the result with `envsense_string_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn envsense_detect_json() -> *mut c_char {
+    serde_json::to_string(&EnvSense::detect())
+        .ok()
+        .and_then(|json| CString::new(json).ok())
+        .map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Evaluate a predicate such as `agent.id=cursor` against the current
+/// environment
+///
+/// Returns `ENVSENSE_TRUE` or `ENVSENSE_FALSE`, or a negative
+/// `ENVSENSE_ERR_*` code.
+///
+/// # Safety
+///
+/// `predicate` must be NULL or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn envsense_check(predicate: *const c_char) -> c_int {
+    if predicate.is_null() {
+        return ENVSENSE_ERR_INVALID_ARGUMENT;
+    }
+    // SAFETY: non-null and NUL-terminated per the contract above
+    let Ok(predicate) = unsafe { CStr::from_ptr(predicate) }.to_str() else {
+        return ENVSENSE_ERR_INVALID_ARGUMENT;
+    };
+    match check::evaluate_predicate(&EnvSense::detect(), predicate) {
+        Ok(evaluation) if evaluation.result.as_bool() => ENVSENSE_TRUE,
+        Ok(_) => ENVSENSE_FALSE,
+        Err(_) => ENVSENSE_ERR_PARSE,
+    }
+}
+
+/// Release a string returned by this library; NULL is ignored
+///
+/// # Safety
+///
+/// `s` must be NULL or a pointer returned by this library that has not been
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn envsense_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: allocated by CString::into_raw in this library
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
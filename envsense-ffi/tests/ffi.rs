@@ -0,0 +1,57 @@
+use envsense_ffi::*;
+use serde_json::Value;
+use std::ffi::{CStr, CString};
+use std::path::Path;
+
+#[test]
+fn detect_json_returns_schema_document() {
+    let ptr = envsense_detect_json();
+    assert!(!ptr.is_null());
+    let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+    unsafe { envsense_string_free(ptr) };
+
+    let value: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["version"], envsense::schema::SCHEMA_VERSION);
+    assert!(value["traits"]["terminal"].is_object());
+}
+
+#[test]
+fn check_reports_results_and_errors() {
+    let check = |predicate: &str| {
+        let predicate = CString::new(predicate).unwrap();
+        unsafe { envsense_check(predicate.as_ptr()) }
+    };
+
+    let interactive = check("terminal.interactive");
+    assert!(interactive == ENVSENSE_TRUE || interactive == ENVSENSE_FALSE);
+    assert_eq!(check("agent.nope"), ENVSENSE_ERR_PARSE);
+    assert_eq!(
+        unsafe { envsense_check(std::ptr::null()) },
+        ENVSENSE_ERR_INVALID_ARGUMENT
+    );
+    unsafe { envsense_string_free(std::ptr::null_mut()) };
+}
+
+#[test]
+fn header_is_up_to_date() {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    let header_path = Path::new(crate_dir).join("include/envsense.h");
+    let mut generated = Vec::new();
+    cbindgen::generate_with_config(
+        crate_dir,
+        cbindgen::Config::from_file(Path::new(crate_dir).join("cbindgen.toml")).unwrap(),
+    )
+    .unwrap()
+    .write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+
+    if std::env::var_os("ENVSENSE_UPDATE_HEADER").is_some() {
+        std::fs::write(&header_path, &generated).unwrap();
+        return;
+    }
+    let committed = std::fs::read_to_string(&header_path).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "include/envsense.h is stale; rerun with ENVSENSE_UPDATE_HEADER=1"
+    );
+}
@@ -1,44 +1,425 @@
 //! Procedural macro implementation for envsense detection merging
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Field, Fields, FieldsNamed, FieldsUnnamed};
+use quote::{quote, quote_spanned};
+use syn::{parse_macro_input, DeriveInput, Field, Fields, LitStr};
 
 /// Derive macro for automatic detection merging
 ///
-/// This macro generates a `DetectionMerger` implementation that automatically
-/// merges detection results based on field names.
-#[proc_macro_derive(DetectionMerger)]
+/// This macro generates a `DetectionMerger` implementation that merges
+/// detection results into each field according to its
+/// `#[detection_merge(...)]` options, or its name when it has none. Enums
+/// merge into the variant selected by `context = "..."` or `fallback`.
+#[proc_macro_derive(DetectionMerger, attributes(detection_merge))]
 pub fn derive_detection_merger(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    match generate_detection_merger(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
 
-    let struct_name = input.ident;
-    let fields = parse_fields(&input.data);
+fn generate_detection_merger(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let struct_name = &input.ident;
+    let options = ContainerOptions::parse(&input.attrs)?;
 
-    let merge_impl = generate_merge_impl(&struct_name, &fields);
+    let (fields, merge_impl) = match &input.data {
+        syn::Data::Struct(data) => {
+            let fields = parse_fields(&data.fields)?;
+            let merge_impl = generate_merge_impl(&fields, |member| quote! { self.#member });
+            (fields, merge_impl)
+        }
+        syn::Data::Enum(data) => {
+            let variants = data
+                .variants
+                .iter()
+                .map(parse_variant)
+                .collect::<syn::Result<Vec<_>>>()?;
+            if variants.iter().filter(|v| v.selector.is_none()).count() > 1 {
+                return Err(syn::Error::new_spanned(
+                    struct_name,
+                    "only one variant can be the `fallback`",
+                ));
+            }
+            let merge_impl = generate_enum_merge(&variants);
+            let fields = variants.into_iter().flat_map(|v| v.fields).collect();
+            (fields, merge_impl)
+        }
+        syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "DetectionMerger can only be derived for structs and enums",
+            ));
+        }
+    };
+    let key_checks = generate_key_checks(options.schema.as_ref(), &fields);
+    let unmapped = generate_unmapped_diagnostics(&fields, options.strict)?;
 
-    TokenStream::from(quote! {
-        impl DetectionMerger for #struct_name {
-            #merge_impl
+    // Detections carry evidence of the evidence field's element type. Without
+    // one, the merger takes whatever evidence its flattened fields merge, or
+    // plain JSON when there are none
+    let mut generics = input.generics.clone();
+    let evidence_field = fields.iter().find_map(|field| match field.mapping_type {
+        MappingType::Evidence => field.element_type.as_ref(),
+        _ => None,
+    });
+    let flattened: Vec<&syn::Type> = fields
+        .iter()
+        .filter(|field| matches!(field.mapping_type, MappingType::Flatten))
+        .map(|field| &field.ty)
+        .collect();
+    let evidence_type = match evidence_field {
+        Some(ty) => quote! { #ty },
+        None if !flattened.is_empty() => {
+            generics.params.push(syn::parse_quote! { __E });
+            let where_clause = generics.make_where_clause();
+            for ty in flattened {
+                where_clause
+                    .predicates
+                    .push(syn::parse_quote! { #ty: DetectionMerger<__E> });
+            }
+            quote! { __E }
         }
+        None => quote! { serde_json::Value },
+    };
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics DetectionMerger<#evidence_type> for #struct_name #ty_generics #where_clause {
+            fn merge_detections(&mut self, detections: &[Detection<#evidence_type>]) {
+                #merge_impl
+            }
+        }
+
+        #(#key_checks)*
+        #(#unmapped)*
     })
 }
 
-/// Custom attribute macro for detection_merge
+/// Options of a struct's or enum's `#[detection_merge(...)]` attribute
+#[derive(Default)]
+struct ContainerOptions {
+    /// Trait group type that merge keys are checked against
+    schema: Option<syn::Type>,
+    /// Whether fields that merge nothing are an error rather than a warning
+    strict: bool,
+}
+
+impl ContainerOptions {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("detection_merge"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("schema") {
+                    let path: LitStr = meta.value()?.parse()?;
+                    options.schema = Some(path.parse()?);
+                } else if meta.path.is_ident("strict") {
+                    options.strict = true;
+                } else {
+                    return Err(meta.error("expected `schema = \"path::to::Traits\"` or `strict`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+/// A warning, or an error under `strict`, for each field that merges
+/// nothing and is not marked `ignore`
 ///
-/// This attribute can be used on struct fields to specify how they should be merged.
-#[proc_macro_attribute]
-pub fn detection_merge(_attr: TokenStream, _item: TokenStream) -> TokenStream {
-    // For now, this is just a marker attribute
-    // The actual parsing happens in the derive macro
-    _item
+/// Stable Rust has no warning API for proc macros, so the warning is a use
+/// of a deprecated constant spanned at the field.
+fn generate_unmapped_diagnostics(
+    fields: &[FieldMapping],
+    strict: bool,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let unmapped = fields
+        .iter()
+        .filter(|field| matches!(field.mapping_type, MappingType::Unmapped));
+    let mut warnings = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    for field in unmapped {
+        let span = syn::spanned::Spanned::span(&field.member);
+        let name = match &field.member {
+            syn::Member::Named(ident) => ident.to_string(),
+            syn::Member::Unnamed(index) => index.index.to_string(),
+        };
+        let message = format!(
+            "field `{}` is not merged; give it a `key` or mark it `#[detection_merge(ignore)]`",
+            name
+        );
+        if strict {
+            let error = syn::Error::new(span, message);
+            match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        } else {
+            warnings.push(quote_spanned! {span=>
+                const _: () = {
+                    #[deprecated(note = #message)]
+                    #[allow(non_upper_case_globals)]
+                    const unmerged_field: () = ();
+                    unmerged_field
+                };
+            });
+        }
+    }
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(warnings),
+    }
+}
+
+/// Compile-time checks that each `key` naming a trait group names a field
+/// of it
+///
+/// Keys are checked against the `schema` type, or the `traits` field's type
+/// when no schema is given. Keys whose first segment is not a trait group,
+/// such as facets, are not checked.
+fn generate_key_checks(
+    schema: Option<&syn::Type>,
+    fields: &[FieldMapping],
+) -> Vec<proc_macro2::TokenStream> {
+    let traits_type = fields.iter().find_map(|field| match field.mapping_type {
+        MappingType::Traits => Some(&field.ty),
+        _ => None,
+    });
+    let Some(schema) = schema.or(traits_type) else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .filter_map(|field| match &field.mapping_type {
+            MappingType::Key(key, _) => Some((key, field.key_span)),
+            _ => None,
+        })
+        .map(|(key, span)| {
+            let message = format!("merge key `{}` names no field of its trait group", key);
+            quote_spanned! {span=>
+                const _: () = {
+                    let schema = &<#schema as ::envsense_macros::MergeValue>::SCHEMA;
+                    assert!(!schema.has_root(#key) || schema.resolves(#key), #message);
+                };
+            }
+        })
+        .collect()
+}
+
+/// Derive macro for merging JSON trait patches into a trait group
+///
+/// Generates a `MergeValue` implementation that merges each named field
+/// from the object key of the same name, or the `key` given with
+/// `#[detection_merge(key = "...")]`, and skips `#[detection_merge(ignore)]`
+/// fields. Legacy flat keys are declared on the struct with
+/// `#[detection_merge(alias(is_tty_stdin = "stdin.tty"))]`.
+#[proc_macro_derive(MergeValue, attributes(detection_merge))]
+pub fn derive_merge_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match generate_merge_value(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn generate_merge_value(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let struct_name = &input.ident;
+    let syn::Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "MergeValue can only be derived for structs with named fields",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "MergeValue can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut keys = Vec::new();
+    let mut docs = Vec::new();
+    let mut idents = Vec::new();
+    let mut types = Vec::new();
+    let mut extensions = None;
+    for field in &named.named {
+        let options = MergeOptions::parse(&field.attrs)?;
+        if options.flatten || options.strategy.is_some() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "trait group fields only support `key`, `ignore` and `extensions`",
+            ));
+        }
+        let Some(ident) = &field.ident else { continue };
+        if options.extensions && extensions.replace(ident).is_some() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "only one field can take the `extensions`",
+            ));
+        }
+        if !options.ignore {
+            keys.push(
+                options
+                    .key
+                    .map_or_else(|| ident.to_string(), |key| key.value()),
+            );
+            docs.push(doc_summary(&field.attrs));
+            idents.push(ident);
+            types.push(&field.ty);
+        }
+    }
+    let aliases = parse_aliases(&input.attrs)?;
+    // Warn at compile time about alias paths that name no field
+    let alias_checks: Vec<_> = aliases
+        .iter()
+        .map(|(_, path)| {
+            quote_spanned! {path.span()=>
+                const _: () = ::envsense_macros::KeyCheck::<{
+                    <#struct_name as ::envsense_macros::MergeValue>::SCHEMA.resolves(#path)
+                }>
+                .check();
+            }
+        })
+        .collect();
+
+    // Keys no field has go to the extensions field, if there is one
+    let (merge_unknown, merge_unknown_path) = match extensions {
+        Some(extensions) => (
+            quote! {
+                for (key, value) in object {
+                    if ![#(#keys),*].contains(&key.as_str()) {
+                        merged |= ::envsense_macros::MergeValue::merge_path(&mut self.#extensions, &[key.as_str()], value);
+                    }
+                }
+            },
+            quote! { ::envsense_macros::MergeValue::merge_path(&mut self.#extensions, path, value) },
+        ),
+        None => (quote! {}, quote! { false }),
+    };
+    let merge_value = if idents.is_empty() {
+        quote! { value.is_object() }
+    } else {
+        quote! {
+            let Some(object) = value.as_object() else {
+                return false;
+            };
+            let mut merged = false;
+            #(
+                if let Some(value) = object.get(#keys) {
+                    merged |= ::envsense_macros::MergeValue::merge_value(&mut self.#idents, value);
+                }
+            )*
+            #merge_unknown
+            merged
+        }
+    };
+    let alias = (!aliases.is_empty()).then(|| {
+        let (from, to): (Vec<_>, Vec<_>) = aliases.into_iter().unzip();
+        quote! {
+            fn alias(&self, key: &str) -> Option<&'static str> {
+                match key {
+                    #(#from => Some(#to),)*
+                    _ => None,
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl ::envsense_macros::MergeValue for #struct_name {
+            const SCHEMA: ::envsense_macros::TraitSchema = ::envsense_macros::TraitSchema::group(&[#(
+                ::envsense_macros::TraitField {
+                    name: #keys,
+                    doc: #docs,
+                    schema: &<#types as ::envsense_macros::MergeValue>::SCHEMA,
+                }
+            ),*]);
+
+            fn merge_value(&mut self, value: &serde_json::Value) -> bool {
+                #merge_value
+            }
+
+            fn merge_path(&mut self, path: &[&str], value: &serde_json::Value) -> bool {
+                match path {
+                    [] => self.merge_value(value),
+                    #(
+                        [#keys, rest @ ..] => {
+                            ::envsense_macros::MergeValue::merge_path(&mut self.#idents, rest, value)
+                        }
+                    )*
+                    _ => #merge_unknown_path,
+                }
+            }
+
+            #alias
+        }
+
+        #(#alias_checks)*
+    })
+}
+
+/// First paragraph of a field's doc comment on one line, without the
+/// trailing period
+fn doc_summary(attrs: &[syn::Attribute]) -> String {
+    let lines = attrs.iter().filter_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(doc),
+                ..
+            }) => Some(doc.value()),
+            _ => None,
+        },
+        _ => None,
+    });
+    let paragraph: Vec<String> = lines
+        .map(|line| line.trim().to_string())
+        .skip_while(String::is_empty)
+        .take_while(|line| !line.is_empty())
+        .collect();
+    paragraph.join(" ").trim_end_matches('.').to_string()
+}
+
+/// `(legacy key, dotted path)` pairs of a struct's
+/// `#[detection_merge(alias(...))]` attributes
+fn parse_aliases(attrs: &[syn::Attribute]) -> syn::Result<Vec<(String, LitStr)>> {
+    let mut aliases = Vec::new();
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("detection_merge"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("alias") {
+                return Err(meta.error("expected `alias(legacy_key = \"dotted.path\")`"));
+            }
+            meta.parse_nested_meta(|alias| {
+                let key = alias
+                    .path
+                    .get_ident()
+                    .ok_or_else(|| alias.error("expected a legacy key name"))?
+                    .to_string();
+                let path: LitStr = alias.value()?.parse()?;
+                aliases.push((key, path));
+                Ok(())
+            })
+        })?;
+    }
+    Ok(aliases)
 }
 
-#[derive(Debug)]
 struct FieldMapping {
-    field_name: String,
+    member: syn::Member,
     mapping_type: MappingType,
     field_type: FieldType,
+    ty: syn::Type,
+    /// Where `key` was given, or the field when it was not
+    key_span: proc_macro2::Span,
+    /// `T` of a `Vec<T>` field
+    element_type: Option<syn::Type>,
 }
 
 #[derive(Debug)]
@@ -47,324 +428,366 @@ enum MappingType {
     Facets,
     Traits,
     Evidence,
+    /// A single value at a dotted facet or trait key, picked with the
+    /// strategy if one is given
+    Key(String, Option<MergeStrategy>),
+    /// A nested struct that merges detections itself
+    Flatten,
+    /// Marked `ignore`
     Ignore,
+    /// Named nothing the merger knows and not marked `ignore`
+    Unmapped,
 }
 
 #[derive(Debug)]
 enum FieldType {
     Contexts,
     Facets,
-    Traits,
-    NestedTraits, // New: for nested trait structures
-    Evidence,
-    SimpleBool,     // New: for simple boolean fields
-    OptionalString, // New: for Option<String> fields like host
+    Vec,
+    SimpleBool, // New: for simple boolean fields
     Other,
 }
 
-fn parse_fields(data: &syn::Data) -> Vec<FieldMapping> {
-    match data {
-        syn::Data::Struct(data) => match &data.fields {
-            Fields::Named(fields) => parse_named_fields(fields),
-            Fields::Unnamed(fields) => parse_unnamed_fields(fields),
-            Fields::Unit => vec![],
-        },
-        _ => vec![],
-    }
+/// How a keyed field combines the values of several detections
+#[derive(Debug, Clone, Copy)]
+enum MergeStrategy {
+    /// The value of the first detection that sets the key
+    FirstWins,
+    /// The value of the last detection that sets the key
+    LastWins,
+    /// The value of the most confident detection, ties going to the later one
+    HighestConfidence,
+    /// Every value, pushed onto a `Vec` field with arrays spread out
+    Append,
 }
 
-fn parse_named_fields(fields: &FieldsNamed) -> Vec<FieldMapping> {
-    fields.named.iter().filter_map(parse_field).collect()
+impl MergeStrategy {
+    fn parse(name: &LitStr) -> syn::Result<Self> {
+        match name.value().as_str() {
+            "first_wins" => Ok(Self::FirstWins),
+            "last_wins" => Ok(Self::LastWins),
+            "highest_confidence" => Ok(Self::HighestConfidence),
+            "append" => Ok(Self::Append),
+            _ => Err(syn::Error::new_spanned(
+                name,
+                "expected `first_wins`, `last_wins`, `highest_confidence` or `append`",
+            )),
+        }
+    }
 }
 
-fn parse_unnamed_fields(_fields: &FieldsUnnamed) -> Vec<FieldMapping> {
-    // For now, we only support named fields
-    vec![]
+/// Options of a `#[detection_merge(...)]` field attribute
+#[derive(Default)]
+struct MergeOptions {
+    key: Option<LitStr>,
+    /// Set by `ignore`, or its older spelling `skip`
+    ignore: bool,
+    flatten: bool,
+    strategy: Option<MergeStrategy>,
+    /// Trait group map that takes the keys no other field has
+    extensions: bool,
 }
 
-fn parse_field(field: &Field) -> Option<FieldMapping> {
-    let field_name = field.ident.as_ref()?.to_string();
-
-    // Determine field type based on the type path
-    let mut field_type = detect_field_type(field);
-
-    // Refine field type based on field name and actual type
-    if matches!(field_type, FieldType::Other) {
-        if let syn::Type::Path(type_path) = &field.ty {
-            if let Some(segment) = type_path.path.segments.last() {
-                match (field_name.as_str(), segment.ident.to_string().as_str()) {
-                    ("contexts", "Vec") => field_type = FieldType::Other, // Vec<String> contexts
-                    ("evidence", "Vec") => field_type = FieldType::Evidence, // Vec<Evidence>
-                    ("contexts", "bool") => field_type = FieldType::SimpleBool, // Simple bool contexts
-                    ("contexts", _) => field_type = FieldType::Contexts,        // Contexts struct
-                    _ => {}
+impl MergeOptions {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("detection_merge"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("key") {
+                    options.key = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("ignore") || meta.path.is_ident("skip") {
+                    options.ignore = true;
+                } else if meta.path.is_ident("flatten") {
+                    options.flatten = true;
+                } else if meta.path.is_ident("strategy") {
+                    let strategy: LitStr = meta.value()?.parse()?;
+                    options.strategy = Some(MergeStrategy::parse(&strategy)?);
+                } else if meta.path.is_ident("extensions") {
+                    options.extensions = true;
+                } else {
+                    return Err(meta.error(
+                        "expected `key = \"...\"`, `strategy = \"...\"`, `ignore`, `flatten` or `extensions`",
+                    ));
                 }
+                Ok(())
+            })?;
+            let chosen = [
+                options.key.is_some(),
+                options.ignore,
+                options.flatten,
+                options.extensions,
+            ]
+            .into_iter()
+            .filter(|&set| set)
+            .count();
+            if chosen > 1 {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`key`, `ignore`, `flatten` and `extensions` cannot be combined",
+                ));
             }
         }
+        Ok(options)
     }
+}
 
-    // Map based on field name
-    match field_name.as_str() {
-        "contexts" => Some(FieldMapping {
-            field_name,
-            mapping_type: MappingType::Contexts,
-            field_type,
-        }),
-        "facets" => Some(FieldMapping {
-            field_name,
-            mapping_type: MappingType::Facets,
-            field_type,
-        }),
-        "traits" => Some(FieldMapping {
-            field_name,
-            mapping_type: MappingType::Traits,
-            field_type,
-        }),
-        "evidence" => Some(FieldMapping {
-            field_name,
-            mapping_type: MappingType::Evidence,
-            field_type,
-        }),
-        "host" => Some(FieldMapping {
-            field_name,
-            mapping_type: MappingType::Facets,
-            field_type: FieldType::OptionalString,
-        }),
-        _ => Some(FieldMapping {
-            field_name,
-            mapping_type: MappingType::Ignore,
-            field_type: FieldType::Other,
+fn parse_fields(fields: &Fields) -> syn::Result<Vec<FieldMapping>> {
+    let newtype = matches!(fields, Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1);
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| parse_field(field, index, newtype))
+        .collect()
+}
+
+/// Decide what a field merges from
+///
+/// Without `key`, the field name is the key. The keys `contexts`, `traits`,
+/// `facets` and `evidence` take the matching part of every detection; other
+/// keys only apply when given explicitly. Fields such as `version` that
+/// merge nothing are unmapped, and warned about unless marked `ignore`. A
+/// `strategy` only applies to explicit keys.
+/// Tuple fields have no name to go by, except that the only field of a
+/// newtype is flattened.
+fn parse_field(field: &Field, index: usize, newtype: bool) -> syn::Result<FieldMapping> {
+    let member = match &field.ident {
+        Some(ident) => syn::Member::Named(ident.clone()),
+        None => syn::Member::Unnamed(syn::Index {
+            index: index as u32,
+            span: syn::spanned::Spanned::span(&field.ty),
         }),
+    };
+    let field_name = field
+        .ident
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let options = MergeOptions::parse(&field.attrs)?;
+    if options.extensions {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`extensions` only applies to fields of a trait group deriving `MergeValue`",
+        ));
     }
-}
+    let key = options.key.as_ref().map(LitStr::value);
 
-fn detect_field_type(field: &Field) -> FieldType {
-    // Enhanced type detection for nested structures
-    if let syn::Type::Path(type_path) = &field.ty {
-        if let Some(segment) = type_path.path.segments.last() {
-            match segment.ident.to_string().as_str() {
-                "Contexts" => FieldType::Contexts,
-                "Facets" => FieldType::Facets,
-                "Traits" => FieldType::Traits,
-                "TerminalTraits" => FieldType::Traits, // Flat terminal traits
-                "NestedTraits" => FieldType::NestedTraits, // New: detect nested traits
-                "Vec" => {
-                    // Check if this is Vec<String> for contexts or Vec<Evidence> for evidence
-                    // For now, we'll determine this based on the field name in parse_field
-                    FieldType::Other // Will be refined in parse_field based on field name
-                }
-                _ => FieldType::Other,
-            }
-        } else {
-            FieldType::Other
-        }
+    let mapping_type = if options.ignore {
+        MappingType::Ignore
+    } else if options.flatten || (newtype && key.is_none()) {
+        MappingType::Flatten
     } else {
-        FieldType::Other
+        match key.as_deref().unwrap_or(&field_name) {
+            "contexts" => MappingType::Contexts,
+            "facets" => MappingType::Facets,
+            "traits" => MappingType::Traits,
+            "evidence" => MappingType::Evidence,
+            key if options.key.is_some() => MappingType::Key(key.to_string(), options.strategy),
+            _ => MappingType::Unmapped,
+        }
+    };
+    if options.strategy.is_some() && !matches!(mapping_type, MappingType::Key(..)) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`strategy` needs a `key` other than contexts, facets, traits or evidence",
+        ));
     }
+
+    Ok(FieldMapping {
+        member,
+        mapping_type,
+        field_type: detect_field_type(field),
+        ty: field.ty.clone(),
+        key_span: options
+            .key
+            .as_ref()
+            .map_or_else(|| syn::spanned::Spanned::span(field), LitStr::span),
+        element_type: vec_element_type(field),
+    })
 }
 
-/// Helper function to generate nested field merging logic
-fn generate_nested_trait_merge(field_name: &syn::Ident) -> proc_macro2::TokenStream {
-    quote! {
-        // Merge nested traits - handle both nested objects and flat keys
+/// An enum variant and the contexts that select it
+struct VariantMapping {
+    ident: syn::Ident,
+    /// Context whose detection selects the variant; `None` for the fallback
+    selector: Option<String>,
+    fields: Vec<FieldMapping>,
+}
 
-        // Agent traits - handle both nested object and flat key formats
-        if let Some(agent_obj) = all_traits.get("agent").and_then(|v| v.as_object()) {
-            if let Some(id) = agent_obj.get("id").and_then(|v| v.as_str()) {
-                self.#field_name.agent.id = Some(id.to_string());
+/// Parse a variant's `#[detection_merge(context = "...")]` or
+/// `#[detection_merge(fallback)]` and its fields
+fn parse_variant(variant: &syn::Variant) -> syn::Result<VariantMapping> {
+    let mut selector = None;
+    let mut fallback = false;
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("detection_merge"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("context") {
+                let context: LitStr = meta.value()?.parse()?;
+                selector = Some(context.value());
+            } else if meta.path.is_ident("fallback") {
+                fallback = true;
+            } else {
+                return Err(meta.error("expected `context = \"...\"` or `fallback`"));
             }
-        } else if let Some(value) = all_traits.get("agent.id").and_then(|v| v.as_str()) {
-            self.#field_name.agent.id = Some(value.to_string());
-        }
+            Ok(())
+        })?;
+    }
+    if selector.is_some() == fallback {
+        return Err(syn::Error::new_spanned(
+            &variant.ident,
+            "each variant needs exactly one of `#[detection_merge(context = \"...\")]` or `#[detection_merge(fallback)]`",
+        ));
+    }
+    Ok(VariantMapping {
+        ident: variant.ident.clone(),
+        selector,
+        fields: parse_fields(&variant.fields)?,
+    })
+}
 
-        // IDE traits - handle both nested object and flat key formats
-        if let Some(ide_obj) = all_traits.get("ide").and_then(|v| v.as_object()) {
-            if let Some(id) = ide_obj.get("id").and_then(|v| v.as_str()) {
-                self.#field_name.ide.id = Some(id.to_string());
-            }
-        } else if let Some(value) = all_traits.get("ide.id").and_then(|v| v.as_str()) {
-            self.#field_name.ide.id = Some(value.to_string());
-        }
+fn vec_element_type(field: &Field) -> Option<syn::Type> {
+    let syn::Type::Path(type_path) = &field.ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Vec" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty.clone()),
+        _ => None,
+    })
+}
 
-        // CI traits - handle both nested object and flat key formats
-        if let Some(ci_obj) = all_traits.get("ci").and_then(|v| v.as_object()) {
-            if let Some(id) = ci_obj.get("id").and_then(|v| v.as_str()) {
-                self.#field_name.ci.id = Some(id.to_string());
-            }
-            if let Some(vendor) = ci_obj.get("vendor").and_then(|v| v.as_str()) {
-                self.#field_name.ci.vendor = Some(vendor.to_string());
-            }
-            if let Some(name) = ci_obj.get("name").and_then(|v| v.as_str()) {
-                self.#field_name.ci.name = Some(name.to_string());
-            }
-            if let Some(is_pr) = ci_obj.get("is_pr").and_then(|v| v.as_bool()) {
-                self.#field_name.ci.is_pr = Some(is_pr);
-            }
-            if let Some(branch) = ci_obj.get("branch").and_then(|v| v.as_str()) {
-                self.#field_name.ci.branch = Some(branch.to_string());
-            }
-        } else {
-            // Fallback to flat key format
-            if let Some(value) = all_traits.get("ci.id").and_then(|v| v.as_str()) {
-                self.#field_name.ci.id = Some(value.to_string());
-            }
-            if let Some(value) = all_traits.get("ci.vendor").and_then(|v| v.as_str()) {
-                self.#field_name.ci.vendor = Some(value.to_string());
-            }
-            if let Some(value) = all_traits.get("ci.name").and_then(|v| v.as_str()) {
-                self.#field_name.ci.name = Some(value.to_string());
-            }
-            if let Some(value) = all_traits.get("ci.is_pr").and_then(|v| v.as_bool()) {
-                self.#field_name.ci.is_pr = Some(value);
-            }
-            if let Some(value) = all_traits.get("ci.branch").and_then(|v| v.as_str()) {
-                self.#field_name.ci.branch = Some(value.to_string());
-            }
-        }
+fn detect_field_type(field: &Field) -> FieldType {
+    let syn::Type::Path(type_path) = &field.ty else {
+        return FieldType::Other;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return FieldType::Other;
+    };
+    match segment.ident.to_string().as_str() {
+        "Contexts" => FieldType::Contexts,
+        "Facets" => FieldType::Facets,
+        "Vec" => FieldType::Vec, // Vec<String> contexts or Vec<Evidence>
+        "bool" => FieldType::SimpleBool,
+        _ => FieldType::Other,
+    }
+}
 
-        // Terminal traits - handle both nested object and flat key formats
-        if let Some(terminal_obj) = all_traits.get("terminal").and_then(|v| v.as_object()) {
-            // Handle nested terminal object
-            if let Some(interactive) = terminal_obj.get("interactive").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.interactive = interactive;
-            }
-            if let Some(stdin_obj) = terminal_obj.get("stdin").and_then(|v| v.as_object()) {
-                if let Some(tty) = stdin_obj.get("tty").and_then(|v| v.as_bool()) {
-                    self.#field_name.terminal.stdin.tty = tty;
-                }
-                if let Some(piped) = stdin_obj.get("piped").and_then(|v| v.as_bool()) {
-                    self.#field_name.terminal.stdin.piped = piped;
-                }
-            }
-            if let Some(stdout_obj) = terminal_obj.get("stdout").and_then(|v| v.as_object()) {
-                if let Some(tty) = stdout_obj.get("tty").and_then(|v| v.as_bool()) {
-                    self.#field_name.terminal.stdout.tty = tty;
-                }
-                if let Some(piped) = stdout_obj.get("piped").and_then(|v| v.as_bool()) {
-                    self.#field_name.terminal.stdout.piped = piped;
-                }
-            }
-            if let Some(stderr_obj) = terminal_obj.get("stderr").and_then(|v| v.as_object()) {
-                if let Some(tty) = stderr_obj.get("tty").and_then(|v| v.as_bool()) {
-                    self.#field_name.terminal.stderr.tty = tty;
-                }
-                if let Some(piped) = stderr_obj.get("piped").and_then(|v| v.as_bool()) {
-                    self.#field_name.terminal.stderr.piped = piped;
-                }
-            }
-            if let Some(supports_hyperlinks) = terminal_obj.get("supports_hyperlinks").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.supports_hyperlinks = supports_hyperlinks;
-            }
-            if let Some(color_level_str) = terminal_obj.get("color_level").and_then(|v| v.as_str()) {
-                if let Ok(color_level) = serde_json::from_str::<serde_json::Value>(&format!("\"{}\"", color_level_str))
-                    .and_then(|v| serde_json::from_value(v)) {
-                    self.#field_name.terminal.color_level = color_level;
-                }
-            }
-        } else {
-            // Fallback to flat key format for all terminal fields
-            if let Some(value) = all_traits.get("terminal.interactive").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.interactive = value;
-            }
-            if let Some(value) = all_traits.get("terminal.stdin.tty").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stdin.tty = value;
-            }
-            if let Some(value) = all_traits.get("terminal.stdin.piped").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stdin.piped = value;
-            }
-            if let Some(value) = all_traits.get("terminal.stdout.tty").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stdout.tty = value;
-            }
-            if let Some(value) = all_traits.get("terminal.stdout.piped").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stdout.piped = value;
-            }
-            if let Some(value) = all_traits.get("terminal.stderr.tty").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stderr.tty = value;
-            }
-            if let Some(value) = all_traits.get("terminal.stderr.piped").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stderr.piped = value;
-            }
-            if let Some(value) = all_traits.get("terminal.supports_hyperlinks").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.supports_hyperlinks = value;
-            }
-            // Handle color level enum
-            if let Some(color_level_str) = all_traits.get("terminal.color_level").and_then(|v| v.as_str()) {
-                // Parse string to enum - this will work regardless of import context
-                if let Ok(color_level) = serde_json::from_str::<serde_json::Value>(&format!("\"{}\"", color_level_str))
-                    .and_then(|v| serde_json::from_value(v)) {
-                    self.#field_name.terminal.color_level = color_level;
+/// Merge a keyed field from each detection's own value under `strategy`
+fn generate_strategy_merge(
+    field_name: &proc_macro2::TokenStream,
+    key: &str,
+    strategy: MergeStrategy,
+) -> proc_macro2::TokenStream {
+    let candidates = quote! {
+        detections.iter().filter_map(|detection| {
+            lookup_key(&detection.facets_patch, &detection.traits_patch, #key)
+                .map(|value| (value, detection.confidence))
+        })
+    };
+    let chosen = match strategy {
+        MergeStrategy::FirstWins => quote! { #candidates.next() },
+        MergeStrategy::LastWins => quote! { #candidates.last() },
+        MergeStrategy::HighestConfidence => quote! {
+            #candidates.reduce(|best, next| if next.1 >= best.1 { next } else { best })
+        },
+        MergeStrategy::Append => {
+            return quote! {
+                for (value, _) in #candidates {
+                    let items = match value {
+                        serde_json::Value::Array(items) => items.clone(),
+                        other => vec![other.clone()],
+                    };
+                    for item in items {
+                        if let Ok(item) = serde_json::from_value(item) {
+                            #field_name.push(item);
+                        }
+                    }
                 }
+            };
+        }
+    };
+    quote! {
+        if let Some((value, _)) = #chosen {
+            if let Ok(value) = serde_json::from_value(value.clone()) {
+                #field_name = value;
             }
         }
+    }
+}
 
-        // CI traits
-        if let Some(value) = all_traits.get("ci.id").and_then(|v| v.as_str()) {
-            self.#field_name.ci.id = Some(value.to_string());
-        }
-        if let Some(value) = all_traits.get("ci.vendor").and_then(|v| v.as_str()) {
-            self.#field_name.ci.vendor = Some(value.to_string());
-        }
-        if let Some(value) = all_traits.get("ci.name").and_then(|v| v.as_str()) {
-            self.#field_name.ci.name = Some(value.to_string());
-        }
-        if let Some(value) = all_traits.get("ci.is_pr").and_then(|v| v.as_bool()) {
-            self.#field_name.ci.is_pr = Some(value);
-        }
-        if let Some(value) = all_traits.get("ci.branch").and_then(|v| v.as_str()) {
-            self.#field_name.ci.branch = Some(value.to_string());
-        }
+fn generate_merge_impl(
+    fields: &[FieldMapping],
+    target: impl Fn(&syn::Member) -> proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let prelude = generate_merge_prelude(fields.iter());
+    let field_merges = generate_field_merges(fields, target);
+    quote! {
+        #prelude
+        #(#field_merges)*
+    }
+}
 
-        // Backward compatibility: handle flat trait keys for migration (only if nested key not present)
-        if !all_traits.contains_key("terminal.interactive") {
-            if let Some(value) = all_traits.get("is_interactive").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.interactive = value;
-            }
-        }
-        if !all_traits.contains_key("terminal.stdin.tty") {
-            if let Some(value) = all_traits.get("is_tty_stdin").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stdin.tty = value;
-            }
-        }
-        if !all_traits.contains_key("terminal.stdout.tty") {
-            if let Some(value) = all_traits.get("is_tty_stdout").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stdout.tty = value;
-            }
-        }
-        if !all_traits.contains_key("terminal.stderr.tty") {
-            if let Some(value) = all_traits.get("is_tty_stderr").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stderr.tty = value;
-            }
-        }
-        if !all_traits.contains_key("terminal.stdin.piped") {
-            if let Some(value) = all_traits.get("is_piped_stdin").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stdin.piped = value;
-            }
-        }
-        if !all_traits.contains_key("terminal.stdout.piped") {
-            if let Some(value) = all_traits.get("is_piped_stdout").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.stdout.piped = value;
-            }
-        }
-        if !all_traits.contains_key("terminal.supports_hyperlinks") {
-            if let Some(value) = all_traits.get("supports_hyperlinks").and_then(|v| v.as_bool()) {
-                self.#field_name.terminal.supports_hyperlinks = value;
-            }
-        }
-        if !all_traits.contains_key("terminal.color_level") {
-            if let Some(color_level_str) = all_traits.get("color_level").and_then(|v| v.as_str()) {
-                // Parse string to enum - this will work regardless of import context
-                if let Ok(color_level) = serde_json::from_str::<serde_json::Value>(&format!("\"{}\"", color_level_str))
-                    .and_then(|v| serde_json::from_value(v)) {
-                    self.#field_name.terminal.color_level = color_level;
+/// Merge into the first variant whose context was detected, or the
+/// fallback, switching to it with default fields if `self` is another one
+fn generate_enum_merge(variants: &[VariantMapping]) -> proc_macro2::TokenStream {
+    let prelude = generate_merge_prelude(variants.iter().flat_map(|v| &v.fields));
+    let (selected, fallback): (Vec<_>, Vec<_>) =
+        variants.iter().partition(|v| v.selector.is_some());
+    let arms = selected.iter().chain(&fallback).map(|variant| {
+        let ident = &variant.ident;
+        let members: Vec<_> = variant.fields.iter().map(|f| &f.member).collect();
+        let bindings: Vec<_> = (0..members.len())
+            .map(|i| syn::Ident::new(&format!("__field{}", i), proc_macro2::Span::call_site()))
+            .collect();
+        let field_merges = generate_field_merges(&variant.fields, |member| {
+            let index = members
+                .iter()
+                .position(|m| *m == member)
+                .unwrap_or_default();
+            let binding = &bindings[index];
+            quote! { (*#binding) }
+        });
+        let condition = match &variant.selector {
+            Some(context) => quote! { all_contexts.iter().any(|context| context == #context) },
+            None => quote! { true },
+        };
+        quote! {
+            if #condition {
+                if !matches!(self, Self::#ident { .. }) {
+                    *self = Self::#ident { #(#members: Default::default()),* };
+                }
+                match self {
+                    #[allow(unused_variables)]
+                    Self::#ident { #(#members: #bindings),* } => {
+                        #(#field_merges)*
+                    }
+                    _ => {}
                 }
             }
         }
+    });
+    quote! {
+        #prelude
+        #(#arms else)* {}
     }
 }
 
-fn generate_merge_impl(
-    _struct_name: &syn::Ident,
-    fields: &[FieldMapping],
+/// Collect the contexts, traits and facets of every detection, with
+/// conflicting trait values recorded
+fn generate_merge_prelude<'a>(
+    mut fields: impl Iterator<Item = &'a FieldMapping>,
 ) -> proc_macro2::TokenStream {
     let mut merge_statements = Vec::new();
 
@@ -374,24 +797,81 @@ fn generate_merge_impl(
         let mut all_traits: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
         let mut all_facets: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
 
-        // Helper function to deep merge JSON objects
-        fn deep_merge_json(target: &mut serde_json::Value, source: &serde_json::Value) {
-            match (target.as_object_mut(), source.as_object()) {
-                (Some(target_map), Some(source_map)) => {
-                    for (key, value) in source_map {
-                        if target_map.contains_key(key) {
-                            if let Some(existing) = target_map.get_mut(key) {
-                                deep_merge_json(existing, value);
-                            }
-                        } else {
-                            target_map.insert(key.clone(), value.clone());
-                        }
+        // Confidence of the detection that set each trait leaf, by dotted path
+        let mut trait_owners: std::collections::HashMap<String, f32> = std::collections::HashMap::new();
+        // Values that lost a conflict, recorded as evidence below
+        let mut trait_conflicts: Vec<(String, serde_json::Value, f32)> = Vec::new();
+
+        fn claim_json(value: &serde_json::Value, path: &str, confidence: f32, owners: &mut std::collections::HashMap<String, f32>) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, child) in map {
+                        claim_json(child, &format!("{}.{}", path, key), confidence, owners);
                     }
                 }
+                serde_json::Value::Null => {}
                 _ => {
-                    // For non-objects, source overwrites target
-                    *target = source.clone();
+                    let held = owners.entry(path.to_string()).or_insert(confidence);
+                    *held = held.max(confidence);
+                }
+            }
+        }
+
+        // Keeps one entry per losing value, at the best confidence it had
+        fn record_conflict(conflicts: &mut Vec<(String, serde_json::Value, f32)>, path: &str, value: &serde_json::Value, confidence: f32) {
+            match conflicts.iter_mut().find(|(p, v, _)| p == path && v == value) {
+                Some(entry) => entry.2 = entry.2.max(confidence),
+                None => conflicts.push((path.to_string(), value.clone(), confidence)),
+            }
+        }
+
+        // Deep merge JSON objects; when two leaves disagree the higher
+        // confidence wins, ties going to the detection merged last
+        fn deep_merge_json(
+            target: &mut serde_json::Value,
+            source: &serde_json::Value,
+            path: &str,
+            confidence: f32,
+            owners: &mut std::collections::HashMap<String, f32>,
+            conflicts: &mut Vec<(String, serde_json::Value, f32)>,
+        ) {
+            if let (Some(target_map), Some(source_map)) = (target.as_object_mut(), source.as_object()) {
+                for (key, value) in source_map {
+                    let child_path = format!("{}.{}", path, key);
+                    if let Some(existing) = target_map.get_mut(key) {
+                        deep_merge_json(existing, value, &child_path, confidence, owners, conflicts);
+                    } else {
+                        target_map.insert(key.clone(), value.clone());
+                        claim_json(value, &child_path, confidence, owners);
+                    }
+                }
+                return;
+            }
+            if source.is_null() {
+                return;
+            }
+            if target.is_null() || target == source {
+                *target = source.clone();
+                claim_json(source, path, confidence, owners);
+                return;
+            }
+
+            let nested = format!("{}.", path);
+            let held = owners
+                .iter()
+                .filter(|(p, _)| *p == path || p.starts_with(&nested))
+                .map(|(_, c)| *c)
+                .reduce(f32::max);
+            if held.is_none_or(|held| confidence >= held) {
+                if let Some(held) = held {
+                    record_conflict(conflicts, path, target, held);
                 }
+                conflicts.retain(|(p, v, _)| !(p == path && v == source));
+                owners.retain(|p, _| p != path && !p.starts_with(&nested));
+                *target = source.clone();
+                claim_json(source, path, confidence, owners);
+            } else {
+                record_conflict(conflicts, path, source, confidence);
             }
         }
 
@@ -403,12 +883,23 @@ fn generate_merge_impl(
                 }
             }
 
-            // Deep merge traits_patch instead of simple extend
-            for (key, value) in &detection.traits_patch {
+            // Deep merge traits_patch instead of simple extend, in key order
+            // so that conflict evidence does not depend on HashMap iteration
+            let mut traits_patch: Vec<_> = detection.traits_patch.iter().collect();
+            traits_patch.sort_by(|a, b| a.0.cmp(b.0));
+            for (key, value) in traits_patch {
                 if let Some(existing) = all_traits.get_mut(key) {
-                    deep_merge_json(existing, value);
+                    deep_merge_json(
+                        existing,
+                        value,
+                        key,
+                        detection.confidence,
+                        &mut trait_owners,
+                        &mut trait_conflicts,
+                    );
                 } else {
                     all_traits.insert(key.clone(), value.clone());
+                    claim_json(value, key, detection.confidence, &mut trait_owners);
                 }
             }
 
@@ -416,122 +907,136 @@ fn generate_merge_impl(
         }
     });
 
-    // Generate field-specific merging logic
+    if fields.any(|field| matches!(field.mapping_type, MappingType::Key(..))) {
+        merge_statements.push(quote! {
+            // Non-null value at a dotted key: a flat facet or trait key, or a
+            // path into the nested traits
+            fn lookup_key<'a>(
+                facets: &'a std::collections::HashMap<String, serde_json::Value>,
+                traits: &'a std::collections::HashMap<String, serde_json::Value>,
+                key: &str,
+            ) -> Option<&'a serde_json::Value> {
+                let nested = || {
+                    let mut segments = key.split('.');
+                    let root = traits.get(segments.next()?)?;
+                    segments.try_fold(root, |value, segment| value.get(segment))
+                };
+                facets
+                    .get(key)
+                    .or_else(|| traits.get(key))
+                    .or_else(nested)
+                    .filter(|value| !value.is_null())
+            }
+        });
+    }
+
+    quote! {
+        #(#merge_statements)*
+    }
+}
+
+/// Merge each field into the place `target` gives for its member
+fn generate_field_merges(
+    fields: &[FieldMapping],
+    target: impl Fn(&syn::Member) -> proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
+    let mut merge_statements = Vec::new();
     for field in fields {
-        let field_name = syn::Ident::new(&field.field_name, proc_macro2::Span::call_site());
+        let field_name = target(&field.member);
 
         match (&field.mapping_type, &field.field_type) {
             (MappingType::Contexts, FieldType::Contexts) => {
                 merge_statements.push(quote! {
                     // Merge contexts - set boolean fields based on presence in all_contexts
-                    self.#field_name.agent = all_contexts.contains(&"agent".to_string());
-                    self.#field_name.ide = all_contexts.contains(&"ide".to_string());
-                    self.#field_name.ci = all_contexts.contains(&"ci".to_string());
-                    self.#field_name.container = all_contexts.contains(&"container".to_string());
-                    self.#field_name.remote = all_contexts.contains(&"remote".to_string());
+                    #field_name.agent = all_contexts.contains(&"agent".to_string());
+                    #field_name.ide = all_contexts.contains(&"ide".to_string());
+                    #field_name.ci = all_contexts.contains(&"ci".to_string());
+                    #field_name.container = all_contexts.contains(&"container".to_string());
+                    #field_name.remote = all_contexts.contains(&"remote".to_string());
                 });
             }
             (MappingType::Contexts, FieldType::SimpleBool) => {
                 // Handle simple boolean contexts field
                 merge_statements.push(quote! {
                     // Merge contexts - set boolean to true if any contexts exist
-                    self.#field_name = !all_contexts.is_empty();
+                    #field_name = !all_contexts.is_empty();
                 });
             }
-            (MappingType::Contexts, FieldType::Other) => {
+            (MappingType::Contexts, FieldType::Vec) => {
                 // Handle Vec<String> contexts field
                 merge_statements.push(quote! {
                     // Merge contexts - extend Vec<String> with all contexts
-                    self.#field_name.extend(all_contexts);
+                    #field_name.extend(all_contexts);
                 });
             }
             (MappingType::Facets, FieldType::Facets) => {
                 merge_statements.push(quote! {
                     // Merge facets - extract string values from all_facets
                     if let Some(value) = all_facets.get("agent_id").and_then(|v| v.as_str()) {
-                        self.#field_name.agent_id = Some(value.to_string());
+                        #field_name.agent_id = Some(value.to_string());
                     }
                     if let Some(value) = all_facets.get("ide_id").and_then(|v| v.as_str()) {
-                        self.#field_name.ide_id = Some(value.to_string());
+                        #field_name.ide_id = Some(value.to_string());
                     }
                     if let Some(value) = all_facets.get("ci_id").and_then(|v| v.as_str()) {
-                        self.#field_name.ci_id = Some(value.to_string());
+                        #field_name.ci_id = Some(value.to_string());
                     }
                     if let Some(value) = all_facets.get("container_id").and_then(|v| v.as_str()) {
-                        self.#field_name.container_id = Some(value.to_string());
+                        #field_name.container_id = Some(value.to_string());
                     }
                     if let Some(value) = all_facets.get("host").and_then(|v| v.as_str()) {
-                        self.#field_name.host = Some(value.to_string());
+                        #field_name.host = Some(value.to_string());
                     }
                     // Legacy CI facet handling removed - CI information now comes from declarative detection
                 });
             }
-            (MappingType::Traits, FieldType::Traits) => {
+            (MappingType::Traits, _) => {
                 merge_statements.push(quote! {
-                    // Merge traits - handle both legacy flat traits and TerminalTraits
-
-                    // Handle TerminalTraits structure
-                    if let Some(value) = all_traits.get("is_interactive").and_then(|v| v.as_bool()) {
-                        self.#field_name.interactive = value;
-                    }
-                    if let Some(value) = all_traits.get("is_tty_stdin").and_then(|v| v.as_bool()) {
-                        self.#field_name.stdin.tty = value;
-                    }
-                    if let Some(value) = all_traits.get("is_tty_stdout").and_then(|v| v.as_bool()) {
-                        self.#field_name.stdout.tty = value;
-                    }
-                    if let Some(value) = all_traits.get("is_tty_stderr").and_then(|v| v.as_bool()) {
-                        self.#field_name.stderr.tty = value;
-                    }
-                    if let Some(value) = all_traits.get("is_piped_stdin").and_then(|v| v.as_bool()) {
-                        self.#field_name.stdin.piped = value;
-                    }
-                    if let Some(value) = all_traits.get("is_piped_stdout").and_then(|v| v.as_bool()) {
-                        self.#field_name.stdout.piped = value;
+                    // Merge traits by dotted path into any MergeValue type.
+                    // Legacy flat keys go first, so the paths they stand for
+                    // win when both are given
+                    let mut trait_paths: Vec<(bool, &str, &serde_json::Value)> = all_traits
+                        .iter()
+                        .map(|(key, value)| match ::envsense_macros::MergeValue::alias(&#field_name, key) {
+                            Some(path) => (false, path, value),
+                            None => (true, key.as_str(), value),
+                        })
+                        .collect();
+                    trait_paths.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+                    for (_, path, value) in trait_paths {
+                        let segments: Vec<&str> = path.split('.').collect();
+                        ::envsense_macros::MergeValue::merge_path(&mut #field_name, &segments, value);
                     }
-                    if let Some(value) = all_traits.get("supports_hyperlinks").and_then(|v| v.as_bool()) {
-                        self.#field_name.supports_hyperlinks = value;
+                });
+            }
+            (MappingType::Evidence, FieldType::Vec) => {
+                merge_statements.push(quote! {
+                    // Merge evidence as-is, it already has the field's type
+                    for detection in detections {
+                        #field_name.extend(detection.evidence.iter().cloned());
                     }
-                    // Handle color level enum
-                    if let Some(color_level_str) = all_traits.get("color_level").and_then(|v| v.as_str()) {
-                        // Parse string to enum - this will work regardless of import context
-                        if let Ok(color_level) = serde_json::from_str::<serde_json::Value>(&format!("\"{}\"", color_level_str))
-                            .and_then(|v| serde_json::from_value(v)) {
-                            self.#field_name.color_level = color_level;
-                        }
+                    // Values that lost a trait conflict
+                    for (path, value, confidence) in trait_conflicts {
+                        #field_name.push(::envsense_macros::TraitConflict { path, value, confidence }.into());
                     }
-
-                    // Legacy flat traits support (for old Traits struct if still used)
-                    // These fields might not exist on TerminalTraits, so we ignore compilation errors
-                    // by using a more generic approach or conditional compilation
                 });
             }
-            (MappingType::Traits, FieldType::NestedTraits) => {
-                // New: Handle nested traits structure
-                let nested_merge = generate_nested_trait_merge(&field_name);
-                merge_statements.push(nested_merge);
-            }
-            (MappingType::Evidence, FieldType::Evidence) => {
+            (MappingType::Key(key, None), _) => {
+                // Any deserializable field type, e.g. Option<String> or bool
                 merge_statements.push(quote! {
-                    // Merge evidence - convert from serde_json::Value back to Evidence
-                    for detection in detections {
-                        for evidence_value in &detection.evidence {
-                            // Try to deserialize as Evidence - this will work regardless of import context
-                            if let Ok(evidence) = serde_json::from_value(evidence_value.clone()) {
-                                self.#field_name.push(evidence);
-                            }
+                    if let Some(value) = lookup_key(&all_facets, &all_traits, #key) {
+                        if let Ok(value) = serde_json::from_value(value.clone()) {
+                            #field_name = value;
                         }
                     }
                 });
             }
-            (MappingType::Facets, FieldType::OptionalString) => {
-                // Handle standalone optional string fields like host
-                let field_name_str = field_name.to_string();
+            (MappingType::Key(key, Some(strategy)), _) => {
+                merge_statements.push(generate_strategy_merge(&field_name, key, *strategy));
+            }
+            (MappingType::Flatten, _) => {
                 merge_statements.push(quote! {
-                    // Merge host field from facets
-                    if let Some(value) = all_facets.get(#field_name_str).and_then(|v| v.as_str()) {
-                        self.#field_name = Some(value.to_string());
-                    }
+                    #field_name.merge_detections(detections);
                 });
             }
             _ => {
@@ -539,10 +1044,5 @@ fn generate_merge_impl(
             }
         }
     }
-
-    quote! {
-        fn merge_detections(&mut self, detections: &[Detection]) {
-            #(#merge_statements)*
-        }
-    }
+    merge_statements
 }
@@ -3,17 +3,56 @@
 use std::collections::HashMap;
 
 /// Represents a single detection result from a detector
+///
+/// `E` is the evidence type. Structs deriving `DetectionMerger` merge the
+/// evidence of their `evidence: Vec<E>` field as-is, so passing the real
+/// evidence type avoids a JSON round-trip per entry.
 #[derive(Debug, Clone)]
-pub struct Detection {
+pub struct Detection<E = serde_json::Value> {
     pub contexts_add: Vec<String>,
     pub traits_patch: HashMap<String, serde_json::Value>,
     pub facets_patch: HashMap<String, serde_json::Value>,
-    pub evidence: Vec<serde_json::Value>, // Generic evidence for now
+    pub evidence: Vec<E>,
     pub confidence: f32,
 }
 
 /// Trait for types that can merge multiple detection results
-pub trait DetectionMerger {
+pub trait DetectionMerger<E = serde_json::Value> {
     /// Merge multiple detection results into this instance
-    fn merge_detections(&mut self, detections: &[Detection]);
+    fn merge_detections(&mut self, detections: &[Detection<E>]);
+}
+
+/// A trait value that lost to a more confident detection
+///
+/// Merging records one per rejected value in the evidence field, converted
+/// with `From<TraitConflict>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitConflict {
+    /// Dotted trait path, e.g. `agent.id`
+    pub path: String,
+    pub value: serde_json::Value,
+    /// Best confidence the rejected value had
+    pub confidence: f32,
+}
+
+impl TraitConflict {
+    /// The rejected value as text, strings without their quotes
+    pub fn value_string(&self) -> String {
+        match &self.value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl From<TraitConflict> for serde_json::Value {
+    fn from(conflict: TraitConflict) -> Self {
+        serde_json::json!({
+            "signal": "conflict",
+            "key": conflict.path,
+            "value": conflict.value_string(),
+            "supports": [],
+            "confidence": conflict.confidence,
+        })
+    }
 }
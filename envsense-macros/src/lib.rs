@@ -36,13 +36,139 @@
 //!
 //! # Field Mapping
 //!
-//! The macro automatically maps fields based on their names and types:
+//! Each field merges from a key, which defaults to the field name:
 //!
 //! - **`contexts`**: Maps to `contexts_add` from detections
-//! - **`facets`**: Maps to `facets_patch` from detections  
-//! - **`traits`**: Maps to `traits_patch` from detections
+//! - **`facets`**: Maps to `facets_patch` from detections
+//! - **`traits`**: Maps to `traits_patch` from detections, merged by dotted
+//!   path into any [`MergeValue`] type
 //! - **`evidence`**: Maps to `evidence` from detections
-//! - **Other fields**: Ignored (no mapping applied)
+//! - **Other fields**: Not merged, with a compile-time warning unless
+//!   marked `ignore`
+//!
+//! The `#[detection_merge(...)]` field attribute changes that:
+//!
+//! - **`key = "ci.branch"`**: Merges from the given key. Keys other than the
+//!   four above take a single facet or trait value, looked up as a flat key
+//!   and then as a path into the nested traits, and deserialize it into the
+//!   field's type
+//! - **`ignore`**: Leaves the field alone, whatever its name, and silences
+//!   the unmapped field warning (`skip` is accepted too)
+//! - **`flatten`**: Calls the field's own `merge_detections`, for nested
+//!   structs that derive `DetectionMerger` themselves
+//! - **`strategy = "..."`**: With a single-value `key`, picks among the
+//!   values set by each detection instead of using the merged value:
+//!   `first_wins`, `last_wins`, `highest_confidence` (ties go to the later
+//!   detection), or `append` to push every value onto a `Vec` field
+//!
+//! ```rust
+//! use envsense_macros::{Detection, DetectionMerger, DetectionMergerDerive};
+//! use serde_json::json;
+//! use std::collections::HashMap;
+//!
+//! #[derive(DetectionMergerDerive, Default)]
+//! pub struct Summary {
+//!     #[detection_merge(key = "ci.branch")]
+//!     pub branch: Option<String>,
+//!     #[detection_merge(key = "ci.branch", strategy = "append")]
+//!     pub branches: Vec<String>,
+//!     #[detection_merge(ignore)]
+//!     pub contexts: Vec<String>,
+//! }
+//!
+//! let mut summary = Summary::default();
+//! summary.merge_detections(&[Detection {
+//!     contexts_add: vec!["ci".to_string()],
+//!     traits_patch: HashMap::from([("ci".to_string(), json!({"branch": "main"}))]),
+//!     facets_patch: HashMap::new(),
+//!     evidence: vec![],
+//!     confidence: 1.0,
+//! }]);
+//! assert_eq!(summary.branch.as_deref(), Some("main"));
+//! assert_eq!(summary.branches, ["main"]);
+//! assert!(summary.contexts.is_empty());
+//! ```
+//!
+//! `#[detection_merge(strict)]` on the struct turns the unmapped field
+//! warning into an error, for mergers where a typo in a field name should
+//! fail the build.
+//!
+//! # Tuple Structs and Enums
+//!
+//! Tuple fields have no name to merge by, so they take a `key` or `flatten`;
+//! the only field of a newtype such as `struct Merged(EnvSense)` is
+//! flattened without one. Enum variants are picked by the detected contexts:
+//! the first variant whose `#[detection_merge(context = "...")]` was detected
+//! wins, else the one marked `#[detection_merge(fallback)]`, else the value is
+//! left alone. Merging into another variant than the current one starts it
+//! from default fields.
+//!
+//! ```rust
+//! use envsense_macros::{Detection, DetectionMerger, DetectionMergerDerive};
+//! use serde_json::json;
+//! use std::collections::HashMap;
+//!
+//! #[derive(DetectionMergerDerive, Debug, PartialEq)]
+//! pub enum Runtime {
+//!     #[detection_merge(context = "ci")]
+//!     Ci(#[detection_merge(key = "ci.branch")] Option<String>),
+//!     #[detection_merge(fallback)]
+//!     Local,
+//! }
+//!
+//! let mut runtime = Runtime::Local;
+//! runtime.merge_detections(&[Detection {
+//!     contexts_add: vec!["ci".to_string()],
+//!     traits_patch: HashMap::from([("ci".to_string(), json!({"branch": "main"}))]),
+//!     facets_patch: HashMap::new(),
+//!     evidence: vec![],
+//!     confidence: 1.0,
+//! }]);
+//! assert_eq!(runtime, Runtime::Ci(Some("main".to_string())));
+//! ```
+//!
+//! # Key Validation
+//!
+//! Keys that start with a trait group name, such as `ci.branch`, are checked
+//! at compile time against the struct's `traits` field type, or the type
+//! given with `#[detection_merge(schema = "crate::traits::NestedTraits")]`
+//! on the struct. A key naming no field of the group fails the build;
+//! facet keys such as `host` are not checked. Trait group aliases (below)
+//! are checked against the group too, and a path naming no field is a
+//! deprecation warning pointing at the alias.
+//!
+//! # Trait Groups
+//!
+//! Trait structs derive `MergeValueDerive`, which merges each field from the
+//! key of the same name, so a new group only needs the derive:
+//!
+//! ```rust
+//! use envsense_macros::MergeValueDerive;
+//!
+//! #[derive(MergeValueDerive, Default)]
+//! pub struct ShellTraits {
+//!     pub id: Option<String>,
+//!     #[detection_merge(ignore)]
+//!     pub cached: bool,
+//! }
+//!
+//! #[derive(MergeValueDerive, Default)]
+//! #[detection_merge(alias(shell_id = "shell.id"))] // legacy flat key
+//! pub struct Traits {
+//!     pub shell: ShellTraits,
+//! }
+//! ```
+//!
+//! A `BTreeMap<String, serde_json::Value>` field marked
+//! `#[detection_merge(extensions)]` takes every key no other field has, so
+//! traits a detector sets before the schema models them are kept rather
+//! than dropped. Merge keys below it are not checked.
+//!
+//! `bool`, `String` and `Option<T>` merge out of the box; enums implement
+//! [`MergeValue`] by hand, with a `SCHEMA` of
+//! `TraitSchema::leaf(ValueKind::Enum)`. The derived [`TraitSchema`] keeps
+//! each field's name, kind and doc comment summary, which is what the CLI
+//! field registry is built from.
 //!
 //! # Supported Types
 //!
@@ -50,9 +176,17 @@
 //!
 //! - **Boolean fields**: Direct assignment from detection values
 //! - **String fields**: Extraction and assignment from detection values
-//! - **Enum fields**: String-to-enum conversion (e.g., ColorLevel)
+//! - **Enum fields**: `FromStr` conversion (e.g., ColorLevel)
 //! - **Struct fields**: JSON deserialization (e.g., CiFacet)
-//! - **Collection fields**: Extend with detection values (e.g., Vec<Evidence>)
+//! - **Collection fields**: Extend with detection values (e.g., `Vec<Evidence>`)
+//!
+//! An `evidence: Vec<E>` field makes the struct implement
+//! `DetectionMerger<E>`, merging `Detection<E>` whose evidence is cloned in
+//! without conversion; trait conflicts are added through
+//! `From<TraitConflict>`. Without an evidence field the struct merges
+//! whatever evidence its `flatten`ed fields merge, or
+//! `Detection<serde_json::Value>` when it has none. A `flatten`ed field must
+//! otherwise merge the same evidence type as its parent.
 //!
 //! # Benefits
 //!
@@ -62,11 +196,13 @@
 //! - **Extensibility**: Easy to add new detector fields without manual merging code
 
 mod detection_merger; // Contains DetectionMerger trait and Detection struct
+mod merge_value;
 
-pub use detection_merger::{Detection, DetectionMerger};
+pub use detection_merger::{Detection, DetectionMerger, TraitConflict};
+#[doc(hidden)]
+pub use merge_value::KeyCheck;
+pub use merge_value::{MergeValue, TraitField, TraitSchema, ValueKind};
 
 // Re-export the derive macro
 pub use envsense_macros_impl::DetectionMerger as DetectionMergerDerive;
-
-// Re-export the attribute macro
-pub use envsense_macros_impl::detection_merge;
+pub use envsense_macros_impl::MergeValue as MergeValueDerive;
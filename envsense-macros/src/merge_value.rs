@@ -0,0 +1,347 @@
+//! Merging JSON trait patches into typed trait groups
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A trait value, or group of them, that detection patches merge into
+///
+/// Derive it with `MergeValueDerive` on trait group structs; each field is
+/// merged from the object key of the same name. Values of the wrong shape
+/// are ignored and leave the current value in place.
+pub trait MergeValue {
+    /// The shape of this value and its fields, for checking merge keys at
+    /// compile time and listing trait fields
+    const SCHEMA: TraitSchema;
+
+    /// Merge `value` into this one, returning whether anything was set
+    fn merge_value(&mut self, value: &Value) -> bool;
+
+    /// Merge `value` at the path below this value, e.g. `["stdin", "tty"]`
+    fn merge_path(&mut self, path: &[&str], value: &Value) -> bool {
+        path.is_empty() && self.merge_value(value)
+    }
+
+    /// Dotted path a legacy flat key stands for, e.g. `is_tty_stdin` for
+    /// `terminal.stdin.tty`
+    fn alias(&self, _key: &str) -> Option<&'static str> {
+        None
+    }
+}
+
+impl MergeValue for bool {
+    const SCHEMA: TraitSchema = TraitSchema::leaf(ValueKind::Bool);
+
+    fn merge_value(&mut self, value: &Value) -> bool {
+        value.as_bool().map(|b| *self = b).is_some()
+    }
+}
+
+impl MergeValue for String {
+    const SCHEMA: TraitSchema = TraitSchema::leaf(ValueKind::String);
+
+    fn merge_value(&mut self, value: &Value) -> bool {
+        value.as_str().map(|s| *self = s.to_string()).is_some()
+    }
+}
+
+impl<T: MergeValue + Default> MergeValue for Option<T> {
+    const SCHEMA: TraitSchema = TraitSchema {
+        optional: true,
+        ..T::SCHEMA
+    };
+
+    fn merge_value(&mut self, value: &Value) -> bool {
+        match self {
+            Some(inner) => inner.merge_value(value),
+            None => {
+                let mut inner = T::default();
+                let merged = inner.merge_value(value);
+                if merged {
+                    *self = Some(inner);
+                }
+                merged
+            }
+        }
+    }
+
+    fn merge_path(&mut self, path: &[&str], value: &Value) -> bool {
+        match self {
+            Some(inner) => inner.merge_path(path, value),
+            None => {
+                let mut inner = T::default();
+                let merged = inner.merge_path(path, value);
+                if merged {
+                    *self = Some(inner);
+                }
+                merged
+            }
+        }
+    }
+}
+
+/// An open-ended map of traits the schema does not model
+///
+/// Objects merge key by key, recursively; any other value replaces the one
+/// at its key.
+impl MergeValue for BTreeMap<String, Value> {
+    const SCHEMA: TraitSchema = TraitSchema::leaf(ValueKind::Map);
+
+    fn merge_value(&mut self, value: &Value) -> bool {
+        let Some(object) = value.as_object() else {
+            return false;
+        };
+        let mut merged = false;
+        for (key, value) in object {
+            merged |= self.merge_path(&[key.as_str()], value);
+        }
+        merged
+    }
+
+    fn merge_path(&mut self, path: &[&str], value: &Value) -> bool {
+        let [key, rest @ ..] = path else {
+            return self.merge_value(value);
+        };
+        if value.is_null() {
+            return false;
+        }
+        let nested = rest.iter().rev().fold(value.clone(), |value, key| {
+            Value::Object(serde_json::Map::from_iter([(key.to_string(), value)]))
+        });
+        match self.get_mut(*key) {
+            Some(existing) => merge_json(existing, nested),
+            None => {
+                self.insert(key.to_string(), nested);
+            }
+        }
+        true
+    }
+}
+
+fn merge_json(target: &mut Value, source: Value) {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => {
+            for (key, value) in source {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (_, Value::Null) => {}
+        (target, source) => *target = source,
+    }
+}
+
+/// What kind of value a trait holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Bool,
+    String,
+    /// A string from a fixed set, such as a color level
+    Enum,
+    /// A trait group with fields of its own
+    Group,
+    /// A map with any keys below it, such as extension traits
+    Map,
+}
+
+/// Shape of a trait value, with the fields of groups nested like the group
+/// itself
+#[derive(Debug, Clone, Copy)]
+pub struct TraitSchema {
+    pub kind: ValueKind,
+    /// Whether the value is an `Option` and may be missing
+    pub optional: bool,
+    pub fields: &'static [TraitField],
+}
+
+/// A named field of a trait group
+#[derive(Debug, Clone, Copy)]
+pub struct TraitField {
+    pub name: &'static str,
+    /// First paragraph of the field's doc comment
+    pub doc: &'static str,
+    pub schema: &'static TraitSchema,
+}
+
+impl TraitSchema {
+    /// A single required value with no fields
+    pub const fn leaf(kind: ValueKind) -> Self {
+        Self {
+            kind,
+            optional: false,
+            fields: &[],
+        }
+    }
+
+    /// A required group of `fields`
+    pub const fn group(fields: &'static [TraitField]) -> Self {
+        Self {
+            kind: ValueKind::Group,
+            optional: false,
+            fields,
+        }
+    }
+
+    /// Whether the first segment of the dotted `path` is a field
+    pub const fn has_root(&self, path: &str) -> bool {
+        let path = path.as_bytes();
+        self.field(path, 0, segment_end(path, 0)).is_some()
+    }
+
+    /// Whether every segment of the dotted `path` names a field below the
+    /// previous one; maps take any path
+    pub const fn resolves(&self, path: &str) -> bool {
+        let path = path.as_bytes();
+        let mut schema = self;
+        let mut start = 0;
+        loop {
+            if matches!(schema.kind, ValueKind::Map) {
+                return true;
+            }
+            let end = segment_end(path, start);
+            match schema.field(path, start, end) {
+                Some(next) => schema = next,
+                None => return false,
+            }
+            if end == path.len() {
+                return true;
+            }
+            start = end + 1;
+        }
+    }
+
+    /// Dotted paths of every leaf value, sorted
+    pub fn leaf_paths(&self) -> Vec<String> {
+        self.leaves().into_iter().map(|(path, _)| path).collect()
+    }
+
+    /// Every leaf value with its dotted path, sorted by path
+    pub fn leaves(&self) -> Vec<(String, &'static TraitField)> {
+        let mut leaves = Vec::new();
+        for field in self.fields {
+            if field.schema.fields.is_empty() {
+                leaves.push((field.name.to_string(), field));
+            } else {
+                leaves.extend(
+                    field
+                        .schema
+                        .leaves()
+                        .into_iter()
+                        .map(|(path, leaf)| (format!("{}.{}", field.name, path), leaf)),
+                );
+            }
+        }
+        leaves.sort_by(|a, b| a.0.cmp(&b.0));
+        leaves
+    }
+
+    const fn field(&self, path: &[u8], start: usize, end: usize) -> Option<&'static TraitSchema> {
+        let mut i = 0;
+        while i < self.fields.len() {
+            let field = &self.fields[i];
+            if bytes_eq(field.name.as_bytes(), path, start, end) {
+                return Some(field.schema);
+            }
+            i += 1;
+        }
+        None
+    }
+}
+
+/// Compile-time check that a dotted trait path resolves, for generated code
+///
+/// `KeyCheck::<{ SCHEMA.resolves(path) }>.check()` is a deprecation warning
+/// when the path names no field: stable Rust lets macros raise no other
+/// kind of warning.
+#[doc(hidden)]
+pub struct KeyCheck<const RESOLVES: bool>;
+
+impl KeyCheck<true> {
+    pub const fn check(self) {}
+}
+
+impl KeyCheck<false> {
+    #[deprecated(note = "this trait path names no field of its trait group")]
+    pub const fn check(self) {}
+}
+
+const fn segment_end(path: &[u8], start: usize) -> usize {
+    let mut end = start;
+    while end < path.len() && path[end] != b'.' {
+        end += 1;
+    }
+    end
+}
+
+const fn bytes_eq(name: &[u8], path: &[u8], start: usize, end: usize) -> bool {
+    if name.len() != end - start {
+        return false;
+    }
+    let mut i = 0;
+    while i < name.len() {
+        if name[i] != path[start + i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOL: TraitSchema = TraitSchema::leaf(ValueKind::Bool);
+
+    const fn field(name: &'static str, schema: &'static TraitSchema) -> TraitField {
+        TraitField {
+            name,
+            doc: "",
+            schema,
+        }
+    }
+
+    const STREAM: TraitSchema = TraitSchema::group(&[field("tty", &BOOL), field("piped", &BOOL)]);
+    const TERMINAL: TraitSchema =
+        TraitSchema::group(&[field("interactive", &BOOL), field("stdin", &STREAM)]);
+    const TRAITS: TraitSchema = TraitSchema::group(&[field("terminal", &TERMINAL)]);
+    const MAP: TraitSchema = TraitSchema::leaf(ValueKind::Map);
+    const EXTENDED: TraitSchema =
+        TraitSchema::group(&[field("terminal", &TERMINAL), field("extra", &MAP)]);
+
+    #[test]
+    fn resolves_dotted_paths() {
+        assert!(TRAITS.resolves("terminal"));
+        assert!(TRAITS.resolves("terminal.stdin.tty"));
+        assert!(!TRAITS.resolves("terminal.stdin.tt"));
+        assert!(!TRAITS.resolves("terminal.interactive.x"));
+        assert!(TRAITS.has_root("terminal.nope"));
+        assert!(!TRAITS.has_root("host"));
+        assert!(EXTENDED.resolves("extra.vendor.region"));
+        assert!(!EXTENDED.resolves("terminal.extra"));
+        assert_eq!(
+            TRAITS.leaf_paths(),
+            [
+                "terminal.interactive",
+                "terminal.stdin.piped",
+                "terminal.stdin.tty"
+            ]
+        );
+    }
+
+    #[test]
+    fn maps_merge_objects_by_key() {
+        let mut map = BTreeMap::new();
+        assert!(map.merge_value(&serde_json::json!({"vendor": {"region": "us"}})));
+        assert!(map.merge_path(&["vendor", "zone"], &serde_json::json!("a")));
+        assert!(map.merge_path(&["sandbox"], &serde_json::json!(true)));
+        assert!(!map.merge_path(&["gone"], &Value::Null));
+        assert_eq!(
+            serde_json::to_value(&map).unwrap(),
+            serde_json::json!({"sandbox": true, "vendor": {"region": "us", "zone": "a"}})
+        );
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
@@ -22,7 +23,8 @@ impl EnvReader for StdEnv {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct AgentInfo {
     pub is_agent: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -55,7 +57,8 @@ impl Default for AgentInfo {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct ContextFacets {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub editor: Option<String>,
@@ -65,7 +68,8 @@ pub struct ContextFacets {
     pub host_confidence: f32,
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct AgentDetection {
     pub agent: AgentInfo,
     pub facets: ContextFacets,
@@ -0,0 +1,57 @@
+//! Pure-ASCII human output for `--ascii`.
+//!
+//! Legacy terminals and some log systems mangle UTF-8, so with `--ascii` every
+//! human-readable line goes through [`transliterate`] before it is printed.
+
+use std::borrow::Cow;
+
+/// Replace every non-ASCII character in `text`
+///
+/// Common punctuation such as arrows, bullets and dashes gets an ASCII
+/// look-alike; anything else becomes a `\u{...}` escape so no information is
+/// lost.
+pub fn transliterate(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            c if c.is_ascii() => out.push(c),
+            '→' | '⇒' => out.push_str("->"),
+            '←' | '⇐' => out.push_str("<-"),
+            '↑' => out.push('^'),
+            '↓' => out.push('v'),
+            '▸' | '▶' | '›' => out.push('>'),
+            '▾' | '▼' => out.push('v'),
+            '•' | '·' | '◦' => out.push('*'),
+            '…' => out.push_str("..."),
+            '–' | '—' | '─' => out.push('-'),
+            '‘' | '’' => out.push('\''),
+            '“' | '”' => out.push('"'),
+            '✓' | '✔' => out.push('+'),
+            '✗' | '✘' => out.push('x'),
+            '\u{a0}' => out.push(' '),
+            c => out.push_str(&format!("\\u{{{:x}}}", u32::from(c))),
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_ascii_untouched() {
+        assert!(matches!(transliterate("ci.id = github"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn replaces_punctuation_and_escapes_the_rest() {
+        assert_eq!(transliterate("a → b … c — d"), "a -> b ... c - d");
+        assert_eq!(transliterate("feature/🚀"), "feature/\\u{1f680}");
+        assert_eq!(transliterate("中"), "\\u{4e2d}");
+        assert!(transliterate("⚙ ci 🤖 cursor").is_ascii());
+    }
+}
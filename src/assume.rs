@@ -0,0 +1,313 @@
+//! Forced detection results from the `assume` config setting.
+//!
+//! Each entry is either a bare context name (`ci`) or a trait assignment
+//! (`ci.id=github_actions`). Assumptions are applied after detection and
+//! always win over what the detectors found.
+
+use crate::check::{FieldRegistry, FieldType};
+use crate::schema::{EnvSense, Evidence, Signal};
+use crate::traits::NestedTraits;
+use serde_json::Value;
+
+/// Validated `assume` entries together with where they came from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Assumptions {
+    contexts: Vec<String>,
+    traits: Vec<(String, Value)>,
+    /// Recorded as the evidence key, usually the config file path
+    source: String,
+}
+
+impl Assumptions {
+    /// Parse and validate entries against the field registry
+    pub fn parse(entries: &[String], source: impl Into<String>) -> Result<Self, String> {
+        let registry = FieldRegistry::new();
+        let mut assumptions = Self {
+            source: source.into(),
+            ..Self::default()
+        };
+
+        for entry in entries {
+            let entry = entry.trim();
+            match entry.split_once('=') {
+                None => {
+                    if !registry.has_context(entry) {
+                        return Err(format!(
+                            "unknown context '{}' in assume (available: {})",
+                            entry,
+                            registry.get_contexts().join(", ")
+                        ));
+                    }
+                    assumptions.contexts.push(entry.to_string());
+                }
+                Some((path, raw)) => {
+                    let path = path.trim();
+                    let info = registry
+                        .resolve_field(&path.split('.').map(str::to_string).collect::<Vec<_>>())
+                        .ok_or_else(|| format!("unknown field '{}' in assume", path))?;
+                    let value = trait_value(path, &info.field_type, raw.trim())?;
+                    assumptions.traits.push((path.to_string(), value));
+                }
+            }
+        }
+
+        // Catch values the field type alone cannot (e.g. an unknown color level)
+        assumptions.apply(&mut EnvSense::default())?;
+        Ok(assumptions)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty() && self.traits.is_empty()
+    }
+
+    /// Force the assumed contexts and traits onto a detection result
+    pub fn apply(&self, env: &mut EnvSense) -> Result<(), String> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        // Assumptions are asserted by the user, so they are fully trusted
+        for context in &self.contexts {
+            add_context(env, context);
+        }
+
+        set_traits(env, &self.traits)?;
+
+        let entries: Vec<String> = self
+            .contexts
+            .iter()
+            .cloned()
+            .chain(self.traits.iter().map(|(path, value)| match value {
+                Value::String(s) => format!("{}={}", path, s),
+                other => format!("{}={}", path, other),
+            }))
+            .collect();
+        let supports: Vec<String> = self
+            .contexts
+            .iter()
+            .cloned()
+            .chain(self.traits.iter().map(|(path, _)| path.clone()))
+            .collect();
+        env.evidence.push(Evidence {
+            signal: Signal::Fs,
+            key: self.source.clone(),
+            value: Some(format!("assume {}", entries.join(", "))),
+            supports,
+            confidence: 1.0,
+            sources: Vec::new(),
+        });
+        Ok(())
+    }
+}
+
+/// Contexts forced on or off for a single run by `--assume` and `--deny`
+///
+/// Applied to the final result, after `assume` and `ENVSENSE_OVERRIDE_*`.
+/// Each context is recorded as [`Signal::Flag`] evidence keyed by its flag,
+/// and predicate reasons mention it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ForcedContexts {
+    assumed: Vec<String>,
+    denied: Vec<String>,
+}
+
+impl ForcedContexts {
+    /// Validate the context names; none may be both assumed and denied
+    pub fn new(assumed: &[String], denied: &[String]) -> Result<Self, String> {
+        let registry = crate::check::registry();
+        for context in assumed.iter().chain(denied) {
+            if !registry.has_context(context) {
+                return Err(format!(
+                    "unknown context '{}' (available: {})",
+                    context,
+                    registry.get_contexts().join(", ")
+                ));
+            }
+        }
+        if let Some(context) = assumed.iter().find(|c| denied.contains(c)) {
+            return Err(format!("context '{}' is both assumed and denied", context));
+        }
+        Ok(Self {
+            assumed: assumed.to_vec(),
+            denied: denied.to_vec(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assumed.is_empty() && self.denied.is_empty()
+    }
+
+    pub fn apply(&self, env: &mut EnvSense) -> Result<(), String> {
+        for context in &self.assumed {
+            add_context(env, context);
+            env.evidence.push(flag_evidence("--assume", context));
+        }
+        for context in &self.denied {
+            remove_context(env, context)?;
+            env.evidence.push(flag_evidence("--deny", context));
+        }
+        Ok(())
+    }
+}
+
+fn flag_evidence(flag: &str, context: &str) -> Evidence {
+    Evidence {
+        signal: Signal::Flag,
+        key: flag.to_string(),
+        value: Some(context.to_string()),
+        supports: vec![context.to_string()],
+        confidence: 1.0,
+        sources: Vec::new(),
+    }
+}
+
+/// Parse `raw` as the value of the trait at `path`
+///
+/// Only checks the JSON type; [`set_traits`] catches values the type alone
+/// cannot, such as an unknown color level.
+pub(crate) fn trait_value(path: &str, field_type: &FieldType, raw: &str) -> Result<Value, String> {
+    match field_type {
+        FieldType::Boolean => raw
+            .parse::<bool>()
+            .map(Value::Bool)
+            .map_err(|_| format!("'{}' expects true or false, got '{}'", path, raw)),
+        FieldType::StreamInfo => Err(format!(
+            "'{}' cannot be assumed directly; assume its fields instead",
+            path
+        )),
+        _ => Ok(Value::String(raw.to_string())),
+    }
+}
+
+/// Set each dotted trait path in `traits` on `env`, fully trusted
+pub(crate) fn set_traits(env: &mut EnvSense, traits: &[(String, Value)]) -> Result<(), String> {
+    if traits.is_empty() {
+        return Ok(());
+    }
+    let mut nested = serde_json::to_value(&env.traits).map_err(|e| e.to_string())?;
+    for (path, value) in traits {
+        // Unset optional fields are omitted when serialized, so insert
+        // into the parent object rather than replacing in place
+        let (parent, field) = path.rsplit_once('.').unwrap_or(("", path));
+        let pointer = format!("/{}", parent.replace('.', "/"));
+        match nested.pointer_mut(&pointer).and_then(Value::as_object_mut) {
+            Some(object) => {
+                object.insert(field.to_string(), value.clone());
+            }
+            None => return Err(format!("cannot assume '{}'", path)),
+        }
+    }
+    env.traits = serde_json::from_value(nested)
+        .map_err(|e| format!("invalid assumed trait value: {}", e))?;
+    for (path, _) in traits {
+        env.confidence.traits.insert(path.clone(), 1.0);
+    }
+    Ok(())
+}
+
+/// Add `context` to `env` with full confidence, keeping contexts sorted
+pub(crate) fn add_context(env: &mut EnvSense, context: &str) {
+    if let Err(at) = env.contexts.binary_search_by(|c| c.as_str().cmp(context)) {
+        env.contexts.insert(at, context.to_string());
+    }
+    env.confidence.contexts.insert(context.to_string(), 1.0);
+}
+
+/// Remove `context` from `env`, resetting the traits it owns to their defaults
+pub(crate) fn remove_context(env: &mut EnvSense, context: &str) -> Result<(), String> {
+    env.contexts.retain(|c| c != context);
+    env.confidence.contexts.remove(context);
+
+    let defaults = serde_json::to_value(NestedTraits::default()).map_err(|e| e.to_string())?;
+    if let Some(default) = defaults.get(context) {
+        let mut nested = serde_json::to_value(&env.traits).map_err(|e| e.to_string())?;
+        nested[context] = default.clone();
+        env.traits = serde_json::from_value(nested).map_err(|e| e.to_string())?;
+        let prefix = format!("{}.", context);
+        env.confidence
+            .traits
+            .retain(|path, _| !path.starts_with(&prefix));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn forces_contexts_and_traits() {
+        let assumptions = Assumptions::parse(
+            &entries(&["ci", "ci.id=github_actions", "terminal.interactive=false"]),
+            ".envsense.toml",
+        )
+        .unwrap();
+
+        let mut env = EnvSense::default();
+        env.traits.terminal.interactive = true;
+        assumptions.apply(&mut env).unwrap();
+
+        assert_eq!(env.contexts, ["ci"]);
+        assert_eq!(env.traits.ci.id.as_deref(), Some("github_actions"));
+        assert!(!env.traits.terminal.interactive);
+        assert_eq!(env.confidence.contexts["ci"], 1.0);
+        assert_eq!(env.confidence.traits["terminal.interactive"], 1.0);
+        let evidence = env.evidence.last().unwrap();
+        assert_eq!(evidence.key, ".envsense.toml");
+        assert_eq!(evidence.signal, Signal::Fs);
+    }
+
+    #[test]
+    fn rejects_unknown_entries() {
+        assert!(Assumptions::parse(&entries(&["cloud"]), "x").is_err());
+        assert!(Assumptions::parse(&entries(&["ci.color=red"]), "x").is_err());
+        assert!(Assumptions::parse(&entries(&["terminal.interactive=maybe"]), "x").is_err());
+        assert!(Assumptions::parse(&entries(&["terminal.stdout=true"]), "x").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_color_level() {
+        assert!(Assumptions::parse(&entries(&["terminal.color_level=millions"]), "x").is_err());
+        assert!(Assumptions::parse(&entries(&["terminal.color_level=ansi256"]), "x").is_ok());
+    }
+
+    #[test]
+    fn forced_contexts_override_detection() {
+        let forced = ForcedContexts::new(&entries(&["ci"]), &entries(&["agent"])).unwrap();
+        let mut env = EnvSense {
+            contexts: entries(&["agent"]),
+            ..EnvSense::default()
+        };
+        env.traits.agent.id = Some("cursor".to_string());
+        forced.apply(&mut env).unwrap();
+
+        assert_eq!(env.contexts, ["ci"]);
+        assert_eq!(env.traits.agent.id, None);
+        let flags: Vec<_> = env
+            .evidence
+            .iter()
+            .map(|e| (e.signal.clone(), e.key.as_str(), e.value.as_deref()))
+            .collect();
+        assert_eq!(
+            flags,
+            [
+                (Signal::Flag, "--assume", Some("ci")),
+                (Signal::Flag, "--deny", Some("agent")),
+            ]
+        );
+
+        assert!(ForcedContexts::new(&entries(&["cloud"]), &[]).is_err());
+        assert!(ForcedContexts::new(&entries(&["ci"]), &entries(&["ci"])).is_err());
+    }
+
+    #[test]
+    fn empty_assumptions_leave_result_untouched() {
+        let mut env = EnvSense::default();
+        Assumptions::default().apply(&mut env).unwrap();
+        assert_eq!(env, EnvSense::default());
+    }
+}
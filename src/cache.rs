@@ -0,0 +1,122 @@
+//! Memoized detection for long-running programs.
+//!
+//! Prompt daemons and language servers ask "where am I running?" far more
+//! often than the answer changes. [`CachedEnvSense`] runs the detectors once
+//! and serves that result until its time-to-live expires or it is
+//! [invalidated](CachedEnvSense::invalidate).
+
+use crate::detectors::EnvSnapshot;
+use crate::engine::DetectionEngine;
+use crate::schema::EnvSense;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// [`EnvSense`] detection cached for a fixed time-to-live
+///
+/// Each call to [`get`](Self::get) after the TTL has passed re-reads the
+/// current process environment and runs the engine again.
+///
+/// ```
+/// use envsense::prelude::*;
+/// use std::time::Duration;
+///
+/// let cached = CachedEnvSense::new(Duration::from_secs(30));
+/// let first = cached.get();
+/// // Served from the cache: no detector runs
+/// assert_eq!(cached.get(), first);
+///
+/// // Force the next call to detect again, e.g. after changing the environment
+/// cached.invalidate();
+/// let _fresh = cached.get();
+/// ```
+pub struct CachedEnvSense {
+    engine: DetectionEngine,
+    ttl: Duration,
+    entry: Mutex<Option<(Instant, EnvSense)>>,
+}
+
+impl CachedEnvSense {
+    /// Cache results of the built-in detectors for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_engine(DetectionEngine::with_default_detectors(), ttl)
+    }
+
+    /// Cache results of a custom engine for `ttl`
+    pub fn with_engine(engine: DetectionEngine, ttl: Duration) -> Self {
+        Self {
+            engine,
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// How long a detection result is reused
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The cached result, detecting again if it is missing or expired
+    pub fn get(&self) -> EnvSense {
+        let mut entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((detected_at, env)) = entry.as_ref()
+            && detected_at.elapsed() < self.ttl
+        {
+            return env.clone();
+        }
+        let env = self.engine.detect_from_snapshot(&EnvSnapshot::current());
+        *entry = Some((Instant::now(), env.clone()));
+        env
+    }
+
+    /// Drop the cached result so the next [`get`](Self::get) detects again
+    pub fn invalidate(&self) {
+        *self.entry.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::{Detection, Detector};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingDetector(Arc<AtomicUsize>);
+
+    impl Detector for CountingDetector {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn detect(&self, _snap: &EnvSnapshot) -> Detection {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Detection::default()
+        }
+    }
+
+    fn counting_cache(ttl: Duration) -> (CachedEnvSense, Arc<AtomicUsize>) {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let engine = DetectionEngine::new().register(CountingDetector(runs.clone()));
+        (CachedEnvSense::with_engine(engine, ttl), runs)
+    }
+
+    #[test]
+    fn reuses_result_within_ttl() {
+        let (cached, runs) = counting_cache(Duration::from_secs(3600));
+        cached.get();
+        cached.get();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        cached.invalidate();
+        cached.get();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn detects_again_after_ttl() {
+        let (cached, runs) = counting_cache(Duration::ZERO);
+        cached.get();
+        cached.get();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+}
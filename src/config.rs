@@ -1,5 +1,26 @@
+use crate::prompt::{PromptStyle, Segment};
+use crate::redact::{DEFAULT_PATTERNS, Redactor};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Per-project config file, searched for from the working directory upward
+pub const PROJECT_CONFIG_FILE: &str = ".envsense.toml";
+
+/// Environment variables that override configuration file values
+///
+/// Precedence is: CLI flags > these variables > config file > defaults.
+pub const CONFIG_ENV_VARS: &[&str] = &[
+    "ENVSENSE_FORMAT",
+    "ENVSENSE_COLOR",
+    "ENVSENSE_RAINBOW",
+    "ENVSENSE_CHECK_MODE",
+    "ENVSENSE_ONLY",
+    "ENVSENSE_DISABLE_DETECTORS",
+    "ENVSENSE_PLUGINS",
+    "ENVSENSE_DAEMON_SOCKET",
+    "ENVSENSE_CACHE",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CliConfig {
@@ -9,6 +30,24 @@ pub struct CliConfig {
     pub output_formatting: OutputFormattingConfig,
     #[serde(default)]
     pub validation: ValidationConfig,
+    #[serde(default)]
+    pub check: CheckConfig,
+    #[serde(default)]
+    pub detectors: DetectorsConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub prompt: PromptConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    /// Contexts (`ci`) or traits (`ci.id=github_actions`) to force
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assume: Vec<String>,
+    /// File the `assume` list was read from
+    #[serde(skip)]
+    pub assume_source: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +63,127 @@ pub struct OutputFormattingConfig {
     pub context_descriptions: bool,
     pub nested_display: bool,
     pub rainbow_colors: bool,
+    /// Default `info` output when neither `--json` nor `--raw` is given
+    pub format: OutputFormat,
+    /// Whether to emit ANSI colors
+    pub color: ColorMode,
+    /// Page human `info` output taller than the terminal through `$PAGER`
+    pub pager: bool,
+    /// Restrict human output to ASCII, like `--ascii`
+    pub ascii: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+    Raw,
+    Markdown,
+    Html,
+    Csv,
+    Tsv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_enum(s).ok_or_else(|| {
+            format!(
+                "unknown format '{}' (available: human, json, raw, markdown, html, csv, tsv)",
+                s
+            )
+        })
+    }
+}
+
+/// Output formats of `envsense check --format`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckFormat {
+    #[default]
+    Human,
+    Json,
+    /// One JSON object per predicate, then a summary line
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+impl std::str::FromStr for CheckFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_enum(s).ok_or_else(|| {
+            format!(
+                "unknown format '{}' (available: human, json, ndjson, csv, tsv)",
+                s
+            )
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color when stdout is a terminal and NO_COLOR is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Colors of human output: a named theme plus per-element overrides
+///
+/// Overrides are styles such as `"bold cyan"` or `"bright_red"`; see
+/// [`crate::theme::Style`].
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub name: ThemeName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    #[serde(rename = "true", skip_serializing_if = "Option::is_none")]
+    pub true_value: Option<String>,
+    #[serde(rename = "false", skip_serializing_if = "Option::is_none")]
+    pub false_value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dim: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Colors cycled through when printing `truecolor`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rainbow: Option<Vec<String>>,
+}
+
+/// Built-in color themes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// Cyan headings, green `true`, red `false`
+    #[default]
+    Default,
+    /// Bright variants for dark terminals
+    Bright,
+    /// Bold and dim only, no hues
+    Mono,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct CheckConfig {
+    /// Evaluation mode when neither `--any` nor `--all` is given
+    pub mode: CheckMode,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckMode {
+    #[default]
+    All,
+    Any,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +193,116 @@ pub struct ValidationConfig {
     pub allowed_characters: String,
 }
 
+/// Which detectors may run; names match `envsense detectors`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DetectorsConfig {
+    /// Run only these detectors (all when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only: Option<Vec<String>>,
+    /// Never run these detectors
+    pub disabled: Vec<String>,
+    /// Discover `envsense-detector-*` plugins on PATH; off unless enabled
+    pub plugins: bool,
+    /// Drop contexts detected with less confidence than this (0 to 1)
+    pub min_confidence: f32,
+    /// Which evidence values to hide
+    pub redact: RedactConfig,
+}
+
+impl Default for DetectorsConfig {
+    fn default() -> Self {
+        Self {
+            only: None,
+            disabled: Vec::new(),
+            plugins: false,
+            min_confidence: 0.0,
+            redact: RedactConfig::default(),
+        }
+    }
+}
+
+/// `[detectors.redact]`: evidence values to replace with `[REDACTED]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RedactConfig {
+    /// Variables whose name contains one of these are hidden
+    pub patterns: Vec<String>,
+    /// Variables never hidden, even when a pattern matches
+    pub allow: Vec<String>,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_PATTERNS.iter().map(|p| p.to_string()).collect(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+impl RedactConfig {
+    pub fn redactor(&self) -> Redactor {
+        Redactor::new(&self.patterns, &self.allow)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptConfig {
+    /// Output style when `--style` is not given
+    pub style: PromptStyle,
+    /// Segments to show, in order, when `--segments` is not given
+    pub segments: Vec<Segment>,
+    /// Drop trailing badges beyond this many terminal columns
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<usize>,
+    /// Replacement glyphs per segment; an empty string removes the glyph
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub glyphs: BTreeMap<Segment, String>,
+    /// Seconds a rendered prompt is reused for the same environment (0 disables)
+    pub cache_ttl: u64,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            style: PromptStyle::default(),
+            segments: Segment::ALL.to_vec(),
+            max_width: None,
+            glyphs: BTreeMap::new(),
+            cache_ttl: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Socket `envsense daemon` listens on (default: in the runtime directory)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket: Option<PathBuf>,
+}
+
+/// Detection results reused across invocations in the same environment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Cache detection results on disk (default: false)
+    pub enabled: bool,
+    /// Seconds a cached result is reused
+    pub ttl: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: 3600,
+        }
+    }
+}
+
 impl Default for ErrorHandlingConfig {
     fn default() -> Self {
         Self {
@@ -48,6 +318,10 @@ impl Default for OutputFormattingConfig {
             context_descriptions: true,
             nested_display: true,
             rainbow_colors: true,
+            format: OutputFormat::default(),
+            color: ColorMode::default(),
+            pager: true,
+            ascii: false,
         }
     }
 }
@@ -61,16 +335,111 @@ impl Default for ValidationConfig {
     }
 }
 
+/// Parse a lowercase enum value the same way the config file does
+fn parse_enum<T: serde::de::DeserializeOwned>(value: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(value.trim().to_lowercase())).ok()
+}
+
+fn read_table(path: &Path) -> Option<toml::Table> {
+    std::fs::read_to_string(path).ok()?.parse().ok()
+}
+
+/// Recursively merge `over` into `base`; non-table values in `over` replace
+fn merge_tables(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(over_table)) => {
+                merge_tables(base_table, over_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 impl CliConfig {
     pub fn load() -> Self {
-        // Try to load from config file, fallback to default
-        if let Some(config_path) = Self::config_file_path()
-            && let Ok(content) = std::fs::read_to_string(config_path)
-            && let Ok(config) = toml::from_str(&content)
-        {
-            return config;
+        let project = std::env::current_dir()
+            .ok()
+            .and_then(|dir| Self::find_project_file(&dir));
+        let mut config = Self::load_layers(project.as_deref(), Self::config_file_path().as_deref());
+        config.apply_env_overrides(|key| std::env::var(key).ok());
+        config
+    }
+
+    /// Find the nearest [`PROJECT_CONFIG_FILE`] in `start` or its ancestors
+    pub fn find_project_file(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Merge the project file under the user file
+    ///
+    /// Values set in the user file win; the project file fills in the rest.
+    /// Unreadable or invalid files are ignored, falling back to defaults.
+    pub fn load_layers(project: Option<&Path>, user: Option<&Path>) -> Self {
+        let project_table = project.and_then(read_table);
+        let user_table = user.and_then(read_table);
+
+        let assume_source = match (&user_table, &project_table) {
+            (Some(table), _) if table.contains_key("assume") => user.map(Path::to_path_buf),
+            (_, Some(table)) if table.contains_key("assume") => project.map(Path::to_path_buf),
+            _ => None,
+        };
+
+        let mut merged = project_table.unwrap_or_default();
+        if let Some(user_table) = user_table {
+            merge_tables(&mut merged, user_table);
+        }
+
+        let mut config: Self = toml::Value::Table(merged).try_into().unwrap_or_default();
+        config.assume_source = assume_source;
+        config
+    }
+
+    /// Apply [`CONFIG_ENV_VARS`] on top of the loaded configuration
+    ///
+    /// Invalid values are ignored, matching how config file errors are handled.
+    pub fn apply_env_overrides(&mut self, get: impl Fn(&str) -> Option<String>) {
+        if let Some(format) = get("ENVSENSE_FORMAT").and_then(|v| parse_enum(&v)) {
+            self.output_formatting.format = format;
+        }
+        if let Some(color) = get("ENVSENSE_COLOR").and_then(|v| parse_enum(&v)) {
+            self.output_formatting.color = color;
+        }
+        if let Some(rainbow) = get("ENVSENSE_RAINBOW").and_then(|v| v.parse::<bool>().ok()) {
+            self.output_formatting.rainbow_colors = rainbow;
+        }
+        if let Some(mode) = get("ENVSENSE_CHECK_MODE").and_then(|v| parse_enum(&v)) {
+            self.check.mode = mode;
+        }
+        if let Some(only) = get("ENVSENSE_ONLY") {
+            self.detectors.only = Some(parse_list(&only));
+        }
+        if let Some(disabled) = get("ENVSENSE_DISABLE_DETECTORS") {
+            self.detectors.disabled = parse_list(&disabled);
+        }
+        if let Some(plugins) = get("ENVSENSE_PLUGINS").and_then(|v| v.parse::<bool>().ok()) {
+            self.detectors.plugins = plugins;
+        }
+        if let Some(socket) = get("ENVSENSE_DAEMON_SOCKET").filter(|v| !v.is_empty()) {
+            self.daemon.socket = Some(PathBuf::from(socket));
+        }
+        if let Some(enabled) = get("ENVSENSE_CACHE").and_then(|v| v.parse::<bool>().ok()) {
+            self.cache.enabled = enabled;
         }
-        Self::default()
     }
 
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -98,6 +467,14 @@ impl CliConfig {
             path
         })
     }
+
+    /// Directory holding user-defined mapping files
+    pub fn mappings_dir() -> Option<PathBuf> {
+        Self::config_dir().map(|mut path| {
+            path.push("mappings");
+            path
+        })
+    }
 }
 
 #[cfg(test)]
@@ -112,8 +489,13 @@ mod tests {
         assert!(config.output_formatting.context_descriptions);
         assert!(config.output_formatting.nested_display);
         assert!(config.output_formatting.rainbow_colors);
+        assert!(config.output_formatting.pager);
+        assert!(!config.output_formatting.ascii);
         assert!(config.validation.validate_predicates);
         assert_eq!(config.validation.allowed_characters, "a-zA-Z0-9_.=-");
+        assert!(config.detectors.only.is_none());
+        assert!(config.detectors.disabled.is_empty());
+        assert!(!config.detectors.plugins);
     }
 
     #[test]
@@ -174,4 +556,192 @@ strict_mode = false
         assert!(config.output_formatting.context_descriptions);
         assert!(config.validation.validate_predicates);
     }
+
+    fn env(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let map: std::collections::HashMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| map.get(key).cloned()
+    }
+
+    #[test]
+    fn test_new_sections_deserialization() {
+        let toml_str = r#"
+[output_formatting]
+format = "json"
+color = "never"
+
+[check]
+mode = "any"
+"#;
+
+        let config: CliConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.output_formatting.format, OutputFormat::Json);
+        assert_eq!(config.output_formatting.color, ColorMode::Never);
+        assert_eq!(config.check.mode, CheckMode::Any);
+        // Untouched fields keep their defaults
+        assert!(config.output_formatting.rainbow_colors);
+    }
+
+    #[test]
+    fn test_env_overrides_take_precedence_over_file() {
+        let mut config: CliConfig = toml::from_str(
+            r#"
+[output_formatting]
+format = "json"
+rainbow_colors = true
+
+[check]
+mode = "all"
+
+[detectors]
+disabled = ["ci"]
+"#,
+        )
+        .unwrap();
+
+        config.apply_env_overrides(env(&[
+            ("ENVSENSE_FORMAT", "raw"),
+            ("ENVSENSE_COLOR", "Always"),
+            ("ENVSENSE_RAINBOW", "false"),
+            ("ENVSENSE_CHECK_MODE", "any"),
+            ("ENVSENSE_ONLY", "terminal, agent"),
+            ("ENVSENSE_DISABLE_DETECTORS", "ide"),
+            ("ENVSENSE_PLUGINS", "true"),
+            ("ENVSENSE_DAEMON_SOCKET", "/tmp/envsense-test.sock"),
+            ("ENVSENSE_CACHE", "true"),
+        ]));
+
+        assert_eq!(config.output_formatting.format, OutputFormat::Raw);
+        assert_eq!(config.output_formatting.color, ColorMode::Always);
+        assert!(!config.output_formatting.rainbow_colors);
+        assert_eq!(config.check.mode, CheckMode::Any);
+        assert_eq!(
+            config.detectors.only,
+            Some(vec!["terminal".to_string(), "agent".to_string()])
+        );
+        assert_eq!(config.detectors.disabled, vec!["ide".to_string()]);
+        assert!(config.detectors.plugins);
+        assert_eq!(
+            config.daemon.socket,
+            Some(PathBuf::from("/tmp/envsense-test.sock"))
+        );
+        assert!(config.cache.enabled);
+    }
+
+    #[test]
+    fn test_invalid_env_overrides_are_ignored() {
+        let mut config = CliConfig::default();
+        config.apply_env_overrides(env(&[
+            ("ENVSENSE_FORMAT", "yaml"),
+            ("ENVSENSE_COLOR", "sometimes"),
+            ("ENVSENSE_RAINBOW", "maybe"),
+            ("ENVSENSE_CHECK_MODE", "none"),
+        ]));
+
+        assert_eq!(config.output_formatting.format, OutputFormat::Human);
+        assert_eq!(config.output_formatting.color, ColorMode::Auto);
+        assert!(config.output_formatting.rainbow_colors);
+        assert_eq!(config.check.mode, CheckMode::All);
+    }
+
+    #[test]
+    fn test_project_file_merged_under_user_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let project = dir.path().join(PROJECT_CONFIG_FILE);
+        let user = dir.path().join("config.toml");
+        std::fs::write(
+            &project,
+            r#"
+assume = ["ci"]
+
+[output_formatting]
+format = "json"
+color = "never"
+
+[check]
+mode = "any"
+"#,
+        )
+        .unwrap();
+        std::fs::write(&user, "[output_formatting]\nformat = \"raw\"\n").unwrap();
+
+        let config = CliConfig::load_layers(Some(&project), Some(&user));
+        // User values win, project values fill in the rest
+        assert_eq!(config.output_formatting.format, OutputFormat::Raw);
+        assert_eq!(config.output_formatting.color, ColorMode::Never);
+        assert_eq!(config.check.mode, CheckMode::Any);
+        assert_eq!(config.assume, vec!["ci".to_string()]);
+        assert_eq!(config.assume_source.as_deref(), Some(project.as_path()));
+    }
+
+    #[test]
+    fn test_missing_layers_use_defaults() {
+        let config = CliConfig::load_layers(None, Some(Path::new("/nonexistent/config.toml")));
+        assert_eq!(config.output_formatting.format, OutputFormat::Human);
+        assert!(config.assume.is_empty());
+        assert!(config.assume_source.is_none());
+    }
+
+    #[test]
+    fn test_find_project_file_searches_upward() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let nested = dir.path().join("packages/app/src");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert!(CliConfig::find_project_file(&nested).is_none_or(|p| !p.starts_with(dir.path())));
+
+        let project = dir.path().join("packages").join(PROJECT_CONFIG_FILE);
+        std::fs::write(&project, "assume = [\"ci\"]\n").unwrap();
+        assert_eq!(CliConfig::find_project_file(&nested), Some(project));
+    }
+
+    #[test]
+    fn test_detectors_config_deserialization() {
+        let toml_str = r#"
+[detectors]
+only = ["terminal", "agent"]
+disabled = ["agent"]
+"#;
+
+        let config: CliConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.detectors.only,
+            Some(vec!["terminal".to_string(), "agent".to_string()])
+        );
+        assert_eq!(config.detectors.disabled, vec!["agent".to_string()]);
+    }
+
+    #[test]
+    fn test_redact_config_deserialization() {
+        let config: CliConfig = toml::from_str("").unwrap();
+        assert!(config.detectors.redact.redactor().is_secret("GITHUB_TOKEN"));
+
+        let toml_str = r#"
+[detectors.redact]
+patterns = ["token", "internal"]
+allow = ["CI_JOB_TOKEN"]
+"#;
+        let config: CliConfig = toml::from_str(toml_str).unwrap();
+        let redactor = config.detectors.redact.redactor();
+        assert!(redactor.is_secret("INTERNAL_HOST"));
+        assert!(!redactor.is_secret("CI_JOB_TOKEN"));
+        assert!(!redactor.is_secret("OPENAI_API_KEY"));
+    }
+
+    #[test]
+    fn test_prompt_config_deserialization() {
+        let config: CliConfig =
+            toml::from_str("[prompt]\nsegments = [\"agent\", \"ci\"]\n").unwrap();
+        assert_eq!(config.prompt.style, PromptStyle::Starship);
+        assert_eq!(config.prompt.segments, vec![Segment::Agent, Segment::Ci]);
+
+        assert_eq!(CliConfig::default().prompt.segments, Segment::ALL);
+        assert!(toml::from_str::<CliConfig>("[prompt]\nsegments = [\"shell\"]\n").is_err());
+
+        let config: CliConfig =
+            toml::from_str("[prompt.glyphs]\nagent = \"A\"\nci = \"\"\n").unwrap();
+        assert_eq!(config.prompt.glyphs[&Segment::Agent], "A");
+        assert_eq!(config.prompt.glyphs[&Segment::Ci], "");
+    }
 }
@@ -0,0 +1,329 @@
+//! Long-running detection daemon for `envsense daemon`.
+//!
+//! Clients send their own environment over a unix socket, so answers are
+//! always about the caller rather than the daemon process. Results are cached
+//! per distinct environment; a cache hit skips detection (and any plugins)
+//! entirely, which keeps prompt integrations well under a millisecond.
+//!
+//! The protocol is one JSON request line per connection, answered by one JSON
+//! response line:
+//!
+//! ```json
+//! {"method": "detect", "snapshot": {...}, "detectors": {...}}
+//! {"status": "detected", "env": {...}}
+//! ```
+
+use crate::config::DetectorsConfig;
+use crate::detectors::EnvSnapshot;
+use crate::engine::DetectionEngine;
+use crate::schema::EnvSense;
+use crate::snapshot::CapturedSnapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long either side waits on a connection before giving up
+pub const DAEMON_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Distinct environments remembered before the cache is cleared
+const CACHE_CAPACITY: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DaemonError {
+    #[error("daemon socket '{0}': {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("a daemon is already listening on '{0}'")]
+    AlreadyRunning(PathBuf),
+    #[error("invalid daemon message: {0}")]
+    Protocol(String),
+    #[error("daemon error: {0}")]
+    Remote(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    /// Detect the environment described by `snapshot`
+    ///
+    /// `detectors` is the caller's detector configuration; the daemon refuses
+    /// to answer if its own differs, since the result would not match.
+    /// `host_fs` lets detectors read the daemon's filesystem, which is the
+    /// client's too when the snapshot is of the client's own process.
+    Detect {
+        snapshot: CapturedSnapshot,
+        detectors: DetectorsConfig,
+        #[serde(default)]
+        host_fs: bool,
+    },
+    /// Forget all cached results
+    Refresh,
+    /// Shut the daemon down
+    Stop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DaemonResponse {
+    Detected { env: Box<EnvSense> },
+    Ok,
+    Error { message: String },
+}
+
+/// Default socket location: the user runtime directory, else the cache directory
+pub fn default_socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .map(|dir| dir.join("envsense.sock"))
+        .or_else(|| dirs::cache_dir().map(|dir| dir.join("envsense").join("daemon.sock")))
+}
+
+/// Build the request for detecting `snap` through the daemon
+///
+/// Unlike `envsense capture`, values are sent unscrubbed: the socket is
+/// private to the user and detection must see exactly what it would locally.
+pub fn detect_request(snap: &EnvSnapshot, detectors: &DetectorsConfig) -> DaemonRequest {
+    let mut snapshot = CapturedSnapshot::from_env_snapshot(snap);
+    snapshot.env = snap.env().iter().collect();
+    DaemonRequest::Detect {
+        snapshot,
+        detectors: detectors.clone(),
+        host_fs: snap.reads_host_fs(),
+    }
+}
+
+/// Detection state held by a running daemon
+pub struct Daemon {
+    engine: DetectionEngine,
+    detectors: DetectorsConfig,
+    cache: HashMap<String, EnvSense>,
+}
+
+impl Daemon {
+    /// `detectors` must be the configuration `engine` was built from
+    pub fn new(engine: DetectionEngine, detectors: DetectorsConfig) -> Self {
+        Self {
+            engine,
+            detectors,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Answer one request; the second value is true when the daemon should stop
+    pub fn handle(&mut self, request: DaemonRequest) -> (DaemonResponse, bool) {
+        match request {
+            DaemonRequest::Detect {
+                snapshot,
+                detectors,
+                host_fs,
+            } => {
+                if detectors != self.detectors {
+                    let message = "detector configuration differs from the daemon's".to_string();
+                    return (DaemonResponse::Error { message }, false);
+                }
+                // The env map is ordered, so equal snapshots serialize identically
+                let key = serde_json::to_string(&(&snapshot, host_fs)).unwrap_or_default();
+                if let Some(env) = self.cache.get(&key) {
+                    return (
+                        DaemonResponse::Detected {
+                            env: Box::new(env.clone()),
+                        },
+                        false,
+                    );
+                }
+                if self.cache.len() >= CACHE_CAPACITY {
+                    self.cache.clear();
+                }
+                let env = self
+                    .engine
+                    .detect_from_snapshot(&snapshot.to_env_snapshot().with_host_fs(host_fs));
+                self.cache.insert(key, env.clone());
+                (DaemonResponse::Detected { env: Box::new(env) }, false)
+            }
+            DaemonRequest::Refresh => {
+                self.cache.clear();
+                (DaemonResponse::Ok, false)
+            }
+            DaemonRequest::Stop => (DaemonResponse::Ok, true),
+        }
+    }
+
+    /// Listen on `socket` until a stop request arrives
+    ///
+    /// A stale socket left by a crashed daemon is replaced; a live one is an
+    /// error. The socket file is removed on shutdown.
+    pub fn serve(mut self, socket: &Path) -> Result<(), DaemonError> {
+        let io_err = |e| DaemonError::Io(socket.to_path_buf(), e);
+
+        if socket.exists() {
+            if UnixStream::connect(socket).is_ok() {
+                return Err(DaemonError::AlreadyRunning(socket.to_path_buf()));
+            }
+            std::fs::remove_file(socket).map_err(io_err)?;
+        }
+        if let Some(parent) = socket.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        let listener = UnixListener::bind(socket).map_err(io_err)?;
+        std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600)).map_err(io_err)?;
+
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            // A misbehaving client only loses its own connection
+            if let Ok(true) = self.handle_connection(stream) {
+                break;
+            }
+        }
+
+        std::fs::remove_file(socket).map_err(io_err)
+    }
+
+    fn handle_connection(&mut self, stream: UnixStream) -> std::io::Result<bool> {
+        stream.set_read_timeout(Some(DAEMON_TIMEOUT))?;
+        stream.set_write_timeout(Some(DAEMON_TIMEOUT))?;
+
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+        let (response, stop) = match serde_json::from_str(&line) {
+            Ok(request) => self.handle(request),
+            Err(e) => {
+                let message = format!("invalid request: {}", e);
+                (DaemonResponse::Error { message }, false)
+            }
+        };
+
+        let mut output = serde_json::to_vec(&response).map_err(std::io::Error::other)?;
+        output.push(b'\n');
+        (&stream).write_all(&output)?;
+        Ok(stop)
+    }
+}
+
+/// Send one request to the daemon listening on `socket`
+pub fn send(socket: &Path, request: &DaemonRequest) -> Result<DaemonResponse, DaemonError> {
+    let io_err = |e| DaemonError::Io(socket.to_path_buf(), e);
+
+    let stream = UnixStream::connect(socket).map_err(io_err)?;
+    stream
+        .set_read_timeout(Some(DAEMON_TIMEOUT))
+        .map_err(io_err)?;
+    stream
+        .set_write_timeout(Some(DAEMON_TIMEOUT))
+        .map_err(io_err)?;
+
+    let mut input =
+        serde_json::to_vec(request).map_err(|e| DaemonError::Protocol(e.to_string()))?;
+    input.push(b'\n');
+    (&stream).write_all(&input).map_err(io_err)?;
+
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .map_err(io_err)?;
+    serde_json::from_str(&line).map_err(|e| DaemonError::Protocol(e.to_string()))
+}
+
+/// Detect `snap` through the daemon listening on `socket`
+pub fn detect(
+    socket: &Path,
+    snap: &EnvSnapshot,
+    detectors: &DetectorsConfig,
+) -> Result<EnvSense, DaemonError> {
+    match send(socket, &detect_request(snap, detectors))? {
+        DaemonResponse::Detected { env } => Ok(*env),
+        DaemonResponse::Error { message } => Err(DaemonError::Remote(message)),
+        DaemonResponse::Ok => Err(DaemonError::Protocol("expected a detection".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::test_utils::create_env_snapshot;
+    use tempfile::TempDir;
+
+    fn daemon() -> Daemon {
+        Daemon::new(
+            DetectionEngine::with_default_detectors(),
+            DetectorsConfig::default(),
+        )
+    }
+
+    fn detected(response: DaemonResponse) -> EnvSense {
+        match response {
+            DaemonResponse::Detected { env } => *env,
+            other => panic!("expected detection, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn detects_the_client_environment() {
+        let mut daemon = daemon();
+        let snap = create_env_snapshot(vec![("GITHUB_ACTIONS", "true")]);
+        let request = detect_request(&snap, &DetectorsConfig::default());
+
+        let (response, stop) = daemon.handle(request.clone());
+        assert!(!stop);
+        let env = detected(response);
+        assert!(env.contexts.contains(&"ci".to_string()));
+        assert_eq!(
+            env,
+            DetectionEngine::with_default_detectors().detect_from_snapshot(&snap)
+        );
+
+        // Served from the cache the second time
+        assert_eq!(daemon.cache.len(), 1);
+        assert_eq!(detected(daemon.handle(request).0), env);
+        assert_eq!(daemon.cache.len(), 1);
+
+        daemon.handle(DaemonRequest::Refresh);
+        assert!(daemon.cache.is_empty());
+    }
+
+    #[test]
+    fn refuses_different_detector_configuration() {
+        let mut daemon = daemon();
+        let detectors = DetectorsConfig {
+            disabled: vec!["ci".to_string()],
+            ..DetectorsConfig::default()
+        };
+        let request = detect_request(&create_env_snapshot(vec![]), &detectors);
+        assert!(matches!(
+            daemon.handle(request).0,
+            DaemonResponse::Error { .. }
+        ));
+    }
+
+    #[test]
+    fn serves_requests_over_socket() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("envsense.sock");
+        let server = {
+            let socket = socket.clone();
+            std::thread::spawn(move || daemon().serve(&socket))
+        };
+        while UnixStream::connect(&socket).is_err() {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let snap = create_env_snapshot(vec![("TERM_PROGRAM", "vscode")]);
+        let env = detect(&socket, &snap, &DetectorsConfig::default()).unwrap();
+        assert_eq!(env.traits.ide.id.as_deref(), Some("vscode"));
+
+        assert!(matches!(
+            daemon().serve(&socket),
+            Err(DaemonError::AlreadyRunning(_))
+        ));
+
+        assert_eq!(
+            send(&socket, &DaemonRequest::Stop).unwrap(),
+            DaemonResponse::Ok
+        );
+        server.join().unwrap().unwrap();
+        assert!(!socket.exists());
+    }
+}
@@ -0,0 +1,198 @@
+//! Warnings for deprecated predicates, fields and flags.
+//!
+//! Renames and removals go through [`DEPRECATIONS`] first: for at least one
+//! minor release the old spelling keeps working and a warning names its
+//! replacement, on stderr and in the `warnings` array of JSON output.
+
+use serde::{Deserialize, Serialize};
+
+/// What kind of input is deprecated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DeprecationKind {
+    /// A `check` predicate field path, e.g. `agent.id`
+    Predicate,
+    /// A key or dotted path accepted by `info --fields`
+    Field,
+    /// A command-line flag, without the leading dashes
+    Flag,
+}
+
+impl std::fmt::Display for DeprecationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Predicate => "predicate",
+            Self::Field => "field",
+            Self::Flag => "flag",
+        })
+    }
+}
+
+/// A deprecated name and what replaces it
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+    pub kind: DeprecationKind,
+    pub name: &'static str,
+    pub replacement: &'static str,
+    /// Release that deprecated the name
+    pub since: &'static str,
+}
+
+/// Everything currently deprecated
+pub const DEPRECATIONS: &[Deprecation] = &[Deprecation {
+    kind: DeprecationKind::Field,
+    name: "facets",
+    replacement: "traits",
+    since: "0.3.0",
+}];
+
+/// A deprecated name that was used
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Warning {
+    pub kind: DeprecationKind,
+    pub message: String,
+    /// The name as it was used
+    pub name: String,
+    pub replacement: String,
+    pub since: String,
+}
+
+/// Collects warnings for one command run
+#[derive(Debug)]
+pub struct Warnings {
+    table: &'static [Deprecation],
+    disabled: bool,
+    warnings: Vec<Warning>,
+}
+
+impl Warnings {
+    /// Check against [`DEPRECATIONS`]; nothing is collected when `disabled`
+    pub fn new(disabled: bool) -> Self {
+        Self::with_table(DEPRECATIONS, disabled)
+    }
+
+    pub fn with_table(table: &'static [Deprecation], disabled: bool) -> Self {
+        Self {
+            table,
+            disabled,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Record a warning if `name` or one of its parent paths is deprecated
+    ///
+    /// Returns whether a warning was recorded. Each name warns at most once.
+    pub fn check(&mut self, kind: DeprecationKind, name: &str) -> bool {
+        if self.disabled
+            || self
+                .warnings
+                .iter()
+                .any(|w| w.kind == kind && w.name == name)
+        {
+            return false;
+        }
+        let Some(deprecation) = self
+            .table
+            .iter()
+            .find(|d| d.kind == kind && is_path_prefix(d.name, name))
+        else {
+            return false;
+        };
+        let replacement = format!(
+            "{}{}",
+            deprecation.replacement,
+            &name[deprecation.name.len()..]
+        );
+        self.warnings.push(Warning {
+            kind,
+            name: name.to_string(),
+            message: format!(
+                "{} '{}' is deprecated since {}; use '{}' instead",
+                kind, name, deprecation.since, replacement
+            ),
+            replacement,
+            since: deprecation.since.to_string(),
+        });
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Print every warning to stderr
+    pub fn emit(&self) {
+        for warning in &self.warnings {
+            eprintln!("warning: {}", warning.message);
+        }
+    }
+}
+
+/// Whether `path` is `prefix` or a dotted path below it
+fn is_path_prefix(prefix: &str, path: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &[Deprecation] = &[
+        Deprecation {
+            kind: DeprecationKind::Predicate,
+            name: "ci.is_pr",
+            replacement: "ci.pull_request",
+            since: "0.4.0",
+        },
+        Deprecation {
+            kind: DeprecationKind::Field,
+            name: "facets",
+            replacement: "traits",
+            since: "0.3.0",
+        },
+    ];
+
+    #[test]
+    fn warns_once_per_deprecated_name() {
+        let mut warnings = Warnings::with_table(TABLE, false);
+        assert!(warnings.check(DeprecationKind::Predicate, "ci.is_pr"));
+        assert!(!warnings.check(DeprecationKind::Predicate, "ci.is_pr"));
+        assert!(!warnings.check(DeprecationKind::Predicate, "ci.id"));
+        assert!(!warnings.check(DeprecationKind::Field, "ci.is_pr"));
+
+        assert_eq!(
+            warnings.as_slice(),
+            [Warning {
+                kind: DeprecationKind::Predicate,
+                name: "ci.is_pr".to_string(),
+                replacement: "ci.pull_request".to_string(),
+                since: "0.4.0".to_string(),
+                message:
+                    "predicate 'ci.is_pr' is deprecated since 0.4.0; use 'ci.pull_request' instead"
+                        .to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn matches_paths_below_a_deprecated_name() {
+        let mut warnings = Warnings::with_table(TABLE, false);
+        assert!(warnings.check(DeprecationKind::Field, "facets.ci_id"));
+        assert!(!warnings.check(DeprecationKind::Field, "facetsx"));
+        assert_eq!(warnings.as_slice()[0].replacement, "traits.ci_id");
+    }
+
+    #[test]
+    fn disabled_collects_nothing() {
+        let mut warnings = Warnings::with_table(TABLE, true);
+        assert!(!warnings.check(DeprecationKind::Field, "facets"));
+        assert!(warnings.is_empty());
+    }
+}
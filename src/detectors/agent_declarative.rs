@@ -1,6 +1,9 @@
 use crate::detectors::env_mapping::get_agent_mappings;
-use crate::detectors::utils::check_generic_overrides;
-use crate::detectors::{Detection, Detector, EnvSnapshot};
+use crate::detectors::utils::{
+    add_mapping_contexts, add_mapping_values, check_generic_overrides, log_checked_mappings,
+};
+use crate::detectors::{Detection, Detector, DetectorMetadata, EnvSnapshot};
+use crate::logging::{trace_event, trace_span};
 use crate::schema::Evidence;
 use crate::traits::AgentTraits;
 use serde_json::json;
@@ -26,25 +29,43 @@ impl DeclarativeAgentDetector {
         } else {
             // Use declarative mappings for agent detection
             let agent_mappings = get_agent_mappings();
+            trace_span!(
+                "mappings",
+                context = "agent",
+                mappings = agent_mappings.len()
+            );
+            log_checked_mappings("agent", &agent_mappings, snap.env());
 
             // Find the highest confidence matching agent
             for mapping in &agent_mappings {
                 // Only consider mappings that add agent context
                 if mapping.contexts.contains(&"agent".to_string())
-                    && mapping.matches(&snap.env_vars)
+                    && mapping.matches(snap.env())
                     && mapping.confidence > confidence
                 {
                     agent_id = Some(mapping.id.clone());
                     confidence = mapping.confidence;
+                    crate::log_event!(
+                        Debug,
+                        "mapping.selected",
+                        context = "agent",
+                        id = mapping.id,
+                        confidence = confidence,
+                    );
+                    trace_event!(id = %mapping.id, confidence, "mapping.selected");
 
                     // Add evidence for this detection using helper methods
-                    for (key, value) in mapping.get_evidence(&snap.env_vars) {
+                    for (key, value) in mapping.get_evidence(snap.env()) {
                         let evidence_item = if let Some(val) = value {
                             Evidence::agent_detection(key, val)
                         } else {
                             Evidence::env_presence(key).with_supports(vec!["agent.id".into()])
                         };
-                        evidence.push(evidence_item.with_confidence(mapping.confidence));
+                        evidence.push(
+                            evidence_item
+                                .with_confidence(mapping.confidence)
+                                .with_mapping(&mapping.id),
+                        );
                     }
 
                     break; // Take the first (highest confidence) match
@@ -58,7 +79,15 @@ impl DeclarativeAgentDetector {
 
 impl Detector for DeclarativeAgentDetector {
     fn name(&self) -> &'static str {
-        "declarative_agent"
+        "agent"
+    }
+
+    fn metadata(&self) -> DetectorMetadata {
+        DetectorMetadata {
+            description: "Coding agents (Cursor, Claude Code, Aider, ...)",
+            contexts: vec!["agent"],
+            traits: vec!["agent.id", "agent.version"],
+        }
     }
 
     fn detect(&self, snap: &EnvSnapshot) -> Detection {
@@ -67,13 +96,14 @@ impl Detector for DeclarativeAgentDetector {
         let (agent_id, confidence, evidence) = self.detect_environments(snap);
 
         // Add agent detection
-        if let Some(agent) = agent_id {
+        if let Some(agent) = &agent_id {
             detection.contexts_add.push("agent".to_string());
             detection.confidence = confidence;
 
             // Create nested AgentTraits object
             let agent_traits = AgentTraits {
                 id: Some(agent.clone()),
+                ..Default::default()
             };
 
             // Insert as nested object under "agent" key
@@ -91,6 +121,17 @@ impl Detector for DeclarativeAgentDetector {
         // Add all evidence
         detection.evidence = evidence;
 
+        if let Some(agent) = &agent_id {
+            let mappings = get_agent_mappings();
+            if let Some(mapping) = mappings
+                .iter()
+                .find(|m| &m.id == agent && m.matches(snap.env()))
+            {
+                add_mapping_contexts(&mut detection, mapping, "agent");
+                add_mapping_values(&mut detection, mapping, snap.env(), "agent");
+            }
+        }
+
         detection
     }
 }
@@ -240,10 +281,10 @@ mod tests {
         assert!(!detection.contexts_add.contains(&"agent".to_string()));
 
         // Should not have agent traits object
-        assert!(detection.traits_patch.get("agent").is_none());
+        assert!(!detection.traits_patch.contains_key("agent"));
 
         // Should not have legacy facet either
-        assert!(detection.facets_patch.get("agent_id").is_none());
+        assert!(!detection.facets_patch.contains_key("agent_id"));
     }
 
     #[test]
@@ -283,10 +324,10 @@ mod tests {
         assert!(!detection.contexts_add.contains(&"agent".to_string()));
 
         // Should not have agent traits object since no agent detected
-        assert!(detection.traits_patch.get("agent").is_none());
+        assert!(!detection.traits_patch.contains_key("agent"));
 
         // Should not have any agent facets
-        assert!(detection.facets_patch.get("agent_id").is_none());
+        assert!(!detection.facets_patch.contains_key("agent_id"));
     }
 
     // =============================================================================
@@ -417,10 +458,10 @@ mod tests {
         assert!(!detection.contexts_add.contains(&"agent".to_string()));
 
         // Should not have agent traits object
-        assert!(detection.traits_patch.get("agent").is_none());
+        assert!(!detection.traits_patch.contains_key("agent"));
 
         // Should not have agent_id in facets
-        assert!(detection.facets_patch.get("agent_id").is_none());
+        assert!(!detection.facets_patch.contains_key("agent_id"));
 
         // Should have no evidence supporting agent.id
         let agent_evidence: Vec<_> = detection
@@ -499,7 +540,7 @@ mod tests {
         // Some agents might have multiple environment variables
         let snapshot = create_env_snapshot(vec![
             ("CURSOR_AGENT", "1"),
-            ("CURSOR_VERSION", "0.1.0"), // Additional env var that shouldn't affect detection
+            ("CURSOR_VERSION", "0.1.0"), // Doesn't affect detection, only agent.version
         ]);
 
         let detection = detector.detect(&snapshot);
@@ -511,8 +552,12 @@ mod tests {
         let agent_traits: AgentTraits = serde_json::from_value(agent_traits_value.clone()).unwrap();
         assert_eq!(agent_traits.id, Some("cursor".to_string()));
 
-        // Should still have exactly one traits_patch entry
-        assert_eq!(detection.traits_patch.len(), 1);
+        // The version comes from the mapping's value mappings
+        assert_eq!(
+            detection.traits_patch.get("agent.version"),
+            Some(&json!("0.1.0"))
+        );
+        assert_eq!(detection.traits_patch.len(), 2);
     }
 
     #[test]
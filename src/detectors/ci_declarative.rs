@@ -1,7 +1,7 @@
 use crate::detectors::declarative::DeclarativeDetector;
 use crate::detectors::env_mapping::get_ci_mappings;
 use crate::detectors::utils::SelectionStrategy;
-use crate::detectors::{Detection, Detector, EnvSnapshot};
+use crate::detectors::{Detection, Detector, DetectorMetadata, EnvSnapshot};
 use serde_json::json;
 
 pub struct DeclarativeCiDetector;
@@ -44,7 +44,15 @@ impl DeclarativeCiDetector {
 
 impl Detector for DeclarativeCiDetector {
     fn name(&self) -> &'static str {
-        "ci-declarative"
+        "ci"
+    }
+
+    fn metadata(&self) -> DetectorMetadata {
+        DetectorMetadata {
+            description: "CI providers (GitHub Actions, GitLab CI, ...)",
+            contexts: vec!["ci"],
+            traits: vec!["ci.id", "ci.vendor", "ci.name", "ci.is_pr", "ci.branch"],
+        }
     }
 
     fn detect(&self, snap: &EnvSnapshot) -> Detection {
@@ -55,11 +63,6 @@ impl Detector for DeclarativeCiDetector {
             .get("ci_id")
             .and_then(|v| v.as_str().map(|s| s.to_string()))
         {
-            // Set is_ci trait to true
-            detection
-                .traits_patch
-                .insert("is_ci".to_string(), json!(true));
-
             // Create CI facet with is_ci: true
             let mut ci_facet = serde_json::Map::new();
             ci_facet.insert("is_ci".to_string(), json!(true));
@@ -92,26 +95,6 @@ impl Detector for DeclarativeCiDetector {
                 .traits_patch
                 .insert("ci.name".to_string(), json!(ci_name));
 
-            // Keep legacy flat traits for backward compatibility
-            detection
-                .traits_patch
-                .insert("ci_vendor".to_string(), json!(id));
-            detection
-                .traits_patch
-                .insert("ci_name".to_string(), json!(ci_name));
-
-            // Process declarative value mappings
-            let mappings = Self::get_mappings();
-            for mapping in &mappings {
-                if mapping.matches(&snap.env_vars) {
-                    let extracted_values = mapping.extract_values(&snap.env_vars);
-                    for (key, value) in extracted_values {
-                        detection.traits_patch.insert(key, value);
-                    }
-                    break; // Use the first matching mapping
-                }
-            }
-
             detection
                 .facets_patch
                 .insert("ci".to_string(), json!(ci_facet));
@@ -205,7 +188,10 @@ mod tests {
         let detection = detector.detect(&snapshot);
 
         assert_eq!(detection.contexts_add, vec!["ci"]);
-        assert_eq!(detection.traits_patch.get("is_pr").unwrap(), &json!(true));
+        assert_eq!(
+            detection.traits_patch.get("ci.is_pr").unwrap(),
+            &json!(true)
+        );
     }
 
     #[test]
@@ -220,7 +206,7 @@ mod tests {
 
         assert_eq!(detection.contexts_add, vec!["ci"]);
         assert_eq!(
-            detection.traits_patch.get("branch").unwrap(),
+            detection.traits_patch.get("ci.branch").unwrap(),
             &json!("main")
         );
     }
@@ -262,7 +248,7 @@ mod tests {
 
         // Should not detect as CI despite GITHUB_ACTIONS being present
         assert!(!detection.contexts_add.contains(&"ci".to_string()));
-        assert!(detection.facets_patch.get("ci_id").is_none());
+        assert!(!detection.facets_patch.contains_key("ci_id"));
     }
 
     #[test]
@@ -277,6 +263,6 @@ mod tests {
 
         // Should not detect as CI despite GITHUB_ACTIONS being present
         assert!(!detection.contexts_add.contains(&"ci".to_string()));
-        assert!(detection.facets_patch.get("ci_id").is_none());
+        assert!(!detection.facets_patch.contains_key("ci_id"));
     }
 }
@@ -0,0 +1,439 @@
+use crate::detectors::{
+    Detection, Detector, DetectorMetadata, EnvSnapshot,
+    confidence::{HIGH, MEDIUM},
+};
+use crate::schema::Evidence;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Detects containers from the variables and files runtimes leave behind
+///
+/// Podman, LXC and systemd-nspawn export `container`; Docker and Podman
+/// create `/.dockerenv` and `/run/.containerenv`; the cgroup and mount
+/// tables name the container ID. Kubernetes pods are recognized by
+/// `KUBERNETES_SERVICE_HOST`. `ENVSENSE_CONTAINER` forces a runtime, or
+/// turns detection off with `none`.
+///
+/// Files are only read for snapshots that allow it (see
+/// [`EnvSnapshot::reads_host_fs`]), so tests and replays depend on their
+/// variables alone.
+pub struct ContainerDetector {
+    root: Option<PathBuf>,
+}
+
+impl ContainerDetector {
+    pub fn new() -> Self {
+        Self { root: None }
+    }
+
+    /// Read marker files below `root` instead of `/`, whatever the snapshot
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(root.into()),
+        }
+    }
+
+    /// Where to look for marker files, if anywhere
+    fn root(&self, snap: &EnvSnapshot) -> Option<PathBuf> {
+        match &self.root {
+            Some(root) => Some(root.clone()),
+            None => snap.reads_host_fs().then(|| PathBuf::from("/")),
+        }
+    }
+}
+
+/// Container traits found so far, the first source of each winning
+#[derive(Default)]
+struct Found {
+    runtime: Option<String>,
+    image: Option<String>,
+    id: Option<String>,
+    confidence: f32,
+    evidence: Vec<Evidence>,
+}
+
+impl Found {
+    fn set(&mut self, field: &str, value: &str, evidence: Evidence, confidence: f32) {
+        let slot = match field {
+            "runtime" => &mut self.runtime,
+            "image" => &mut self.image,
+            _ => &mut self.id,
+        };
+        if slot.is_some() || value.is_empty() {
+            return;
+        }
+        *slot = Some(value.to_string());
+        self.confidence = self.confidence.max(confidence);
+        let mut supports = vec![format!("container.{}", field)];
+        if field == "runtime" {
+            supports.insert(0, "container".to_string());
+        }
+        self.evidence.push(evidence.with_supports(supports));
+    }
+
+    /// Podman's `/run/.containerenv`, which holds `key="value"` lines when
+    /// the container was not started with a minimal environment
+    fn containerenv(&mut self, root: &Path) {
+        const PATH: &str = "/run/.containerenv";
+        let Ok(contents) = std::fs::read_to_string(root.join(&PATH[1..])) else {
+            return;
+        };
+        let field = |key: &str| {
+            contents.lines().find_map(|line| {
+                let value = line.strip_prefix(key)?.strip_prefix('=')?;
+                Some(value.trim_matches('"').to_string())
+            })
+        };
+        // engine="podman-5.0.3", or buildah's own
+        let runtime = field("engine")
+            .and_then(|engine| engine.split('-').next().map(str::to_string))
+            .filter(|engine| !engine.is_empty())
+            .unwrap_or_else(|| "podman".to_string());
+        self.set(
+            "runtime",
+            &runtime,
+            Evidence::file_marker(PATH, None),
+            MEDIUM,
+        );
+        for key in ["image", "id"] {
+            if let Some(value) = field(key) {
+                let evidence = Evidence::file_marker(PATH, Some(format!("{key}={value}")));
+                self.set(key, &value, evidence, MEDIUM);
+            }
+        }
+    }
+
+    fn dockerenv(&mut self, root: &Path) {
+        if root.join(".dockerenv").exists() {
+            let evidence = Evidence::file_marker("/.dockerenv", None);
+            self.set("runtime", "docker", evidence, MEDIUM);
+        }
+    }
+
+    /// Container IDs in the cgroup paths, under cgroup v1 or a cgroup v2
+    /// hierarchy without its own namespace
+    fn cgroup(&mut self, root: &Path) {
+        const PATH: &str = "/proc/self/cgroup";
+        let Ok(contents) = std::fs::read_to_string(root.join(&PATH[1..])) else {
+            return;
+        };
+        // hierarchy-ID:controllers:path
+        for path in contents
+            .lines()
+            .filter_map(|line| line.splitn(3, ':').nth(2))
+        {
+            let Some((runtime, id)) = parse_cgroup(path) else {
+                continue;
+            };
+            let evidence = || Evidence::file_marker(PATH, Some(path.to_string()));
+            if let Some(runtime) = runtime {
+                self.set("runtime", runtime, evidence(), MEDIUM);
+            }
+            self.set("id", id, evidence(), MEDIUM);
+            return;
+        }
+    }
+
+    /// Container IDs in the mount table, which Docker and Podman bind
+    /// `/etc/hostname` from even when the cgroup path is hidden
+    fn mountinfo(&mut self, root: &Path) {
+        const PATH: &str = "/proc/self/mountinfo";
+        let Ok(contents) = std::fs::read_to_string(root.join(&PATH[1..])) else {
+            return;
+        };
+        for (runtime, marker) in [
+            ("docker", "/docker/containers/"),
+            ("podman", "/overlay-containers/"),
+        ] {
+            let id = contents.lines().find_map(|line| {
+                let (_, rest) = line.split_once(marker)?;
+                let id = rest.split('/').next()?;
+                is_container_id(id).then_some(id)
+            });
+            if let Some(id) = id {
+                let evidence = Evidence::file_marker(PATH, Some(format!("{marker}{id}")));
+                self.set("runtime", runtime, evidence.clone(), MEDIUM);
+                self.set("id", id, evidence, MEDIUM);
+                return;
+            }
+        }
+    }
+}
+
+/// The runtime and ID named by one cgroup path, e.g.
+/// `/docker/<id>` or `/kubepods.slice/.../cri-containerd-<id>.scope`
+fn parse_cgroup(path: &str) -> Option<(Option<&'static str>, &str)> {
+    let id = path.rsplit('/').find_map(|segment| {
+        let segment = segment.strip_suffix(".scope").unwrap_or(segment);
+        let id = segment.rsplit('-').next()?;
+        is_container_id(id).then_some(id)
+    })?;
+    let runtime = if path.contains("kubepods") {
+        Some("kubernetes")
+    } else if path.contains("docker") {
+        Some("docker")
+    } else if path.contains("libpod") {
+        Some("podman")
+    } else if path.contains("lxc") {
+        Some("lxc")
+    } else if path.contains("crio") {
+        Some("cri-o")
+    } else if path.contains("containerd") {
+        Some("containerd")
+    } else {
+        None
+    };
+    Some((runtime, id))
+}
+
+/// A full 64-character container ID
+fn is_container_id(id: &str) -> bool {
+    id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Docker and Podman name the host after the 12-character short ID
+fn is_short_id(hostname: &str) -> bool {
+    hostname.len() == 12
+        && hostname
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+impl Detector for ContainerDetector {
+    fn name(&self) -> &'static str {
+        "container"
+    }
+
+    fn metadata(&self) -> DetectorMetadata {
+        DetectorMetadata {
+            description: "Containers: Docker, Podman, Kubernetes, LXC, ...",
+            contexts: vec!["container"],
+            traits: vec!["container.runtime", "container.image", "container.id"],
+        }
+    }
+
+    fn detect(&self, snap: &EnvSnapshot) -> Detection {
+        let var = |key| snap.get_env(key).filter(|v| !v.is_empty());
+        let mut found = Found::default();
+
+        match var("ENVSENSE_CONTAINER").as_deref() {
+            Some("none") => return Detection::default(),
+            Some(runtime) => {
+                let evidence = Evidence::env_var("ENVSENSE_CONTAINER", runtime);
+                found.set("runtime", runtime, evidence, HIGH);
+            }
+            None => {}
+        }
+        // Set by the runtime itself: podman, lxc, systemd-nspawn, oci, ...
+        if let Some(runtime) = var("container") {
+            let evidence = Evidence::env_var("container", &runtime);
+            found.set("runtime", &runtime, evidence, HIGH);
+        }
+        if let Some(root) = self.root(snap) {
+            found.containerenv(&root);
+            found.dockerenv(&root);
+            found.cgroup(&root);
+            found.mountinfo(&root);
+        }
+        if let Some(host) = var("KUBERNETES_SERVICE_HOST") {
+            let evidence = Evidence::env_var("KUBERNETES_SERVICE_HOST", host);
+            found.set("runtime", "kubernetes", evidence, MEDIUM);
+        }
+        if found.runtime.is_some()
+            && let Some(hostname) = var("HOSTNAME").filter(|h| is_short_id(h))
+        {
+            let evidence = Evidence::env_var("HOSTNAME", &hostname);
+            found.set("id", &hostname, evidence, MEDIUM);
+        }
+
+        let mut detection = Detection::default();
+        let Some(runtime) = found.runtime else {
+            return detection;
+        };
+        detection.contexts_add.push("container".to_string());
+        detection.confidence = found.confidence;
+        detection
+            .traits_patch
+            .insert("container.runtime".to_string(), json!(runtime));
+        if let Some(image) = found.image {
+            detection
+                .traits_patch
+                .insert("container.image".to_string(), json!(image));
+        }
+        if let Some(id) = found.id {
+            detection
+                .traits_patch
+                .insert("container.id".to_string(), json!(id));
+        }
+        detection.evidence = found.evidence;
+        detection
+    }
+}
+
+impl Default for ContainerDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::test_utils::create_env_snapshot;
+
+    const ID: &str = "3f4e1b2c5d6a7980a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718";
+
+    fn traits(detector: ContainerDetector, vars: Vec<(&str, &str)>) -> serde_json::Value {
+        json!(detector.detect(&create_env_snapshot(vars)).traits_patch)
+    }
+
+    fn root_with(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        for (path, contents) in files {
+            let path = root.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn detects_runtime_from_env() {
+        let detection =
+            ContainerDetector::new().detect(&create_env_snapshot(vec![("container", "podman")]));
+        assert_eq!(detection.contexts_add, ["container"]);
+        assert_eq!(detection.confidence, HIGH);
+        assert_eq!(
+            json!(detection.traits_patch),
+            json!({"container.runtime": "podman"})
+        );
+
+        assert_eq!(
+            traits(
+                ContainerDetector::new(),
+                vec![
+                    ("KUBERNETES_SERVICE_HOST", "10.96.0.1"),
+                    ("HOSTNAME", "web-7d4b9c-x2k"),
+                ]
+            ),
+            json!({"container.runtime": "kubernetes"})
+        );
+        assert_eq!(traits(ContainerDetector::new(), vec![]), json!({}));
+    }
+
+    #[test]
+    fn override_forces_or_disables() {
+        assert_eq!(
+            traits(
+                ContainerDetector::new(),
+                vec![("ENVSENSE_CONTAINER", "custom"), ("container", "lxc")]
+            ),
+            json!({"container.runtime": "custom"})
+        );
+        let root = root_with(&[(".dockerenv", "")]);
+        assert_eq!(
+            traits(
+                ContainerDetector::with_root(root.path()),
+                vec![("ENVSENSE_CONTAINER", "none"), ("container", "lxc")]
+            ),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn detects_docker_from_files() {
+        let root = root_with(&[
+            (".dockerenv", ""),
+            ("proc/self/cgroup", &format!("12:pids:/docker/{ID}\n0::/\n")),
+        ]);
+        assert_eq!(
+            traits(
+                ContainerDetector::with_root(root.path()),
+                vec![("HOSTNAME", &ID[..12])]
+            ),
+            json!({"container.runtime": "docker", "container.id": ID})
+        );
+
+        // cgroup v2 hides the path, but the hostname mount does not
+        let root = root_with(&[
+            (".dockerenv", ""),
+            ("proc/self/cgroup", "0::/\n"),
+            (
+                "proc/self/mountinfo",
+                &format!(
+                    "612 590 8:1 /var/lib/docker/containers/{ID}/hostname /etc/hostname rw - ext4 /dev/sda1 rw\n"
+                ),
+            ),
+        ]);
+        assert_eq!(
+            traits(ContainerDetector::with_root(root.path()), vec![]),
+            json!({"container.runtime": "docker", "container.id": ID})
+        );
+
+        // Without either, the short ID in the hostname is the best there is
+        let root = root_with(&[(".dockerenv", "")]);
+        assert_eq!(
+            traits(
+                ContainerDetector::with_root(root.path()),
+                vec![("HOSTNAME", &ID[..12])]
+            ),
+            json!({"container.runtime": "docker", "container.id": &ID[..12]})
+        );
+    }
+
+    #[test]
+    fn detects_podman_image() {
+        let root = root_with(&[(
+            "run/.containerenv",
+            &format!(
+                "engine=\"podman-5.0.3\"\nname=\"web\"\nid=\"{ID}\"\nimage=\"docker.io/library/alpine:latest\"\nimageid=\"9c6f\"\nrootless=1\n"
+            ),
+        )]);
+        let detection = ContainerDetector::with_root(root.path())
+            .detect(&create_env_snapshot(vec![("container", "podman")]));
+        assert_eq!(
+            json!(detection.traits_patch),
+            json!({
+                "container.runtime": "podman",
+                "container.image": "docker.io/library/alpine:latest",
+                "container.id": ID,
+            })
+        );
+        assert!(
+            detection
+                .evidence
+                .iter()
+                .any(|e| e.key == "/run/.containerenv" && e.supports == ["container.image"])
+        );
+
+        // A minimal containerenv still says podman
+        let root = root_with(&[("run/.containerenv", "")]);
+        assert_eq!(
+            traits(ContainerDetector::with_root(root.path()), vec![]),
+            json!({"container.runtime": "podman"})
+        );
+    }
+
+    #[test]
+    fn parses_cgroup_paths() {
+        assert_eq!(
+            parse_cgroup(&format!(
+                "/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod1.slice/cri-containerd-{ID}.scope"
+            )),
+            Some((Some("kubernetes"), ID))
+        );
+        assert_eq!(
+            parse_cgroup(&format!("/system.slice/docker-{ID}.scope")),
+            Some((Some("docker"), ID))
+        );
+        assert_eq!(
+            parse_cgroup(&format!("/machine.slice/libpod-{ID}.scope/container")),
+            Some((Some("podman"), ID))
+        );
+        assert_eq!(
+            parse_cgroup("/user.slice/user-1000.slice/session-2.scope"),
+            None
+        );
+        assert_eq!(parse_cgroup("/"), None);
+    }
+}
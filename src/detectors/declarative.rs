@@ -1,6 +1,7 @@
 use crate::detectors::env_mapping::EnvMapping;
 use crate::detectors::utils::{
-    DetectionConfig, SelectionStrategy, basic_declarative_detection, check_generic_overrides,
+    DetectionConfig, SelectionStrategy, add_mapping_contexts, add_mapping_values,
+    basic_declarative_detection, check_generic_overrides,
 };
 use crate::detectors::{Detection, EnvSnapshot};
 use crate::schema::Evidence;
@@ -58,7 +59,7 @@ pub trait DeclarativeDetector {
 
         basic_declarative_detection(
             &Self::get_mappings(),
-            &snap.env_vars,
+            snap.env(),
             &config,
             Self::get_selection_strategy(),
         )
@@ -74,11 +75,18 @@ pub trait DeclarativeDetector {
                 .contexts_add
                 .push(Self::get_context_name().to_string());
 
+            let mappings = Self::get_mappings();
+            let selected = mappings.iter().find(|m| {
+                m.facets.get(Self::get_facet_key()) == Some(&detected_id) && m.matches(snap.env())
+            });
+
             // Create nested trait object based on context type
             match Self::get_context_name() {
                 "ide" => {
                     let ide_traits = crate::traits::IdeTraits {
                         id: Some(detected_id.clone()),
+                        variant: selected.and_then(|m| m.facets.get("ide_variant").cloned()),
+                        ..Default::default()
                     };
                     detection
                         .traits_patch
@@ -87,6 +95,7 @@ pub trait DeclarativeDetector {
                 "agent" => {
                     let agent_traits = crate::traits::AgentTraits {
                         id: Some(detected_id.clone()),
+                        ..Default::default()
                     };
                     detection.traits_patch.insert(
                         "agent".to_string(),
@@ -118,12 +127,133 @@ pub trait DeclarativeDetector {
 
             detection.confidence = confidence;
             detection.evidence = evidence;
+
+            if let Some(mapping) = selected {
+                add_mapping_contexts(&mut detection, mapping, Self::get_context_name());
+                add_mapping_values(
+                    &mut detection,
+                    mapping,
+                    snap.env(),
+                    Self::get_context_name(),
+                );
+            }
         }
 
         detection
     }
 }
 
+/// Define a detector that is nothing but a list of mappings
+///
+/// Expands to a unit struct implementing [`DeclarativeDetector`] and
+/// [`Detector`](crate::detectors::Detector): it adds `context` and sets
+/// `<context>.id` to the id of the matching mapping, with evidence from its
+/// indicators. `selection` defaults to confidence and `evidence` to true;
+/// `also_adds` declares the other contexts its mappings can add.
+/// A `context` whose trait group has no `id` is a compile-time warning.
+///
+/// ```ignore
+/// declarative_detector! {
+///     /// Editors and IDEs
+///     pub struct DeclarativeIdeDetector {
+///         name: "ide",
+///         description: "Editors and IDEs (VS Code, Cursor, Neovim, ...)",
+///         context: "ide",
+///         facet_key: "ide_id",
+///         mappings: get_ide_mappings(),
+///         selection: SelectionStrategy::Priority,
+///     }
+/// }
+/// ```
+macro_rules! declarative_detector {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $detector:ident {
+            name: $name:literal,
+            description: $description:literal,
+            context: $context:literal,
+            facet_key: $facet_key:literal,
+            mappings: $mappings:expr
+            $(, selection: $selection:expr)?
+            $(, evidence: $evidence:expr)?
+            $(, also_adds: [$($also:literal),* $(,)?])?
+            $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $detector;
+
+        impl $detector {
+            pub fn new() -> Self {
+                Self
+            }
+        }
+
+        impl Default for $detector {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl $crate::detectors::declarative::DeclarativeDetector for $detector {
+            fn get_mappings() -> Vec<$crate::detectors::env_mapping::EnvMapping> {
+                $mappings
+            }
+
+            fn get_detector_type() -> &'static str {
+                $context
+            }
+
+            fn get_context_name() -> &'static str {
+                $context
+            }
+
+            fn get_facet_key() -> &'static str {
+                $facet_key
+            }
+
+            $(
+                fn get_selection_strategy() -> $crate::detectors::utils::SelectionStrategy {
+                    $selection
+                }
+            )?
+
+            $(
+                fn should_generate_evidence() -> bool {
+                    $evidence
+                }
+            )?
+        }
+
+        impl $crate::detectors::Detector for $detector {
+            fn name(&self) -> &'static str {
+                $name
+            }
+
+            fn metadata(&self) -> $crate::detectors::DetectorMetadata {
+                $crate::detectors::DetectorMetadata {
+                    description: $description,
+                    contexts: vec![$context $($(, $also)*)?],
+                    traits: vec![concat!($context, ".id")],
+                }
+            }
+
+            fn detect(&self, snap: &$crate::detectors::EnvSnapshot) -> $crate::detectors::Detection {
+                $crate::detectors::declarative::DeclarativeDetector::create_detection(self, snap)
+            }
+        }
+
+        // Warn when the context has no `id` trait to report
+        const _: () = envsense_macros::KeyCheck::<{
+            <$crate::traits::NestedTraits as envsense_macros::MergeValue>::SCHEMA
+                .resolves(concat!($context, ".id"))
+        }>
+        .check();
+    };
+}
+
+pub(crate) use declarative_detector;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,4 +344,37 @@ mod tests {
         assert_eq!(detection.confidence, 0.0);
         assert!(detection.evidence.is_empty());
     }
+
+    declarative_detector! {
+        struct MacroDetector {
+            name: "macro-test",
+            description: "Detector defined by declarative_detector!",
+            context: "agent",
+            facet_key: "test_id",
+            mappings: TestDetector::get_mappings(),
+            evidence: false,
+        }
+    }
+
+    #[test]
+    fn test_declarative_detector_macro() {
+        use crate::detectors::Detector;
+
+        let detector = MacroDetector::new();
+        let mut env_vars = HashMap::new();
+        env_vars.insert("TEST_VAR".to_string(), "1".to_string());
+        let snap = EnvSnapshot::with_mock_tty(env_vars, false, false, false);
+
+        let detection = detector.detect(&snap);
+
+        assert_eq!(detector.name(), "macro-test");
+        assert_eq!(detector.metadata().traits, ["agent.id"]);
+        // The mapping's own contexts are added after the detector's
+        assert_eq!(detection.contexts_add, ["agent", "test"]);
+        assert_eq!(
+            detection.facets_patch.get("test_id").unwrap(),
+            &json!("test")
+        );
+        assert!(detection.evidence.is_empty());
+    }
 }
@@ -1,4 +1,6 @@
 use crate::detectors::confidence::{HIGH, LOW, MEDIUM};
+use crate::detectors::env_provider::EnvProvider;
+use crate::detectors::user_mappings::{MappingKind, with_installed};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
@@ -488,7 +490,7 @@ impl ValueTransform {
 
 impl EnvMapping {
     /// Check if this mapping matches the given environment variables
-    pub fn matches(&self, env_vars: &HashMap<String, String>) -> bool {
+    pub fn matches(&self, env_vars: &dyn EnvProvider) -> bool {
         let mut required_indicators = Vec::new();
         let mut optional_indicators = Vec::new();
 
@@ -521,21 +523,18 @@ impl EnvMapping {
         true
     }
 
-    fn indicator_matches(
-        &self,
-        indicator: &EnvIndicator,
-        env_vars: &HashMap<String, String>,
-    ) -> bool {
+    /// Check whether a single indicator matches the given environment variables
+    pub fn indicator_matches(&self, indicator: &EnvIndicator, env_vars: &dyn EnvProvider) -> bool {
         if indicator.prefix {
             // Check if any key starts with the prefix
-            env_vars.keys().any(|key| key.starts_with(&indicator.key))
+            !env_vars.with_prefix(&indicator.key).is_empty()
         } else {
             // Check exact key match
             match env_vars.get(&indicator.key) {
                 Some(value) => {
                     // If we expect a specific value, check it
                     if let Some(expected_value) = &indicator.value
-                        && value != expected_value
+                        && &value != expected_value
                     {
                         return false;
                     }
@@ -567,22 +566,16 @@ impl EnvMapping {
     }
 
     /// Get the evidence key-value pairs that support this detection
-    pub fn get_evidence(
-        &self,
-        env_vars: &HashMap<String, String>,
-    ) -> Vec<(String, Option<String>)> {
+    pub fn get_evidence(&self, env_vars: &dyn EnvProvider) -> Vec<(String, Option<String>)> {
         let mut evidence = Vec::new();
 
         for indicator in &self.indicators {
             if indicator.prefix {
-                // For prefix matches, collect all matching keys
-                for (key, value) in env_vars {
-                    if key.starts_with(&indicator.key) {
-                        evidence.push((key.clone(), Some(value.clone())));
-                    }
-                }
+                // For prefix matches, collect all matching keys, by name
+                let matched = env_vars.with_prefix(&indicator.key);
+                evidence.extend(matched.into_iter().map(|(key, value)| (key, Some(value))));
             } else if let Some(value) = env_vars.get(&indicator.key) {
-                evidence.push((indicator.key.clone(), Some(value.clone())));
+                evidence.push((indicator.key.clone(), Some(value)));
             }
         }
 
@@ -590,34 +583,42 @@ impl EnvMapping {
     }
 
     /// Extract values from environment variables according to value mappings
-    pub fn extract_values(
+    pub fn extract_values(&self, env_vars: &dyn EnvProvider) -> HashMap<String, serde_json::Value> {
+        self.extract_values_with_warnings(env_vars).0
+    }
+
+    /// [`EnvMapping::extract_values`], along with the problems it worked
+    /// around
+    ///
+    /// Problems don't stop extraction: a value that fails validation is still
+    /// used, and a value that fails to transform is skipped. `envsense
+    /// mappings lint` reports the configuration problems ahead of time.
+    pub fn extract_values_with_warnings(
         &self,
-        env_vars: &HashMap<String, String>,
-    ) -> HashMap<String, serde_json::Value> {
+        env_vars: &dyn EnvProvider,
+    ) -> (HashMap<String, serde_json::Value>, Vec<String>) {
         let mut extracted = HashMap::new();
-        let mut validation_errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut warn = |message: String| {
+            warnings.push(format!("mapping '{}': {}", self.id, message));
+        };
 
         // Validate all mappings before processing
         for mapping in &self.value_mappings {
             if let Err(e) = mapping.validate_config() {
-                validation_errors.push(format!(
-                    "Config validation failed for {}: {}",
+                warn(format!(
+                    "config validation failed for {}: {}",
                     mapping.target_key, e
                 ));
             }
             if let Err(e) = mapping.check_circular_dependencies(&self.value_mappings) {
-                validation_errors.push(format!(
-                    "Circular dependency detected for {}: {}",
+                warn(format!(
+                    "circular dependency detected for {}: {}",
                     mapping.target_key, e
                 ));
             }
         }
 
-        // Log validation errors but continue processing
-        for error in &validation_errors {
-            eprintln!("Validation Error: {}", error);
-        }
-
         // Process mappings in dependency order (no conditions first, then conditional ones)
         let mappings_to_process: Vec<&ValueMapping> = self.value_mappings.iter().collect();
         let mut processed_count = 0;
@@ -642,25 +643,25 @@ impl EnvMapping {
                 if let Some(value) = env_vars.get(&mapping.source_key) {
                     match mapping.transform.as_ref() {
                         Some(transform) => {
-                            match transform.apply(value) {
+                            match transform.apply(&value) {
                                 Ok(transformed) => {
                                     // Validate the transformed value
                                     if let Err(e) = mapping.validate_value(&transformed) {
-                                        eprintln!(
-                                            "Warning: Value validation failed for {}: {}",
+                                        warn(format!(
+                                            "value validation failed for {}: {}",
                                             mapping.target_key, e
-                                        );
+                                        ));
                                         // Continue processing even if validation fails
                                     }
                                     extracted.insert(mapping.target_key.clone(), transformed);
                                     processed_count += 1;
                                 }
                                 Err(e) => {
-                                    // Log error but continue with other mappings
-                                    eprintln!(
-                                        "Warning: Failed to transform {}: {}",
+                                    // Report but continue with other mappings
+                                    warn(format!(
+                                        "failed to transform {}: {}",
                                         mapping.source_key, e
-                                    );
+                                    ));
                                 }
                             }
                         }
@@ -668,10 +669,10 @@ impl EnvMapping {
                             let value_json = json!(value);
                             // Validate the raw value
                             if let Err(e) = mapping.validate_value(&value_json) {
-                                eprintln!(
-                                    "Warning: Value validation failed for {}: {}",
+                                warn(format!(
+                                    "value validation failed for {}: {}",
                                     mapping.target_key, e
-                                );
+                                ));
                                 // Continue processing even if validation fails
                             }
                             extracted.insert(mapping.target_key.clone(), value_json);
@@ -679,10 +680,10 @@ impl EnvMapping {
                         }
                     }
                 } else if mapping.required {
-                    eprintln!(
-                        "Warning: Required value mapping missing: {}",
+                    warn(format!(
+                        "required value mapping missing: {}",
                         mapping.source_key
-                    );
+                    ));
                 }
             }
 
@@ -692,12 +693,27 @@ impl EnvMapping {
             }
         }
 
-        extracted
+        (extracted, warnings)
     }
 }
 
-/// Predefined environment mappings for common environments
+/// Agent mappings, including any installed user mappings
 pub fn get_agent_mappings() -> Vec<EnvMapping> {
+    with_installed(MappingKind::Agent, builtin_agent_mappings())
+}
+
+/// IDE mappings, including any installed user mappings
+pub fn get_ide_mappings() -> Vec<EnvMapping> {
+    with_installed(MappingKind::Ide, builtin_ide_mappings())
+}
+
+/// CI mappings, including any installed user mappings
+pub fn get_ci_mappings() -> Vec<EnvMapping> {
+    with_installed(MappingKind::Ci, builtin_ci_mappings())
+}
+
+/// Predefined environment mappings for common environments
+pub fn builtin_agent_mappings() -> Vec<EnvMapping> {
     vec![
         // Replit detection
         EnvMapping {
@@ -729,7 +745,14 @@ pub fn get_agent_mappings() -> Vec<EnvMapping> {
             }],
             facets: HashMap::new(),
             contexts: vec!["agent".to_string()],
-            value_mappings: vec![],
+            value_mappings: vec![ValueMapping {
+                target_key: "version".to_string(),
+                source_key: "CURSOR_VERSION".to_string(),
+                required: false,
+                transform: None,
+                condition: None,
+                validation_rules: vec![],
+            }],
         },
         // Claude Code detection
         EnvMapping {
@@ -809,7 +832,14 @@ pub fn get_agent_mappings() -> Vec<EnvMapping> {
             }],
             facets: HashMap::new(),
             contexts: vec!["agent".to_string()],
-            value_mappings: vec![],
+            value_mappings: vec![ValueMapping {
+                target_key: "version".to_string(),
+                source_key: "AIDER_VERSION".to_string(),
+                required: false,
+                transform: None,
+                condition: None,
+                validation_rules: vec![],
+            }],
         },
         // Generic code agent detection
         EnvMapping {
@@ -831,7 +861,7 @@ pub fn get_agent_mappings() -> Vec<EnvMapping> {
 }
 
 /// Predefined environment mappings for IDE detection
-pub fn get_ide_mappings() -> Vec<EnvMapping> {
+pub fn builtin_ide_mappings() -> Vec<EnvMapping> {
     vec![
         // Neovim detection (works for both :terminal and :!command modes)
         // Priority 4 ensures nvim takes precedence in nested scenarios, such as
@@ -893,9 +923,19 @@ pub fn get_ide_mappings() -> Vec<EnvMapping> {
                     priority: 3,
                 },
             ],
-            facets: HashMap::from([("ide_id".to_string(), "cursor".to_string())]),
+            facets: HashMap::from([
+                ("ide_id".to_string(), "cursor".to_string()),
+                ("ide_variant".to_string(), "stable".to_string()),
+            ]),
             contexts: vec!["ide".to_string()],
-            value_mappings: vec![],
+            value_mappings: vec![ValueMapping {
+                target_key: "version".to_string(),
+                source_key: "TERM_PROGRAM_VERSION".to_string(),
+                required: false,
+                transform: None,
+                condition: None,
+                validation_rules: vec![],
+            }],
         },
         // VS Code Insiders detection (medium priority)
         EnvMapping {
@@ -919,9 +959,71 @@ pub fn get_ide_mappings() -> Vec<EnvMapping> {
                     priority: 2,
                 },
             ],
-            facets: HashMap::from([("ide_id".to_string(), "vscode-insiders".to_string())]),
+            facets: HashMap::from([
+                ("ide_id".to_string(), "vscode-insiders".to_string()),
+                ("ide_variant".to_string(), "insiders".to_string()),
+            ]),
             contexts: vec!["ide".to_string()],
-            value_mappings: vec![],
+            value_mappings: vec![ValueMapping {
+                target_key: "version".to_string(),
+                source_key: "TERM_PROGRAM_VERSION".to_string(),
+                required: false,
+                transform: None,
+                condition: None,
+                validation_rules: vec![],
+            }],
+        },
+        // VS Code remote sessions (dev containers, Codespaces, SSH)
+        EnvMapping {
+            id: "vscode-remote".to_string(),
+            confidence: HIGH,
+            indicators: vec![
+                EnvIndicator {
+                    key: "TERM_PROGRAM".to_string(),
+                    value: Some("vscode".to_string()),
+                    required: true,
+                    prefix: false,
+                    contains: None,
+                    priority: 1,
+                },
+                EnvIndicator {
+                    key: "REMOTE_CONTAINERS".to_string(),
+                    value: None,
+                    required: false,
+                    prefix: false,
+                    contains: None,
+                    priority: 1,
+                },
+                EnvIndicator {
+                    key: "CODESPACES".to_string(),
+                    value: None,
+                    required: false,
+                    prefix: false,
+                    contains: None,
+                    priority: 1,
+                },
+                EnvIndicator {
+                    key: "SSH_CONNECTION".to_string(),
+                    value: None,
+                    required: false,
+                    prefix: false,
+                    contains: None,
+                    priority: 1,
+                },
+            ],
+            facets: HashMap::from([
+                ("ide_id".to_string(), "vscode".to_string()),
+                ("ide_variant".to_string(), "remote".to_string()),
+            ]),
+            contexts: vec!["ide".to_string(), "remote".to_string()],
+            value_mappings: vec![ValueMapping {
+                target_key: "version".to_string(),
+                source_key: "TERM_PROGRAM_VERSION".to_string(),
+                required: false,
+                transform: None,
+                condition: None,
+                validation_rules: vec![],
+            }],
         },
         // VS Code detection (lowest priority)
         EnvMapping {
@@ -935,9 +1037,19 @@ pub fn get_ide_mappings() -> Vec<EnvMapping> {
                 contains: None,
                 priority: 1,
             }],
-            facets: HashMap::from([("ide_id".to_string(), "vscode".to_string())]),
+            facets: HashMap::from([
+                ("ide_id".to_string(), "vscode".to_string()),
+                ("ide_variant".to_string(), "stable".to_string()),
+            ]),
             contexts: vec!["ide".to_string()],
-            value_mappings: vec![],
+            value_mappings: vec![ValueMapping {
+                target_key: "version".to_string(),
+                source_key: "TERM_PROGRAM_VERSION".to_string(),
+                required: false,
+                transform: None,
+                condition: None,
+                validation_rules: vec![],
+            }],
         },
     ]
 }
@@ -948,7 +1060,7 @@ pub fn get_host_mappings() -> Vec<EnvMapping> {
 }
 
 /// Predefined environment mappings for CI detection
-pub fn get_ci_mappings() -> Vec<EnvMapping> {
+pub fn builtin_ci_mappings() -> Vec<EnvMapping> {
     vec![
         // GitHub Actions detection
         EnvMapping {
@@ -2001,10 +2113,16 @@ mod tests {
             ("INVALID_ENV".to_string(), "hi".to_string()), // Too short
         ]);
 
-        let extracted = mapping.extract_values(&env_vars);
+        let (extracted, warnings) = mapping.extract_values_with_warnings(&env_vars);
 
-        // Both values should be extracted (validation failures are logged but don't prevent extraction)
+        // Both values should be extracted (validation failures are reported but don't prevent extraction)
         assert_eq!(extracted.get("valid_value"), Some(&json!("hello")));
         assert_eq!(extracted.get("invalid_value"), Some(&json!("hi")));
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with(
+                "mapping 'test-validation': value validation failed for invalid_value:"
+            )
+        );
     }
 }
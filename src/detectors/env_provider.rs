@@ -0,0 +1,172 @@
+//! Where a snapshot's environment variables come from.
+//!
+//! Detectors look variables up by name through [`EnvProvider`], so the
+//! process environment is only read as far as they need. Only prefix
+//! indicators and consumers of the whole environment (cache keys, captures,
+//! plugins) enumerate it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::sync::{Arc, OnceLock};
+
+/// A source of environment variables
+///
+/// Implemented by [`ProcessEnv`] for the running process, by
+/// `HashMap<String, String>` for fixed variables and by [`LayeredEnv`] for
+/// one source over another.
+pub trait EnvProvider: Debug + Send + Sync {
+    /// The value of `key`, if it is set
+    fn get(&self, key: &str) -> Option<String>;
+
+    /// Every variable, in no particular order
+    fn iter(&self) -> Box<dyn Iterator<Item = (String, String)> + '_>;
+
+    /// Whether `key` is set
+    fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The variables whose names start with `prefix`, sorted by name
+    fn with_prefix(&self, prefix: &str) -> Vec<(String, String)> {
+        let mut vars: Vec<_> = self.iter().filter(|(k, _)| k.starts_with(prefix)).collect();
+        vars.sort();
+        vars
+    }
+}
+
+impl EnvProvider for HashMap<String, String> {
+    fn get(&self, key: &str) -> Option<String> {
+        HashMap::get(self, key).cloned()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (String, String)> + '_> {
+        Box::new(HashMap::iter(self).map(|(k, v)| (k.clone(), v.clone())))
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.contains_key(key)
+    }
+}
+
+/// The variables of the running process, read when asked for
+///
+/// Lookups go to the process every time. The full list is read once, the
+/// first time something enumerates it. Variables whose name or value is not
+/// valid UTF-8 are skipped.
+#[derive(Debug, Default)]
+pub struct ProcessEnv {
+    all: OnceLock<HashMap<String, String>>,
+}
+
+impl ProcessEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn all(&self) -> &HashMap<String, String> {
+        self.all.get_or_init(|| {
+            std::env::vars_os()
+                .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+                .collect()
+        })
+    }
+}
+
+impl EnvProvider for ProcessEnv {
+    fn get(&self, key: &str) -> Option<String> {
+        std::env::var(key).ok()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (String, String)> + '_> {
+        EnvProvider::iter(self.all())
+    }
+}
+
+/// Variables from `top`, falling back to `base` for the rest
+///
+/// Used to apply a `.env` file or `--env` values over another environment
+/// without copying it.
+#[derive(Debug, Clone)]
+pub struct LayeredEnv {
+    top: Arc<dyn EnvProvider>,
+    base: Arc<dyn EnvProvider>,
+}
+
+impl LayeredEnv {
+    pub fn new(top: Arc<dyn EnvProvider>, base: Arc<dyn EnvProvider>) -> Self {
+        Self { top, base }
+    }
+}
+
+impl EnvProvider for LayeredEnv {
+    fn get(&self, key: &str) -> Option<String> {
+        self.top.get(key).or_else(|| self.base.get(key))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (String, String)> + '_> {
+        let mut vars: BTreeMap<String, String> = self.base.iter().collect();
+        vars.extend(self.top.iter());
+        Box::new(vars.into_iter())
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.top.contains(key) || self.base.contains(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn map_provider_looks_up_and_lists() {
+        let env = map(&[("CI", "true"), ("CI_JOB_ID", "7"), ("TERM", "dumb")]);
+        assert_eq!(EnvProvider::get(&env, "CI"), Some("true".to_string()));
+        assert!(!EnvProvider::contains(&env, "HOME"));
+        assert_eq!(
+            env.with_prefix("CI"),
+            [
+                ("CI".to_string(), "true".to_string()),
+                ("CI_JOB_ID".to_string(), "7".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn layers_override_their_base() {
+        let base: Arc<dyn EnvProvider> = Arc::new(map(&[("CI", "true"), ("TERM", "dumb")]));
+        let top: Arc<dyn EnvProvider> = Arc::new(map(&[("TERM", "xterm")]));
+        let env = LayeredEnv::new(top, base);
+
+        assert_eq!(env.get("TERM"), Some("xterm".to_string()));
+        assert_eq!(env.get("CI"), Some("true".to_string()));
+        assert_eq!(env.get("HOME"), None);
+        let all: Vec<_> = env.iter().collect();
+        assert_eq!(
+            all,
+            [
+                ("CI".to_string(), "true".to_string()),
+                ("TERM".to_string(), "xterm".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn process_env_reads_the_process() {
+        let env = ProcessEnv::new();
+        let path = std::env::var("PATH").ok();
+        assert_eq!(env.get("PATH"), path);
+        assert_eq!(
+            env.iter().any(|(k, _)| k == "PATH"),
+            path.is_some(),
+            "enumeration sees the same variables"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Detector plugins run as external executables.
+//!
+//! When plugins are enabled, any executable on `PATH` named
+//! `envsense-detector-<name>` is registered as a detector called `<name>`.
+//! For each detection run it receives one JSON object on stdin, with the
+//! values of secret-looking variables replaced by [`REDACTED`]:
+//!
+//! ```json
+//! {"protocol_version": 1, "env": {"CI": "true"}, "tty": {"stdin": false, "stdout": true, "stderr": true}}
+//! ```
+//!
+//! and must print one JSON object on stdout and exit with status 0. All
+//! fields are optional and mirror [`Detection`]:
+//!
+//! ```json
+//! {"contexts_add": ["ci"], "traits_patch": {"ci": {"id": "acme"}}, "evidence": [], "confidence": 0.9}
+//! ```
+//!
+//! A plugin that fails, times out or prints invalid JSON contributes nothing
+//! and is listed in the result's diagnostics.
+
+use crate::detectors::{Detection, Detector, DetectorMetadata, EnvSnapshot};
+use crate::redact::{REDACTED, Redactor};
+use crate::schema::Evidence;
+use crate::snapshot::TtyState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Version of the stdin/stdout protocol sent to plugins
+pub const PLUGIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Executable name prefix that marks a detector plugin
+pub const PLUGIN_PREFIX: &str = "envsense-detector-";
+
+/// How long a plugin may run before it is killed
+pub const PLUGIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What a plugin receives on stdin
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PluginRequest {
+    pub protocol_version: u32,
+    pub env: HashMap<String, String>,
+    pub tty: TtyState,
+}
+
+/// What a plugin prints on stdout
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PluginResponse {
+    pub contexts_add: Vec<String>,
+    pub traits_patch: HashMap<String, serde_json::Value>,
+    pub facets_patch: HashMap<String, serde_json::Value>,
+    pub evidence: Vec<Evidence>,
+    pub confidence: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+}
+
+impl From<PluginResponse> for Detection {
+    fn from(response: PluginResponse) -> Self {
+        Detection {
+            contexts_add: response.contexts_add,
+            traits_patch: response.traits_patch,
+            facets_patch: response.facets_patch,
+            evidence: response.evidence,
+            confidence: response.confidence,
+            priority: response.priority,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// A detector backed by an `envsense-detector-*` executable
+#[derive(Debug, Clone)]
+pub struct ExternalDetector {
+    name: &'static str,
+    path: PathBuf,
+    timeout: Duration,
+    redactor: Redactor,
+}
+
+impl ExternalDetector {
+    pub fn new(name: &str, path: PathBuf) -> Self {
+        Self {
+            // Detector names are `'static`; plugins are discovered once per
+            // process, so leaking the handful of names is fine.
+            name: Box::leak(name.to_string().into_boxed_str()),
+            path,
+            timeout: PLUGIN_TIMEOUT,
+            redactor: Redactor::default(),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Decide which variable values the plugin sees as [`REDACTED`]
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run the plugin once and parse its response
+    pub fn invoke(&self, snap: &EnvSnapshot) -> Result<PluginResponse, String> {
+        let request = PluginRequest {
+            protocol_version: PLUGIN_PROTOCOL_VERSION,
+            env: snap
+                .env()
+                .iter()
+                .map(|(key, value)| {
+                    let value = if self.redactor.hides(&key, &value) {
+                        REDACTED.to_string()
+                    } else {
+                        value
+                    };
+                    (key, value)
+                })
+                .collect(),
+            tty: TtyState {
+                stdin: snap.is_tty_stdin(),
+                stdout: snap.is_tty_stdout(),
+                stderr: snap.is_tty_stderr(),
+            },
+        };
+        let input = serde_json::to_vec(&request).map_err(|e| e.to_string())?;
+
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("failed to start: {}", e))?;
+
+        // Feed stdin and drain stdout on threads so large payloads cannot
+        // block us past the deadline. A plugin that ignores stdin may exit
+        // before reading it; that is not an error.
+        if let Some(mut stdin) = child.stdin.take() {
+            std::thread::spawn(move || {
+                let _ = stdin.write_all(&input);
+            });
+        }
+        let reader = child.stdout.take().map(|mut stdout| {
+            std::thread::spawn(move || {
+                let mut output = String::new();
+                stdout.read_to_string(&mut output).map(|_| output)
+            })
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child.try_wait().map_err(|e| e.to_string())? {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("timed out after {:?}", self.timeout));
+                }
+                None => std::thread::sleep(Duration::from_millis(5)),
+            }
+        };
+        if !status.success() {
+            return Err(format!("exited with {}", status));
+        }
+
+        let output = match reader.map(|handle| handle.join()) {
+            Some(Ok(Ok(output))) => output,
+            Some(Ok(Err(e))) => return Err(e.to_string()),
+            Some(Err(_)) => return Err("failed to read output".to_string()),
+            None => String::new(),
+        };
+        serde_json::from_str(&output).map_err(|e| format!("invalid response: {}", e))
+    }
+}
+
+impl Detector for ExternalDetector {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn metadata(&self) -> DetectorMetadata {
+        DetectorMetadata {
+            description: "External detector plugin",
+            contexts: Vec::new(),
+            traits: Vec::new(),
+        }
+    }
+
+    fn detect(&self, snap: &EnvSnapshot) -> Detection {
+        self.try_detect(snap).unwrap_or_default()
+    }
+
+    fn try_detect(&self, snap: &EnvSnapshot) -> Result<Detection, String> {
+        self.invoke(snap)
+            .map(Detection::from)
+            .map_err(|e| format!("{} {}", self.path.display(), e))
+    }
+}
+
+/// Find `envsense-detector-*` executables in the directories of `path_var`
+///
+/// The first executable for a given name wins, matching shell lookup.
+pub fn discover_plugins(path_var: &OsStr) -> Vec<ExternalDetector> {
+    let mut plugins: Vec<ExternalDetector> = Vec::new();
+    for dir in std::env::split_paths(path_var) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<(String, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                let name = plugin_name(&path)?;
+                is_executable(&path).then_some((name, path))
+            })
+            .collect();
+        found.sort();
+        for (name, path) in found {
+            if !plugins.iter().any(|p| p.name == name) {
+                plugins.push(ExternalDetector::new(&name, path));
+            }
+        }
+    }
+    plugins
+}
+
+fn plugin_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    let name = file_name.strip_prefix(PLUGIN_PREFIX)?;
+    let name = if cfg!(windows) {
+        name.strip_suffix(".exe").unwrap_or(name)
+    } else {
+        name
+    };
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::detectors::test_utils::create_env_snapshot;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn write_plugin(dir: &Path, name: &str, script: &str) -> PathBuf {
+        let path = dir.join(format!("{}{}", PLUGIN_PREFIX, name));
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn discovers_executables_in_path_order() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        let winner = write_plugin(first.path(), "acme", "exit 0");
+        write_plugin(second.path(), "acme", "exit 0");
+        write_plugin(second.path(), "devbox", "exit 0");
+        std::fs::write(second.path().join("envsense-detector-notexec"), "").unwrap();
+
+        let path_var = std::env::join_paths([first.path(), second.path()]).unwrap();
+        let plugins = discover_plugins(&path_var);
+        let names: Vec<_> = plugins.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["acme", "devbox"]);
+        assert_eq!(plugins[0].path(), winner);
+    }
+
+    #[test]
+    fn passes_snapshot_and_parses_detection() {
+        let dir = TempDir::new().unwrap();
+        // Echo the ACME_ID value from the request back as ci.id
+        let path = write_plugin(
+            dir.path(),
+            "acme",
+            r#"id=$(sed -n 's/.*"ACME_ID":"\([^"]*\)".*/\1/p')
+printf '{"contexts_add":["ci"],"traits_patch":{"ci":{"id":"%s"}},"confidence":0.9}' "$id""#,
+        );
+
+        let detector = ExternalDetector::new("acme", path);
+        let detection = detector.detect(&create_env_snapshot(vec![("ACME_ID", "acme-ci")]));
+        assert_eq!(detection.contexts_add, ["ci"]);
+        assert_eq!(detection.traits_patch["ci"]["id"], "acme-ci");
+        assert_eq!(detection.confidence, 0.9);
+    }
+
+    #[test]
+    fn secret_values_are_redacted_from_the_request() {
+        let dir = TempDir::new().unwrap();
+        let request = dir.path().join("request.json");
+        let path = write_plugin(
+            dir.path(),
+            "spy",
+            &format!("cat > '{}'\nprintf '{{}}'", request.display()),
+        );
+
+        let snap = create_env_snapshot(vec![("ACME_ID", "7"), ("ACME_TOKEN", "hunter2")]);
+        ExternalDetector::new("spy", path).detect(&snap);
+        let sent: PluginRequest =
+            serde_json::from_str(&std::fs::read_to_string(&request).unwrap()).unwrap();
+        assert_eq!(sent.env["ACME_ID"], "7");
+        assert_eq!(sent.env["ACME_TOKEN"], REDACTED);
+    }
+
+    #[test]
+    fn failures_contribute_nothing() {
+        let dir = TempDir::new().unwrap();
+        let snap = create_env_snapshot(vec![]);
+
+        let failing = ExternalDetector::new("fail", write_plugin(dir.path(), "fail", "exit 3"));
+        assert!(failing.invoke(&snap).unwrap_err().contains("exited"));
+        assert_eq!(failing.detect(&snap), Detection::default());
+
+        let garbage =
+            ExternalDetector::new("garbage", write_plugin(dir.path(), "garbage", "echo nope"));
+        assert!(
+            garbage
+                .invoke(&snap)
+                .unwrap_err()
+                .contains("invalid response")
+        );
+
+        let slow = ExternalDetector::new("slow", write_plugin(dir.path(), "slow", "sleep 5"))
+            .with_timeout(Duration::from_millis(100));
+        assert!(slow.invoke(&snap).unwrap_err().contains("timed out"));
+    }
+}
@@ -1,57 +1,23 @@
-use crate::detectors::declarative::DeclarativeDetector;
+use crate::detectors::declarative::declarative_detector;
 use crate::detectors::env_mapping::get_ide_mappings;
 use crate::detectors::utils::SelectionStrategy;
-use crate::detectors::{Detection, Detector, EnvSnapshot};
 
-pub struct DeclarativeIdeDetector;
-
-impl DeclarativeIdeDetector {
-    pub fn new() -> Self {
-        Self
-    }
-}
-
-impl DeclarativeDetector for DeclarativeIdeDetector {
-    fn get_mappings() -> Vec<crate::detectors::env_mapping::EnvMapping> {
-        get_ide_mappings()
-    }
-
-    fn get_detector_type() -> &'static str {
-        "ide"
-    }
-
-    fn get_context_name() -> &'static str {
-        "ide"
-    }
-
-    fn get_facet_key() -> &'static str {
-        "ide_id"
-    }
-
-    fn get_selection_strategy() -> SelectionStrategy {
-        SelectionStrategy::Priority
-    }
-}
-
-impl Detector for DeclarativeIdeDetector {
-    fn name(&self) -> &'static str {
-        "ide-declarative"
-    }
-
-    fn detect(&self, snap: &EnvSnapshot) -> Detection {
-        self.create_detection(snap)
-    }
-}
-
-impl Default for DeclarativeIdeDetector {
-    fn default() -> Self {
-        Self::new()
+declarative_detector! {
+    pub struct DeclarativeIdeDetector {
+        name: "ide",
+        description: "Editors and IDEs (VS Code, Cursor, Neovim, ...)",
+        context: "ide",
+        facet_key: "ide_id",
+        mappings: get_ide_mappings(),
+        selection: SelectionStrategy::Priority,
+        also_adds: ["remote"],
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::detectors::Detector;
     use crate::detectors::confidence::HIGH;
     use serde_json::json;
 
@@ -95,6 +61,46 @@ mod tests {
         assert_eq!(detection.confidence, HIGH);
     }
 
+    #[test]
+    fn detects_ide_variants() {
+        let detector = DeclarativeIdeDetector::new();
+        let variant = |vars| {
+            let detection = detector.detect(&create_env_snapshot(vars));
+            let ide: crate::traits::IdeTraits =
+                serde_json::from_value(detection.traits_patch["ide"].clone()).unwrap();
+            (ide.id.unwrap(), ide.variant.unwrap())
+        };
+
+        assert_eq!(
+            variant(vec![
+                ("TERM_PROGRAM", "vscode"),
+                ("TERM_PROGRAM_VERSION", "1.86.0-insider"),
+            ]),
+            ("vscode-insiders".to_string(), "insiders".to_string())
+        );
+        assert_eq!(
+            variant(vec![
+                ("TERM_PROGRAM", "vscode"),
+                ("REMOTE_CONTAINERS", "true")
+            ]),
+            ("vscode".to_string(), "remote".to_string())
+        );
+        assert_eq!(
+            variant(vec![
+                ("TERM_PROGRAM", "vscode"),
+                ("SSH_CONNECTION", "10.0.0.1 5022 10.0.0.2 22"),
+            ]),
+            ("vscode".to_string(), "remote".to_string())
+        );
+
+        let remote = detector.detect(&create_env_snapshot(vec![
+            ("TERM_PROGRAM", "vscode"),
+            ("CODESPACES", "true"),
+        ]));
+        assert_eq!(remote.contexts_add, ["ide", "remote"]);
+        assert!(detector.metadata().contexts.contains(&"remote"));
+    }
+
     #[test]
     fn detects_cursor_ide() {
         let detector = DeclarativeIdeDetector::new();
@@ -129,7 +135,7 @@ mod tests {
             detection.facets_patch.get("ide_id").unwrap(),
             &json!("nvim")
         );
-        assert!(detection.evidence.len() >= 1);
+        assert!(!detection.evidence.is_empty());
         assert_eq!(detection.confidence, HIGH);
     }
 
@@ -152,7 +158,7 @@ mod tests {
             detection.facets_patch.get("ide_id").unwrap(),
             &json!("nvim")
         );
-        assert!(detection.evidence.len() >= 1);
+        assert!(!detection.evidence.is_empty());
         assert_eq!(detection.confidence, HIGH);
     }
 
@@ -213,7 +219,7 @@ mod tests {
             detection.facets_patch.get("ide_id").unwrap(),
             &json!("nvim")
         );
-        assert!(detection.evidence.len() >= 1);
+        assert!(!detection.evidence.is_empty());
         assert_eq!(detection.confidence, HIGH);
     }
 
@@ -268,7 +274,7 @@ mod tests {
 
         // Should not detect as IDE despite TERM_PROGRAM being present
         assert!(!detection.contexts_add.contains(&"ide".to_string()));
-        assert!(detection.facets_patch.get("ide_id").is_none());
+        assert!(!detection.facets_patch.contains_key("ide_id"));
     }
 
     #[test]
@@ -283,7 +289,7 @@ mod tests {
 
         // Should not detect as IDE despite TERM_PROGRAM being present
         assert!(!detection.contexts_add.contains(&"ide".to_string()));
-        assert!(detection.facets_patch.get("ide_id").is_none());
+        assert!(!detection.facets_patch.contains_key("ide_id"));
     }
 
     #[test]
@@ -304,6 +310,11 @@ mod tests {
         let ide_traits: crate::traits::IdeTraits =
             serde_json::from_value(ide_value.clone()).unwrap();
         assert_eq!(ide_traits.id, Some("vscode".to_string()));
+        assert_eq!(ide_traits.variant, Some("stable".to_string()));
+        assert_eq!(
+            detection.traits_patch.get("ide.version"),
+            Some(&json!("1.85.0"))
+        );
 
         // Verify legacy facet is still present for backward compatibility
         assert_eq!(
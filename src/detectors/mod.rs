@@ -1,16 +1,34 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
+#[cfg(feature = "declarative-mappings")]
 pub mod agent_declarative;
+#[cfg(feature = "declarative-mappings")]
 pub mod ci_declarative;
-pub mod declarative;
+pub mod container;
+#[cfg(feature = "declarative-mappings")]
+pub(crate) mod declarative;
+#[cfg(feature = "declarative-mappings")]
 pub mod env_mapping;
+pub mod env_provider;
+pub mod external;
+#[cfg(feature = "declarative-mappings")]
 pub mod ide_declarative;
+pub mod shell;
 pub mod terminal;
+#[doc(hidden)]
 pub mod test_utils;
 pub mod tty;
-pub mod utils;
+#[cfg(feature = "declarative-mappings")]
+pub mod user_mappings;
+#[cfg(feature = "declarative-mappings")]
+pub(crate) mod utils;
+#[cfg(feature = "declarative-mappings")]
 pub use agent_declarative::DeclarativeAgentDetector;
+#[cfg(feature = "declarative-mappings")]
 pub use ci_declarative::DeclarativeCiDetector;
+pub use env_provider::{EnvProvider, LayeredEnv, ProcessEnv};
+#[cfg(feature = "declarative-mappings")]
 pub use ide_declarative::DeclarativeIdeDetector;
 pub use tty::TtyDetector;
 
@@ -67,6 +85,41 @@ pub mod confidence {
 pub trait Detector {
     fn name(&self) -> &'static str;
     fn detect(&self, snap: &EnvSnapshot) -> Detection;
+
+    /// Describe what this detector can produce
+    fn metadata(&self) -> DetectorMetadata {
+        DetectorMetadata::default()
+    }
+
+    /// Merge priority; breaks ties between equally confident detections
+    ///
+    /// Detections are merged in ascending priority, ties in registration
+    /// order. When two set the same trait to different values the higher
+    /// [`Detection::confidence`] wins, and on equal confidence the one merged
+    /// last. The built-in detectors all use 0.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// [`detect`](Self::detect), for detectors that can fail
+    ///
+    /// The engine calls this instead of `detect`. An error, like a panic in
+    /// either method, drops this detector's result and is recorded in
+    /// [`EnvSense::diagnostics`](crate::schema::EnvSense::diagnostics); the
+    /// other detectors still run.
+    fn try_detect(&self, snap: &EnvSnapshot) -> Result<Detection, String> {
+        Ok(self.detect(snap))
+    }
+}
+
+/// Static description of a detector, used by `envsense detectors`
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct DetectorMetadata {
+    pub description: &'static str,
+    /// Contexts the detector may add
+    pub contexts: Vec<&'static str>,
+    /// Nested trait paths the detector may set
+    pub traits: Vec<&'static str>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -76,6 +129,11 @@ pub struct Detection {
     pub facets_patch: HashMap<String, serde_json::Value>,
     pub evidence: Vec<crate::schema::Evidence>,
     pub confidence: f32,
+    /// Overrides [`Detector::priority`] for this result only
+    pub priority: Option<i32>,
+    /// Problems the detector worked around, reported as warnings in
+    /// [`EnvSense::diagnostics`](crate::schema::EnvSense::diagnostics)
+    pub warnings: Vec<String>,
 }
 
 impl Default for Detection {
@@ -86,27 +144,35 @@ impl Default for Detection {
             facets_patch: HashMap::new(),
             evidence: Vec::new(),
             confidence: 0.0,
+            priority: None,
+            warnings: Vec::new(),
         }
     }
 }
 
+/// The environment variables and TTY state that detectors read
+///
+/// Variables come from an [`EnvProvider`]: the running process for
+/// [`current`](Self::current), a map for tests and replays. Cloning a
+/// snapshot shares the provider.
 #[derive(Debug, Clone)]
 pub struct EnvSnapshot {
-    pub env_vars: HashMap<String, String>,
+    env: Arc<dyn EnvProvider>,
     pub tty_detector: TtyDetector,
+    reads_host_fs: bool,
 }
 
 impl EnvSnapshot {
     /// Create snapshot with real TTY detection for production use
     /// Respects ENVSENSE_TTY_* environment variable overrides
     pub fn current() -> Self {
-        let env_vars: HashMap<String, String> = std::env::vars().collect();
+        let env = ProcessEnv::new();
 
         // Check for TTY environment variable overrides
         let tty_detector = if let (Some(stdin), Some(stdout), Some(stderr)) = (
-            env_vars.get("ENVSENSE_TTY_STDIN"),
-            env_vars.get("ENVSENSE_TTY_STDOUT"),
-            env_vars.get("ENVSENSE_TTY_STDERR"),
+            env.get("ENVSENSE_TTY_STDIN"),
+            env.get("ENVSENSE_TTY_STDOUT"),
+            env.get("ENVSENSE_TTY_STDERR"),
         ) {
             // Parse boolean values from environment variables
             let stdin_tty = stdin.parse::<bool>().unwrap_or(false);
@@ -118,18 +184,21 @@ impl EnvSnapshot {
             TtyDetector::real()
         };
 
+        Self::from_provider(env, tty_detector).with_host_fs(true)
+    }
+
+    /// Create snapshot reading variables from `env`
+    pub fn from_provider(env: impl EnvProvider + 'static, tty_detector: TtyDetector) -> Self {
         Self {
-            env_vars,
+            env: Arc::new(env),
             tty_detector,
+            reads_host_fs: false,
         }
     }
 
     /// Create snapshot with mock TTY detection for testing
     pub fn for_testing(env_vars: HashMap<String, String>, tty_detector: TtyDetector) -> Self {
-        Self {
-            env_vars,
-            tty_detector,
-        }
+        Self::from_provider(env_vars, tty_detector)
     }
 
     /// Create snapshot with mock TTY detection using convenience constructor
@@ -139,9 +208,15 @@ impl EnvSnapshot {
         stdout: bool,
         stderr: bool,
     ) -> Self {
+        Self::from_provider(env_vars, TtyDetector::mock(stdin, stdout, stderr))
+    }
+
+    /// This snapshot with `vars` set over its own variables
+    pub fn layered(self, vars: HashMap<String, String>) -> Self {
         Self {
-            env_vars,
-            tty_detector: TtyDetector::mock(stdin, stdout, stderr),
+            env: Arc::new(LayeredEnv::new(Arc::new(vars), self.env)),
+            tty_detector: self.tty_detector,
+            reads_host_fs: self.reads_host_fs,
         }
     }
 
@@ -158,8 +233,45 @@ impl EnvSnapshot {
         self.tty_detector.is_tty_stderr()
     }
 
-    pub fn get_env(&self, key: &str) -> Option<&String> {
-        self.env_vars.get(key)
+    /// Whether piped stdin already has bytes to read
+    ///
+    /// `ENVSENSE_STDIN_HAS_DATA` overrides the check, as replayed snapshots
+    /// cannot poll the stdin they were captured with.
+    pub fn stdin_has_data(&self) -> Option<bool> {
+        match self.get_env("ENVSENSE_STDIN_HAS_DATA") {
+            Some(value) => value.parse().ok(),
+            None => self.tty_detector.stdin_has_data(),
+        }
+    }
+
+    /// Whether detectors may read this machine's filesystem
+    ///
+    /// True for [`current`](Self::current) and false for other snapshots, so
+    /// tests, replays and env files depend on their variables alone.
+    pub fn reads_host_fs(&self) -> bool {
+        self.reads_host_fs
+    }
+
+    /// This snapshot, letting detectors read the filesystem or not
+    ///
+    /// For variables captured on this machine, such as a daemon client's.
+    pub fn with_host_fs(mut self, reads_host_fs: bool) -> Self {
+        self.reads_host_fs = reads_host_fs;
+        self
+    }
+
+    pub fn get_env(&self, key: &str) -> Option<String> {
+        self.env.get(key)
+    }
+
+    /// Where this snapshot's variables come from
+    pub fn env(&self) -> &dyn EnvProvider {
+        self.env.as_ref()
+    }
+
+    /// Every variable, for consumers that need the whole environment
+    pub fn env_vars(&self) -> HashMap<String, String> {
+        self.env.iter().collect()
     }
 }
 
@@ -177,10 +289,7 @@ mod tests {
         assert!(snapshot.is_tty_stdin());
         assert!(!snapshot.is_tty_stdout());
         assert!(!snapshot.is_tty_stderr());
-        assert_eq!(
-            snapshot.get_env("TERM"),
-            Some(&"xterm-256color".to_string())
-        );
+        assert_eq!(snapshot.get_env("TERM"), Some("xterm-256color".to_string()));
     }
 
     #[test]
@@ -194,10 +303,35 @@ mod tests {
         assert!(snapshot.is_tty_stdin());
         assert!(!snapshot.is_tty_stdout());
         assert!(!snapshot.is_tty_stderr());
-        assert_eq!(
-            snapshot.get_env("TEST_VAR"),
-            Some(&"test_value".to_string())
-        );
+        assert_eq!(snapshot.get_env("TEST_VAR"), Some("test_value".to_string()));
+    }
+
+    #[test]
+    fn test_env_snapshot_layered() {
+        let mut env_vars = HashMap::new();
+        env_vars.insert("TERM".to_string(), "dumb".to_string());
+        env_vars.insert("CI".to_string(), "true".to_string());
+        let snapshot = EnvSnapshot::with_mock_tty(env_vars, true, false, false);
+
+        let layered = snapshot
+            .clone()
+            .layered(HashMap::from([("TERM".to_string(), "xterm".to_string())]));
+        assert_eq!(layered.get_env("TERM"), Some("xterm".to_string()));
+        assert_eq!(layered.get_env("CI"), Some("true".to_string()));
+        assert!(layered.is_tty_stdin());
+        // The original is untouched
+        assert_eq!(snapshot.get_env("TERM"), Some("dumb".to_string()));
+    }
+
+    #[test]
+    fn test_env_snapshot_host_fs_is_separate_from_tty() {
+        let snapshot = EnvSnapshot::with_mock_tty(HashMap::new(), true, true, true);
+        assert!(!snapshot.reads_host_fs());
+
+        let local = snapshot.with_host_fs(true).layered(HashMap::new());
+        assert!(local.reads_host_fs());
+        assert!(local.is_tty_stdin());
+        assert!(EnvSnapshot::current().reads_host_fs());
     }
 
     #[test]
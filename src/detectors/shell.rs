@@ -0,0 +1,218 @@
+use crate::detectors::{
+    Detection, Detector, DetectorMetadata, EnvSnapshot,
+    confidence::{HIGH, MEDIUM},
+};
+use crate::schema::Evidence;
+use serde_json::json;
+
+/// Detects the shell from the variables each shell leaves behind
+///
+/// Shells export little about themselves, so this is a heuristic: MSYS
+/// shells set `MSYSTEM`, PowerShell prepends a per-user entry to
+/// `PSModulePath`, cmd sets `PROMPT`, and otherwise the login shell in
+/// `SHELL` is assumed.
+pub struct ShellDetector;
+
+impl ShellDetector {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A detected shell with the variable and value that identify it
+struct Shell {
+    id: String,
+    key: &'static str,
+    value: String,
+    confidence: f32,
+}
+
+impl Shell {
+    fn new(id: impl Into<String>, key: &'static str, value: &str, confidence: f32) -> Self {
+        Self {
+            id: id.into(),
+            key,
+            value: value.to_string(),
+            confidence,
+        }
+    }
+}
+
+fn detect_shell(snap: &EnvSnapshot) -> Option<Shell> {
+    let var = |key| snap.get_env(key).filter(|v| !v.is_empty());
+    let windows = var("ComSpec").or_else(|| var("COMSPEC")).is_some();
+
+    // Git for Windows' launcher sets EXEPATH; plain MSYS2 shells do not
+    if let Some(msystem) = var("MSYSTEM") {
+        let id = if var("EXEPATH").is_some() {
+            "git-bash"
+        } else {
+            "msys2"
+        };
+        return Some(Shell::new(id, "MSYSTEM", &msystem, HIGH));
+    }
+
+    if let Some(modules) = var("PSModulePath") {
+        if !windows {
+            return Some(Shell::new("pwsh", "PSModulePath", &modules, MEDIUM));
+        }
+        // cmd sees the two machine-wide entries; PowerShell adds the user's
+        // Documents\PowerShell (pwsh) or Documents\WindowsPowerShell one
+        let entries: Vec<String> = modules
+            .split(';')
+            .filter(|entry| !entry.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if entries.len() >= 3 {
+            let id = if entries.iter().any(|entry| entry.contains("\\powershell\\")) {
+                "pwsh"
+            } else {
+                "powershell"
+            };
+            return Some(Shell::new(id, "PSModulePath", &modules, MEDIUM));
+        }
+    } else if let Some(channel) = var("POWERSHELL_DISTRIBUTION_CHANNEL")
+        && !windows
+    {
+        return Some(Shell::new(
+            "pwsh",
+            "POWERSHELL_DISTRIBUTION_CHANNEL",
+            &channel,
+            MEDIUM,
+        ));
+    }
+
+    if windows
+        && var("SHELL").is_none()
+        && let Some(prompt) = var("PROMPT")
+    {
+        return Some(Shell::new("cmd", "PROMPT", &prompt, MEDIUM));
+    }
+
+    // The login shell, which is usually the one running
+    let shell = var("SHELL")?;
+    let name = shell.rsplit(['/', '\\']).next().unwrap_or(&shell);
+    let name = name.strip_suffix(".exe").unwrap_or(name);
+    (!name.is_empty()).then(|| Shell::new(name, "SHELL", &shell, MEDIUM))
+}
+
+impl Detector for ShellDetector {
+    fn name(&self) -> &'static str {
+        "shell"
+    }
+
+    fn metadata(&self) -> DetectorMetadata {
+        DetectorMetadata {
+            description: "The shell: bash, zsh, PowerShell, cmd, Git Bash, ...",
+            contexts: vec!["shell"],
+            traits: vec!["shell.id"],
+        }
+    }
+
+    fn detect(&self, snap: &EnvSnapshot) -> Detection {
+        let mut detection = Detection::default();
+        if let Some(shell) = detect_shell(snap) {
+            detection.contexts_add.push("shell".to_string());
+            detection.confidence = shell.confidence;
+            detection
+                .traits_patch
+                .insert("shell.id".to_string(), json!(shell.id));
+            // The variable is certain; what it says about the shell is not
+            detection.evidence.push(
+                Evidence::env_var(shell.key, shell.value)
+                    .with_supports(vec!["shell".into(), "shell.id".into()]),
+            );
+        }
+        detection
+    }
+}
+
+impl Default for ShellDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::test_utils::create_env_snapshot;
+
+    fn shell(vars: Vec<(&str, &str)>) -> Option<String> {
+        ShellDetector::new()
+            .detect(&create_env_snapshot(vars))
+            .traits_patch
+            .get("shell.id")
+            .and_then(|id| id.as_str().map(str::to_string))
+    }
+
+    const MACHINE_MODULES: &str = r"C:\Program Files\WindowsPowerShell\Modules;C:\WINDOWS\system32\WindowsPowerShell\v1.0\Modules";
+
+    #[test]
+    fn detects_unix_login_shell() {
+        assert_eq!(shell(vec![("SHELL", "/bin/zsh")]), Some("zsh".into()));
+        assert_eq!(
+            shell(vec![("SHELL", "/usr/local/bin/fish")]),
+            Some("fish".into())
+        );
+        assert_eq!(shell(vec![]), None);
+    }
+
+    #[test]
+    fn detects_windows_shells() {
+        let user_modules =
+            format!(r"C:\Users\me\Documents\WindowsPowerShell\Modules;{MACHINE_MODULES}");
+        let pwsh_modules = format!(
+            r"C:\Users\me\Documents\PowerShell\Modules;C:\Program Files\PowerShell\Modules;c:\program files\powershell\7\Modules;{MACHINE_MODULES}"
+        );
+
+        assert_eq!(
+            shell(vec![
+                ("ComSpec", r"C:\WINDOWS\system32\cmd.exe"),
+                ("PSModulePath", &user_modules),
+            ]),
+            Some("powershell".into())
+        );
+        assert_eq!(
+            shell(vec![
+                ("ComSpec", r"C:\WINDOWS\system32\cmd.exe"),
+                ("PSModulePath", &pwsh_modules),
+                ("POWERSHELL_DISTRIBUTION_CHANNEL", "MSI:Windows 10 Pro"),
+            ]),
+            Some("pwsh".into())
+        );
+        // cmd inherits only the machine-wide module paths
+        assert_eq!(
+            shell(vec![
+                ("ComSpec", r"C:\WINDOWS\system32\cmd.exe"),
+                ("PSModulePath", MACHINE_MODULES),
+                ("PROMPT", "$P$G"),
+            ]),
+            Some("cmd".into())
+        );
+        assert_eq!(
+            shell(vec![
+                ("ComSpec", r"C:\WINDOWS\system32\cmd.exe"),
+                ("MSYSTEM", "MINGW64"),
+                ("EXEPATH", r"C:\Program Files\Git"),
+                ("SHELL", "/usr/bin/bash"),
+            ]),
+            Some("git-bash".into())
+        );
+        assert_eq!(
+            shell(vec![("MSYSTEM", "UCRT64"), ("SHELL", "/usr/bin/bash")]),
+            Some("msys2".into())
+        );
+    }
+
+    #[test]
+    fn detects_pwsh_elsewhere() {
+        assert_eq!(
+            shell(vec![
+                ("SHELL", "/bin/bash"),
+                ("PSModulePath", "/home/me/.local/share/powershell/Modules"),
+            ]),
+            Some("pwsh".into())
+        );
+    }
+}
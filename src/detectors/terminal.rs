@@ -1,7 +1,8 @@
-use crate::detectors::{Detection, Detector, EnvSnapshot, confidence::TERMINAL};
+use crate::detectors::{Detection, Detector, DetectorMetadata, EnvSnapshot, confidence::TERMINAL};
 use crate::schema::Evidence;
-use crate::traits::stream::StreamInfo;
-use crate::traits::terminal::{ColorLevel, TerminalTraits};
+use crate::traits::stream::{StdinInfo, StreamInfo};
+use crate::traits::terminal::{ColorLevel, TerminalTraits, probe_color_level, resolve_color_level};
+use crate::traits::terminal_db::{emulator, hyperlink_support};
 use serde_json::json;
 
 pub struct TerminalDetector;
@@ -17,6 +18,30 @@ impl Detector for TerminalDetector {
         "terminal"
     }
 
+    fn metadata(&self) -> DetectorMetadata {
+        DetectorMetadata {
+            description: "TTY state, color level, hyperlink support and emulator",
+            contexts: vec!["terminal"],
+            traits: vec![
+                "terminal.interactive",
+                "terminal.color_level",
+                "terminal.stdin.tty",
+                "terminal.stdin.piped",
+                "terminal.stdin.has_data",
+                "terminal.stdout.tty",
+                "terminal.stdout.piped",
+                "terminal.stderr.tty",
+                "terminal.stderr.piped",
+                "terminal.supports_hyperlinks",
+                "terminal.program",
+                "terminal.program_version",
+                "terminal.emulator",
+                "terminal.emulator_version",
+                "terminal.term",
+            ],
+        }
+    }
+
     fn detect(&self, snap: &EnvSnapshot) -> Detection {
         let mut detection = Detection {
             confidence: TERMINAL,
@@ -27,47 +52,54 @@ impl Detector for TerminalDetector {
         let is_interactive = snap.is_tty_stdin() && snap.is_tty_stdout();
 
         // Detect color level and hyperlinks support, but allow override
-        let color_level = if let Some(override_color) = snap.env_vars.get("ENVSENSE_COLOR_LEVEL") {
-            match override_color.as_str() {
-                "none" => ColorLevel::None,
-                "ansi16" => ColorLevel::Ansi16,
-                "ansi256" => ColorLevel::Ansi256,
-                "truecolor" => ColorLevel::Truecolor,
-                _ => ColorLevel::None,
-            }
-        } else {
-            // Use runtime detection
-            let level = supports_color::on(supports_color::Stream::Stdout);
-            match level {
-                Some(l) => {
-                    if l.has_16m {
-                        ColorLevel::Truecolor
-                    } else if l.has_256 {
-                        ColorLevel::Ansi256
-                    } else if l.has_basic {
-                        ColorLevel::Ansi16
-                    } else {
-                        ColorLevel::None
-                    }
-                }
-                None => ColorLevel::None,
+        let env_var = |key: &str| snap.get_env(key);
+        let (color_level, color_decided_by) =
+            if let Some(override_color) = snap.get_env("ENVSENSE_COLOR_LEVEL") {
+                // Replayed snapshots carry their level as an override; keep
+                // the variable that decided it when the two agree
+                let level: ColorLevel = override_color.parse().unwrap_or(ColorLevel::None);
+                let (resolved, decided_by) = resolve_color_level(env_var, || level.clone());
+                (level.clone(), decided_by.filter(|_| resolved == level))
+            } else {
+                // Color variables first, then runtime detection
+                resolve_color_level(env_var, probe_color_level)
+            };
+
+        let (supports_hyperlinks, hyperlinks_decided_by) = match snap
+            .get_env("ENVSENSE_SUPPORTS_HYPERLINKS")
+            .and_then(|v| v.parse::<bool>().ok())
+        {
+            // As with the color level, keep the deciding variable of a
+            // replayed snapshot when it agrees with the override
+            Some(forced) => {
+                let (supported, decided_by) = hyperlink_support(env_var, snap.is_tty_stdout());
+                (forced, decided_by.filter(|_| supported == forced))
             }
+            None => hyperlink_support(env_var, snap.is_tty_stdout()),
         };
 
-        let supports_hyperlinks = snap
-            .env_vars
-            .get("ENVSENSE_SUPPORTS_HYPERLINKS")
-            .and_then(|v| v.parse::<bool>().ok())
-            .unwrap_or_else(|| supports_hyperlinks::on(supports_hyperlinks::Stream::Stdout));
+        let var = |key| snap.get_env(key).filter(|v| !v.is_empty());
+        let emulator = emulator(env_var);
 
         // Create nested TerminalTraits object
         let terminal_traits = TerminalTraits {
             interactive: is_interactive,
             color_level,
-            stdin: StreamInfo::from_tty(snap.is_tty_stdin()),
+            stdin: StdinInfo {
+                has_data: snap.stdin_has_data().filter(|_| !snap.is_tty_stdin()),
+                ..StreamInfo::from_tty(snap.is_tty_stdin()).into()
+            },
             stdout: StreamInfo::from_tty(snap.is_tty_stdout()),
             stderr: StreamInfo::from_tty(snap.is_tty_stderr()),
             supports_hyperlinks,
+            program: var("TERM_PROGRAM"),
+            program_version: var("TERM_PROGRAM_VERSION"),
+            term: var("TERM"),
+            emulator: emulator.as_ref().map(|e| e.id.to_string()),
+            emulator_version: emulator
+                .as_ref()
+                .and_then(|e| e.version.as_ref())
+                .map(|(_, version)| version.clone()),
         };
 
         // Insert as nested object under "terminal" key
@@ -131,10 +163,50 @@ impl Detector for TerminalDetector {
         ));
 
         // Add evidence for interactive detection
+        if terminal_traits.interactive {
+            detection.contexts_add.push("terminal".to_string());
+        }
         detection
             .evidence
             .push(Evidence::terminal_interactive(terminal_traits.interactive));
 
+        // Say which variable, if any, decided the color level
+        if let Some((key, value)) = color_decided_by {
+            detection
+                .evidence
+                .push(Evidence::terminal_env(key, value, "color_level"));
+        }
+
+        if let Some((key, value)) = hyperlinks_decided_by {
+            detection
+                .evidence
+                .push(Evidence::terminal_env(key, value, "supports_hyperlinks"));
+        }
+
+        // The emulator reports itself through environment variables
+        for (key, field) in [
+            ("TERM_PROGRAM", "program"),
+            ("TERM_PROGRAM_VERSION", "program_version"),
+            ("TERM", "term"),
+        ] {
+            if let Some(value) = var(key) {
+                detection
+                    .evidence
+                    .push(Evidence::terminal_env(key, value, field));
+            }
+        }
+        if let Some(emulator) = emulator {
+            let (key, value) = emulator.marker;
+            detection
+                .evidence
+                .push(Evidence::terminal_env(key, value, "emulator"));
+            if let Some((key, version)) = emulator.version {
+                detection
+                    .evidence
+                    .push(Evidence::terminal_env(key, version, "emulator_version"));
+            }
+        }
+
         detection
     }
 }
@@ -217,6 +289,114 @@ mod tests {
         assert_eq!(detection.confidence, 1.0);
     }
 
+    #[test]
+    fn reports_terminal_program_from_env() {
+        let detector = TerminalDetector::new();
+        let snapshot = create_env_snapshot_with_tty(
+            vec![
+                ("TERM_PROGRAM", "iTerm.app"),
+                ("TERM_PROGRAM_VERSION", "3.5.0"),
+                ("TERM", ""),
+            ],
+            true,
+            true,
+            true,
+        );
+
+        let detection = detector.detect(&snapshot);
+
+        let terminal = &detection.traits_patch["terminal"];
+        assert_eq!(terminal["program"], json!("iTerm.app"));
+        assert_eq!(terminal["program_version"], json!("3.5.0"));
+        // Empty values are left unset
+        assert!(terminal.get("term").is_none());
+
+        let program = detection
+            .evidence
+            .iter()
+            .find(|e| e.supports.contains(&"terminal.program".to_string()))
+            .unwrap();
+        assert_eq!(program.key, "TERM_PROGRAM");
+        assert!(!detection.evidence.iter().any(|e| e.key == "TERM"));
+    }
+
+    #[test]
+    fn color_variables_decide_color_level_with_evidence() {
+        let detector = TerminalDetector::new();
+        let snapshot = create_env_snapshot_with_tty(
+            vec![("FORCE_COLOR", "3"), ("CLICOLOR", "0")],
+            false,
+            false,
+            false,
+        );
+
+        let detection = detector.detect(&snapshot);
+
+        assert_eq!(
+            detection.traits_patch["terminal"]["color_level"],
+            json!("truecolor")
+        );
+        let decided_by: Vec<&str> = detection
+            .evidence
+            .iter()
+            .filter(|e| e.supports.contains(&"terminal.color_level".to_string()))
+            .map(|e| e.key.as_str())
+            .collect();
+        assert_eq!(decided_by, ["FORCE_COLOR"]);
+
+        let snapshot = create_env_snapshot_with_tty(
+            vec![("NO_COLOR", "1"), ("FORCE_COLOR", "3")],
+            true,
+            true,
+            true,
+        );
+        let detection = detector.detect(&snapshot);
+        assert_eq!(
+            detection.traits_patch["terminal"]["color_level"],
+            json!("none")
+        );
+    }
+
+    #[test]
+    fn hyperlinks_are_off_inside_old_tmux() {
+        let detector = TerminalDetector::new();
+        let snapshot = create_env_snapshot_with_tty(
+            vec![
+                ("TERM_PROGRAM", "tmux"),
+                ("TERM_PROGRAM_VERSION", "3.3a"),
+                ("KITTY_WINDOW_ID", "1"),
+            ],
+            true,
+            true,
+            true,
+        );
+
+        let detection = detector.detect(&snapshot);
+
+        assert_eq!(
+            detection.traits_patch["terminal"]["supports_hyperlinks"],
+            json!(false)
+        );
+        let decided_by: Vec<&str> = detection
+            .evidence
+            .iter()
+            .filter(|e| {
+                e.supports
+                    .contains(&"terminal.supports_hyperlinks".to_string())
+            })
+            .map(|e| e.key.as_str())
+            .collect();
+        assert_eq!(decided_by, ["TERM_PROGRAM"]);
+
+        let snapshot =
+            create_env_snapshot_with_tty(vec![("KITTY_WINDOW_ID", "1")], true, true, true);
+        let detection = detector.detect(&snapshot);
+        assert_eq!(
+            detection.traits_patch["terminal"]["supports_hyperlinks"],
+            json!(true)
+        );
+    }
+
     #[test]
     fn detects_piped_io() {
         let detector = TerminalDetector::new();
@@ -740,7 +920,7 @@ mod tests {
         assert_eq!(detector.name(), "terminal");
 
         // Test Default implementation
-        let default_detector = TerminalDetector::default();
+        let default_detector = TerminalDetector;
         assert_eq!(default_detector.name(), "terminal");
     }
 
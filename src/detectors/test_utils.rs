@@ -87,13 +87,10 @@ mod tests {
             ("ANOTHER_KEY", "another_value"),
         ]);
 
-        assert_eq!(
-            snapshot.get_env("TEST_KEY"),
-            Some(&"test_value".to_string())
-        );
+        assert_eq!(snapshot.get_env("TEST_KEY"), Some("test_value".to_string()));
         assert_eq!(
             snapshot.get_env("ANOTHER_KEY"),
-            Some(&"another_value".to_string())
+            Some("another_value".to_string())
         );
         assert_eq!(snapshot.get_env("MISSING_KEY"), None);
 
@@ -112,10 +109,7 @@ mod tests {
             true,  // stderr is TTY
         );
 
-        assert_eq!(
-            snapshot.get_env("TEST_KEY"),
-            Some(&"test_value".to_string())
-        );
+        assert_eq!(snapshot.get_env("TEST_KEY"), Some("test_value".to_string()));
 
         // Custom TTY settings should be respected
         assert!(snapshot.is_tty_stdin());
@@ -83,6 +83,81 @@ impl TtyDetector {
             Self::Mock { stderr, .. } => *stderr,
         }
     }
+
+    /// Whether piped stdin already has bytes to read, checked without
+    /// blocking
+    ///
+    /// `None` when stdin is a TTY, for mocks, and on platforms without a
+    /// non-blocking check.
+    pub fn stdin_has_data(&self) -> Option<bool> {
+        match self {
+            Self::Real if !self.is_tty_stdin() => poll_stdin(),
+            _ => None,
+        }
+    }
+}
+
+/// What stdin is connected to, as far as pending input is concerned
+///
+/// `poll` is only trustworthy for pipes and sockets: every unix reports a
+/// regular file as readable, and devices vary (macOS's `poll` does not
+/// support them; Linux, the BSDs and illumos report `/dev/null` as
+/// readable), so those are answered from `fstat` instead.
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StdinSource {
+    /// Redirected from a file of this many bytes
+    File(u64),
+    /// A character or block device such as `/dev/null`
+    Device,
+    /// A pipe, FIFO or socket, which `poll` answers
+    Stream,
+}
+
+#[cfg(unix)]
+impl StdinSource {
+    fn from_stat(stat: &rustix::fs::Stat) -> Self {
+        use rustix::fs::FileType;
+
+        match FileType::from_raw_mode(stat.st_mode as _) {
+            FileType::RegularFile => Self::File(stat.st_size.max(0) as u64),
+            FileType::CharacterDevice | FileType::BlockDevice => Self::Device,
+            _ => Self::Stream,
+        }
+    }
+
+    /// The answer when no poll is needed
+    fn pending(self) -> Option<bool> {
+        match self {
+            Self::File(len) => Some(len > 0),
+            Self::Device => Some(false),
+            Self::Stream => None,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn poll_stdin() -> Option<bool> {
+    use rustix::event::{PollFd, PollFlags, poll};
+    use std::os::fd::AsFd;
+
+    let stdin = std::io::stdin();
+    let fd = stdin.as_fd();
+    let Ok(stat) = rustix::fs::fstat(fd) else {
+        // A closed stdin has nothing to read
+        return Some(false);
+    };
+    if let Some(pending) = StdinSource::from_stat(&stat).pending() {
+        return Some(pending);
+    }
+    let mut fds = [PollFd::new(&fd, PollFlags::IN)];
+    poll(&mut fds, Some(&rustix::event::Timespec::default())).ok()?;
+    Some(fds[0].revents().contains(PollFlags::IN))
+}
+
+#[cfg(not(unix))]
+fn poll_stdin() -> Option<bool> {
+    None
 }
 
 #[cfg(test)]
@@ -95,6 +170,7 @@ mod tests {
         assert!(detector.is_tty_stdin());
         assert!(!detector.is_tty_stdout());
         assert!(detector.is_tty_stderr());
+        assert_eq!(detector.stdin_has_data(), None);
     }
 
     #[test]
@@ -123,5 +199,31 @@ mod tests {
         let _stdin = detector.is_tty_stdin();
         let _stdout = detector.is_tty_stdout();
         let _stderr = detector.is_tty_stderr();
+        let _has_data = detector.stdin_has_data();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stdin_source_from_fstat() {
+        use std::io::Write;
+        use std::os::fd::AsFd;
+
+        let source =
+            |fd: std::os::fd::BorrowedFd| StdinSource::from_stat(&rustix::fs::fstat(fd).unwrap());
+
+        let mut file = tempfile::tempfile().unwrap();
+        assert_eq!(source(file.as_fd()), StdinSource::File(0));
+        assert_eq!(StdinSource::File(0).pending(), Some(false));
+        file.write_all(b"input").unwrap();
+        assert_eq!(source(file.as_fd()), StdinSource::File(5));
+        assert_eq!(StdinSource::File(5).pending(), Some(true));
+
+        let null = std::fs::File::open("/dev/null").unwrap();
+        assert_eq!(source(null.as_fd()), StdinSource::Device);
+        assert_eq!(StdinSource::Device.pending(), Some(false));
+
+        let (socket, _peer) = std::os::unix::net::UnixStream::pair().unwrap();
+        assert_eq!(source(socket.as_fd()), StdinSource::Stream);
+        assert_eq!(StdinSource::Stream.pending(), None);
     }
 }
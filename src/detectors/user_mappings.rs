@@ -0,0 +1,491 @@
+//! User-defined environment mappings loaded from files.
+//!
+//! Every `.toml`, `.yaml`/`.yml` or `.json` file in the mappings directory
+//! (`~/.config/envsense/mappings` by default) may define extra
+//! [`EnvMapping`]s for the agent, IDE and CI detectors:
+//!
+//! ```toml
+//! [[ci]]
+//! id = "acme-ci"
+//! confidence = 0.9
+//! indicators = [{ key = "ACME_BUILD_ID" }]
+//! ```
+//!
+//! Precedence: a user mapping whose id matches a built-in replaces it in
+//! place; new ids are tried before the built-ins, so they win ties. The same
+//! id defined twice across user files is an error.
+//!
+//! A mapping adds every context in its `contexts` list, so mappings can
+//! introduce contexts of their own. Describe them in a `contexts` table to
+//! have `check --list` explain them:
+//!
+//! ```toml
+//! [contexts]
+//! sandbox = "Sandboxed execution environment"
+//!
+//! [[agent]]
+//! id = "sandboxed-agent"
+//! confidence = 0.9
+//! indicators = [{ key = "SANDBOX_AGENT" }]
+//! contexts = ["agent", "sandbox"]
+//! ```
+
+use crate::check::ContextInfo;
+use crate::detectors::env_mapping::EnvMapping;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// File extensions recognized in the mappings directory
+pub const MAPPING_EXTENSIONS: &[&str] = &["toml", "yaml", "yml", "json"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum MappingError {
+    #[error("failed to read mapping file '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("invalid mapping file '{path}': {message}")]
+    Parse { path: String, message: String },
+    #[error("unsupported mapping file '{0}' (expected one of: toml, yaml, yml, json)")]
+    UnsupportedFormat(String),
+    #[error("{kind} mapping '{id}' in '{path}' is already defined in '{first}'")]
+    DuplicateId {
+        kind: &'static str,
+        id: String,
+        path: String,
+        first: String,
+    },
+}
+
+/// Which declarative detector a mapping belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingKind {
+    Agent,
+    Ide,
+    Ci,
+}
+
+impl MappingKind {
+    pub const ALL: [MappingKind; 3] = [MappingKind::Agent, MappingKind::Ide, MappingKind::Ci];
+
+    /// Table name in mapping files, which is also the context it adds
+    pub fn name(self) -> &'static str {
+        match self {
+            MappingKind::Agent => "agent",
+            MappingKind::Ide => "ide",
+            MappingKind::Ci => "ci",
+        }
+    }
+
+    /// Id the detector reports when `mapping` is selected
+    pub fn detected_id(self, mapping: &EnvMapping) -> String {
+        self.facet_key()
+            .and_then(|key| mapping.facets.get(key))
+            .unwrap_or(&mapping.id)
+            .clone()
+    }
+
+    /// Facet the detector reads the detected id from, if any
+    fn facet_key(self) -> Option<&'static str> {
+        match self {
+            MappingKind::Agent => None,
+            MappingKind::Ide => Some("ide_id"),
+            MappingKind::Ci => Some("ci_id"),
+        }
+    }
+}
+
+/// Contents of a single mapping file, one list per detector
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MappingFile {
+    /// Descriptions of contexts the mappings add, by name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub contexts: BTreeMap<String, String>,
+    pub agent: Vec<EnvMapping>,
+    pub ide: Vec<EnvMapping>,
+    pub ci: Vec<EnvMapping>,
+}
+
+impl MappingFile {
+    /// Load a mapping file, choosing the format from its extension
+    pub fn load(path: &Path) -> Result<Self, MappingError> {
+        let content = std::fs::read_to_string(path).map_err(|source| MappingError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::parse(&content, path)
+    }
+
+    /// Parse file contents; `path` selects the format and labels errors
+    pub fn parse(content: &str, path: &Path) -> Result<Self, MappingError> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let parsed: Result<Self, String> = match extension {
+            "toml" => toml::from_str(content).map_err(|e| e.to_string()),
+            "yaml" | "yml" => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+            "json" => serde_json::from_str(content).map_err(|e| e.to_string()),
+            _ => return Err(MappingError::UnsupportedFormat(path.display().to_string())),
+        };
+        let mut file = parsed.map_err(|message| MappingError::Parse {
+            path: path.display().to_string(),
+            message: message.trim_end().to_string(),
+        })?;
+        file.fill_defaults();
+        Ok(file)
+    }
+
+    pub fn mappings(&self, kind: MappingKind) -> &[EnvMapping] {
+        match kind {
+            MappingKind::Agent => &self.agent,
+            MappingKind::Ide => &self.ide,
+            MappingKind::Ci => &self.ci,
+        }
+    }
+
+    fn mappings_mut(&mut self, kind: MappingKind) -> &mut Vec<EnvMapping> {
+        match kind {
+            MappingKind::Agent => &mut self.agent,
+            MappingKind::Ide => &mut self.ide,
+            MappingKind::Ci => &mut self.ci,
+        }
+    }
+
+    /// Fill in the context and id facet that built-in mappings spell out
+    fn fill_defaults(&mut self) {
+        for kind in MappingKind::ALL {
+            for mapping in self.mappings_mut(kind) {
+                if mapping.contexts.is_empty() {
+                    mapping.contexts.push(kind.name().to_string());
+                }
+                if let Some(key) = kind.facet_key() {
+                    mapping
+                        .facets
+                        .entry(key.to_string())
+                        .or_insert_with(|| mapping.id.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Mapping files loaded from a directory, in load order
+#[derive(Debug, Clone, Default)]
+pub struct UserMappings {
+    pub files: Vec<(PathBuf, MappingFile)>,
+}
+
+impl UserMappings {
+    /// Load every recognized file in `dir`, sorted by file name
+    ///
+    /// A missing directory yields no mappings.
+    pub fn load_dir(dir: &Path) -> Result<Self, MappingError> {
+        let paths = mapping_files(dir).map_err(|source| MappingError::Io {
+            path: dir.display().to_string(),
+            source,
+        })?;
+
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let file = MappingFile::load(&path)?;
+            files.push((path, file));
+        }
+        Self::from_files(files)
+    }
+
+    /// Combine already-parsed files, rejecting ids defined more than once
+    pub fn from_files(files: Vec<(PathBuf, MappingFile)>) -> Result<Self, MappingError> {
+        for kind in MappingKind::ALL {
+            let mut seen: Vec<(&str, &Path)> = Vec::new();
+            for (path, file) in &files {
+                for mapping in file.mappings(kind) {
+                    if let Some((_, first)) = seen.iter().find(|(id, _)| *id == mapping.id) {
+                        return Err(MappingError::DuplicateId {
+                            kind: kind.name(),
+                            id: mapping.id.clone(),
+                            path: path.display().to_string(),
+                            first: first.display().to_string(),
+                        });
+                    }
+                    seen.push((&mapping.id, path));
+                }
+            }
+        }
+        Ok(Self { files })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.iter().all(|(_, file)| {
+            MappingKind::ALL
+                .iter()
+                .all(|kind| file.mappings(*kind).is_empty())
+        })
+    }
+
+    /// Contexts the files describe or their mappings add, in load order
+    ///
+    /// The first description of a context wins; contexts mappings add
+    /// without one are described by the mapping.
+    pub fn declared_contexts(&self) -> Vec<ContextInfo> {
+        let mut contexts: Vec<ContextInfo> = Vec::new();
+        let mut declare = |name: &str, description: String| {
+            if !contexts.iter().any(|c| c.name == name) {
+                contexts.push(ContextInfo::new(name, description));
+            }
+        };
+        for (_, file) in &self.files {
+            for (name, description) in &file.contexts {
+                declare(name, description.clone());
+            }
+        }
+        for (_, file) in &self.files {
+            for kind in MappingKind::ALL {
+                for mapping in file.mappings(kind) {
+                    for context in &mapping.contexts {
+                        declare(
+                            context,
+                            format!("Added by {} mapping '{}'", kind.name(), mapping.id),
+                        );
+                    }
+                }
+            }
+        }
+        contexts
+    }
+
+    /// All user mappings of one kind, in load order
+    pub fn mappings(&self, kind: MappingKind) -> Vec<EnvMapping> {
+        self.files
+            .iter()
+            .flat_map(|(_, file)| file.mappings(kind).iter().cloned())
+            .collect()
+    }
+}
+
+/// Files in `dir` with a [`MAPPING_EXTENSIONS`] extension, sorted by name
+///
+/// A missing directory yields no files; other files are ignored.
+pub fn mapping_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let recognized = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| MAPPING_EXTENSIONS.contains(&e));
+        if recognized && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Merge user mappings into the built-ins following the module precedence rules
+pub fn merge_mappings(mut builtin: Vec<EnvMapping>, user: Vec<EnvMapping>) -> Vec<EnvMapping> {
+    let mut added = Vec::new();
+    for mapping in user {
+        match builtin.iter_mut().find(|b| b.id == mapping.id) {
+            Some(existing) => *existing = mapping,
+            None => added.push(mapping),
+        }
+    }
+    added.extend(builtin);
+    added
+}
+
+static INSTALLED: OnceLock<UserMappings> = OnceLock::new();
+
+/// Make user mappings visible to `get_agent_mappings()` and friends
+///
+/// Can only be done once per process; returns `false` if mappings were
+/// already installed.
+pub fn install(mappings: UserMappings) -> bool {
+    INSTALLED.set(mappings).is_ok()
+}
+
+/// Merge installed user mappings of `kind`, if any, into `builtin`
+pub(crate) fn with_installed(kind: MappingKind, builtin: Vec<EnvMapping>) -> Vec<EnvMapping> {
+    match INSTALLED.get() {
+        Some(user) => merge_mappings(builtin, user.mappings(kind)),
+        None => builtin,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::env_mapping::get_ci_mappings;
+    use std::collections::HashMap;
+
+    fn parse(content: &str, name: &str) -> Result<MappingFile, MappingError> {
+        MappingFile::parse(content, Path::new(name))
+    }
+
+    #[test]
+    fn parses_all_formats_with_defaults() {
+        let toml = parse(
+            r#"
+[[ci]]
+id = "acme-ci"
+confidence = 0.9
+indicators = [{ key = "ACME_BUILD_ID" }]
+"#,
+            "acme.toml",
+        )
+        .unwrap();
+        let yaml = parse(
+            "ci:\n  - id: acme-ci\n    confidence: 0.9\n    indicators:\n      - key: ACME_BUILD_ID\n",
+            "acme.yaml",
+        )
+        .unwrap();
+        let json = parse(
+            r#"{"ci": [{"id": "acme-ci", "confidence": 0.9, "indicators": [{"key": "ACME_BUILD_ID"}]}]}"#,
+            "acme.json",
+        )
+        .unwrap();
+
+        for file in [toml, yaml, json] {
+            let mapping = &file.ci[0];
+            assert_eq!(mapping.contexts, ["ci"]);
+            assert_eq!(mapping.facets["ci_id"], "acme-ci");
+            assert!(mapping.matches(&HashMap::from([(
+                "ACME_BUILD_ID".to_string(),
+                "7".to_string()
+            )])));
+        }
+    }
+
+    #[test]
+    fn parse_errors_name_the_file_and_line() {
+        let err = parse("[[ci]]\nid = \"x\"\nconfidence = \"high\"\n", "bad.toml").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("bad.toml"), "{}", message);
+        assert!(message.contains("line 3"), "{}", message);
+
+        let err = parse("{}", "mappings.txt").unwrap_err();
+        assert!(matches!(err, MappingError::UnsupportedFormat(_)));
+
+        let err = parse("[[cloud]]\nid = \"x\"\n", "unknown.toml").unwrap_err();
+        assert!(err.to_string().contains("cloud"));
+    }
+
+    #[test]
+    fn user_mappings_replace_or_precede_builtins() {
+        let file = parse(
+            r#"
+[[ci]]
+id = "github-actions"
+confidence = 0.5
+indicators = [{ key = "MY_GHA" }]
+
+[[ci]]
+id = "acme-ci"
+confidence = 0.9
+indicators = [{ key = "ACME_BUILD_ID" }]
+"#,
+            "ci.toml",
+        )
+        .unwrap();
+
+        let builtin = get_ci_mappings();
+        let builtin_len = builtin.len();
+        let merged = merge_mappings(builtin, file.ci);
+
+        assert_eq!(merged.len(), builtin_len + 1);
+        assert_eq!(merged[0].id, "acme-ci");
+        let gha: Vec<_> = merged.iter().filter(|m| m.id == "github-actions").collect();
+        assert_eq!(gha.len(), 1);
+        assert_eq!(gha[0].indicators[0].key, "MY_GHA");
+    }
+
+    #[test]
+    fn declared_contexts_come_from_tables_and_mappings() {
+        let a = parse(
+            "[contexts]\nsandbox = \"Sandboxed\"\n\n[[agent]]\nid = \"bot\"\nconfidence = 0.9\nindicators = []\ncontexts = [\"agent\", \"k8s\"]\n",
+            "a.toml",
+        )
+        .unwrap();
+        let b = parse(
+            r#"{"contexts": {"sandbox": "Later", "vm": "Virtual machine"}}"#,
+            "b.json",
+        )
+        .unwrap();
+
+        let user =
+            UserMappings::from_files(vec![("a.toml".into(), a), ("b.json".into(), b)]).unwrap();
+        let contexts: Vec<(String, String)> = user
+            .declared_contexts()
+            .into_iter()
+            .map(|c| (c.name, c.description))
+            .collect();
+        let expected = [
+            ("sandbox", "Sandboxed"),
+            ("vm", "Virtual machine"),
+            ("agent", "Added by agent mapping 'bot'"),
+            ("k8s", "Added by agent mapping 'bot'"),
+        ];
+        assert_eq!(
+            contexts,
+            expected.map(|(name, description)| (name.to_string(), description.to_string()))
+        );
+    }
+
+    #[test]
+    fn duplicate_ids_across_files_are_rejected() {
+        let a = parse(
+            "[[agent]]\nid = \"bot\"\nconfidence = 0.9\nindicators = []\n",
+            "a.toml",
+        )
+        .unwrap();
+        let b = parse(
+            "agent:\n  - id: bot\n    confidence: 0.8\n    indicators: []\n",
+            "b.yml",
+        )
+        .unwrap();
+
+        let err =
+            UserMappings::from_files(vec![("a.toml".into(), a), ("b.yml".into(), b)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "agent mapping 'bot' in 'b.yml' is already defined in 'a.toml'"
+        );
+    }
+
+    #[test]
+    fn load_dir_reads_recognized_files_in_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("b.json"),
+            r#"{"ide": [{"id": "fleet", "confidence": 0.8, "indicators": [{"key": "FLEET"}]}]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("a.toml"),
+            "[[ide]]\nid = \"helix\"\nconfidence = 0.8\nindicators = [{ key = \"HELIX\" }]\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("README.md"), "not a mapping").unwrap();
+
+        let user = UserMappings::load_dir(dir.path()).unwrap();
+        let ids: Vec<_> = user
+            .mappings(MappingKind::Ide)
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(ids, ["helix", "fleet"]);
+
+        assert!(
+            UserMappings::load_dir(&dir.path().join("missing"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
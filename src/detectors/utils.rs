@@ -1,12 +1,15 @@
 use crate::detectors::env_mapping::EnvMapping;
-use crate::detectors::{EnvSnapshot, confidence::HIGH};
+use crate::detectors::{Detection, EnvProvider, EnvSnapshot, confidence::HIGH};
+use crate::log_event;
+use crate::logging::{self, Level, trace_event, trace_span};
 use crate::schema::Evidence;
-use std::collections::HashMap;
+use crate::traits::NestedTraits;
+use envsense_macros::MergeValue;
 
 /// Generate evidence from a mapping's indicators
 pub fn generate_evidence_from_mapping(
     mapping: &EnvMapping,
-    env_vars: &HashMap<String, String>,
+    env_vars: &dyn EnvProvider,
     supports: Vec<String>,
 ) -> Vec<Evidence> {
     let mut evidence = Vec::new();
@@ -20,17 +23,81 @@ pub fn generate_evidence_from_mapping(
         evidence.push(
             evidence_item
                 .with_supports(supports.clone())
-                .with_confidence(mapping.confidence),
+                .with_confidence(mapping.confidence)
+                .with_mapping(&mapping.id),
         );
     }
 
     evidence
 }
 
+/// Add the contexts `mapping` lists besides the detector's own `context`,
+/// with the detection's evidence supporting them
+///
+/// This is how mapping files add contexts such as `sandbox` that no
+/// detector knows about.
+pub fn add_mapping_contexts(detection: &mut Detection, mapping: &EnvMapping, context: &str) {
+    for extra in mapping.contexts.iter().filter(|c| *c != context) {
+        if !detection.contexts_add.contains(extra) {
+            detection.contexts_add.push(extra.clone());
+        }
+        for evidence in &mut detection.evidence {
+            if !evidence.supports.contains(extra) {
+                evidence.supports.push(extra.clone());
+            }
+        }
+    }
+}
+
+/// Patch the traits `mapping`'s value mappings extract from `env_vars`,
+/// keeping any warnings about them
+///
+/// Target keys that name a field of the detector's `context` group patch
+/// that field, so `version` patches `agent.version`. Other keys are used as
+/// they are and land in `extra` unless they name a trait path.
+pub fn add_mapping_values(
+    detection: &mut Detection,
+    mapping: &EnvMapping,
+    env_vars: &dyn EnvProvider,
+    context: &str,
+) {
+    let (values, warnings) = mapping.extract_values_with_warnings(env_vars);
+    detection.warnings.extend(warnings);
+    for (key, value) in values {
+        let scoped = format!("{}.{}", context, key);
+        let path = if NestedTraits::SCHEMA.resolves(&scoped) {
+            scoped
+        } else {
+            key
+        };
+        detection.traits_patch.insert(path, value);
+    }
+}
+
+/// Log which variables each mapping consults and whether it matched
+///
+/// Only evaluated at [`Level::Debug`].
+pub fn log_checked_mappings(context: &str, mappings: &[EnvMapping], env_vars: &dyn EnvProvider) {
+    if !logging::enabled(Level::Debug) {
+        return;
+    }
+    for mapping in mappings {
+        let vars: Vec<&str> = mapping.indicators.iter().map(|i| i.key.as_str()).collect();
+        log_event!(
+            Debug,
+            "mapping.checked",
+            context = context,
+            id = mapping.id,
+            matched = mapping.matches(env_vars),
+            vars = vars.join(","),
+        );
+    }
+}
+
 /// Find the best mapping by confidence (highest confidence wins)
 pub fn find_best_mapping_by_confidence<'a>(
     mappings: &'a [EnvMapping],
-    env_vars: &HashMap<String, String>,
+    env_vars: &dyn EnvProvider,
 ) -> Option<&'a EnvMapping> {
     let mut best_mapping = None;
     let mut best_confidence = 0.0;
@@ -48,7 +115,7 @@ pub fn find_best_mapping_by_confidence<'a>(
 /// Find the best mapping by priority (highest priority wins)
 pub fn find_best_mapping_by_priority<'a>(
     mappings: &'a [EnvMapping],
-    env_vars: &HashMap<String, String>,
+    env_vars: &dyn EnvProvider,
 ) -> Option<&'a EnvMapping> {
     let mut best_mapping = None;
     let mut best_priority = 0;
@@ -84,16 +151,27 @@ pub struct DetectionConfig {
 /// Basic declarative detection pattern
 pub fn basic_declarative_detection(
     mappings: &[EnvMapping],
-    env_vars: &HashMap<String, String>,
+    env_vars: &dyn EnvProvider,
     config: &DetectionConfig,
     selection_strategy: SelectionStrategy,
 ) -> (Option<String>, f32, Vec<Evidence>) {
+    trace_span!("mappings", context = %config.context_name, mappings = mappings.len());
+    log_checked_mappings(&config.context_name, mappings, env_vars);
     let best_mapping = match selection_strategy {
         SelectionStrategy::Confidence => find_best_mapping_by_confidence(mappings, env_vars),
         SelectionStrategy::Priority => find_best_mapping_by_priority(mappings, env_vars),
     };
 
     if let Some(mapping) = best_mapping {
+        log_event!(
+            Debug,
+            "mapping.selected",
+            context = config.context_name,
+            id = mapping.id,
+            confidence = mapping.confidence,
+            strategy = format!("{:?}", selection_strategy).to_lowercase(),
+        );
+        trace_event!(id = %mapping.id, confidence = mapping.confidence, "mapping.selected");
         let id = mapping.facets.get(&config.facet_key).cloned();
         let confidence = mapping.confidence;
         let evidence = if config.should_generate_evidence {
@@ -126,20 +204,33 @@ pub fn check_generic_overrides(
 
     // Check for assume override (disable detection)
     if snap.get_env(&assume_key).map(|v| v == "1").unwrap_or(false) {
+        log_event!(
+            Debug,
+            "override.used",
+            var = assume_key,
+            detector = detector_type
+        );
         return Some((None, 0.0, vec![]));
     }
 
     // Check for direct override
     if let Some(override_value) = snap.get_env(&override_key) {
+        log_event!(
+            Debug,
+            "override.used",
+            var = override_key,
+            detector = detector_type,
+            value = override_value,
+        );
         if override_value == "none" {
             return Some((None, 0.0, vec![]));
         } else {
             let evidence = vec![
-                Evidence::env_var(&override_key, override_value)
+                Evidence::env_var(&override_key, &override_value)
                     .with_supports(vec![format!("{}.id", detector_type)])
                     .with_confidence(HIGH),
             ];
-            return Some((Some(override_value.clone()), HIGH, evidence));
+            return Some((Some(override_value), HIGH, evidence));
         }
     }
 
@@ -150,6 +241,7 @@ pub fn check_generic_overrides(
 mod tests {
     use super::*;
     use crate::detectors::confidence::HIGH;
+    use std::collections::HashMap;
 
     fn create_test_mapping(id: &str, confidence: f32, priority: u8) -> EnvMapping {
         EnvMapping {
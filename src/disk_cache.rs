@@ -0,0 +1,125 @@
+//! Small files in the user cache directory, keyed by a hash of the
+//! environment.
+//!
+//! Used for rendered prompt lines and, when `[cache]` is enabled, whole
+//! detection results. Entries expire after a time-to-live and are pruned on
+//! the next write; a changed environment simply hashes to a different key.
+
+use crate::detectors::EnvSnapshot;
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Variables that change constantly without affecting detection
+///
+/// Leaving them out of the cache key keeps the cache warm while moving
+/// between directories.
+const VOLATILE_VARS: &[&str] = &["PWD", "OLDPWD", "_", "SHLVL", "LINES", "COLUMNS"];
+
+/// Cached strings on disk, one file per key
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl DiskCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    /// `name` under the user cache directory (`$XDG_CACHE_HOME/envsense` on
+    /// Linux), if there is one
+    pub fn user_dir(name: &str) -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("envsense").join(name))
+    }
+
+    /// Cache key for `snap` with `settings`
+    ///
+    /// `settings` must capture everything else that changes the cached
+    /// value, such as the detector configuration.
+    pub fn key(snap: &EnvSnapshot, settings: &str) -> String {
+        let env: BTreeMap<_, _> = snap
+            .env()
+            .iter()
+            .filter(|(k, _)| !VOLATILE_VARS.contains(&k.as_str()))
+            .collect();
+
+        let mut hasher = DefaultHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        env.hash(&mut hasher);
+        snap.is_tty_stdin().hash(&mut hasher);
+        snap.is_tty_stdout().hash(&mut hasher);
+        snap.is_tty_stderr().hash(&mut hasher);
+        snap.stdin_has_data().hash(&mut hasher);
+        settings.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// The cached value for `key`, unless it is missing or older than the TTL
+    pub fn get(&self, key: &str) -> Option<String> {
+        let path = self.dir.join(key);
+        let age = std::fs::metadata(&path)
+            .ok()?
+            .modified()
+            .ok()?
+            .elapsed()
+            .ok()?;
+        if age > self.ttl {
+            return None;
+        }
+        std::fs::read_to_string(path).ok()
+    }
+
+    /// Store `value` under `key` and prune expired entries
+    ///
+    /// Failures are ignored: the cache only ever saves work.
+    pub fn put(&self, key: &str, value: &str) {
+        if std::fs::create_dir_all(&self.dir).is_err() {
+            return;
+        }
+        if let Ok(entries) = std::fs::read_dir(&self.dir) {
+            for entry in entries.filter_map(Result::ok) {
+                let expired = entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.elapsed().ok())
+                    .is_some_and(|age| age > self.ttl);
+                if expired {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        let _ = std::fs::write(self.dir.join(key), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ignores_volatile_vars() {
+        let snap = |pwd: &str, term: &str| {
+            crate::detectors::test_utils::create_env_snapshot(vec![("PWD", pwd), ("TERM", term)])
+        };
+        let key = DiskCache::key(&snap("/a", "xterm"), "s");
+        assert_eq!(key, DiskCache::key(&snap("/b", "xterm"), "s"));
+        assert_ne!(key, DiskCache::key(&snap("/a", "dumb"), "s"));
+        assert_ne!(key, DiskCache::key(&snap("/a", "xterm"), "t"));
+    }
+
+    #[test]
+    fn entries_expire() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = DiskCache::new(dir.path().join("prompt"), Duration::from_secs(60));
+        assert_eq!(cache.get("k"), None);
+        cache.put("k", "⚙ ci");
+        assert_eq!(cache.get("k").as_deref(), Some("⚙ ci"));
+
+        let expired = DiskCache::new(dir.path().join("prompt"), Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(expired.get("k"), None);
+    }
+}
@@ -0,0 +1,350 @@
+//! Diagnostics for `envsense doctor`.
+//!
+//! Runs detection and flags environment setups that commonly lead to
+//! surprising results, with a suggested fix for each.
+
+use crate::config::CONFIG_ENV_VARS;
+use crate::detectors::EnvSnapshot;
+use crate::detectors::env_mapping::get_agent_mappings;
+use crate::hook::DETECTED_PREFIX;
+use crate::schema::EnvSense;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Where to report environments envsense does not recognize
+pub const ISSUES_URL: &str = "https://github.com/technicalpickles/envsense/issues";
+
+const VALID_COLOR_LEVELS: &[&str] = &["none", "ansi16", "ansi256", "truecolor"];
+const TTY_OVERRIDES: &[&str] = &[
+    "ENVSENSE_TTY_STDIN",
+    "ENVSENSE_TTY_STDOUT",
+    "ENVSENSE_TTY_STDERR",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+}
+
+/// A single diagnostic finding
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Stable identifier for scripting (e.g. `term_unset`)
+    pub code: &'static str,
+    pub message: String,
+    pub suggestion: String,
+}
+
+impl Finding {
+    fn new(
+        severity: Severity,
+        code: &'static str,
+        message: impl Into<String>,
+        suggestion: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            suggestion: suggestion.into(),
+        }
+    }
+}
+
+/// Detection summary plus findings
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Report {
+    pub detected: EnvSense,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    pub fn has_warnings(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|f| f.severity == Severity::Warning)
+    }
+}
+
+/// Collect findings for `snap`, given the detection result `env` for it
+pub fn diagnose(snap: &EnvSnapshot, env: EnvSense) -> Report {
+    let mut findings = Vec::new();
+
+    check_term(snap, &mut findings);
+    check_color_conflicts(snap, &mut findings);
+    check_overrides(snap, &mut findings);
+    check_ci_vendor(snap, &env, &mut findings);
+    check_unrecognized_ide(snap, &env, &mut findings);
+    check_ambiguous_agent(snap, &mut findings);
+
+    Report {
+        detected: env,
+        findings,
+    }
+}
+
+fn check_term(snap: &EnvSnapshot, findings: &mut Vec<Finding>) {
+    match snap.get_env("TERM").as_deref() {
+        None if snap.is_tty_stdout() => findings.push(Finding::new(
+            Severity::Warning,
+            "term_unset",
+            "TERM is unset although stdout is a terminal",
+            "export TERM (e.g. TERM=xterm-256color) so color support can be detected",
+        )),
+        Some("dumb") if snap.is_tty_stdout() => findings.push(Finding::new(
+            Severity::Info,
+            "term_dumb",
+            "TERM=dumb disables color and cursor features in most tools",
+            "set TERM to your terminal's real type if this is an interactive session",
+        )),
+        _ => {}
+    }
+}
+
+fn check_color_conflicts(snap: &EnvSnapshot, findings: &mut Vec<Finding>) {
+    let no_color = snap.get_env("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let force_color = snap.get_env("FORCE_COLOR").is_some_and(|v| !v.is_empty());
+    if no_color && force_color {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "color_conflict",
+            "NO_COLOR and FORCE_COLOR are both set",
+            "unset one of them; envsense lets NO_COLOR win, but other tools disagree",
+        ));
+    }
+}
+
+fn check_overrides(snap: &EnvSnapshot, findings: &mut Vec<Finding>) {
+    let tty_set: Vec<&str> = TTY_OVERRIDES
+        .iter()
+        .copied()
+        .filter(|k| snap.get_env(k).is_some())
+        .collect();
+    if !tty_set.is_empty() && tty_set.len() < TTY_OVERRIDES.len() {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "partial_tty_override",
+            format!(
+                "only {} set; TTY overrides are ignored unless all three are present",
+                tty_set.join(", ")
+            ),
+            format!("set all of {}", TTY_OVERRIDES.join(", ")),
+        ));
+    }
+
+    if let Some(level) = snap.get_env("ENVSENSE_COLOR_LEVEL")
+        && !VALID_COLOR_LEVELS.contains(&level.as_str())
+    {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "invalid_color_override",
+            format!("ENVSENSE_COLOR_LEVEL={} is not a valid level", level),
+            format!("use one of: {}", VALID_COLOR_LEVELS.join(", ")),
+        ));
+    }
+
+    let active: Vec<String> = snap
+        .env()
+        .with_prefix("ENVSENSE_")
+        .into_iter()
+        .filter(|(k, _)| {
+            !TTY_OVERRIDES.contains(&k.as_str())
+                && k.as_str() != "ENVSENSE_COLOR_LEVEL"
+                && !CONFIG_ENV_VARS.contains(&k.as_str())
+                && !k.starts_with(DETECTED_PREFIX)
+        })
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect();
+    if !active.is_empty() {
+        findings.push(Finding::new(
+            Severity::Info,
+            "overrides_active",
+            format!("detection overrides are active: {}", active.join(", ")),
+            "unset them to see what envsense detects on its own",
+        ));
+    }
+}
+
+fn check_ci_vendor(snap: &EnvSnapshot, env: &EnvSense, findings: &mut Vec<Finding>) {
+    let ci_flag = snap
+        .get_env("CI")
+        .is_some_and(|v| matches!(v.to_lowercase().as_str(), "true" | "1"));
+    let generic = env
+        .traits
+        .ci
+        .vendor
+        .as_deref()
+        .is_none_or(|v| v == "generic");
+    if ci_flag && generic {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "ci_vendor_unknown",
+            "CI is set but no CI vendor mapping matched",
+            format!(
+                "if this runs on a known CI service, please report its environment variables at {}",
+                ISSUES_URL
+            ),
+        ));
+    }
+}
+
+fn check_unrecognized_ide(snap: &EnvSnapshot, env: &EnvSense, findings: &mut Vec<Finding>) {
+    if env.contexts.iter().any(|c| c == "ide") {
+        return;
+    }
+    if let Some(program) = snap.get_env("TERM_PROGRAM")
+        && matches!(program.as_str(), "zed" | "JetBrains-JediTerm")
+    {
+        findings.push(Finding::new(
+            Severity::Info,
+            "ide_unrecognized",
+            format!(
+                "TERM_PROGRAM={} looks like an editor terminal but no IDE was detected",
+                program
+            ),
+            format!("please report this environment at {}", ISSUES_URL),
+        ));
+    }
+}
+
+fn check_ambiguous_agent(snap: &EnvSnapshot, findings: &mut Vec<Finding>) {
+    let matched: Vec<String> = get_agent_mappings()
+        .into_iter()
+        .filter(|m| m.contexts.iter().any(|c| c == "agent") && m.matches(snap.env()))
+        .map(|m| m.id)
+        .collect();
+    if matched.len() > 1 {
+        findings.push(Finding::new(
+            Severity::Info,
+            "agent_ambiguous",
+            format!("several agent mappings match: {}", matched.join(", ")),
+            "the first match wins; run `envsense explain agent` to see why",
+        ));
+    }
+}
+
+/// Render a report as human-readable text
+pub fn render_report(report: &Report) -> String {
+    let mut out = String::new();
+    let env = &report.detected;
+
+    let mut contexts = env.contexts.clone();
+    contexts.sort();
+    let _ = writeln!(out, "Detection:");
+    let _ = writeln!(
+        out,
+        "  contexts: {}",
+        if contexts.is_empty() {
+            "none".to_string()
+        } else {
+            contexts.join(", ")
+        }
+    );
+    for (name, id) in [
+        ("agent", &env.traits.agent.id),
+        ("ide", &env.traits.ide.id),
+        ("ci", &env.traits.ci.id),
+    ] {
+        if let Some(id) = id {
+            let _ = writeln!(out, "  {}.id: {}", name, id);
+        }
+    }
+    let terminal = &env.traits.terminal;
+    let _ = writeln!(
+        out,
+        "  terminal: interactive={}, stdin.tty={}, stdout.tty={}, stderr.tty={}",
+        terminal.interactive, terminal.stdin.tty, terminal.stdout.tty, terminal.stderr.tty
+    );
+    out.push('\n');
+
+    if report.findings.is_empty() {
+        let _ = writeln!(out, "No problems found.");
+        return out;
+    }
+
+    let _ = writeln!(out, "Findings:");
+    for finding in &report.findings {
+        let label = match finding.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+        };
+        let _ = writeln!(out, "  [{}] {}", label, finding.message);
+        let _ = writeln!(out, "      fix: {}", finding.suggestion);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::test_utils::create_env_snapshot;
+    use std::collections::HashMap;
+
+    fn diagnose_snapshot(snap: &EnvSnapshot) -> Report {
+        diagnose(snap, EnvSense::detect_from(snap))
+    }
+
+    fn codes(report: &Report) -> Vec<&'static str> {
+        report.findings.iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn clean_environment_has_no_findings() {
+        let snap = create_env_snapshot(vec![("TERM", "xterm-256color")]);
+        let report = diagnose_snapshot(&snap);
+        assert!(report.findings.is_empty());
+        assert!(!report.has_warnings());
+    }
+
+    #[test]
+    fn flags_unset_term_on_tty() {
+        let snap = EnvSnapshot::with_mock_tty(HashMap::new(), true, true, true);
+        assert_eq!(codes(&diagnose_snapshot(&snap)), ["term_unset"]);
+    }
+
+    #[test]
+    fn flags_color_conflict() {
+        let snap = create_env_snapshot(vec![("NO_COLOR", "1"), ("FORCE_COLOR", "1")]);
+        let report = diagnose_snapshot(&snap);
+        assert_eq!(codes(&report), ["color_conflict"]);
+        assert!(report.has_warnings());
+    }
+
+    #[test]
+    fn flags_ci_without_vendor() {
+        let snap = create_env_snapshot(vec![("CI", "true")]);
+        assert_eq!(codes(&diagnose_snapshot(&snap)), ["ci_vendor_unknown"]);
+
+        let snap = create_env_snapshot(vec![("CI", "true"), ("GITHUB_ACTIONS", "true")]);
+        assert!(diagnose_snapshot(&snap).findings.is_empty());
+    }
+
+    #[test]
+    fn flags_partial_tty_overrides_and_bad_color_level() {
+        let snap = create_env_snapshot(vec![
+            ("ENVSENSE_TTY_STDIN", "true"),
+            ("ENVSENSE_COLOR_LEVEL", "millions"),
+        ]);
+        assert_eq!(
+            codes(&diagnose_snapshot(&snap)),
+            ["partial_tty_override", "invalid_color_override"]
+        );
+    }
+
+    #[test]
+    fn reports_active_overrides() {
+        let snap = create_env_snapshot(vec![("ENVSENSE_AGENT", "none")]);
+        let report = diagnose_snapshot(&snap);
+        assert_eq!(codes(&report), ["overrides_active"]);
+        assert!(report.findings[0].message.contains("ENVSENSE_AGENT=none"));
+    }
+
+    #[test]
+    fn ignores_exported_detection_results() {
+        let snap = create_env_snapshot(vec![("ENVSENSE_DETECTED_AGENT", "cursor")]);
+        assert!(codes(&diagnose_snapshot(&snap)).is_empty());
+    }
+}
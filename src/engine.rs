@@ -1,59 +1,572 @@
-use crate::detectors::{Detector, EnvSnapshot};
-use crate::schema::{EnvSense, SCHEMA_VERSION};
+use crate::assume::Assumptions;
+use crate::check::ContextInfo;
+use crate::detectors::container::ContainerDetector;
+use crate::detectors::external::ExternalDetector;
+use crate::detectors::shell::ShellDetector;
+use crate::detectors::terminal::TerminalDetector;
+#[cfg(feature = "declarative-mappings")]
+use crate::detectors::{DeclarativeAgentDetector, DeclarativeCiDetector, DeclarativeIdeDetector};
+use crate::detectors::{Detection, Detector, DetectorMetadata, EnvSnapshot};
+use crate::log_event;
+use crate::logging::{trace_event, trace_span};
+use crate::overrides::Overrides;
+use crate::redact::Redactor;
+use crate::schema::{
+    Confidence, DetectorTiming, Diagnostic, DiagnosticKind, EnvSense, Evidence, EvidenceSource,
+    SCHEMA_VERSION, Signal, Timings, TraitProvenance,
+};
 use crate::traits::NestedTraits;
 use envsense_macros::DetectionMerger;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Instant;
+
+/// A detection as the derived merging consumes it, evidence kept typed
+type MergeDetection = envsense_macros::Detection<Evidence>;
 
 pub struct DetectionEngine {
     detectors: Vec<Box<dyn Detector>>,
+    selection: DetectorSelection,
+    assumptions: Assumptions,
+    priorities: HashMap<String, i32>,
+    timings: bool,
+    targets: Vec<String>,
+    min_confidence: f32,
+    redactor: Redactor,
+    provenance: bool,
+    /// Problems found while building the engine, reported with every result
+    warnings: Vec<Diagnostic>,
+}
+
+/// Which registered detectors are allowed to run
+///
+/// Detectors are matched by [`Detector::name`]. When `only` is set, every
+/// detector not listed is skipped; `disabled` is applied on top of that.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DetectorSelection {
+    pub only: Option<Vec<String>>,
+    pub disabled: Vec<String>,
+}
+
+impl DetectorSelection {
+    pub fn allows(&self, name: &str) -> bool {
+        let included = self
+            .only
+            .as_ref()
+            .is_none_or(|only| only.iter().any(|n| n == name));
+        included && !self.disabled.iter().any(|n| n == name)
+    }
+
+    fn names(&self) -> impl Iterator<Item = &String> {
+        self.only.iter().flatten().chain(self.disabled.iter())
+    }
+}
+
+/// What a single detector did during a detection run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectorRun {
+    pub name: &'static str,
+    pub ran: bool,
+    /// Effective merge priority, including any override from the detection
+    pub priority: i32,
+    pub contexts_added: Vec<String>,
+    pub confidence: f32,
+    /// Wall time of the detector, 0 when it did not run
+    pub elapsed_us: u64,
+}
+
+/// What the detectors an engine already ran returned, so that a later run
+/// against the same snapshot only runs the detectors it adds
+///
+/// Filled in by [`DetectionEngine::run_reusing`]. `check --quiet` uses this
+/// to widen its targets one predicate at a time.
+#[derive(Debug, Clone, Default)]
+pub struct DetectorResults {
+    done: HashMap<&'static str, (Result<Detection, Diagnostic>, u64)>,
+}
+
+/// Detection result together with per-detector run information
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionReport {
+    pub env: EnvSense,
+    /// One entry per registered detector, in merge order
+    pub runs: Vec<DetectorRun>,
 }
 
 impl DetectionEngine {
     pub fn new() -> Self {
         Self {
             detectors: Vec::new(),
+            selection: DetectorSelection::default(),
+            assumptions: Assumptions::default(),
+            priorities: HashMap::new(),
+            timings: false,
+            targets: Vec::new(),
+            min_confidence: 0.0,
+            redactor: Redactor::default(),
+            provenance: false,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Engine with the built-in terminal, shell, container, agent, CI and IDE
+    /// detectors
+    ///
+    /// The agent, CI and IDE detectors need the `declarative-mappings` feature.
+    pub fn with_default_detectors() -> Self {
+        let engine = Self::new()
+            .register(TerminalDetector::new())
+            .register(ShellDetector::new())
+            .register(ContainerDetector::new());
+        #[cfg(feature = "declarative-mappings")]
+        let engine = engine
+            .register(DeclarativeAgentDetector::new())
+            .register(DeclarativeCiDetector::new())
+            .register(DeclarativeIdeDetector::new());
+        engine
+    }
+
+    /// Add a detector; among equal priorities it merges after the ones
+    /// already registered
+    ///
+    /// Its [`Detection`] is merged with the
+    /// others into the same [`EnvSense`] following the rule described on
+    /// [`order`](Self::order). Names should be unique, since
+    /// [`DetectorSelection`] and `envsense detectors` refer to detectors by
+    /// name.
+    ///
+    /// ```
+    /// use envsense::prelude::*;
+    /// use serde_json::json;
+    ///
+    /// struct Devbox;
+    ///
+    /// impl Detector for Devbox {
+    ///     fn name(&self) -> &'static str {
+    ///         "devbox"
+    ///     }
+    ///
+    ///     fn detect(&self, snap: &EnvSnapshot) -> Detection {
+    ///         let mut detection = Detection::default();
+    ///         if snap.get_env("DEVBOX_SHELL_ENABLED").is_some() {
+    ///             detection.contexts_add.push("container".to_string());
+    ///             detection.confidence = 0.8;
+    ///         }
+    ///         detection
+    ///     }
+    /// }
+    ///
+    /// let engine = DetectionEngine::with_default_detectors().register(Devbox);
+    /// let vars = [("DEVBOX_SHELL_ENABLED".to_string(), "1".to_string())].into();
+    /// let env = engine.detect_from_snapshot(&EnvSnapshot::with_mock_tty(vars, false, false, false));
+    /// assert!(env.contexts.contains(&"container".to_string()));
+    /// ```
+    pub fn register<D: Detector + 'static>(self, detector: D) -> Self {
+        self.register_boxed(Box::new(detector))
+    }
+
+    /// [`register`](Self::register) for detectors chosen at runtime
+    pub fn register_boxed(mut self, detector: Box<dyn Detector>) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    /// Register external detector plugins
+    ///
+    /// Plugins whose name is already taken by a registered detector are
+    /// skipped with a warning in the result's diagnostics, so a plugin can
+    /// never shadow a built-in.
+    pub fn with_external_detectors(mut self, plugins: Vec<ExternalDetector>) -> Self {
+        for plugin in plugins {
+            if self.detectors.iter().any(|d| d.name() == plugin.name()) {
+                self.warnings.push(Diagnostic::warning(
+                    plugin.name(),
+                    format!(
+                        "ignoring detector plugin '{}': name already in use",
+                        plugin.path().display()
+                    ),
+                ));
+                continue;
+            }
+            self = self.register(plugin);
+        }
+        self
+    }
+
+    /// Report `diagnostic` with every result, for problems found while
+    /// setting up detection such as mapping files that failed to load
+    pub fn with_warning(mut self, diagnostic: Diagnostic) -> Self {
+        self.warnings.push(diagnostic);
+        self
+    }
+
+    /// Restrict which registered detectors run
+    ///
+    /// Returns an error naming any detector in the selection that is not
+    /// registered, so typos do not silently disable nothing.
+    pub fn with_selection(mut self, selection: DetectorSelection) -> Result<Self, String> {
+        let unknown: Vec<&str> = selection
+            .names()
+            .filter(|n| !self.detectors.iter().any(|d| d.name() == n.as_str()))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            let known: Vec<&str> = self.detectors.iter().map(|d| d.name()).collect();
+            return Err(format!(
+                "unknown detector(s): {} (available: {})",
+                unknown.join(", "),
+                known.join(", ")
+            ));
         }
+        self.selection = selection;
+        Ok(self)
+    }
+
+    /// Force contexts and traits onto every detection result
+    pub fn with_assumptions(mut self, assumptions: Assumptions) -> Self {
+        self.assumptions = assumptions;
+        self
+    }
+
+    /// Override the merge priority of the detector called `name`
+    ///
+    /// Takes precedence over [`Detector::priority`]; a
+    /// [`Detection::priority`](crate::detectors::Detection::priority) set by
+    /// the detector itself still wins for that result.
+    pub fn with_priority(mut self, name: &str, priority: i32) -> Self {
+        self.priorities.insert(name.to_string(), priority);
+        self
+    }
+
+    /// Record how long each detector took in [`EnvSense::timings`]
+    ///
+    /// Off by default, so that detecting the same environment twice gives
+    /// equal results.
+    pub fn with_timings(mut self, enabled: bool) -> Self {
+        self.timings = enabled;
+        self
+    }
+
+    /// Record which detection and evidence set each trait in
+    /// [`EnvSense::provenance`]
+    ///
+    /// Off by default. For traits several detections set, this is the one
+    /// whose value merging kept.
+    pub fn with_provenance(mut self, enabled: bool) -> Self {
+        self.provenance = enabled;
+        self
+    }
+
+    /// Only run detectors that can produce one of `targets`
+    ///
+    /// Targets are context names (`ci`) or dotted trait paths (`ci.id`,
+    /// `terminal.stdout`), matched against each detector's
+    /// [`metadata`](Detector::metadata). Detectors that declare nothing, such
+    /// as plugins, always run, and so does every detector when a target is
+    /// declared by none of them. No targets, the default, runs everything.
+    pub fn with_targets<S: AsRef<str>>(mut self, targets: &[S]) -> Self {
+        self.targets = targets.iter().map(|t| t.as_ref().to_string()).collect();
+        self
     }
 
-    pub fn register<D: Detector + 'static>(mut self, detector: D) -> Self {
-        self.detectors.push(Box::new(detector));
+    /// Drop contexts whose merged confidence is below `min`
+    ///
+    /// A dropped context loses its traits too, as if no detector had found
+    /// it; assumptions and `ENVSENSE_OVERRIDE_*` variables still apply
+    /// afterwards. The default, 0, keeps everything.
+    pub fn with_min_confidence(mut self, min: f32) -> Self {
+        self.min_confidence = min;
         self
     }
 
+    /// Hide evidence values that `redactor` considers secret
+    ///
+    /// Defaults to [`Redactor::default`]. Applied last, to the evidence of
+    /// assumptions and overrides too.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Which registered detectors [`with_targets`](Self::with_targets) lets
+    /// run, in registration order
+    fn targeted(&self) -> Vec<bool> {
+        if self.targets.is_empty() {
+            return vec![true; self.detectors.len()];
+        }
+        let declared: Vec<DetectorMetadata> = self.detectors.iter().map(|d| d.metadata()).collect();
+        let unclaimed = self
+            .targets
+            .iter()
+            .any(|target| !declared.iter().any(|meta| produces(meta, target)));
+        declared
+            .iter()
+            .map(|meta| {
+                unclaimed
+                    || (meta.contexts.is_empty() && meta.traits.is_empty())
+                    || self.targets.iter().any(|target| produces(meta, target))
+            })
+            .collect()
+    }
+
+    fn priority_of(&self, detector: &dyn Detector) -> i32 {
+        self.priorities
+            .get(detector.name())
+            .copied()
+            .unwrap_or_else(|| detector.priority())
+    }
+
+    /// Detector names and priorities in the order their results are merged
+    ///
+    /// Detections are merged in ascending priority; detectors with equal
+    /// priority keep their registration order. Contexts and evidence from
+    /// every detector are kept. When two detectors set the same trait to
+    /// different values the more confident one wins; on equal confidence the
+    /// one merged last does, i.e. the highest priority, or the one registered
+    /// later on a tie. Each rejected value is recorded as
+    /// [`Signal::Conflict`] evidence.
+    pub fn order(&self) -> Vec<(&'static str, i32)> {
+        let mut order: Vec<_> = self
+            .detectors
+            .iter()
+            .map(|d| (d.name(), self.priority_of(d.as_ref())))
+            .collect();
+        order.sort_by_key(|&(_, priority)| priority);
+        order
+    }
+
+    /// Names and metadata of the registered detectors, in registration order
+    pub fn detectors(&self) -> Vec<(&'static str, DetectorMetadata)> {
+        self.detectors
+            .iter()
+            .map(|d| (d.name(), d.metadata()))
+            .collect()
+    }
+
+    /// Contexts the registered detectors declare in their metadata, each
+    /// described by the first detector declaring it
+    ///
+    /// Pass them to [`check::install_contexts`](crate::check::install_contexts)
+    /// so predicates, `--assume` and `--list` know contexts such as
+    /// `container` that no trait group stands for.
+    pub fn declared_contexts(&self) -> Vec<ContextInfo> {
+        let mut contexts: Vec<ContextInfo> = Vec::new();
+        for detector in &self.detectors {
+            let meta = detector.metadata();
+            for context in meta.contexts {
+                if !contexts.iter().any(|c| c.name == context) {
+                    contexts.push(ContextInfo::new(context, meta.description));
+                }
+            }
+        }
+        contexts
+    }
+
     pub fn detect(&self) -> EnvSense {
         let snapshot = EnvSnapshot::current();
         self.detect_from_snapshot(&snapshot)
     }
 
     pub fn detect_from_snapshot(&self, snapshot: &EnvSnapshot) -> EnvSense {
+        self.run(snapshot).env
+    }
+
+    /// Run all detectors and report what each of them contributed
+    pub fn run(&self, snapshot: &EnvSnapshot) -> DetectionReport {
+        self.run_reusing(snapshot, &mut DetectorResults::default())
+    }
+
+    /// [`run`](Self::run), taking the result of any detector in `results`
+    /// instead of running it again and adding the ones that did run
+    ///
+    /// `results` must come from this engine and `snapshot`.
+    pub fn run_reusing(
+        &self,
+        snapshot: &EnvSnapshot,
+        results: &mut DetectorResults,
+    ) -> DetectionReport {
         let mut result = EnvSense {
             contexts: Vec::new(),
             traits: NestedTraits::default(),
             evidence: Vec::new(),
             version: SCHEMA_VERSION.to_string(),
+            confidence: Confidence::default(),
+            diagnostics: Vec::new(),
+            timings: None,
+            provenance: None,
         };
+        let mut diagnostics = self.warnings.clone();
+        let mut runs = Vec::with_capacity(self.detectors.len());
+        let started = Instant::now();
+        trace_span!("detect", detectors = self.detectors.len());
+        log_event!(Debug, "detect.start", detectors = self.detectors.len(),);
 
-        // Collect all detections
-        let detections: Vec<envsense_macros::Detection> = self
-            .detectors
+        // Collect detections from the selected detectors; skipped ones never execute
+        let mut collected = Vec::with_capacity(self.detectors.len());
+        let targeted = self.targeted();
+        for (detector, targeted) in self.detectors.iter().zip(targeted) {
+            let priority = self.priority_of(detector.as_ref());
+            let allowed = self.selection.allows(detector.name());
+            if !allowed || !targeted {
+                if allowed {
+                    log_event!(
+                        Verbose,
+                        "detector.skipped",
+                        name = detector.name(),
+                        reason = "not targeted",
+                    );
+                } else {
+                    log_event!(Verbose, "detector.skipped", name = detector.name());
+                }
+                runs.push(DetectorRun {
+                    name: detector.name(),
+                    ran: false,
+                    priority,
+                    contexts_added: Vec::new(),
+                    confidence: 0.0,
+                    elapsed_us: 0,
+                });
+                collected.push(None);
+                continue;
+            }
+
+            let reused = results.done.get(detector.name()).cloned();
+            let fresh = reused.is_none();
+            let (outcome, elapsed_us) = match reused {
+                Some(done) => done,
+                None => {
+                    trace_span!("detector", name = detector.name());
+                    let detector_started = Instant::now();
+                    let outcome = run_detector(detector.as_ref(), snapshot);
+                    let elapsed_us = detector_started.elapsed().as_micros() as u64;
+                    results
+                        .done
+                        .insert(detector.name(), (outcome.clone(), elapsed_us));
+                    (outcome, elapsed_us)
+                }
+            };
+            let mut detection = match outcome {
+                Ok(detection) => detection,
+                Err(diagnostic) => {
+                    if fresh {
+                        trace_event!(kind = ?diagnostic.kind, message = %diagnostic.message, "detector failed");
+                        log_event!(
+                            Verbose,
+                            "detector.failed",
+                            name = detector.name(),
+                            message = diagnostic.message,
+                            elapsed_us = elapsed_us,
+                        );
+                    }
+                    diagnostics.push(diagnostic);
+                    Detection::default()
+                }
+            };
+            diagnostics.extend(
+                detection
+                    .warnings
+                    .drain(..)
+                    .map(|message| Diagnostic::warning(detector.name(), message)),
+            );
+            credit_detector(&mut detection.evidence, detector.name());
+            let priority = detection.priority.unwrap_or(priority);
+            if fresh {
+                log_event!(
+                    Verbose,
+                    "detector.run",
+                    name = detector.name(),
+                    contexts = detection.contexts_add.join(","),
+                    confidence = detection.confidence,
+                    evidence = detection.evidence.len(),
+                    priority = priority,
+                    elapsed_us = elapsed_us,
+                );
+            }
+            runs.push(DetectorRun {
+                name: detector.name(),
+                ran: true,
+                priority,
+                contexts_added: detection.contexts_add.clone(),
+                confidence: detection.confidence,
+                elapsed_us,
+            });
+            collected.push(Some(detection));
+        }
+
+        let detector_timings: Vec<DetectorTiming> = runs
+            .iter()
+            .filter(|run| run.ran)
+            .map(|run| DetectorTiming {
+                detector: run.name.to_string(),
+                elapsed_us: run.elapsed_us,
+            })
+            .collect();
+
+        // Stable sort: equal priorities keep registration order, and the
+        // detection merged last wins equally confident conflicting traits
+        let mut merge_order: Vec<usize> = (0..runs.len()).collect();
+        merge_order.sort_by_key(|&i| runs[i].priority);
+        let (merged_from, detections): (Vec<&str>, Vec<MergeDetection>) = merge_order
             .iter()
-            .map(|detector| {
-                let detection = detector.detect(snapshot);
-                envsense_macros::Detection {
+            .filter_map(|&i| {
+                collected[i]
+                    .take()
+                    .map(|detection| (runs[i].name, detection))
+            })
+            .map(|(name, detection)| {
+                let detection = MergeDetection {
                     contexts_add: detection.contexts_add,
                     traits_patch: detection.traits_patch, // Now contains nested objects
                     facets_patch: detection.facets_patch, // Legacy support
-                    evidence: detection
-                        .evidence
-                        .into_iter()
-                        .map(|e| serde_json::to_value(e).unwrap())
-                        .collect(),
+                    evidence: detection.evidence,
                     confidence: detection.confidence,
-                }
+                };
+                (name, detection)
             })
-            .collect();
+            .unzip();
+        let runs: Vec<DetectorRun> = merge_order.into_iter().map(|i| runs[i].clone()).collect();
 
-        // Use the macro-generated merging logic
-        result.merge_detections(&detections);
+        {
+            trace_span!("merge", detections = detections.len());
+            // Use the macro-generated merging logic
+            result.merge_detections(&detections);
+            // Contexts are listed by name, whichever detector found them
+            result.contexts.sort();
+            dedup_evidence(&mut result.evidence);
+            for conflict in result
+                .evidence
+                .iter()
+                .filter(|e| e.signal == Signal::Conflict)
+            {
+                log_event!(
+                    Verbose,
+                    "trait.conflict",
+                    key = conflict.key,
+                    rejected = conflict.value.as_deref().unwrap_or_default(),
+                    confidence = conflict.confidence,
+                );
+            }
+            result.confidence = attribute_confidence(&detections, &result);
+        }
+        result.diagnostics = diagnostics;
+        match result.drop_uncertain_contexts(self.min_confidence) {
+            Ok(dropped) => {
+                for context in dropped {
+                    log_event!(
+                        Verbose,
+                        "context.dropped",
+                        name = context,
+                        min_confidence = self.min_confidence,
+                    );
+                }
+            }
+            Err(e) => result.diagnostics.push(Diagnostic::warning(
+                "detectors.min_confidence",
+                format!("ignoring min confidence: {}", e),
+            )),
+        }
 
         // Validate the nested structure (development aid)
         if cfg!(debug_assertions)
@@ -65,7 +578,46 @@ impl DetectionEngine {
             );
         }
 
-        result
+        // Applied after validation: an assumed context need not come with traits
+        if let Err(e) = self.assumptions.apply(&mut result) {
+            result.diagnostics.push(Diagnostic::warning(
+                "assume",
+                format!("ignoring assumptions: {}", e),
+            ));
+        }
+        // ENVSENSE_OVERRIDE_* variables are set per run, so they beat `assume`
+        let (overrides, errors) = Overrides::from_env(snapshot.env());
+        for error in errors {
+            result.diagnostics.push(Diagnostic::warning(
+                "overrides",
+                format!("ignoring {}", error),
+            ));
+        }
+        if let Err(e) = overrides.apply(&mut result) {
+            result.diagnostics.push(Diagnostic::warning(
+                "overrides",
+                format!("ignoring overrides: {}", e),
+            ));
+        }
+        self.redactor.redact(&mut result.evidence);
+        if self.provenance {
+            result.provenance = Some(trait_provenance(&merged_from, &detections, &result));
+        }
+        let total_us = started.elapsed().as_micros() as u64;
+        if self.timings {
+            result.timings = Some(Timings {
+                total_us,
+                detectors: detector_timings,
+            });
+        }
+        log_event!(
+            Verbose,
+            "detect.done",
+            contexts = result.contexts.join(","),
+            elapsed_us = total_us,
+        );
+
+        DetectionReport { env: result, runs }
     }
 
     /// Validate the nested structure for debugging during development
@@ -96,7 +648,9 @@ impl DetectionEngine {
         // Check that evidence field paths reference valid nested fields
         for evidence in &result.evidence {
             for supported_field in &evidence.supports {
-                if !self.is_valid_nested_field_path(supported_field) {
+                if !result.contexts.contains(supported_field)
+                    && !self.is_valid_nested_field_path(supported_field)
+                {
                     return Err(format!(
                         "Evidence references invalid field path: {}",
                         supported_field
@@ -110,52 +664,32 @@ impl DetectionEngine {
 
     /// Check if a field path is valid for the nested structure
     fn is_valid_nested_field_path(&self, field_path: &str) -> bool {
-        // Valid nested field paths for the new schema
-        matches!(
-            field_path,
-            // Agent fields
-            "agent.id" |
-            // IDE fields
-            "ide.id" |
-            // Terminal fields
-            "terminal.interactive" |
-            "terminal.color_level" |
-            "terminal.stdin.tty" |
-            "terminal.stdin.piped" |
-            "terminal.stdout.tty" |
-            "terminal.stdout.piped" |
-            "terminal.stderr.tty" |
-            "terminal.stderr.piped" |
-            "terminal.supports_hyperlinks" |
-            // CI fields
-            "ci.id" |
-            "ci.vendor" |
-            "ci.name" |
-            "ci.is_pr" |
-            "ci.branch" |
-            // Legacy flat fields (for backward compatibility)
-            "agent_id" |
-            "ide_id" |
-            "ci_id" |
-            "is_interactive" |
-            "is_tty_stdin" |
-            "is_tty_stdout" |
-            "is_tty_stderr" |
-            "is_piped_stdin" |
-            "is_piped_stdout" |
-            "color_level" |
-            "supports_hyperlinks" |
-            "ci_vendor" |
-            "ci_name" |
-            "is_pr" |
-            "branch" |
-            // Context fields
-            "agent" |
-            "ide" |
-            "ci" |
-            "container" |
-            "remote"
-        )
+        // Trait paths come from the schema, so new fields are always valid
+        <NestedTraits as envsense_macros::MergeValue>::SCHEMA.resolves(field_path)
+            || matches!(
+                field_path,
+                // Legacy flat fields (for backward compatibility)
+                "agent_id" |
+                "ide_id" |
+                "ci_id" |
+                "is_interactive" |
+                "is_tty_stdin" |
+                "is_tty_stdout" |
+                "is_tty_stderr" |
+                "is_piped_stdin" |
+                "is_piped_stdout" |
+                "color_level" |
+                "supports_hyperlinks" |
+                "ci_vendor" |
+                "ci_name" |
+                "is_pr" |
+                "branch"
+            )
+            // Contexts the registered detectors can add
+            || self
+                .detectors
+                .iter()
+                .any(|d| d.metadata().contexts.contains(&field_path))
     }
 }
 
@@ -164,3 +698,651 @@ impl Default for DetectionEngine {
         Self::new()
     }
 }
+
+/// Whether a detector declaring `meta` can produce the context or trait
+/// path `target`, or a trait below it
+fn produces(meta: &DetectorMetadata, target: &str) -> bool {
+    meta.contexts.contains(&target)
+        || meta.traits.iter().any(|t| {
+            let nested = |outer: &str, inner: &str| {
+                inner
+                    .strip_prefix(outer)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            };
+            nested(target, t) || nested(t, target)
+        })
+}
+
+/// Run one detector, turning an error or a panic into a [`Diagnostic`]
+fn run_detector(detector: &dyn Detector, snapshot: &EnvSnapshot) -> Result<Detection, Diagnostic> {
+    let diagnostic = |kind, message| Diagnostic {
+        detector: detector.name().to_string(),
+        kind,
+        message,
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| detector.try_detect(snapshot))) {
+        Ok(Ok(detection)) => Ok(detection),
+        Ok(Err(message)) => Err(diagnostic(DiagnosticKind::Error, message)),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panicked".to_string());
+            Err(diagnostic(DiagnosticKind::Panic, message))
+        }
+    }
+}
+
+/// Name `detector` in the sources of each of its evidence entries
+///
+/// Entries from a declarative mapping already name the mapping.
+fn credit_detector(evidence: &mut [Evidence], detector: &str) {
+    for entry in evidence {
+        if entry.sources.is_empty() {
+            entry.sources.push(EvidenceSource {
+                detector: detector.to_string(),
+                mapping: None,
+            });
+        }
+        for source in &mut entry.sources {
+            source.detector = detector.to_string();
+        }
+    }
+}
+
+/// Merge entries citing the same signal, key and value
+///
+/// The first entry keeps its place and gains the supports and sources of
+/// the later ones, and the highest confidence among them.
+fn dedup_evidence(evidence: &mut Vec<Evidence>) {
+    let mut merged: Vec<Evidence> = Vec::with_capacity(evidence.len());
+    for entry in evidence.drain(..) {
+        let Some(first) = merged
+            .iter_mut()
+            .find(|e| e.signal == entry.signal && e.key == entry.key && e.value == entry.value)
+        else {
+            merged.push(entry);
+            continue;
+        };
+        for support in entry.supports {
+            if !first.supports.contains(&support) {
+                first.supports.push(support);
+            }
+        }
+        for source in entry.sources {
+            if !first.sources.contains(&source) {
+                first.sources.push(source);
+            }
+        }
+        first.confidence = first.confidence.max(entry.confidence);
+    }
+    *evidence = merged;
+}
+
+/// Credit each context and set trait of `env` with the confidence of the
+/// detections that produced it
+fn attribute_confidence(detections: &[MergeDetection], env: &EnvSense) -> Confidence {
+    let mut set_traits = Vec::new();
+    if let Ok(traits) = serde_json::to_value(&env.traits) {
+        leaf_paths("", &traits, &mut set_traits);
+    }
+    let set_traits: HashSet<String> = set_traits.into_iter().collect();
+
+    let mut confidence = Confidence::default();
+    for detection in detections {
+        for context in &detection.contexts_add {
+            Confidence::raise(&mut confidence.contexts, context, detection.confidence);
+        }
+        // Patches mix nested objects with legacy flat keys; only paths that
+        // made it into the merged traits count
+        let mut patched = Vec::new();
+        for (key, value) in &detection.traits_patch {
+            leaf_paths(key, value, &mut patched);
+        }
+        for path in patched.iter().filter(|p| set_traits.contains(*p)) {
+            Confidence::raise(&mut confidence.traits, path, detection.confidence);
+        }
+    }
+    confidence
+}
+
+/// For each set trait of `env`, the detection whose value merging kept and
+/// the evidence behind it
+///
+/// `names` holds the detector name of each of `detections`, in merge order.
+fn trait_provenance(
+    names: &[&str],
+    detections: &[MergeDetection],
+    env: &EnvSense,
+) -> BTreeMap<String, TraitProvenance> {
+    let mut set_traits = Vec::new();
+    if let Ok(traits) = serde_json::to_value(&env.traits) {
+        leaf_paths("", &traits, &mut set_traits);
+    }
+    let patched: Vec<Vec<String>> = detections
+        .iter()
+        .map(|detection| {
+            let mut paths = Vec::new();
+            for (key, value) in &detection.traits_patch {
+                leaf_paths(key, value, &mut paths);
+            }
+            paths
+        })
+        .collect();
+
+    let mut provenance = BTreeMap::new();
+    for path in set_traits {
+        let supports = |e: &Evidence| {
+            e.supports.iter().any(|s| {
+                path.strip_prefix(s.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+        };
+        // Evidence without sources was added after merging, by `assume` or
+        // an override, and names the exact path it set
+        if let Some(forced) = env.evidence.iter().rev().find(|e| {
+            e.sources.is_empty() && e.signal != Signal::Conflict && e.supports.contains(&path)
+        }) {
+            let entry = TraitProvenance {
+                detector: None,
+                confidence: forced.confidence,
+                evidence: vec![forced.key.clone()],
+            };
+            provenance.insert(path, entry);
+            continue;
+        }
+        // Merging keeps the most confident value, and the last of equals
+        let winner = (0..detections.len())
+            .filter(|&i| patched[i].contains(&path))
+            .reduce(|kept, i| {
+                if detections[i].confidence >= detections[kept].confidence {
+                    i
+                } else {
+                    kept
+                }
+            });
+        let Some(winner) = winner else {
+            continue;
+        };
+        let detector = names[winner];
+        let evidence = env
+            .evidence
+            .iter()
+            .filter(|e| e.sources.iter().any(|s| s.detector == detector) && supports(e))
+            .map(|e| e.key.clone())
+            .collect();
+        let entry = TraitProvenance {
+            detector: Some(detector.to_string()),
+            confidence: detections[winner].confidence,
+            evidence,
+        };
+        provenance.insert(path, entry);
+    }
+    provenance
+}
+
+/// Dotted paths of the non-null leaves under `value`
+fn leaf_paths(prefix: &str, value: &Value, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                leaf_paths(&path, child, paths);
+            }
+        }
+        Value::Null => {}
+        _ => paths.push(prefix.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::test_utils::create_env_snapshot;
+
+    #[test]
+    #[cfg(feature = "declarative-mappings")]
+    fn carries_detection_confidence_into_result() {
+        let snap = create_env_snapshot(vec![("GITHUB_ACTIONS", "true"), ("CURSOR_AGENT", "1")]);
+        let env = DetectionEngine::with_default_detectors().detect_from_snapshot(&snap);
+
+        assert_eq!(env.confidence.contexts.get("ci"), Some(&1.0));
+        assert_eq!(env.confidence.contexts.get("agent"), Some(&1.0));
+        assert_eq!(env.confidence.traits.get("ci.id"), Some(&1.0));
+        assert_eq!(
+            env.confidence.traits.get("terminal.interactive"),
+            Some(&1.0)
+        );
+        // Unset traits and legacy flat patch keys are not reported
+        assert!(!env.confidence.traits.contains_key("ide.id"));
+        assert!(!env.confidence.traits.contains_key("is_interactive"));
+        assert!(!env.confidence.contexts.contains_key("ide"));
+    }
+
+    struct FixedDetector {
+        name: &'static str,
+        context: &'static str,
+        traits: Value,
+        confidence: f32,
+    }
+
+    impl Detector for FixedDetector {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn detect(&self, _snap: &EnvSnapshot) -> crate::detectors::Detection {
+            let mut detection = crate::detectors::Detection {
+                contexts_add: vec![self.context.to_string()],
+                confidence: self.confidence,
+                ..Default::default()
+            };
+            detection
+                .traits_patch
+                .insert(self.context.to_string(), self.traits.clone());
+            detection
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "declarative-mappings")]
+    fn merges_custom_detectors_with_builtins() {
+        let custom: Vec<Box<dyn Detector>> = vec![Box::new(FixedDetector {
+            name: "buildbox",
+            context: "ci",
+            traits: serde_json::json!({"id": "buildbox", "name": "Buildbox"}),
+            confidence: 0.9,
+        })];
+        let engine = custom
+            .into_iter()
+            .fold(DetectionEngine::with_default_detectors(), |engine, d| {
+                engine.register_boxed(d)
+            });
+        let snap = create_env_snapshot(vec![("CURSOR_AGENT", "1")]);
+        let report = engine.run(&snap);
+
+        assert_eq!(report.env.contexts, ["agent", "ci"]);
+        assert_eq!(report.env.traits.agent.id.as_deref(), Some("cursor"));
+        assert_eq!(report.env.traits.ci.id.as_deref(), Some("buildbox"));
+        assert_eq!(report.env.confidence.contexts.get("ci"), Some(&0.9));
+        assert_eq!(report.runs.last().unwrap().name, "buildbox");
+    }
+
+    fn conflicting_ci_engine() -> DetectionEngine {
+        DetectionEngine::new()
+            .register(FixedDetector {
+                name: "first",
+                context: "ci",
+                traits: serde_json::json!({"id": "first"}),
+                confidence: 0.9,
+            })
+            .register(FixedDetector {
+                name: "second",
+                context: "ci",
+                traits: serde_json::json!({"id": "second"}),
+                confidence: 0.9,
+            })
+    }
+
+    #[test]
+    fn later_registration_wins_on_equal_priority() {
+        let engine = conflicting_ci_engine();
+        let report = engine.run(&create_env_snapshot(vec![]));
+
+        assert_eq!(engine.order(), [("first", 0), ("second", 0)]);
+        assert_eq!(report.env.traits.ci.id.as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn higher_priority_wins_regardless_of_registration() {
+        let engine = conflicting_ci_engine().with_priority("first", 10);
+        let report = engine.run(&create_env_snapshot(vec![]));
+
+        assert_eq!(engine.order(), [("second", 0), ("first", 10)]);
+        assert_eq!(report.env.traits.ci.id.as_deref(), Some("first"));
+        let names: Vec<_> = report.runs.iter().map(|r| (r.name, r.priority)).collect();
+        assert_eq!(names, [("second", 0), ("first", 10)]);
+    }
+
+    #[test]
+    fn higher_confidence_wins_conflicting_traits() {
+        let engine = conflicting_ci_engine().register(FixedDetector {
+            name: "third",
+            context: "ci",
+            traits: serde_json::json!({"id": "third", "name": "Third"}),
+            confidence: 0.5,
+        });
+        let env = engine.detect_from_snapshot(&create_env_snapshot(vec![]));
+
+        assert_eq!(env.traits.ci.id.as_deref(), Some("second"));
+        assert_eq!(env.traits.ci.name.as_deref(), Some("Third"));
+        let conflicts: Vec<_> = env
+            .evidence
+            .iter()
+            .filter(|e| e.signal == Signal::Conflict)
+            .map(|e| (e.key.as_str(), e.value.as_deref(), e.confidence))
+            .collect();
+        assert_eq!(
+            conflicts,
+            [("ci.id", Some("first"), 0.9), ("ci.id", Some("third"), 0.5)]
+        );
+    }
+
+    #[test]
+    fn provenance_names_the_detection_merging_kept() {
+        let engine = conflicting_ci_engine().register(FixedDetector {
+            name: "third",
+            context: "ci",
+            traits: serde_json::json!({"id": "third", "name": "Third"}),
+            confidence: 0.5,
+        });
+        let snap = create_env_snapshot(vec![]);
+        assert_eq!(engine.detect_from_snapshot(&snap).provenance, None);
+
+        let env = engine.with_provenance(true).detect_from_snapshot(&snap);
+        let provenance = env.provenance.unwrap();
+        let detector = |path: &str| provenance[path].detector.as_deref();
+        assert_eq!(detector("ci.id"), Some("second"));
+        assert_eq!(provenance["ci.id"].confidence, 0.9);
+        assert_eq!(detector("ci.name"), Some("third"));
+        assert!(!provenance.contains_key("agent.id"));
+    }
+
+    #[test]
+    #[cfg(feature = "declarative-mappings")]
+    fn provenance_lists_evidence_and_overrides() {
+        let snap = create_env_snapshot(vec![
+            ("CURSOR_AGENT", "1"),
+            ("GITHUB_ACTIONS", "true"),
+            ("ENVSENSE_OVERRIDE_CI_BRANCH", "dev"),
+        ]);
+        let env = DetectionEngine::with_default_detectors()
+            .with_provenance(true)
+            .detect_from_snapshot(&snap);
+        let provenance = env.provenance.unwrap();
+
+        let agent = &provenance["agent.id"];
+        assert_eq!(agent.detector.as_deref(), Some("agent"));
+        assert_eq!(agent.evidence, ["CURSOR_AGENT"]);
+        let branch = &provenance["ci.branch"];
+        assert_eq!(branch.detector, None);
+        assert_eq!(branch.evidence, ["ENVSENSE_OVERRIDE_CI_BRANCH"]);
+        assert_eq!(provenance["ci.id"].detector.as_deref(), Some("ci"));
+        assert_eq!(
+            provenance["terminal.stdout.tty"].evidence,
+            ["terminal.stdout.tty"]
+        );
+    }
+
+    #[test]
+    fn invalid_overrides_are_warnings() {
+        let snap = create_env_snapshot(vec![("ENVSENSE_OVERRIDE_TERMINAL_INTERACTIVE", "maybe")]);
+        let env = DetectionEngine::with_default_detectors().detect_from_snapshot(&snap);
+
+        let [warning] = env.diagnostics.as_slice() else {
+            panic!("expected one diagnostic, got {:?}", env.diagnostics);
+        };
+        assert_eq!(warning.detector, "overrides");
+        assert_eq!(warning.kind, DiagnosticKind::Warning);
+        assert!(
+            warning
+                .message
+                .starts_with("ignoring ENVSENSE_OVERRIDE_TERMINAL_INTERACTIVE:")
+        );
+    }
+
+    struct FailingDetector {
+        name: &'static str,
+        panics: bool,
+    }
+
+    impl Detector for FailingDetector {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn detect(&self, _snap: &EnvSnapshot) -> Detection {
+            panic!("{} blew up", self.name);
+        }
+
+        fn try_detect(&self, snap: &EnvSnapshot) -> Result<Detection, String> {
+            if self.panics {
+                return Ok(self.detect(snap));
+            }
+            Err("config unreadable".to_string())
+        }
+    }
+
+    #[test]
+    fn failing_detectors_are_recorded_as_diagnostics() {
+        let engine = DetectionEngine::new()
+            .register(FailingDetector {
+                name: "panicky",
+                panics: true,
+            })
+            .register(FixedDetector {
+                name: "buildbox",
+                context: "ci",
+                traits: serde_json::json!({"id": "buildbox"}),
+                confidence: 0.9,
+            })
+            .register(FailingDetector {
+                name: "broken",
+                panics: false,
+            });
+        let report = engine.run(&create_env_snapshot(vec![]));
+
+        assert_eq!(report.env.contexts, ["ci"]);
+        assert_eq!(report.env.traits.ci.id.as_deref(), Some("buildbox"));
+        assert_eq!(
+            report.env.diagnostics,
+            [
+                Diagnostic {
+                    detector: "panicky".to_string(),
+                    kind: DiagnosticKind::Panic,
+                    message: "panicky blew up".to_string(),
+                },
+                Diagnostic {
+                    detector: "broken".to_string(),
+                    kind: DiagnosticKind::Error,
+                    message: "config unreadable".to_string(),
+                },
+            ]
+        );
+        assert!(report.runs.iter().all(|run| run.ran));
+    }
+
+    struct WarningDetector;
+
+    impl Detector for WarningDetector {
+        fn name(&self) -> &'static str {
+            "warns"
+        }
+
+        fn detect(&self, _snap: &EnvSnapshot) -> Detection {
+            Detection {
+                contexts_add: vec!["ci".to_string()],
+                warnings: vec!["mapping 'x': required value mapping missing: X".to_string()],
+                ..Detection::default()
+            }
+        }
+    }
+
+    #[test]
+    fn detector_warnings_are_diagnostics() {
+        let env = DetectionEngine::new()
+            .register(WarningDetector)
+            .detect_from_snapshot(&create_env_snapshot(vec![]));
+        assert_eq!(env.contexts, ["ci"]);
+        assert_eq!(
+            env.diagnostics,
+            [Diagnostic::warning(
+                "warns",
+                "mapping 'x': required value mapping missing: X"
+            )]
+        );
+    }
+
+    struct CountingDetector(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    impl Detector for CountingDetector {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn detect(&self, _snap: &EnvSnapshot) -> Detection {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Detection {
+                contexts_add: vec!["ci".to_string()],
+                ..Detection::default()
+            }
+        }
+    }
+
+    #[test]
+    fn reused_results_are_not_run_again() {
+        let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let engine = DetectionEngine::new().register(CountingDetector(count.clone()));
+        let snap = create_env_snapshot(vec![]);
+        let mut results = DetectorResults::default();
+
+        engine.run_reusing(&snap, &mut results);
+        let report = engine.run_reusing(&snap, &mut results);
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(report.runs[0].ran);
+        assert_eq!(report.env.contexts, ["ci"]);
+    }
+
+    #[test]
+    #[cfg(feature = "declarative-mappings")]
+    fn timings_are_opt_in() {
+        let snap = create_env_snapshot(vec![]);
+        assert_eq!(
+            DetectionEngine::new().detect_from_snapshot(&snap).timings,
+            None
+        );
+
+        let engine = DetectionEngine::with_default_detectors()
+            .with_timings(true)
+            .with_selection(DetectorSelection {
+                only: None,
+                disabled: vec!["ide".to_string()],
+            })
+            .unwrap();
+        let timings = engine.detect_from_snapshot(&snap).timings.unwrap();
+        let names: Vec<_> = timings
+            .detectors
+            .iter()
+            .map(|t| t.detector.as_str())
+            .collect();
+        assert_eq!(names, ["terminal", "shell", "container", "agent", "ci"]);
+        let detectors_us: u64 = timings.detectors.iter().map(|t| t.elapsed_us).sum();
+        assert!(timings.total_us >= detectors_us);
+    }
+
+    #[test]
+    #[cfg(feature = "declarative-mappings")]
+    fn targets_run_only_detectors_that_produce_them() {
+        let ran = |engine: DetectionEngine| -> Vec<&'static str> {
+            let report = engine.run(&create_env_snapshot(vec![("CI", "true")]));
+            report
+                .runs
+                .iter()
+                .filter(|r| r.ran)
+                .map(|r| r.name)
+                .collect()
+        };
+        let plugin = || FixedDetector {
+            name: "plugin",
+            context: "container",
+            traits: serde_json::json!({}),
+            confidence: 0.5,
+        };
+        let engine = || DetectionEngine::with_default_detectors().register(plugin());
+
+        assert_eq!(ran(engine().with_targets(&["ci"])), ["ci", "plugin"]);
+        assert_eq!(
+            ran(engine().with_targets(&["terminal.stdout", "agent.id"])),
+            ["terminal", "agent", "plugin"]
+        );
+        assert_eq!(
+            ran(engine().with_targets(&["container"])),
+            ["container", "plugin"]
+        );
+        assert_eq!(ran(engine().with_targets(&["remote"])), ["ide", "plugin"]);
+        // Nothing declares `sandbox`, so any detector might produce it
+        assert_eq!(ran(engine().with_targets(&["sandbox"])).len(), 7);
+        assert_eq!(ran(engine().with_targets::<&str>(&[])).len(), 7);
+    }
+
+    #[test]
+    #[cfg(feature = "declarative-mappings")]
+    fn min_confidence_drops_uncertain_contexts() {
+        let snapshot =
+            create_env_snapshot(vec![("GITHUB_ACTIONS", "true"), ("IS_CODE_AGENT", "1")]);
+        let env = DetectionEngine::with_default_detectors().detect_from_snapshot(&snapshot);
+        assert!(env.contexts.contains(&"agent".to_string()));
+        assert_eq!(env.confidence.contexts["agent"], 0.6);
+
+        let env = DetectionEngine::with_default_detectors()
+            .with_min_confidence(0.8)
+            .detect_from_snapshot(&snapshot);
+        assert_eq!(env.contexts, ["ci"]);
+        assert_eq!(env.traits.agent.id, None);
+        assert!(!env.confidence.contexts.contains_key("agent"));
+        assert_eq!(env.traits.ci.id.as_deref(), Some("github_actions"));
+    }
+
+    /// Cites `CI=true` in support of `supports`
+    struct CitesCi {
+        name: &'static str,
+        supports: &'static str,
+        confidence: f32,
+    }
+
+    impl Detector for CitesCi {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn detect(&self, _snap: &EnvSnapshot) -> Detection {
+            Detection {
+                evidence: vec![
+                    Evidence::env_var("CI", "true")
+                        .with_supports(vec![self.supports.to_string()])
+                        .with_confidence(self.confidence)
+                        .with_mapping("generic"),
+                ],
+                ..Detection::default()
+            }
+        }
+    }
+
+    #[test]
+    fn duplicate_evidence_is_merged_with_its_sources() {
+        let env = DetectionEngine::new()
+            .register(CitesCi {
+                name: "first",
+                supports: "ci",
+                confidence: 0.7,
+            })
+            .register(CitesCi {
+                name: "second",
+                supports: "ci.id",
+                confidence: 0.9,
+            })
+            .detect_from_snapshot(&create_env_snapshot(vec![("CI", "true")]));
+
+        assert_eq!(env.evidence.len(), 1);
+        let evidence = &env.evidence[0];
+        assert_eq!(evidence.supports, ["ci", "ci.id"]);
+        assert_eq!(evidence.confidence, 0.9);
+        let sources: Vec<String> = evidence.sources.iter().map(|s| s.to_string()).collect();
+        assert_eq!(sources, ["first/generic", "second/generic"]);
+    }
+}
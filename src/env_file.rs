@@ -0,0 +1,143 @@
+//! `.env` file parsing for `--env-file`.
+//!
+//! Supports the common dotenv subset: `KEY=value` lines, an optional
+//! `export ` prefix, `#` comments, single-quoted literal values and
+//! double-quoted values with `\n`, `\t`, `\"` and `\\` escapes.
+
+use std::path::Path;
+
+/// Read and parse the `.env` file at `path`
+pub fn load(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read '{}': {}", path.display(), e))?;
+    parse(&content).map_err(|e| format!("'{}': {}", path.display(), e))
+}
+
+/// Parse `.env` content into variables, in file order
+pub fn parse(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let error = |message: &str| format!("line {}: {}", index + 1, message);
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected KEY=value"))?;
+        let key = key.trim_end();
+        let valid_key = key
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_key {
+            return Err(error(&format!("invalid variable name '{}'", key)));
+        }
+        let value = parse_value(value.trim_start()).map_err(error)?;
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
+fn parse_value(raw: &str) -> Result<String, &'static str> {
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated single quote")?;
+        return trailing(&rest[end + 1..]).map(|_| rest[..end].to_string());
+    }
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return trailing(&rest[i + 1..]).map(|_| value),
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('"' | '\\' | '$')) => value.push(c),
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("unterminated double quote");
+    }
+    // Unquoted values end at an inline ` #` comment
+    let value = match raw.find(" #") {
+        Some(end) => &raw[..end],
+        None => raw,
+    };
+    Ok(value.trim_end().to_string())
+}
+
+/// Only whitespace or a comment may follow a closing quote
+fn trailing(rest: &str) -> Result<(), &'static str> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err("unexpected text after closing quote")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(content: &str) -> Vec<(String, String)> {
+        parse(content).unwrap()
+    }
+
+    #[test]
+    fn parses_common_syntax() {
+        let content = r#"
+# container env
+CI=true
+export GITHUB_ACTIONS=true
+TERM_PROGRAM = vscode  # trailing comment
+EMPTY=
+SINGLE='literal \n $HOME'
+DOUBLE="line\nnext \"quoted\""
+URL=https://example.com/#anchor
+"#;
+        assert_eq!(
+            vars(content),
+            [
+                ("CI", "true"),
+                ("GITHUB_ACTIONS", "true"),
+                ("TERM_PROGRAM", "vscode"),
+                ("EMPTY", ""),
+                ("SINGLE", r"literal \n $HOME"),
+                ("DOUBLE", "line\nnext \"quoted\""),
+                ("URL", "https://example.com/#anchor"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        assert_eq!(
+            parse("A=1\nnot a var\n").unwrap_err(),
+            "line 2: expected KEY=value"
+        );
+        assert_eq!(
+            parse("1A=x").unwrap_err(),
+            "line 1: invalid variable name '1A'"
+        );
+        assert_eq!(
+            parse("A=\"open").unwrap_err(),
+            "line 1: unterminated double quote"
+        );
+        assert_eq!(
+            parse("A='x' y").unwrap_err(),
+            "line 1: unexpected text after closing quote"
+        );
+    }
+}
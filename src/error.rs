@@ -0,0 +1,107 @@
+//! Errors reported by envsense.
+//!
+//! Every [`EnvsenseError`] has an [`ErrorKind`]. The `envsense` binary prints
+//! the error and turns its kind into the exit code in one place, so the code
+//! below it returns errors instead of printing them.
+
+use crate::check::ParseError;
+use thiserror::Error;
+
+/// Broad category of an [`EnvsenseError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A runtime failure, or a check that did not hold
+    Failure,
+    /// Invalid input: a predicate, file, flag or configuration value
+    Input,
+    /// Output could not be serialized
+    Serialization,
+}
+
+impl ErrorKind {
+    /// Exit code the `envsense` binary uses for this kind (1, 2 or 3)
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Failure => 1,
+            ErrorKind::Input => 2,
+            ErrorKind::Serialization => 3,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum EnvsenseError {
+    /// A predicate that could not be parsed
+    #[error("parsing '{predicate}': {source}")]
+    Predicate {
+        predicate: String,
+        #[source]
+        source: ParseError,
+    },
+    /// A predicate naming an unknown field
+    #[error(transparent)]
+    Field(#[from] ParseError),
+    /// Any other invalid input
+    #[error("{0}")]
+    Input(String),
+    /// A runtime failure, such as a file that cannot be written
+    #[error("{0}")]
+    Failure(String),
+    #[error("failed to serialize output: {0}")]
+    Serialization(#[from] serde_json::Error),
+    /// A failure the command has already explained on its own output, such
+    /// as a predicate that evaluated to false
+    #[error("command failed")]
+    Reported(ErrorKind),
+}
+
+impl EnvsenseError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            EnvsenseError::Predicate { .. } | EnvsenseError::Field(_) | EnvsenseError::Input(_) => {
+                ErrorKind::Input
+            }
+            EnvsenseError::Failure(_) => ErrorKind::Failure,
+            EnvsenseError::Serialization(_) => ErrorKind::Serialization,
+            EnvsenseError::Reported(kind) => *kind,
+        }
+    }
+
+    /// Exit code for this error; see [`ErrorKind::exit_code`]
+    pub fn exit_code(&self) -> i32 {
+        self.kind().exit_code()
+    }
+
+    /// An invalid input error from anything printable
+    pub fn input(message: impl ToString) -> Self {
+        EnvsenseError::Input(message.to_string())
+    }
+
+    /// A runtime failure from anything printable
+    pub fn failure(message: impl ToString) -> Self {
+        EnvsenseError::Failure(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_kinds_to_exit_codes() {
+        let parse = crate::check::parse_predicate("").unwrap_err();
+        let predicate = EnvsenseError::Predicate {
+            predicate: String::new(),
+            source: parse,
+        };
+        assert_eq!(predicate.exit_code(), 2);
+        assert_eq!(predicate.to_string(), "parsing '': empty input");
+        assert_eq!(EnvsenseError::failure("boom").exit_code(), 1);
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert_eq!(EnvsenseError::from(json).exit_code(), 3);
+        assert_eq!(
+            EnvsenseError::Reported(ErrorKind::Input).kind(),
+            ErrorKind::Input
+        );
+    }
+}
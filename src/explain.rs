@@ -0,0 +1,549 @@
+//! Decision-chain tracing for `envsense explain`.
+//!
+//! Re-runs the declarative selection logic against a snapshot and records
+//! every mapping that was considered, which of its indicators matched, and
+//! which mapping won, so users can see *why* a context was (not) detected.
+
+use crate::check::{self, Check, FieldRegistry};
+use crate::detectors::declarative::DeclarativeDetector;
+use crate::detectors::env_mapping::{EnvIndicator, EnvMapping, get_agent_mappings};
+use crate::detectors::utils::{
+    SelectionStrategy, check_generic_overrides, find_best_mapping_by_confidence,
+    find_best_mapping_by_priority,
+};
+use crate::detectors::{DeclarativeCiDetector, DeclarativeIdeDetector, EnvSnapshot};
+use crate::engine::DetectionEngine;
+use crate::redact::{REDACTED, is_secret_key};
+use crate::schema::EnvSense;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Outcome of a single indicator within a mapping
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct IndicatorTrace {
+    pub key: String,
+    pub condition: String,
+    pub actual: Option<String>,
+    pub required: bool,
+    pub matched: bool,
+}
+
+/// Outcome of a single mapping considered by a detector
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MappingTrace {
+    pub id: String,
+    pub confidence: f32,
+    pub priority: u8,
+    pub matched: bool,
+    pub selected: bool,
+    pub indicators: Vec<IndicatorTrace>,
+}
+
+/// Full decision chain for one context
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ContextExplanation {
+    pub context: String,
+    pub detected: bool,
+    pub strategy: String,
+    pub winner: Option<String>,
+    pub confidence: f32,
+    pub notes: Vec<String>,
+    pub mappings: Vec<MappingTrace>,
+}
+
+/// Result of explaining a predicate or context
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Explanation {
+    pub target: Option<String>,
+    pub result: Option<String>,
+    pub reason: Option<String>,
+    pub contexts: Vec<ContextExplanation>,
+}
+
+/// Explain a predicate or context (or every context when `target` is `None`)
+///
+/// `engine`'s result for `snap` decides what is reported as detected, while
+/// the mapping traces are recomputed from `snap`. Every context the engine's
+/// detectors declare can be explained.
+pub fn explain(
+    target: Option<&str>,
+    snap: &EnvSnapshot,
+    engine: &DetectionEngine,
+) -> Result<Explanation, String> {
+    let env = &engine.detect_from_snapshot(snap);
+    let contexts: Vec<String> = engine
+        .declared_contexts()
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    let Some(target) = target else {
+        return Ok(Explanation {
+            target: None,
+            result: None,
+            reason: None,
+            contexts: contexts
+                .iter()
+                .map(|ctx| explain_context(ctx, snap, env))
+                .collect(),
+        });
+    };
+
+    let parsed = check::parse_predicate(target).map_err(|e| e.to_string())?;
+    let registry = FieldRegistry::new();
+    let context = match &parsed.check {
+        Check::Context(ctx) => ctx.clone(),
+        Check::NestedField { path, .. } => {
+            check::validate_field_path(path, &registry).map_err(|e| e.to_string())?;
+            path[0].clone()
+        }
+    };
+    if !contexts.contains(&context) {
+        return Err(format!(
+            "cannot explain '{}': supported contexts are {}",
+            context,
+            contexts.join(", ")
+        ));
+    }
+
+    let evaluation = check::evaluate(env, parsed, &registry);
+    Ok(Explanation {
+        target: Some(target.to_string()),
+        result: Some(evaluation.result.format(false)),
+        reason: evaluation.reason,
+        contexts: vec![explain_context(&context, snap, env)],
+    })
+}
+
+fn explain_context(context: &str, snap: &EnvSnapshot, env: &EnvSense) -> ContextExplanation {
+    let detected = env.contexts.iter().any(|c| c == context);
+    match context {
+        "agent" => explain_declarative(
+            context,
+            snap,
+            detected,
+            env.traits.agent.id.clone(),
+            get_agent_mappings()
+                .into_iter()
+                .filter(|m| m.contexts.iter().any(|c| c == "agent"))
+                .collect(),
+            None,
+        ),
+        "ide" => explain_declarative(
+            context,
+            snap,
+            detected,
+            env.traits.ide.id.clone(),
+            DeclarativeIdeDetector::get_mappings(),
+            Some(DeclarativeIdeDetector::get_selection_strategy()),
+        ),
+        "ci" => explain_declarative(
+            context,
+            snap,
+            detected,
+            env.traits.ci.id.clone(),
+            DeclarativeCiDetector::get_mappings(),
+            Some(DeclarativeCiDetector::get_selection_strategy()),
+        ),
+        "terminal" => explain_terminal(snap, env),
+        _ => explain_evidence(context, detected, env),
+    }
+}
+
+/// Trace a declarative detector; `strategy: None` is the agent detector's
+/// first-match rule.
+fn explain_declarative(
+    context: &str,
+    snap: &EnvSnapshot,
+    detected: bool,
+    winner: Option<String>,
+    mappings: Vec<EnvMapping>,
+    strategy: Option<SelectionStrategy>,
+) -> ContextExplanation {
+    let strategy_name = match strategy {
+        None => "first match",
+        Some(SelectionStrategy::Confidence) => "highest confidence",
+        Some(SelectionStrategy::Priority) => "highest priority",
+    };
+    let mut explanation = ContextExplanation {
+        context: context.to_string(),
+        detected,
+        strategy: strategy_name.to_string(),
+        winner,
+        confidence: 0.0,
+        notes: Vec::new(),
+        mappings: Vec::new(),
+    };
+
+    if let Some((_, confidence, evidence)) = check_generic_overrides(snap, context) {
+        explanation.strategy = "override".to_string();
+        explanation.confidence = confidence;
+        match evidence.first() {
+            Some(e) => explanation.notes.push(format!(
+                "{}={} overrides mapping detection",
+                e.key,
+                e.value.as_deref().unwrap_or("")
+            )),
+            None => explanation.notes.push(format!(
+                "detection disabled by an ENVSENSE_* override for {}",
+                context
+            )),
+        }
+        return explanation;
+    }
+
+    let selected_id = match strategy {
+        None => mappings
+            .iter()
+            .find(|m| m.confidence > 0.0 && m.matches(snap.env())),
+        Some(SelectionStrategy::Confidence) => {
+            find_best_mapping_by_confidence(&mappings, snap.env())
+        }
+        Some(SelectionStrategy::Priority) => find_best_mapping_by_priority(&mappings, snap.env()),
+    }
+    .map(|m| m.id.clone());
+
+    for mapping in &mappings {
+        let selected = selected_id.as_deref() == Some(mapping.id.as_str());
+        if selected {
+            explanation.confidence = mapping.confidence;
+        }
+        explanation.mappings.push(MappingTrace {
+            id: mapping.id.clone(),
+            confidence: mapping.confidence,
+            priority: mapping.get_highest_priority(),
+            matched: mapping.matches(snap.env()),
+            selected,
+            indicators: mapping
+                .indicators
+                .iter()
+                .map(|i| trace_indicator(mapping, i, snap))
+                .collect(),
+        });
+    }
+
+    explanation
+}
+
+/// Describe how a single indicator fared against the snapshot
+pub fn trace_indicator(
+    mapping: &EnvMapping,
+    indicator: &EnvIndicator,
+    snap: &EnvSnapshot,
+) -> IndicatorTrace {
+    let condition = if indicator.prefix {
+        format!("any {}* set", indicator.key)
+    } else if let Some(value) = &indicator.value {
+        format!("{} == \"{}\"", indicator.key, value)
+    } else if let Some(substring) = &indicator.contains {
+        format!("{} contains \"{}\"", indicator.key, substring)
+    } else {
+        format!("{} set", indicator.key)
+    };
+
+    let actual = if indicator.prefix {
+        let keys: Vec<String> = snap
+            .env()
+            .with_prefix(&indicator.key)
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        (!keys.is_empty()).then(|| keys.join(", "))
+    } else {
+        snap.get_env(&indicator.key).map(|v| {
+            if is_secret_key(&indicator.key) {
+                REDACTED.to_string()
+            } else {
+                v.clone()
+            }
+        })
+    };
+
+    IndicatorTrace {
+        key: indicator.key.clone(),
+        condition,
+        actual,
+        required: indicator.required,
+        matched: mapping.indicator_matches(indicator, snap.env()),
+    }
+}
+
+fn explain_terminal(snap: &EnvSnapshot, env: &EnvSense) -> ContextExplanation {
+    let terminal = &env.traits.terminal;
+    let source = |key: &str| {
+        if snap.get_env(key).is_some() {
+            format!("from {}", key)
+        } else {
+            "runtime detection".to_string()
+        }
+    };
+
+    // A color variable such as NO_COLOR decides unless the level is overridden
+    let color_source = match env
+        .evidence
+        .iter()
+        .find(|e| e.supports.iter().any(|s| s == "terminal.color_level"))
+    {
+        Some(evidence) if snap.get_env("ENVSENSE_COLOR_LEVEL").is_none() => {
+            format!("from {}", evidence.key)
+        }
+        _ => source("ENVSENSE_COLOR_LEVEL"),
+    };
+
+    let notes = vec![
+        format!("stdin.tty = {}", snap.is_tty_stdin()),
+        format!("stdout.tty = {}", snap.is_tty_stdout()),
+        format!("stderr.tty = {}", snap.is_tty_stderr()),
+        format!(
+            "interactive = {} (stdin and stdout are both TTYs)",
+            terminal.interactive
+        ),
+        format!(
+            "color_level = {} ({})",
+            serde_json::to_value(&terminal.color_level)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            color_source
+        ),
+        format!(
+            "supports_hyperlinks = {} ({})",
+            terminal.supports_hyperlinks,
+            source("ENVSENSE_SUPPORTS_HYPERLINKS")
+        ),
+    ];
+
+    ContextExplanation {
+        context: "terminal".to_string(),
+        detected: terminal.interactive,
+        strategy: "tty".to_string(),
+        winner: None,
+        confidence: crate::detectors::confidence::TERMINAL,
+        notes,
+        mappings: Vec::new(),
+    }
+}
+
+/// Trace a context from the evidence its detectors recorded, for detectors
+/// without mappings such as the shell and container detectors and plugins
+fn explain_evidence(context: &str, detected: bool, env: &EnvSense) -> ContextExplanation {
+    let evidence: Vec<_> = env
+        .evidence
+        .iter()
+        .filter(|e| e.supports_path(context))
+        .collect();
+    let notes = evidence
+        .iter()
+        .map(|e| match &e.value {
+            Some(value) => format!("{}={} ({})", e.key, value, e.signal.as_str()),
+            None => format!("{} ({})", e.key, e.signal.as_str()),
+        })
+        .collect();
+
+    ContextExplanation {
+        context: context.to_string(),
+        detected,
+        strategy: "evidence".to_string(),
+        winner: None,
+        confidence: evidence.iter().map(|e| e.confidence).fold(0.0, f32::max),
+        notes,
+        mappings: Vec::new(),
+    }
+}
+
+/// Render an explanation as human-readable text
+pub fn render_explanation(explanation: &Explanation) -> String {
+    let mut out = String::new();
+
+    if let Some(target) = &explanation.target {
+        let _ = writeln!(
+            out,
+            "{} = {}",
+            target,
+            explanation.result.as_deref().unwrap_or("")
+        );
+        if let Some(reason) = &explanation.reason {
+            let _ = writeln!(out, "  reason: {}", reason);
+        }
+        out.push('\n');
+    }
+
+    for (i, ctx) in explanation.contexts.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let status = if ctx.detected {
+            "detected"
+        } else {
+            "not detected"
+        };
+        let _ = write!(out, "{}: {}", ctx.context, status);
+        if let Some(winner) = &ctx.winner {
+            let _ = write!(out, " (id={}, confidence={:.2})", winner, ctx.confidence);
+        }
+        out.push('\n');
+        let _ = writeln!(out, "  strategy: {}", ctx.strategy);
+        for note in &ctx.notes {
+            let _ = writeln!(out, "  {}", note);
+        }
+        if !ctx.mappings.is_empty() {
+            let _ = writeln!(out, "  mappings:");
+        }
+        for mapping in &ctx.mappings {
+            let marker = if mapping.selected {
+                "=>"
+            } else if mapping.matched {
+                "+ "
+            } else {
+                "- "
+            };
+            let _ = write!(
+                out,
+                "  {} {} (confidence={:.2}",
+                marker, mapping.id, mapping.confidence
+            );
+            if mapping.priority > 0 {
+                let _ = write!(out, ", priority={}", mapping.priority);
+            }
+            let _ = writeln!(out, ")");
+            for indicator in &mapping.indicators {
+                let _ = write!(
+                    out,
+                    "       [{}] {}{}",
+                    if indicator.matched { "x" } else { " " },
+                    indicator.condition,
+                    if indicator.required {
+                        " (required)"
+                    } else {
+                        ""
+                    }
+                );
+                match &indicator.actual {
+                    Some(actual) => {
+                        let _ = writeln!(out, ", actual: {}", actual);
+                    }
+                    None => out.push('\n'),
+                }
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::test_utils::create_env_snapshot;
+
+    fn explain_snapshot(target: Option<&str>, snap: &EnvSnapshot) -> Result<Explanation, String> {
+        explain(target, snap, &DetectionEngine::with_default_detectors())
+    }
+
+    #[test]
+    fn explains_selected_agent_mapping() {
+        let snap = create_env_snapshot(vec![("CURSOR_AGENT", "1")]);
+        let explanation = explain_snapshot(Some("agent"), &snap).unwrap();
+
+        assert_eq!(explanation.result.as_deref(), Some("true"));
+        let agent = &explanation.contexts[0];
+        assert!(agent.detected);
+        assert_eq!(agent.winner.as_deref(), Some("cursor"));
+
+        let selected: Vec<_> = agent.mappings.iter().filter(|m| m.selected).collect();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, "cursor");
+        assert!(selected[0].indicators.iter().any(|i| i.matched));
+    }
+
+    #[test]
+    fn explains_missing_context() {
+        let snap = create_env_snapshot(vec![]);
+        let explanation = explain_snapshot(Some("ci.id=github_actions"), &snap).unwrap();
+
+        assert_eq!(explanation.result.as_deref(), Some("false"));
+        let ci = &explanation.contexts[0];
+        assert!(!ci.detected);
+        assert!(ci.mappings.iter().all(|m| !m.matched && !m.selected));
+    }
+
+    #[test]
+    fn reports_overrides() {
+        let snap = create_env_snapshot(vec![("ENVSENSE_AGENT", "custom"), ("CURSOR_AGENT", "1")]);
+        let explanation = explain_snapshot(Some("agent"), &snap).unwrap();
+
+        let agent = &explanation.contexts[0];
+        assert_eq!(agent.strategy, "override");
+        assert!(agent.mappings.is_empty());
+        assert!(agent.notes[0].contains("ENVSENSE_AGENT=custom"));
+    }
+
+    #[test]
+    fn names_the_variable_that_decided_color_level() {
+        let snap = create_env_snapshot(vec![("NO_COLOR", "1")]);
+        let explanation = explain_snapshot(Some("terminal.color_level"), &snap).unwrap();
+
+        let terminal = &explanation.contexts[0];
+        assert!(
+            terminal
+                .notes
+                .contains(&"color_level = none (from NO_COLOR)".to_string())
+        );
+    }
+
+    #[test]
+    fn explains_every_declared_context_without_target() {
+        let snap = create_env_snapshot(vec![]);
+        let explanation = explain_snapshot(None, &snap).unwrap();
+        let contexts: Vec<_> = explanation
+            .contexts
+            .iter()
+            .map(|c| c.context.clone())
+            .collect();
+        let declared: Vec<_> = DetectionEngine::with_default_detectors()
+            .declared_contexts()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(contexts, declared);
+    }
+
+    #[test]
+    fn explains_contexts_from_evidence() {
+        let snap = create_env_snapshot(vec![("container", "podman")]);
+        let explanation = explain_snapshot(Some("container"), &snap).unwrap();
+
+        let container = &explanation.contexts[0];
+        assert!(container.detected);
+        assert_eq!(container.strategy, "evidence");
+        assert_eq!(container.notes, ["container=podman (env)"]);
+    }
+
+    #[test]
+    fn redacts_secret_indicator_values() {
+        let mapping = EnvMapping {
+            id: "test".to_string(),
+            confidence: 1.0,
+            indicators: vec![EnvIndicator {
+                key: "SERVICE_TOKEN".to_string(),
+                value: None,
+                required: false,
+                prefix: false,
+                contains: None,
+                priority: 0,
+            }],
+            facets: Default::default(),
+            contexts: vec![],
+            value_mappings: vec![],
+        };
+        let snap = create_env_snapshot(vec![("SERVICE_TOKEN", "hunter2")]);
+        let trace = trace_indicator(&mapping, &mapping.indicators[0], &snap);
+        assert_eq!(trace.actual.as_deref(), Some(REDACTED));
+        assert!(trace.matched);
+    }
+
+    #[test]
+    fn rejects_unknown_predicates() {
+        let snap = create_env_snapshot(vec![]);
+        assert!(explain_snapshot(Some("bogus.field"), &snap).is_err());
+    }
+}
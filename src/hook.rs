@@ -0,0 +1,93 @@
+//! Shell integration snippets for `envsense hook`.
+//!
+//! Detected values are exported under [`DETECTED_PREFIX`] rather than as
+//! `ENVSENSE_AGENT` and friends: those are detection overrides, and exporting
+//! them would feed one run's result into the next.
+
+use crate::schema::EnvSense;
+
+/// Prefix of the variables exported by `use envsense`
+pub const DETECTED_PREFIX: &str = "ENVSENSE_DETECTED_";
+
+/// direnv stdlib extension providing `use envsense` and check helpers
+pub const DIRENV_STDLIB: &str = r#"# envsense direnv integration
+#
+# Install with:   envsense hook direnv > ~/.config/direnv/lib/envsense.sh
+# Then in .envrc: use envsense
+
+# Export ENVSENSE_DETECTED_* variables describing the current environment
+use_envsense() {
+  local exports
+  exports="$(envsense hook direnv --exports)" || return
+  eval "$exports"
+  if [ -f .envsense.toml ]; then
+    watch_file .envsense.toml
+  fi
+}
+
+# Succeed if all predicates match, e.g. `envsense_check agent !ci`
+envsense_check() {
+  envsense check --quiet "$@"
+}
+
+# Succeed if any predicate matches
+envsense_check_any() {
+  envsense check --quiet --any "$@"
+}
+"#;
+
+/// `export` statements for the detected contexts and ids
+///
+/// Every variable is always exported, empty when nothing was detected, so
+/// `.envrc` files can test them with `[ -n ... ]` under `set -u`.
+pub fn direnv_exports(env: &EnvSense) -> String {
+    let vars = [
+        ("CONTEXTS", env.contexts.join(" ")),
+        ("AGENT", env.traits.agent.id.clone().unwrap_or_default()),
+        ("IDE", env.traits.ide.id.clone().unwrap_or_default()),
+        ("CI", env.traits.ci.id.clone().unwrap_or_default()),
+    ];
+    vars.iter()
+        .map(|(name, value)| {
+            format!(
+                "export {}{}={}\n",
+                DETECTED_PREFIX,
+                name,
+                shell_quote(value)
+            )
+        })
+        .collect()
+}
+
+/// Single-quote `value` for POSIX shells
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_detected_values() {
+        let mut env = EnvSense {
+            contexts: vec!["agent".to_string(), "ci".to_string()],
+            ..EnvSense::default()
+        };
+        env.traits.agent.id = Some("cursor".to_string());
+        env.traits.ci.id = Some("github_actions".to_string());
+
+        assert_eq!(
+            direnv_exports(&env),
+            "export ENVSENSE_DETECTED_CONTEXTS='agent ci'\n\
+             export ENVSENSE_DETECTED_AGENT='cursor'\n\
+             export ENVSENSE_DETECTED_IDE=''\n\
+             export ENVSENSE_DETECTED_CI='github_actions'\n"
+        );
+    }
+
+    #[test]
+    fn quotes_single_quotes() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
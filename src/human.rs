@@ -0,0 +1,398 @@
+//! The human-readable and `--raw` output of `envsense info`.
+//!
+//! `info` runs from shell prompts and status bars on every redraw, so the
+//! sections are written straight into one buffer instead of being assembled
+//! from a `String` per line.
+
+use crate::check;
+use crate::schema::{EnvSense, Evidence};
+use crate::theme::Theme;
+use serde_json::{Map, Value, json};
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// Sections rendered when no fields are selected
+pub const DEFAULT_FIELDS: &[&str] = &["contexts", "traits"];
+
+const SECTIONS: &[&str] = &["contexts", "traits", "facets", "meta", "evidence"];
+
+/// Render the selected sections of a detection result
+///
+/// `fields` is the comma-separated `--fields` value; dotted paths such as
+/// `traits.ci.branch` narrow their section. `raw` drops headings and
+/// indentation for scripts, and `show_confidence` appends each context's
+/// confidence score.
+pub fn render_human(
+    env: &EnvSense,
+    fields: Option<&str>,
+    theme: &Theme,
+    raw: bool,
+    show_confidence: bool,
+) -> Result<String, String> {
+    let selected: Vec<&str> = match fields {
+        Some(f) => f
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect(),
+        None => DEFAULT_FIELDS.to_vec(),
+    };
+    // Dotted paths narrow their section; sections render in first-mention order
+    let mut sections: Vec<&str> = Vec::new();
+    for s in &selected {
+        let section = s.split('.').next().unwrap_or_default();
+        if !SECTIONS.contains(&section) {
+            return Err(format!("unknown field: {}", s));
+        }
+        if !sections.contains(&section) {
+            sections.push(section);
+        }
+    }
+
+    // Only the JSON-shaped sections that are shown get serialized
+    let mut doc = Map::new();
+    for name in ["traits", "facets", "meta"] {
+        if sections.contains(&name) {
+            let value = match name {
+                "traits" => serde_json::to_value(&env.traits).unwrap_or_default(),
+                "meta" => meta(env),
+                _ => json!({}),
+            };
+            doc.insert(name.to_string(), value);
+        }
+    }
+    let mut doc = Value::Object(doc);
+    let paths: Vec<&str> = selected
+        .iter()
+        .copied()
+        .filter(|s| s.contains('.'))
+        .collect();
+    if !paths.is_empty() {
+        let mut narrowed = check::select_paths(&doc, &paths)?;
+        if let (Value::Object(doc), Value::Object(narrowed)) = (&mut doc, &mut narrowed) {
+            // A whole section requested alongside some of its paths is shown in full
+            for (name, value) in doc.iter_mut() {
+                if !selected.contains(&name.as_str()) {
+                    *value = narrowed.remove(name).unwrap_or_else(|| json!({}));
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        match *section {
+            "contexts" => write_contexts(&mut out, env, theme, raw, show_confidence),
+            "traits" => write_traits(&mut out, &doc["traits"], theme, raw),
+            "facets" => write_items(&mut out, "Facets:", &doc["facets"], theme, raw, false),
+            "meta" => write_items(&mut out, "Meta:", &doc["meta"], theme, raw, true),
+            _ => write_evidence(&mut out, &env.evidence, theme, raw),
+        }
+    }
+    Ok(out)
+}
+
+/// The `meta` section: the schema version and, if recorded, timings
+fn meta(env: &EnvSense) -> Value {
+    let mut meta = json!({ "schema_version": env.version });
+    if let Some(timings) = &env.timings {
+        meta["timings"] = json!(timings);
+    }
+    meta
+}
+
+fn write_contexts(out: &mut String, env: &EnvSense, theme: &Theme, raw: bool, scores: bool) {
+    let mut contexts: Vec<&str> = env.contexts.iter().map(String::as_str).collect();
+    contexts.sort_unstable();
+    if !raw {
+        out.push_str(&theme.heading("Contexts:"));
+        out.push('\n');
+    }
+    for (j, context) in contexts.into_iter().enumerate() {
+        if !raw {
+            out.push_str("  - ");
+        } else if j > 0 {
+            out.push('\n');
+        }
+        out.push_str(context);
+        if scores && let Some(score) = env.confidence.contexts.get(context) {
+            out.push_str(" (");
+            push_score(out, *score);
+            out.push(')');
+        }
+        if !raw {
+            out.push('\n');
+        }
+    }
+}
+
+fn write_traits(out: &mut String, traits: &Value, theme: &Theme, raw: bool) {
+    if !raw {
+        out.push_str(&theme.heading("Traits:"));
+        out.push('\n');
+        write_nested(out, traits, 1, theme);
+        return;
+    }
+    // `context.field = value` lines, sorted by the joined name
+    let Value::Object(contexts) = traits else {
+        return;
+    };
+    let mut rows: Vec<(&str, &str, &Value)> = contexts
+        .iter()
+        .filter_map(|(context, fields)| Some((context, fields.as_object()?)))
+        .flat_map(|(context, fields)| {
+            fields
+                .iter()
+                .map(move |(field, value)| (context.as_str(), field.as_str(), value))
+        })
+        .collect();
+    rows.sort_by(|a, b| joined_name(a.0, a.1).cmp(joined_name(b.0, b.1)));
+    for (j, (context, field, value)) in rows.into_iter().enumerate() {
+        if j > 0 {
+            out.push('\n');
+        }
+        out.push_str(context);
+        out.push('.');
+        out.push_str(field);
+        out.push_str(" = ");
+        out.push_str(&plain(value));
+    }
+}
+
+/// The bytes of `context.field`, for sorting without joining
+fn joined_name<'a>(context: &'a str, field: &'a str) -> impl Iterator<Item = u8> + 'a {
+    context.bytes().chain([b'.']).chain(field.bytes())
+}
+
+/// Nested objects as indented `key:` blocks, empty ones as `none`
+fn write_nested(out: &mut String, value: &Value, indent: usize, theme: &Theme) {
+    let Value::Object(map) = value else {
+        push_indent(out, indent);
+        theme.push_value(out, &simple_value(value));
+        out.push('\n');
+        return;
+    };
+    for (key, value) in map {
+        push_indent(out, indent);
+        out.push_str(key);
+        match value {
+            Value::Object(fields) if fields.is_empty() => {
+                out.push_str(": ");
+                theme.push_value(out, "none");
+                out.push('\n');
+            }
+            Value::Object(_) => {
+                out.push_str(":\n");
+                write_nested(out, value, indent + 1, theme);
+            }
+            _ => {
+                out.push_str(": ");
+                theme.push_value(out, &simple_value(value));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// `key = value` lines for the facets and meta sections
+fn write_items(
+    out: &mut String,
+    heading: &str,
+    section: &Value,
+    theme: &Theme,
+    raw: bool,
+    always_heading: bool,
+) {
+    let mut items: Vec<(&String, &Value)> = section.as_object().into_iter().flatten().collect();
+    items.sort_by(|a, b| a.0.cmp(b.0));
+    if raw {
+        for (j, (key, value)) in items.into_iter().enumerate() {
+            if j > 0 {
+                out.push('\n');
+            }
+            out.push_str(key);
+            out.push_str(" = ");
+            out.push_str(&plain(value));
+        }
+        return;
+    }
+    if items.is_empty() && !always_heading {
+        return;
+    }
+    out.push_str(&theme.heading(heading));
+    for (key, value) in items {
+        out.push_str("\n  ");
+        out.push_str(key);
+        out.push_str(" = ");
+        theme.push_value(out, &plain(value));
+    }
+}
+
+fn write_evidence(out: &mut String, evidence: &[Evidence], theme: &Theme, raw: bool) {
+    if raw {
+        for (j, e) in evidence.iter().enumerate() {
+            if j > 0 {
+                out.push('\n');
+            }
+            push_entry(out, e);
+        }
+        return;
+    }
+    out.push_str(&theme.heading("Evidence:"));
+    out.push('\n');
+    // Reused for each entry and its `(signal, confidence, sources)` details
+    let mut scratch = String::new();
+    for e in evidence {
+        scratch.clear();
+        push_entry(&mut scratch, e);
+        out.push_str("  - ");
+        theme.push_value(out, &scratch);
+        out.push(' ');
+
+        scratch.clear();
+        scratch.push('(');
+        scratch.push_str(e.signal.as_str());
+        scratch.push_str(", ");
+        push_score(&mut scratch, e.confidence);
+        for source in &e.sources {
+            let _ = write!(scratch, ", {}", source);
+        }
+        scratch.push(')');
+        out.push_str(&theme.dim(&scratch));
+        out.push('\n');
+    }
+}
+
+/// `KEY=value`, or just `KEY` for evidence of presence
+fn push_entry(out: &mut String, evidence: &Evidence) {
+    out.push_str(&evidence.key);
+    if let Some(value) = &evidence.value {
+        out.push('=');
+        out.push_str(value);
+    }
+}
+
+/// Confidence score with at least one decimal, e.g. `1.0` or `0.95`
+fn push_score(out: &mut String, score: f32) {
+    let start = out.len();
+    let _ = write!(out, "{}", score);
+    if !out[start..].contains('.') {
+        out.push_str(".0");
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+/// Strings as they are, anything else as JSON
+fn plain(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(s) => Cow::Borrowed(s),
+        other => Cow::Owned(other.to_string()),
+    }
+}
+
+/// Scalars as they are and arrays as `[a, b]`
+fn simple_value(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(s) => Cow::Borrowed(s),
+        Value::Bool(true) => Cow::Borrowed("true"),
+        Value::Bool(false) => Cow::Borrowed("false"),
+        Value::Null => Cow::Borrowed("null"),
+        Value::Array(items) => {
+            let mut text = String::from("[");
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    text.push_str(", ");
+                }
+                text.push_str(&simple_value(item));
+            }
+            text.push(']');
+            Cow::Owned(text)
+        }
+        other => Cow::Owned(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Signal;
+
+    fn env() -> EnvSense {
+        let mut env = EnvSense {
+            contexts: vec!["ci".to_string(), "agent".to_string()],
+            ..EnvSense::default()
+        };
+        env.traits.ci.id = Some("github_actions".to_string());
+        env.traits.ci.branch = Some("main".to_string());
+        env.confidence.contexts.insert("ci".to_string(), 1.0);
+        env.evidence.push(Evidence {
+            signal: Signal::Env,
+            key: "GITHUB_ACTIONS".to_string(),
+            value: Some("true".to_string()),
+            supports: vec!["ci".to_string()],
+            confidence: 0.95,
+            sources: Vec::new(),
+        });
+        env
+    }
+
+    fn render(fields: Option<&str>, raw: bool) -> Result<String, String> {
+        render_human(&env(), fields, &Theme::plain(), raw, false)
+    }
+
+    #[test]
+    fn renders_default_sections() {
+        let out = render(None, false).unwrap();
+        assert!(out.starts_with("Contexts:\n  - agent\n  - ci\n\nTraits:\n"));
+        assert!(out.contains("  ci:\n    branch: main\n    id: github_actions\n"));
+
+        let raw = render(None, true).unwrap();
+        assert!(raw.starts_with("agent\nci\n"));
+        assert!(raw.contains("\nci.branch = main\nci.id = github_actions"));
+        assert!(!raw.ends_with('\n'));
+    }
+
+    #[test]
+    fn dotted_fields_narrow_their_section() {
+        assert_eq!(
+            render(Some("traits.ci.branch"), true).unwrap(),
+            "ci.branch = main"
+        );
+        // The whole section wins over one of its paths
+        let out = render(Some("traits.ci.branch,traits"), true).unwrap();
+        assert!(out.contains("ci.id = github_actions"));
+        assert_eq!(
+            render(Some("bogus"), false).unwrap_err(),
+            "unknown field: bogus"
+        );
+        assert!(render(Some("traits.ci.bogus"), false).is_err());
+    }
+
+    #[test]
+    fn renders_evidence_and_confidence() {
+        let out = render(Some("evidence"), false).unwrap();
+        assert_eq!(out, "Evidence:\n  - GITHUB_ACTIONS=true (env, 0.95)\n");
+        assert_eq!(
+            render(Some("evidence"), true).unwrap(),
+            "GITHUB_ACTIONS=true"
+        );
+
+        let out = render_human(&env(), Some("contexts"), &Theme::plain(), true, true).unwrap();
+        assert_eq!(out, "agent\nci (1.0)");
+    }
+
+    #[test]
+    fn signal_names_match_serialization() {
+        for signal in [Signal::Env, Signal::Tty, Signal::Conflict, Signal::Flag] {
+            assert_eq!(json!(signal), signal.as_str());
+        }
+    }
+}
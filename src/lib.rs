@@ -1,10 +1,120 @@
+//! Detect the runtime environment: coding agents, IDEs, CI systems and
+//! terminal capabilities.
+//!
+//! # Stable API
+//!
+//! [`prelude`] re-exports everything an embedding tool needs:
+//!
+//! - [`EnvSense::detect`](schema::EnvSense::detect) detects the current
+//!   process, [`EnvSense::detect_from`](schema::EnvSense::detect_from) a
+//!   captured or synthetic [`EnvSnapshot`](detectors::EnvSnapshot).
+//! - [`DetectionEngine`](engine::DetectionEngine) runs a chosen set of
+//!   detectors, including custom [`Detector`](detectors::Detector)s.
+//! - [`CachedEnvSense`](cache::CachedEnvSense) reuses a detection result for
+//!   a time-to-live, for long-running programs.
+//! - [`check::evaluate_predicate`] evaluates predicates such as
+//!   `agent.id=cursor`; [`check::registry`] lists every field they can name.
+//!
+//! The items in [`prelude`] and the modules [`cache`], [`check`],
+//! [`detectors`], [`engine`], [`error`], [`schema`], [`traits`], [`assume`],
+//! [`overrides`] and [`redact`] follow semver: breaking changes only happen
+//! in a new minor release while envsense is below 1.0, and renames go
+//! through the deprecation period described in `CONTRACT.md`.
+//!
+//! Hidden modules support the `envsense` binary (configuration, output
+//! rendering, the daemon), are only built with the `cli` feature, and may
+//! change in any release.
+//!
+//! # Features
+//!
+//! - `cli` (default): the binary and its support modules; implies the rest.
+//! - `schemars` (default): `JsonSchema` for the output types.
+//! - `terminal-detection` (default): probe color support; without it only
+//!   the color variables and `ENVSENSE_COLOR_LEVEL` are used.
+//! - `declarative-mappings` (default): the built-in agent, CI and IDE
+//!   detectors and user mapping files.
+//! - `tracing`: debug-level `tracing` spans around each detector, the mapping
+//!   lookups and the merge, for embedders with their own subscriber.
+//!
+//! ```
+//! use envsense::prelude::*;
+//!
+//! let env = EnvSense::detect();
+//! let interactive = check::evaluate_predicate(&env, "terminal.interactive").unwrap();
+//! println!("interactive: {}", interactive.result.as_bool());
+//! ```
+
+#[cfg(feature = "cli")]
+#[doc(hidden)]
 pub mod agent;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod ascii;
+pub mod assume;
+pub mod cache;
 pub mod check;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
 pub mod config;
+#[cfg(all(unix, feature = "cli"))]
+#[doc(hidden)]
+pub mod daemon;
+#[doc(hidden)]
+pub mod deprecation;
 // Legacy CI module removed - using declarative CI detection
 pub mod detectors;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod disk_cache;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod doctor;
 pub mod engine;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod env_file;
+pub mod error;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod explain;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod hook;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod human;
+#[doc(hidden)]
+pub mod logging;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod mappings;
+pub mod overrides;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod pager;
+pub mod prelude;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod prompt;
+pub mod redact;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod report;
 pub mod schema;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod server;
+#[doc(hidden)]
+pub mod snapshot;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod theme;
 pub mod traits;
+#[cfg(feature = "tui")]
+#[doc(hidden)]
+pub mod tui;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod wait;
 
 pub use traits::terminal::TerminalTraits;
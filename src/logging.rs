@@ -0,0 +1,142 @@
+//! Diagnostic logging of detection runs for `-v`, `-vv` and `--debug`.
+//!
+//! Events are written to stderr as logfmt lines, e.g.
+//! `envsense[debug] mapping.matched detector=agent id=cursor`, so they can be
+//! grepped or parsed without mixing into regular output on stdout.
+//!
+//! With the `tracing` feature the detection pipeline also opens `tracing`
+//! spans (`detect`, `detector`, `mappings`, `merge`) and emits a
+//! `mapping.selected` event, for embedders with their own subscriber.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Off as u8);
+
+/// How much to log, set once per process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Off = 0,
+    /// `-v`: one line per detector and per run, with timing
+    Verbose = 1,
+    /// `-vv` or `--debug`: also mappings and environment variables consulted
+    Debug = 2,
+}
+
+impl Level {
+    /// Level for `-v` given `verbose` times, or `--debug`
+    pub fn from_flags(verbose: u8, debug: bool) -> Self {
+        match (verbose, debug) {
+            (_, true) | (2.., _) => Level::Debug,
+            (1, _) => Level::Verbose,
+            (0, _) => Level::Off,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Off => "off",
+            Level::Verbose => "verbose",
+            Level::Debug => "debug",
+        }
+    }
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Whether events at `level` are logged
+pub fn enabled(level: Level) -> bool {
+    level != Level::Off && LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
+/// Print an event to stderr; use [`log_event!`](crate::log_event) instead
+pub fn emit(level: Level, event: &str, fields: &[(&str, String)]) {
+    eprintln!("{}", format_event(level, event, fields));
+}
+
+/// One logfmt line; values with spaces, quotes or `=` are quoted
+pub fn format_event(level: Level, event: &str, fields: &[(&str, String)]) -> String {
+    let mut line = format!("envsense[{}] {}", level.name(), event);
+    for (key, value) in fields {
+        let quote = value.is_empty() || value.contains([' ', '"', '=', '\n', '\t']);
+        if quote {
+            line.push_str(&format!(" {}={:?}", key, value));
+        } else {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    line
+}
+
+/// Log an event with `key = value` fields when its level is enabled
+///
+/// Values are only formatted when the event is actually logged.
+///
+/// ```
+/// envsense::log_event!(Debug, "mapping.matched", detector = "agent", id = "cursor");
+/// ```
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_event {
+    ($level:ident, $event:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        if $crate::logging::enabled($crate::logging::Level::$level) {
+            $crate::logging::emit(
+                $crate::logging::Level::$level,
+                $event,
+                &[$((stringify!($key), ($value).to_string())),*],
+            );
+        }
+    };
+}
+
+/// Enter a `tracing` debug span until the end of the enclosing block
+///
+/// Expands to nothing without the `tracing` feature.
+macro_rules! trace_span {
+    ($($args:tt)+) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::debug_span!($($args)+).entered();
+    };
+}
+
+/// Emit a `tracing` debug event; nothing without the `tracing` feature
+macro_rules! trace_event {
+    ($($args:tt)+) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($($args)+);
+    };
+}
+
+pub(crate) use {trace_event, trace_span};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_from_flags() {
+        assert_eq!(Level::from_flags(0, false), Level::Off);
+        assert_eq!(Level::from_flags(1, false), Level::Verbose);
+        assert_eq!(Level::from_flags(2, false), Level::Debug);
+        assert_eq!(Level::from_flags(3, false), Level::Debug);
+        assert_eq!(Level::from_flags(0, true), Level::Debug);
+    }
+
+    #[test]
+    fn formats_logfmt_lines() {
+        assert_eq!(
+            format_event(
+                Level::Verbose,
+                "detector.run",
+                &[
+                    ("name", "agent".to_string()),
+                    ("contexts", "agent,ci".to_string()),
+                    ("reason", "not set".to_string()),
+                    ("value", String::new()),
+                ]
+            ),
+            r#"envsense[verbose] detector.run name=agent contexts=agent,ci reason="not set" value="""#
+        );
+    }
+}
@@ -1,11 +1,45 @@
 use clap::{Args, ColorChoice, CommandFactory, FromArgMatches, Parser, Subcommand};
-use colored::Colorize;
+use envsense::ascii;
+use envsense::assume::{Assumptions, ForcedContexts};
 use envsense::check::{self, FieldRegistry};
-use envsense::config::CliConfig;
+use envsense::config::{
+    CheckFormat, CheckMode, CliConfig, ColorMode, DetectorsConfig, OutputFormat,
+};
+#[cfg(unix)]
+use envsense::daemon::{self, DaemonRequest, DaemonResponse};
+use envsense::deprecation::{DeprecationKind, Warnings};
+use envsense::detectors::EnvSnapshot;
+use envsense::detectors::external;
+use envsense::detectors::user_mappings::{self, MappingError, MappingFile, UserMappings};
+use envsense::disk_cache::DiskCache;
+use envsense::doctor;
+use envsense::engine::{DetectionEngine, DetectorResults, DetectorSelection};
+use envsense::env_file;
+use envsense::error::{EnvsenseError, ErrorKind};
+use envsense::explain;
+use envsense::hook;
+use envsense::human;
+use envsense::log_event;
+use envsense::logging;
+use envsense::mappings;
+use envsense::pager;
+use envsense::prompt::{self, PromptStyle, Segment};
+use envsense::report;
+use envsense::wait;
 // Legacy CI detection removed - using declarative system
-use envsense::schema::EnvSense;
-use serde_json::{Map, Value, json};
+use envsense::schema::compat::{Compat, CompatVersion, SchemaVersion};
+use envsense::schema::{Diagnostic, DiagnosticKind, EnvSense, EvidenceFilter, Timings};
+use envsense::schema::{json_schema, migrate};
+use envsense::server::{Server, info_json};
+use envsense::snapshot::CapturedSnapshot;
+use envsense::theme::Theme;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::ffi::OsString;
 use std::io::{IsTerminal, stdout};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
 
 fn check_predicate_long_help() -> &'static str {
     check::check_predicate_long_help()
@@ -16,13 +50,77 @@ fn check_predicate_long_help() -> &'static str {
     name = "envsense",
     about = "Environment awareness utilities",
     version = env!("CARGO_PKG_VERSION"),
-    arg_required_else_help = true
+    arg_required_else_help = true,
+    allow_external_subcommands = true,
+    after_help = "Predicates can be given without `check`: `envsense agent.id=cursor` is short for `envsense check agent.id=cursor`."
 )]
 struct Cli {
     /// Disable color
     #[arg(long = "no-color", global = true)]
     no_color: bool,
 
+    /// Run only these detectors (comma-separated, see `envsense detectors`)
+    #[arg(long, global = true, value_name = "list", value_delimiter = ',')]
+    only: Option<Vec<String>>,
+
+    /// Skip a detector; may be repeated or comma-separated
+    #[arg(
+        long = "disable-detector",
+        global = true,
+        value_name = "name",
+        value_delimiter = ','
+    )]
+    disable_detector: Vec<String>,
+
+    /// Force a context on for this run; may be repeated or comma-separated
+    #[arg(long, global = true, value_name = "context", value_delimiter = ',')]
+    assume: Vec<String>,
+
+    /// Force a context off for this run, clearing its traits
+    #[arg(long, global = true, value_name = "context", value_delimiter = ',')]
+    deny: Vec<String>,
+
+    /// Detect in-process even when `envsense daemon` is running
+    #[arg(long = "no-daemon", global = true)]
+    no_daemon: bool,
+
+    /// Detect afresh instead of reusing a cached result or prompt line
+    #[arg(long = "no-cache", global = true)]
+    no_cache: bool,
+
+    /// Don't warn about deprecated predicates, fields or flags
+    #[arg(long = "no-deprecation-warnings", global = true)]
+    no_deprecation_warnings: bool,
+
+    /// Restrict human output to ASCII (no unicode symbols or emoji)
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Log detection steps to stderr; repeat (-vv) for mappings and variables
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log everything -vv does
+    #[arg(long, global = true)]
+    debug: bool,
+
+    /// Print how long each detector took to stderr; implies --no-daemon
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Record which detector and evidence set each trait (shown by
+    /// info --json); implies --no-daemon
+    #[arg(long, global = true)]
+    provenance: bool,
+
+    /// Reject saved `--snapshot` results with fields this version does not know
+    #[arg(long = "strict-snapshot", global = true)]
+    strict_snapshot: bool,
+
+    /// Drop contexts detected with less confidence than this (0 to 1)
+    #[arg(long = "min-confidence", global = true, value_name = "score", value_parser = parse_confidence)]
+    min_confidence: Option<f32>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -33,6 +131,194 @@ enum Commands {
     Info(InfoArgs),
     /// Evaluate predicates against the environment
     Check(CheckCmd),
+    /// Fail with a custom message unless predicates hold
+    Assert(AssertArgs),
+    /// List every field predicates can use
+    Fields(FieldsArgs),
+    /// Poll detection until predicates hold or a timeout expires
+    Wait(WaitArgs),
+    /// Save the raw environment snapshot (secrets scrubbed) as JSON
+    Capture(CaptureArgs),
+    /// Show the full decision chain behind a predicate or context
+    Explain(ExplainArgs),
+    /// Diagnose suspicious environment setups and suggest fixes
+    Doctor(DoctorArgs),
+    /// List registered detectors and what they produced
+    Detectors(DetectorsArgs),
+    /// Work with user-defined environment mapping files
+    #[command(subcommand)]
+    Mappings(MappingsCommand),
+    /// Serve cached detection results to other envsense calls over a socket
+    #[cfg(unix)]
+    Daemon(DaemonArgs),
+    /// Answer JSON-RPC requests from an editor or tool
+    Serve(ServeArgs),
+    /// Print compact badges for a shell prompt (empty when nothing is detected)
+    Prompt(PromptArgs),
+    /// Print integration snippets for other tools
+    #[command(subcommand)]
+    Hook(HookCommand),
+    /// Print the JSON Schema of `info --json` or `check --json` output
+    Schema(SchemaArgs),
+    /// Browse contexts, traits and evidence interactively
+    #[cfg(feature = "tui")]
+    Tui(TuiArgs),
+    /// `envsense <predicate>...` is shorthand for `envsense check <predicate>...`
+    #[command(external_subcommand)]
+    Predicate(Vec<String>),
+}
+
+#[derive(Subcommand, Clone)]
+enum HookCommand {
+    /// direnv stdlib extension providing `use envsense` and `envsense_check`
+    Direnv(DirenvHookArgs),
+}
+
+#[derive(Args, Clone)]
+struct DirenvHookArgs {
+    /// Print `export` statements for the current environment instead
+    #[arg(long)]
+    exports: bool,
+}
+
+#[derive(Args, Clone)]
+struct PromptArgs {
+    /// Output style: starship or tmux
+    #[arg(long, value_name = "style")]
+    style: Option<PromptStyle>,
+
+    /// Segments to show, in order (comma-separated: ci, agent, ide, container, remote)
+    #[arg(long, value_name = "list", value_delimiter = ',')]
+    segments: Option<Vec<Segment>>,
+
+    /// Drop trailing badges that do not fit in this many terminal columns
+    #[arg(long, value_name = "n")]
+    max_width: Option<usize>,
+}
+
+#[derive(Args, Clone)]
+struct SchemaArgs {
+    /// Output to describe: info or check
+    #[arg(value_name = "document", default_value = "info")]
+    document: json_schema::Document,
+
+    /// Schema version to export (default: current)
+    #[arg(long, value_name = "version")]
+    version: Option<String>,
+}
+
+#[derive(Args, Clone)]
+struct ServeArgs {
+    /// Read requests from stdin and write responses to stdout
+    #[arg(long, required = true)]
+    stdio: bool,
+}
+
+#[cfg(unix)]
+#[derive(Args, Clone)]
+struct DaemonArgs {
+    /// Socket to listen on or talk to (default: config, then runtime directory)
+    #[arg(long, value_name = "path")]
+    socket: Option<PathBuf>,
+
+    /// Ask the running daemon to drop its cached results
+    #[arg(long, conflicts_with = "stop")]
+    refresh: bool,
+
+    /// Ask the running daemon to shut down
+    #[arg(long)]
+    stop: bool,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Args, Clone)]
+struct TuiArgs {
+    /// Seconds between automatic refreshes
+    #[arg(long, value_name = "secs", default_value_t = 2)]
+    interval: u64,
+}
+
+#[derive(Subcommand, Clone)]
+enum MappingsCommand {
+    /// Check built-in and user mappings for errors
+    Lint(MappingsLintArgs),
+    /// Show what a mapping file detects for a fixture environment
+    Test(MappingsTestArgs),
+}
+
+#[derive(Args, Clone)]
+struct MappingsTestArgs {
+    /// Mapping file to test (toml, yaml or json)
+    #[arg(value_name = "file")]
+    file: PathBuf,
+
+    /// Environment to test against: an `envsense capture` file, a JSON/TOML/YAML
+    /// table, or KEY=VALUE lines
+    #[arg(long, value_name = "path")]
+    fixture: Option<PathBuf>,
+
+    /// Set a variable for the test (repeatable; applied on top of --fixture)
+    #[arg(long = "env", value_name = "KEY=VALUE")]
+    vars: Vec<String>,
+
+    /// Output JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Clone)]
+struct MappingsLintArgs {
+    /// Mapping files to check (defaults to the files in the mappings directory)
+    #[arg(value_name = "file")]
+    files: Vec<PathBuf>,
+
+    /// Output JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Clone)]
+struct DetectorsArgs {
+    /// Output JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Evaluate against a snapshot saved by `envsense capture`
+    #[arg(long, value_name = "path")]
+    snapshot: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+struct DoctorArgs {
+    /// Output JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Evaluate against a snapshot saved by `envsense capture`
+    #[arg(long, value_name = "path")]
+    snapshot: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+struct ExplainArgs {
+    /// Predicate or context to explain (all contexts when omitted)
+    #[arg(value_name = "PREDICATE")]
+    target: Option<String>,
+
+    /// Output JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Evaluate against a snapshot saved by `envsense capture`
+    #[arg(long, value_name = "path")]
+    snapshot: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+struct CaptureArgs {
+    /// Write the snapshot to a file instead of stdout
+    #[arg(short, long, value_name = "path")]
+    output: Option<PathBuf>,
 }
 
 #[derive(Args, Clone)]
@@ -45,10 +331,43 @@ struct InfoArgs {
     #[arg(long)]
     raw: bool,
 
-    /// Comma-separated keys to include: contexts,traits,facets,meta
+    /// Output format: human, json, raw, markdown, html, csv or tsv
+    #[arg(long, value_name = "format", conflicts_with_all = ["json", "raw"])]
+    format: Option<OutputFormat>,
+
+    /// Stable, versioned key<TAB>value output for scripts
+    #[arg(long, conflicts_with_all = ["json", "raw", "format", "fields", "tree", "compact", "confidence", "evidence_for"])]
+    porcelain: bool,
+
+    /// With --json, serialize in an older schema layout (available: 0.2)
+    #[arg(
+        long,
+        value_name = "version",
+        requires = "json",
+        conflicts_with_all = ["fields", "evidence_for"]
+    )]
+    compat: Option<CompatVersion>,
+
+    /// With --json, print this schema version's layout (available: 0.2, 0.3;
+    /// default: current). Unknown versions are an error
+    #[arg(
+        long,
+        value_name = "version",
+        requires = "json",
+        conflicts_with = "compat"
+    )]
+    schema_version: Option<SchemaVersion>,
+
+    /// Comma-separated keys to include: contexts,traits,facets,meta, or
+    /// dotted paths such as traits.ci.branch
     #[arg(long, value_name = "list")]
     fields: Option<String>,
 
+    /// Only evidence supporting this context or field, e.g. ci or ci.id;
+    /// shows just the evidence unless --fields says otherwise
+    #[arg(long, value_name = "target")]
+    evidence_for: Option<String>,
+
     /// Use tree structure for nested display (hierarchical is default)
     #[arg(long)]
     tree: bool,
@@ -56,6 +375,95 @@ struct InfoArgs {
     /// Compact output without extra formatting
     #[arg(long)]
     compact: bool,
+
+    /// Show detection confidence next to each context
+    #[arg(long)]
+    confidence: bool,
+
+    /// Never page output through $PAGER
+    #[arg(long)]
+    no_pager: bool,
+
+    /// Evaluate against a `envsense capture` snapshot or saved `info --json` result
+    #[arg(long, value_name = "path")]
+    snapshot: Option<PathBuf>,
+
+    /// Detect using the variables in a .env file instead of the real environment
+    #[arg(long, value_name = "path", conflicts_with = "snapshot")]
+    env_file: Option<PathBuf>,
+
+    /// With --env-file, layer the file's variables over the real environment
+    #[arg(long, requires = "env_file")]
+    merge_env: bool,
+}
+
+#[derive(Args, Clone)]
+struct AssertArgs {
+    /// Predicates that must hold, in `check` syntax
+    #[arg(value_name = "PREDICATE", required = true)]
+    predicates: Vec<String>,
+
+    /// Message printed to stderr when the assertion fails
+    #[arg(short, long, value_name = "text")]
+    message: Option<String>,
+
+    /// Extra line printed after the failure message; may be repeated
+    #[arg(long, value_name = "text")]
+    hint: Vec<String>,
+
+    /// Pass if any predicate matches (default is ALL)
+    #[arg(long, conflicts_with = "all")]
+    any: bool,
+
+    /// Require all predicates to match (default behavior)
+    #[arg(long)]
+    all: bool,
+
+    /// Evaluate against a `envsense capture` snapshot or saved `info --json` result
+    #[arg(long, value_name = "path")]
+    snapshot: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+struct FieldsArgs {
+    /// Output JSON with each field's path, context, type and description
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Clone)]
+struct WaitArgs {
+    /// Predicates to wait for, in `check` syntax
+    #[arg(value_name = "PREDICATE", required = true)]
+    predicates: Vec<String>,
+
+    /// Give up after this long, e.g. 500ms, 30s or 2m (default: wait forever)
+    #[arg(long, value_name = "duration", value_parser = wait::parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Time between detections
+    #[arg(long, value_name = "duration", value_parser = wait::parse_duration, default_value = "1s")]
+    interval: Duration,
+
+    /// Stop waiting when any predicate matches (default is ALL)
+    #[arg(long, conflicts_with = "all")]
+    any: bool,
+
+    /// Wait until all predicates match (default behavior)
+    #[arg(long)]
+    all: bool,
+
+    /// Don't print a message on timeout
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Re-read the variables in this .env file on every detection
+    #[arg(long, value_name = "path")]
+    env_file: Option<PathBuf>,
+
+    /// With --env-file, layer the file's variables over the real environment
+    #[arg(long, requires = "env_file")]
+    merge_env: bool,
 }
 
 #[derive(Args, Clone)]
@@ -68,6 +476,10 @@ pub struct CheckCmd {
     )]
     pub predicates: Vec<String>,
 
+    /// Also read predicates from stdin, one per line (same as `-`)
+    #[arg(long, conflicts_with = "list")]
+    pub stdin: bool,
+
     /// Show explanations for results
     #[arg(short, long)]
     pub explain: bool,
@@ -76,6 +488,14 @@ pub struct CheckCmd {
     #[arg(long)]
     pub json: bool,
 
+    /// Print an aligned table of predicates, results and reasons
+    #[arg(long, conflicts_with_all = ["json", "quiet", "list"])]
+    pub report: bool,
+
+    /// Output format: human, json, ndjson (one JSON object per line), csv or tsv
+    #[arg(long, value_name = "format", conflicts_with_all = ["json", "report"])]
+    pub format: Option<CheckFormat>,
+
     /// Suppress output (useful in scripts)
     #[arg(short, long)]
     pub quiet: bool,
@@ -99,387 +519,443 @@ pub struct CheckCmd {
     /// Show context descriptions in list mode
     #[arg(long, requires = "list")]
     pub descriptions: bool,
+
+    /// Evaluate against a `envsense capture` snapshot or saved `info --json` result
+    #[arg(long, value_name = "path")]
+    pub snapshot: Option<PathBuf>,
+
+    /// Detect using the variables in a .env file instead of the real environment
+    #[arg(long, value_name = "path", conflicts_with = "snapshot")]
+    pub env_file: Option<PathBuf>,
+
+    /// With --env-file, layer the file's variables over the real environment
+    #[arg(long, requires = "env_file")]
+    pub merge_env: bool,
 }
 
 // JsonCheck struct removed - using new EvaluationResult system
 
-#[derive(Debug)]
-struct Snapshot {
-    contexts: Vec<String>,
-    traits: Value,
-    facets: Value,
-    meta: Value,
-    evidence: Value,
+/// Read the live environment, or a captured snapshot if given
+fn load_env_snapshot(snapshot: Option<&Path>) -> Result<EnvSnapshot, EnvsenseError> {
+    match snapshot {
+        Some(path) => match CapturedSnapshot::load(path) {
+            Ok(captured) => Ok(captured.to_env_snapshot()),
+            Err(e) => Err(EnvsenseError::input(e)),
+        },
+        None => Ok(live_snapshot()),
+    }
+}
+
+/// The live environment, read once per run
+///
+/// Cache keys, daemon requests and detection all need it; sharing one
+/// capture probes the TTYs and enumerates the environment at most once.
+fn live_snapshot() -> EnvSnapshot {
+    static LIVE: OnceLock<EnvSnapshot> = OnceLock::new();
+    LIVE.get_or_init(EnvSnapshot::current).clone()
+}
+
+/// The variables in the .env file at `path`
+///
+/// Only the file's variables are seen unless `merge` layers them over the
+/// real environment. TTY detection is always live.
+fn env_file_snapshot(path: &Path, merge: bool) -> Result<EnvSnapshot, EnvsenseError> {
+    let vars: HashMap<String, String> = env_file::load(path)
+        .map_err(EnvsenseError::input)?
+        .into_iter()
+        .collect();
+    let live = live_snapshot();
+    Ok(if merge {
+        live.layered(vars)
+    } else {
+        EnvSnapshot::from_provider(vars, live.tty_detector)
+    })
 }
 
-fn collect_snapshot() -> Snapshot {
-    let env = EnvSense::detect();
+/// Detect against the variables in the .env file at `path`
+fn detect_env_file(
+    cli: &Cli,
+    config: &CliConfig,
+    path: &Path,
+    merge: bool,
+    targets: &[String],
+) -> Result<EnvSense, EnvsenseError> {
+    let snapshot = env_file_snapshot(path, merge)?;
+    let engine = prepare_engine(cli, config)?.with_targets(targets);
+    force_contexts(
+        cli,
+        report_timings(report_diagnostics(engine.detect_from_snapshot(&snapshot))),
+    )
+}
 
-    Snapshot {
-        contexts: env.contexts, // Now Vec<String> instead of Contexts struct
-        traits: serde_json::to_value(env.traits).unwrap(), // Nested structure
-        facets: json!({}),      // Empty for new schema (backward compatibility)
-        meta: json!({
-            "schema_version": env.version,
-        }),
-        evidence: serde_json::to_value(env.evidence).unwrap(),
+/// A detection result saved at `snapshot`, or the daemon's for a live run
+fn finished_result(
+    cli: &Cli,
+    config: &CliConfig,
+    snapshot: Option<&Path>,
+) -> Result<Option<EnvSense>, EnvsenseError> {
+    if let Some(path) = snapshot
+        && let Some(env) = load_saved_result(path, cli.strict_snapshot)?
+    {
+        log_event!(Verbose, "snapshot.result", path = path.display());
+        return Ok(Some(env));
     }
+    #[cfg(unix)]
+    if snapshot.is_none()
+        && let Some(env) = detect_via_daemon(cli, config)
+    {
+        return Ok(Some(env));
+    }
+    #[cfg(not(unix))]
+    let _ = config;
+    Ok(None)
 }
 
-fn filter_json_fields(value: Value, fields: &str) -> Result<Value, String> {
-    let requested: Vec<&str> = fields
-        .split(',')
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
+/// Detect the live environment, or replay a captured snapshot if given
+///
+/// Live detection is answered by a running daemon when one can; the engine
+/// is only built when it can't. A saved detection result, from this or an
+/// older release, is used as-is instead of being replayed.
+fn detect_env(
+    cli: &Cli,
+    config: &CliConfig,
+    snapshot: Option<&Path>,
+) -> Result<EnvSense, EnvsenseError> {
+    detect_targets(cli, config, snapshot, &[])
+}
+
+/// [`detect_env`], running only the detectors that can produce `targets`
+///
+/// A daemon, saved or cached result is still used in full.
+fn detect_targets(
+    cli: &Cli,
+    config: &CliConfig,
+    snapshot: Option<&Path>,
+    targets: &[String],
+) -> Result<EnvSense, EnvsenseError> {
+    if let Some(env) = cached_result(cli, config, snapshot)? {
+        return force_contexts(cli, env);
+    }
+    force_contexts(cli, detect_uncached(cli, config, snapshot, targets)?)
+}
+
+/// The disk cache's result for the live environment, detecting everything
+/// and storing it on a miss; `None` when caching is off for this run
+fn cached_result(
+    cli: &Cli,
+    config: &CliConfig,
+    snapshot: Option<&Path>,
+) -> Result<Option<EnvSense>, EnvsenseError> {
+    if snapshot.is_some() {
+        return Ok(None);
+    }
+    let Some((cache, key)) = detection_cache(cli, config) else {
+        return Ok(None);
+    };
+    if let Some(env) = cache
+        .get(&key)
+        .and_then(|json| serde_json::from_str::<EnvSense>(&json).ok())
+    {
+        log_event!(Verbose, "cache.hit", key = key);
+        return Ok(Some(env));
+    }
+    // Detect everything, so the cached result answers any later call
+    let env = detect_uncached(cli, config, None, &[])?;
+    cache.put(&key, &serde_json::to_string(&env)?);
+    Ok(Some(env))
+}
+
+/// [`detect_targets`] without the disk cache or `--assume`/`--deny`
+fn detect_uncached(
+    cli: &Cli,
+    config: &CliConfig,
+    snapshot: Option<&Path>,
+    targets: &[String],
+) -> Result<EnvSense, EnvsenseError> {
+    if let Some(env) = finished_result(cli, config, snapshot)? {
+        return Ok(env);
+    }
+    let engine = prepare_engine(cli, config)?.with_targets(targets);
+    Ok(report_timings(report_diagnostics(
+        engine.detect_from_snapshot(&load_env_snapshot(snapshot)?),
+    )))
+}
+
+/// The `[cache]` directory and this environment's key in it, unless caching
+/// is off for this run
+fn detection_cache(cli: &Cli, config: &CliConfig) -> Option<(DiskCache, String)> {
+    // Cached timings would not describe this run, and cached results carry
+    // no provenance
+    if !config.cache.enabled || cli.no_cache || cli.timings || cli.provenance {
+        return None;
+    }
+    let dir = DiskCache::user_dir("detect")?;
+    // Everything besides the environment that changes the result
+    let settings = json!({
+        "detectors": effective_detectors(cli, config),
+        "assume": config.assume,
+        "mappings": mapping_stamps(),
+    });
+    let key = DiskCache::key(&live_snapshot(), &settings.to_string());
+    Some((
+        DiskCache::new(dir, Duration::from_secs(config.cache.ttl)),
+        key,
+    ))
+}
+
+/// Mapping files and when they were last modified, so that editing one
+/// changes the cache key
+fn mapping_stamps() -> Vec<(PathBuf, Option<SystemTime>)> {
+    let Some(entries) = CliConfig::mappings_dir().and_then(|dir| std::fs::read_dir(dir).ok())
+    else {
+        return Vec::new();
+    };
+    let mut stamps: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            (entry.path(), modified)
+        })
         .collect();
-    let obj = value
-        .as_object()
-        .ok_or_else(|| "expected object".to_string())?;
-    let mut map = Map::new();
-    for k in requested {
-        if let Some(v) = obj.get(k) {
-            map.insert(k.to_string(), v.clone());
-        } else {
-            return Err(format!("unknown field: {}", k));
+    stamps.sort();
+    stamps
+}
+
+/// Apply `--assume` and `--deny` to a detection result
+fn force_contexts(cli: &Cli, mut env: EnvSense) -> Result<EnvSense, EnvsenseError> {
+    let forced = ForcedContexts::new(&cli.assume, &cli.deny).map_err(EnvsenseError::input)?;
+    forced.apply(&mut env).map_err(EnvsenseError::failure)?;
+    Ok(env)
+}
+
+/// Print a warning to stderr for each failed detector and ignored setting
+///
+/// They stay in `env`, so JSON output lists them as well. Cached and daemon
+/// results are not reported again.
+fn report_diagnostics(env: EnvSense) -> EnvSense {
+    for diagnostic in &env.diagnostics {
+        match diagnostic.kind {
+            DiagnosticKind::Warning => eprintln!("Warning: {}", diagnostic.message),
+            _ => eprintln!(
+                "Warning: detector '{}' failed: {}",
+                diagnostic.detector, diagnostic.message
+            ),
         }
     }
-    Ok(Value::Object(map))
+    env
 }
 
-fn value_to_string(v: &Value) -> String {
-    match v {
-        Value::String(s) => s.clone(),
-        _ => v.to_string(),
+/// Print the `--timings` table to stderr when `env` carries timings
+fn report_timings(env: EnvSense) -> EnvSense {
+    if let Some(timings) = &env.timings {
+        eprint!("{}", format_timings(timings));
     }
+    env
 }
 
-fn colorize_value_with_rainbow(v: &str, color: bool) -> String {
-    if !color {
-        return v.to_string();
+/// Detector wall times as an aligned table, in milliseconds
+fn format_timings(timings: &Timings) -> String {
+    let rows: Vec<(&str, u64)> = timings
+        .detectors
+        .iter()
+        .map(|t| (t.detector.as_str(), t.elapsed_us))
+        .chain([("total", timings.total_us)])
+        .collect();
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("detector".len());
+    let mut table = format!("{:<width$}  {:>9}\n", "detector", "time");
+    for (name, us) in rows {
+        let ms = format!("{:.3} ms", us as f64 / 1000.0);
+        table.push_str(&format!("{:<width$}  {:>9}\n", name, ms));
     }
+    table
+}
 
-    // Apply rainbow effect to "truecolor" when colors are enabled
-    if v == "truecolor" {
-        return format_color_level_with_rainbow(v, true);
+/// Read `path` as a saved `info --json` result; `None` if it is something else
+///
+/// `strict` rejects fields the current schema does not have.
+fn load_saved_result(path: &Path, strict: bool) -> Result<Option<EnvSense>, EnvsenseError> {
+    // Unreadable or non-JSON files are reported by the capture loader
+    let Some(value) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+    else {
+        return Ok(None);
+    };
+    if value.get("format_version").is_some() || value.get("contexts").is_none() {
+        return Ok(None);
     }
+    let read = if strict {
+        migrate::migrate_strict
+    } else {
+        migrate::migrate
+    };
+    read(value)
+        .map(Some)
+        .map_err(|e| EnvsenseError::input(format!("'{}': {}", path.display(), e)))
+}
 
-    match v {
-        "true" => v.green().to_string(),
-        "false" | "none" => v.red().to_string(),
-        _ => v.to_string(),
+/// Ask a running daemon to detect the live environment
+///
+/// Any failure, including no daemon running, yields `None` so the caller
+/// falls back to in-process detection.
+#[cfg(unix)]
+fn detect_via_daemon(cli: &Cli, config: &CliConfig) -> Option<EnvSense> {
+    // The daemon's own timings would not cover this process, and it records
+    // no provenance
+    if cli.no_daemon || cli.timings || cli.provenance {
+        return None;
     }
+    let socket = config
+        .daemon
+        .socket
+        .clone()
+        .or_else(daemon::default_socket_path)?;
+    // Assumptions come from the caller's project, so they are applied here
+    let assumptions = parse_assumptions(config).ok()?;
+    let detectors = effective_detectors(cli, config);
+    let mut env = daemon::detect(&socket, &live_snapshot(), &detectors).ok()?;
+    log_event!(Verbose, "daemon.answered", socket = socket.display());
+    assumptions.apply(&mut env).ok()?;
+    Some(env)
 }
 
-fn format_color_level_with_rainbow(value: &str, enable_rainbow: bool) -> String {
-    if !enable_rainbow || value != "truecolor" {
-        return value.to_string();
+/// Detector configuration with CLI flags applied
+///
+/// `--only` replaces the configured list; `--disable-detector` adds to it.
+fn effective_detectors(cli: &Cli, config: &CliConfig) -> DetectorsConfig {
+    let mut detectors = config.detectors.clone();
+    if let Some(only) = &cli.only {
+        detectors.only = Some(only.clone());
     }
-
-    // Create rainbow effect for "truecolor" using colored crate
-    value
-        .chars()
-        .enumerate()
-        .map(|(i, c)| {
-            let char_str = c.to_string();
-            match i % 7 {
-                0 => char_str.red().to_string(),
-                1 => char_str.bright_red().to_string(), // Orange approximation
-                2 => char_str.yellow().to_string(),
-                3 => char_str.green().to_string(),
-                4 => char_str.blue().to_string(),
-                5 => char_str.magenta().to_string(),
-                6 => char_str.cyan().to_string(),
-                _ => char_str,
-            }
-        })
-        .collect()
-}
-
-fn render_nested_value_with_rainbow(
-    value: &serde_json::Value,
-    indent: usize,
-    color: bool,
-) -> String {
-    let indent_str = "  ".repeat(indent);
-
-    match value {
-        serde_json::Value::Object(map) => {
-            let mut result = String::new();
-            for (key, val) in map {
-                match val {
-                    serde_json::Value::Object(obj_map) => {
-                        if obj_map.is_empty() {
-                            // Empty object shows as "= none" with red color
-                            let none_value = if color {
-                                "none".red().to_string()
-                            } else {
-                                "none".to_string()
-                            };
-                            result.push_str(&format!("{}{}: {}\n", indent_str, key, none_value));
-                        } else {
-                            // For nested objects, show the key with colon and expand recursively
-                            result.push_str(&format!("{}{}:\n", indent_str, key));
-                            result.push_str(&render_nested_value_with_rainbow(
-                                val,
-                                indent + 1,
-                                color,
-                            ));
-                        }
-                    }
-                    _ => {
-                        // For simple values, show key = value
-                        let formatted_value = format_simple_value(val);
-                        let colored_value = colorize_value_with_rainbow(&formatted_value, color);
-                        result.push_str(&format!("{}{}: {}\n", indent_str, key, colored_value));
-                    }
-                }
-            }
-            result
-        }
-        _ => {
-            let formatted_value = format_simple_value(value);
-            let colored_value = colorize_value_with_rainbow(&formatted_value, color);
-            format!("{}{}\n", indent_str, colored_value)
-        }
+    if let Some(min) = cli.min_confidence {
+        detectors.min_confidence = min;
     }
+    detectors
+        .disabled
+        .extend(cli.disable_detector.iter().cloned());
+    detectors
 }
 
-fn format_simple_value(value: &serde_json::Value) -> String {
-    match value {
-        serde_json::Value::String(s) => s.clone(),
-        serde_json::Value::Bool(b) => b.to_string(),
-        serde_json::Value::Number(n) => n.to_string(),
-        serde_json::Value::Null => "null".to_string(),
-        serde_json::Value::Object(_) => {
-            // This should be handled by the caller, but fallback to JSON if needed
-            value.to_string()
-        }
-        serde_json::Value::Array(arr) => {
-            // Simple array formatting
-            format!(
-                "[{}]",
-                arr.iter()
-                    .map(format_simple_value)
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )
-        }
+fn parse_confidence(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(score) if (0.0..=1.0).contains(&score) => Ok(score),
+        _ => Err(format!("expected a number from 0 to 1, got '{}'", value)),
     }
 }
 
-fn render_nested_traits(traits: &Value, color: bool, raw: bool, out: &mut String) {
-    if let Value::Object(map) = traits {
-        if raw {
-            // For raw output, flatten the nested structure
-            let mut all_items: Vec<(String, String)> = Vec::new();
-            for (context, context_traits) in map {
-                if let Value::Object(fields) = context_traits {
-                    for (field, value) in fields {
-                        all_items.push((format!("{}.{}", context, field), value_to_string(value)));
-                    }
-                }
-            }
-            all_items.sort_by(|a, b| a.0.cmp(&b.0));
-            for (j, (k, v)) in all_items.into_iter().enumerate() {
-                if j > 0 {
-                    out.push('\n');
-                }
-                out.push_str(&format!("{} = {}", k, v));
-            }
-        } else {
-            let heading = if color {
-                "Traits:".bold().cyan().to_string()
-            } else {
-                "Traits:".to_string()
-            };
-            out.push_str(&heading);
-
-            // Sort contexts for consistent output
-            let mut contexts: Vec<_> = map.keys().collect();
-            contexts.sort();
-
-            for context in contexts {
-                if let Some(Value::Object(fields)) = map.get(context) {
-                    // Only show contexts that have at least one non-null field
-                    let has_values = fields.iter().any(|(_, value)| {
-                        !(value.is_null()
-                            || (value.is_string() && value.as_str() == Some(""))
-                            || (value.is_object()
-                                && value.as_object().is_some_and(|obj| obj.is_empty())))
-                    });
-
-                    if has_values {
-                        out.push('\n');
-                        out.push_str("  ");
-                        out.push_str(context);
-                        out.push(':');
-
-                        // Sort fields within each context
-                        let mut field_items: Vec<_> = fields.iter().collect();
-                        field_items.sort_by(|a, b| a.0.cmp(b.0));
-
-                        for (field, value) in field_items {
-                            // Skip null/empty values
-                            if value.is_null()
-                                || (value.is_string() && value.as_str() == Some(""))
-                                || (value.is_object()
-                                    && value.as_object().is_some_and(|obj| obj.is_empty()))
-                            {
-                                continue;
-                            }
-
-                            out.push('\n');
-                            out.push_str("    ");
-                            out.push_str(field);
-                            out.push_str(" = ");
-                            out.push_str(&colorize_value_with_rainbow(
-                                &value_to_string(value),
-                                color,
-                            ));
-                        }
-                    }
-                }
-            }
-        }
+fn parse_assumptions(config: &CliConfig) -> Result<Assumptions, String> {
+    let source = config
+        .assume_source
+        .as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "config".to_string());
+    Assumptions::parse(&config.assume, &source).map_err(|e| format!("{}: {}", source, e))
+}
+
+/// Build the detection engine, honoring CLI flags over config
+fn build_engine(cli: &Cli, config: &CliConfig) -> Result<DetectionEngine, EnvsenseError> {
+    let detectors = effective_detectors(cli, config);
+    let selection = DetectorSelection {
+        only: detectors.only,
+        disabled: detectors.disabled,
+    };
+    let assumptions = parse_assumptions(config);
+    let min_confidence = detectors.min_confidence;
+    if !(0.0..=1.0).contains(&min_confidence) {
+        return Err(EnvsenseError::input(format!(
+            "detectors.min_confidence: expected a number from 0 to 1, got {}",
+            min_confidence
+        )));
+    }
+
+    let redactor = detectors.redact.redactor();
+    let mut engine = DetectionEngine::with_default_detectors();
+    if detectors.plugins
+        && let Some(path) = std::env::var_os("PATH")
+    {
+        let plugins = external::discover_plugins(&path)
+            .into_iter()
+            .map(|plugin| plugin.with_redactor(redactor.clone()))
+            .collect();
+        engine = engine.with_external_detectors(plugins);
     }
+
+    engine
+        .with_timings(cli.timings)
+        .with_provenance(cli.provenance)
+        .with_min_confidence(min_confidence)
+        .with_redactor(redactor)
+        .with_selection(selection)
+        .and_then(|engine| Ok(engine.with_assumptions(assumptions?)))
+        .map_err(EnvsenseError::input)
 }
 
-fn render_human(
-    snapshot: &Snapshot,
-    fields: Option<&str>,
-    color: bool,
-    raw: bool,
-) -> Result<String, String> {
-    let default_fields = ["contexts", "traits"];
-    let selected: Vec<&str> = match fields {
-        Some(f) => f
-            .split(',')
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .collect(),
-        None => default_fields.to_vec(),
+/// Install user mappings, then build the engine
+///
+/// Mapping files that fail to load are skipped with a warning in the
+/// result's diagnostics; `mappings lint` reports them in detail.
+fn prepare_engine(cli: &Cli, config: &CliConfig) -> Result<DetectionEngine, EnvsenseError> {
+    let broken = install_user_mappings().err();
+    let engine = build_engine(cli, config)?;
+    Ok(match broken {
+        Some(e) => engine.with_warning(Diagnostic::warning(
+            "mappings",
+            format!("ignoring mapping files: {}", e),
+        )),
+        None => engine,
+    })
+}
+
+/// Load mapping files from the config directory and make them visible to detection
+fn install_user_mappings() -> Result<(), MappingError> {
+    let Some(dir) = CliConfig::mappings_dir() else {
+        return Ok(());
     };
-    for s in &selected {
-        if !["contexts", "traits", "facets", "meta"].contains(s) {
-            return Err(format!("unknown field: {}", s));
-        }
+    let mappings = UserMappings::load_dir(&dir)?;
+    if !mappings.is_empty() {
+        user_mappings::install(mappings);
     }
-    let mut out = String::new();
-    for (i, field) in selected.iter().enumerate() {
-        match *field {
-            "contexts" => {
-                let mut ctx = snapshot.contexts.clone();
-                ctx.sort();
-                if raw {
-                    for (j, c) in ctx.iter().enumerate() {
-                        if j > 0 {
-                            out.push('\n');
-                        }
-                        out.push_str(c);
-                    }
-                } else {
-                    let heading = if color {
-                        "Contexts:".bold().cyan().to_string()
-                    } else {
-                        "Contexts:".to_string()
-                    };
-                    out.push_str(&heading);
-                    out.push('\n');
-                    for context in &ctx {
-                        out.push_str(&format!("  - {}\n", context));
-                    }
-                }
-            }
-            "traits" => {
-                if raw {
-                    render_nested_traits(&snapshot.traits, color, raw, &mut out);
-                } else {
-                    let heading = if color {
-                        "Traits:".bold().cyan().to_string()
-                    } else {
-                        "Traits:".to_string()
-                    };
-                    out.push_str(&heading);
-                    out.push('\n');
-                    out.push_str(&render_nested_value_with_rainbow(
-                        &snapshot.traits,
-                        1, // Start with 1 level of indentation for traits
-                        color,
-                    ));
-                }
-            }
-            "facets" => {
-                let mut items: Vec<(String, String)> = if let Value::Object(map) = &snapshot.facets
-                {
-                    map.iter()
-                        .map(|(k, v)| (k.clone(), value_to_string(v)))
-                        .collect()
-                } else {
-                    Vec::new()
-                };
-                items.sort_by(|a, b| a.0.cmp(&b.0));
-                if raw {
-                    for (j, (k, v)) in items.into_iter().enumerate() {
-                        if j > 0 {
-                            out.push('\n');
-                        }
-                        out.push_str(&format!("{} = {}", k, v));
-                    }
-                } else if !items.is_empty() {
-                    let heading = if color {
-                        "Facets:".bold().cyan().to_string()
-                    } else {
-                        "Facets:".to_string()
-                    };
-                    out.push_str(&heading);
-                    for (k, v) in items {
-                        out.push('\n');
-                        out.push_str("  ");
-                        out.push_str(&k);
-                        out.push_str(" = ");
-                        out.push_str(&colorize_value_with_rainbow(&v, color));
-                    }
-                }
-            }
-            "meta" => {
-                let mut items: Vec<(String, String)> = if let Value::Object(map) = &snapshot.meta {
-                    map.iter()
-                        .map(|(k, v)| (k.clone(), value_to_string(v)))
-                        .collect()
-                } else {
-                    Vec::new()
-                };
-                items.sort_by(|a, b| a.0.cmp(&b.0));
-                if raw {
-                    for (j, (k, v)) in items.into_iter().enumerate() {
-                        if j > 0 {
-                            out.push('\n');
-                        }
-                        out.push_str(&format!("{} = {}", k, v));
-                    }
-                } else {
-                    let heading = if color {
-                        "Meta:".bold().cyan().to_string()
-                    } else {
-                        "Meta:".to_string()
-                    };
-                    out.push_str(&heading);
-                    for (k, v) in items {
-                        out.push('\n');
-                        out.push_str("  ");
-                        out.push_str(&k);
-                        out.push_str(" = ");
-                        out.push_str(&colorize_value_with_rainbow(&v, color));
-                    }
-                }
-            }
-            _ => {}
-        }
-        if i + 1 < selected.len() {
-            out.push('\n');
-        }
+    Ok(())
+}
+
+/// Make the contexts detectors and mapping files declare known to the field
+/// registry
+///
+/// Runs before the command line is parsed, since help text lists contexts.
+/// Broken mapping files are reported in the diagnostics once the engine is
+/// built.
+fn declare_contexts() {
+    let mut contexts = DetectionEngine::with_default_detectors().declared_contexts();
+    if let Some(dir) = CliConfig::mappings_dir()
+        && let Ok(mappings) = UserMappings::load_dir(&dir)
+    {
+        contexts.extend(mappings.declared_contexts());
+    }
+    check::install_contexts(contexts);
+}
+
+fn filter_json_fields(value: Value, fields: &str) -> Result<Value, String> {
+    let requested: Vec<&str> = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    if !value.is_object() {
+        return Err("expected object".to_string());
+    }
+    check::select_paths(&value, &requested)
+}
+
+fn value_to_string(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        _ => v.to_string(),
     }
-    Ok(out)
 }
 
 // Legacy evaluate function replaced by new evaluation system in check.rs
@@ -487,11 +963,16 @@ fn render_human(
 
 // Legacy evidence helper functions removed - using new evaluation system
 
-fn run_check(args: CheckCmd, _config: &CliConfig) -> Result<(), i32> {
+fn run_check(
+    mut args: CheckCmd,
+    cli: &Cli,
+    color: ColorChoice,
+    config: &CliConfig,
+) -> Result<(), EnvsenseError> {
     // Validate flag combinations first
     if let Err(validation_error) = validate_check_flags(&args) {
         eprintln!("{}", validation_error);
-        return Err(1);
+        return Err(EnvsenseError::Reported(ErrorKind::Failure));
     }
 
     if args.list {
@@ -499,16 +980,57 @@ fn run_check(args: CheckCmd, _config: &CliConfig) -> Result<(), i32> {
         return Ok(());
     }
 
+    // `-` stands for the predicates on stdin; --stdin appends them
+    let from_stdin = args.stdin || args.predicates.iter().any(|p| p == "-");
+    if from_stdin {
+        let read = check::read_predicates(std::io::stdin().lock()).map_err(|e| {
+            EnvsenseError::input(format!("failed to read predicates from stdin: {}", e))
+        })?;
+        if read.is_empty() {
+            return Err(EnvsenseError::input("no predicates read from stdin"));
+        }
+        let at = args
+            .predicates
+            .iter()
+            .position(|p| p == "-")
+            .unwrap_or(args.predicates.len());
+        args.predicates.retain(|p| p != "-");
+        args.predicates.splice(at..at, read);
+    }
+
     if args.predicates.is_empty() {
         display_check_usage_error();
-        return Err(1);
+        return Err(EnvsenseError::Reported(ErrorKind::Failure));
     }
 
-    let env = EnvSense::detect();
-    let registry = FieldRegistry::new();
+    // Explicit --any/--all win over the configured default mode
+    let any = args.any || (!args.all && config.check.mode == CheckMode::Any);
+
+    // Nothing but the exit code is reported, so stop at the first predicate
+    // that decides it
+    if args.quiet {
+        let mut warnings = Warnings::new(cli.no_deprecation_warnings);
+        let parsed = parse_predicates(&args.predicates, &mut warnings)?;
+        warnings.emit();
+        return quiet_check(&args, cli, config, parsed, any);
+    }
+
+    // Only the detectors the predicates can observe need to run
+    let targets = check::predicate_targets(&args.predicates);
+    let env = match &args.env_file {
+        Some(path) => detect_env_file(cli, config, path, args.merge_env, &targets)?,
+        None => detect_targets(cli, config, args.snapshot.as_deref(), &targets)?,
+    };
 
     // Special case for single "ci" predicate for backward compatibility
-    if args.predicates.len() == 1 && args.predicates[0] == "ci" && !args.any && !args.all {
+
+    if !from_stdin
+        && args.format.is_none()
+        && args.predicates.len() == 1
+        && args.predicates[0] == "ci"
+        && !args.any
+        && !args.all
+    {
         if env.contexts.contains(&"ci".to_string()) {
             if !args.quiet {
                 let name = env.traits.ci.name.as_deref().unwrap_or("Generic CI");
@@ -520,52 +1042,251 @@ fn run_check(args: CheckCmd, _config: &CliConfig) -> Result<(), i32> {
             if !args.quiet {
                 println!("No CI detected");
             }
-            return Err(1);
+            return Err(EnvsenseError::Reported(ErrorKind::Failure));
         }
     }
 
-    let mut results = Vec::new();
+    let mut warnings = Warnings::new(cli.no_deprecation_warnings);
+    let results = evaluate_predicates(&args.predicates, &env, &mut warnings)?;
 
-    for predicate in &args.predicates {
-        let parsed = match check::parse_predicate(predicate) {
-            Ok(p) => p,
-            Err(e) => {
-                eprintln!("Error parsing '{}': {}", predicate, e);
-                return Err(2);
-            }
+    let overall = if any {
+        results.iter().any(|r| r.result.as_bool())
+    } else {
+        // ALL mode unless --any or `check.mode = "any"` is configured
+        results.iter().all(|r| r.result.as_bool())
+    };
+
+    warnings.emit();
+    let theme = theme(cli, color, config);
+    if args.report {
+        print!(
+            "{}",
+            check::render_report(&results, &args.predicates, overall, any, &theme)
+        );
+    } else if args.format == Some(CheckFormat::Ndjson) && !args.quiet {
+        print!(
+            "{}",
+            check::render_ndjson(
+                &results,
+                &args.predicates,
+                overall,
+                any,
+                args.explain,
+                warnings.as_slice(),
+            )
+        );
+    } else if let Some(format @ (CheckFormat::Csv | CheckFormat::Tsv)) = args.format
+        && !args.quiet
+    {
+        let delimiter = if format == CheckFormat::Csv {
+            ','
+        } else {
+            '\t'
         };
+        print!(
+            "{}",
+            check::render_delimited(&results, &args.predicates, delimiter)
+        );
+    } else if !args.quiet {
+        check::output_check_results(
+            &results,
+            &args.predicates,
+            overall,
+            any,
+            args.json || args.format == Some(CheckFormat::Json),
+            args.explain,
+            warnings.as_slice(),
+            &theme,
+        )?;
+    }
+
+    decided(overall)
+}
+
+/// `check --quiet`: evaluate predicates in order until one decides the exit
+/// status
+///
+/// The engine is built once and each predicate only runs the detectors it
+/// adds. A cached, saved or daemon result answers every predicate.
+fn quiet_check(
+    args: &CheckCmd,
+    cli: &Cli,
+    config: &CliConfig,
+    parsed: Vec<check::ParsedCheck>,
+    any: bool,
+) -> Result<(), EnvsenseError> {
+    let registry = check::registry();
+    let decides =
+        |env: &EnvSense, parsed| check::evaluate(env, parsed, registry).result.as_bool() == any;
+
+    let snapshot = match &args.env_file {
+        Some(path) => env_file_snapshot(path, args.merge_env)?,
+        None => {
+            let snapshot = args.snapshot.as_deref();
+            let finished = match cached_result(cli, config, snapshot)? {
+                Some(env) => Some(env),
+                None => finished_result(cli, config, snapshot)?,
+            };
+            if let Some(env) = finished {
+                let env = force_contexts(cli, env)?;
+                let decisive = parsed.into_iter().any(|parsed| decides(&env, parsed));
+                return decided(if decisive { any } else { !any });
+            }
+            load_env_snapshot(snapshot)?
+        }
+    };
+
+    let mut engine = prepare_engine(cli, config)?;
+    let mut results = DetectorResults::default();
+    let mut targets = Vec::new();
+    let mut last = None;
+    for (predicate, parsed) in args.predicates.iter().zip(parsed) {
+        targets.extend(check::predicate_targets(std::slice::from_ref(predicate)));
+        engine = engine.with_targets(&targets);
+        let env = force_contexts(cli, engine.run_reusing(&snapshot, &mut results).env)?;
+        if decides(&env, parsed) {
+            report_timings(report_diagnostics(env));
+            return decided(any);
+        }
+        last = Some(env);
+    }
+    if let Some(env) = last {
+        report_timings(report_diagnostics(env));
+    }
+    decided(!any)
+}
+
+/// Exit status of a check whose overall result is `overall`
+fn decided(overall: bool) -> Result<(), EnvsenseError> {
+    if overall {
+        Ok(())
+    } else {
+        Err(EnvsenseError::Reported(ErrorKind::Failure))
+    }
+}
+
+/// Parse, validate and evaluate `predicates` in order
+///
+/// Fails with an input error on the first predicate that does not parse or
+/// names an unknown field.
+fn evaluate_predicates(
+    predicates: &[String],
+    env: &EnvSense,
+    warnings: &mut Warnings,
+) -> Result<Vec<check::EvaluationResult>, EnvsenseError> {
+    let registry = check::registry();
+    Ok(parse_predicates(predicates, warnings)?
+        .into_iter()
+        .map(|parsed| check::evaluate(env, parsed, registry))
+        .collect())
+}
+
+/// Parse and validate `predicates`, noting deprecated names in `warnings`
+fn parse_predicates(
+    predicates: &[String],
+    warnings: &mut Warnings,
+) -> Result<Vec<check::ParsedCheck>, EnvsenseError> {
+    let registry = check::registry();
+    let mut parsed_checks = Vec::with_capacity(predicates.len());
+
+    for predicate in predicates {
+        let parsed =
+            check::parse_predicate(predicate).map_err(|source| EnvsenseError::Predicate {
+                predicate: predicate.clone(),
+                source,
+            })?;
 
         // Perform strict field validation for nested fields
-        if let check::Check::NestedField { ref path, .. } = parsed.check
-            && let Err(validation_error) = check::validate_field_path(path, &registry)
-        {
-            eprintln!("Error: {}", validation_error);
-            return Err(2);
+        if let check::Check::NestedField { ref path, .. } = parsed.check {
+            check::validate_field_path(path, registry)?;
         }
 
-        let eval_result = check::evaluate(&env, parsed, &registry);
-        results.push(eval_result);
+        let name = match &parsed.check {
+            check::Check::Context(context) => context.clone(),
+            check::Check::NestedField { path, .. } => path.join("."),
+        };
+        warnings.check(DeprecationKind::Predicate, &name);
+
+        parsed_checks.push(parsed);
     }
+    Ok(parsed_checks)
+}
 
-    let overall = if args.any {
+fn run_assert(args: AssertArgs, cli: &Cli, config: &CliConfig) -> Result<(), EnvsenseError> {
+    let targets = check::predicate_targets(&args.predicates);
+    let env = detect_targets(cli, config, args.snapshot.as_deref(), &targets)?;
+    let mut warnings = Warnings::new(cli.no_deprecation_warnings);
+    let results = evaluate_predicates(&args.predicates, &env, &mut warnings)?;
+    warnings.emit();
+
+    let any = args.any || (!args.all && config.check.mode == CheckMode::Any);
+    let holds = if any {
         results.iter().any(|r| r.result.as_bool())
     } else {
-        // Default is ALL mode, --all flag is explicit but same behavior
         results.iter().all(|r| r.result.as_bool())
     };
+    if holds {
+        return Ok(());
+    }
+
+    let message = args
+        .message
+        .unwrap_or_else(|| format!("assertion failed: {}", args.predicates.join(" ")));
+    eprintln!("error: {}", message);
+    for (result, predicate) in results.iter().zip(&args.predicates) {
+        if result.result.as_bool() {
+            continue;
+        }
+        match &result.reason {
+            Some(reason) => eprintln!("  failed: {} ({})", predicate, reason),
+            None => eprintln!("  failed: {}", predicate),
+        }
+    }
+    for hint in &args.hint {
+        eprintln!("hint: {}", hint);
+    }
+    Err(EnvsenseError::Reported(ErrorKind::Failure))
+}
 
+fn run_wait(args: WaitArgs, cli: &Cli, config: &CliConfig) -> Result<(), EnvsenseError> {
+    let any = args.any || (!args.all && config.check.mode == CheckMode::Any);
+    let engine =
+        prepare_engine(cli, config)?.with_targets(&check::predicate_targets(&args.predicates));
+    let mut first = true;
+    let held = wait::poll::<EnvsenseError>(args.timeout, args.interval, || {
+        // A fresh capture every time: this process's variables never change,
+        // but stdin, file markers and the .env file can. The daemon and the
+        // result cache would only repeat the first answer.
+        let snapshot = match &args.env_file {
+            Some(path) => env_file_snapshot(path, args.merge_env)?,
+            None => EnvSnapshot::current(),
+        };
+        let mut env = engine.detect_from_snapshot(&snapshot);
+        if first {
+            env = report_timings(report_diagnostics(env));
+        }
+        let env = force_contexts(cli, env)?;
+        let mut warnings = Warnings::new(cli.no_deprecation_warnings || !first);
+        let results = evaluate_predicates(&args.predicates, &env, &mut warnings)?;
+        warnings.emit();
+        first = false;
+        Ok(if any {
+            results.iter().any(|r| r.result.as_bool())
+        } else {
+            results.iter().all(|r| r.result.as_bool())
+        })
+    })?;
+    if held {
+        return Ok(());
+    }
     if !args.quiet {
-        check::output_check_results(
-            &results,
-            &args.predicates,
-            overall,
-            args.any,
-            args.json,
-            args.explain,
+        eprintln!(
+            "Timed out after {:?} waiting for: {}",
+            args.timeout.unwrap_or_default(),
+            args.predicates.join(" ")
         );
     }
-
-    if overall { Ok(()) } else { Err(1) }
+    Err(EnvsenseError::Reported(ErrorKind::Failure))
 }
 
 // Legacy output_results function removed - using new output system in check.rs
@@ -746,17 +1467,152 @@ fn list_checks() {
         let context_fields = registry.get_context_fields(context);
         if !context_fields.is_empty() {
             println!("\n  {} fields:", context);
-            let mut sorted_fields = context_fields;
-            sorted_fields.sort_by(|a, b| a.0.cmp(b.0));
 
-            for (field_path, field_info) in sorted_fields {
+            for (field_path, field_info) in context_fields {
                 println!("    {:<25} # {}", field_path, field_info.description);
             }
         }
     }
+    for extension in registry.get_extensions() {
+        let field_path = format!("{}.<key>", extension);
+        if let Some(field_info) = registry.extension(&field_path) {
+            println!("\n  {} fields:", extension);
+            println!("    {:<25} # {}", field_path, field_info.description);
+        }
+    }
+}
+
+/// Re-parse `envsense <predicate>...` as `envsense check <predicate>...`
+///
+/// Only arguments that look like predicates are accepted, so a mistyped
+/// subcommand such as `envsense inof` is clap's usual error, with its
+/// suggestion, rather than a false result. Global flags may follow the
+/// predicates, as they may follow `check`.
+fn expand_predicate_shortcut(args: &[String], color: ColorChoice) -> Cli {
+    let looks_like_predicate = match check::parse_predicate(&args[0]) {
+        Ok(parsed) => match parsed.check {
+            check::Check::Context(context) => FieldRegistry::new().has_context(&context),
+            check::Check::NestedField { .. } => true,
+        },
+        Err(_) => false,
+    };
+    let mut command = Cli::command().color(color);
+    if !looks_like_predicate {
+        unknown_subcommand(&mut command, &args[0]).exit();
+    }
+    let mut argv: Vec<OsString> = std::env::args_os().collect();
+    // The external subcommand holds every argument from the predicate on
+    argv.insert(argv.len() - args.len(), "check".into());
+    let matches = command.get_matches_from(argv);
+    Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
+
+/// clap's error for an unknown subcommand, suggesting similar ones
+fn unknown_subcommand(command: &mut clap::Command, name: &str) -> clap::Error {
+    use clap::error::{ContextKind, ContextValue};
+
+    // The same similarity cutoff clap applies to its own suggestions
+    let mut suggestions: Vec<(f64, String)> = command
+        .get_subcommands()
+        .filter(|s| !s.is_hide_set())
+        .map(|s| (strsim::jaro(name, s.get_name()), s.get_name().to_string()))
+        .filter(|(confidence, _)| *confidence > 0.7)
+        .collect();
+    suggestions.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let mut error = clap::Error::new(clap::error::ErrorKind::InvalidSubcommand).with_cmd(command);
+    error.insert(
+        ContextKind::InvalidSubcommand,
+        ContextValue::String(name.to_string()),
+    );
+    if !suggestions.is_empty() {
+        error.insert(
+            ContextKind::SuggestedSubcommand,
+            ContextValue::Strings(suggestions.into_iter().map(|(_, name)| name).collect()),
+        );
+    }
+    error.insert(
+        ContextKind::Usage,
+        ContextValue::StyledStr(command.render_usage()),
+    );
+    error
+}
+
+fn run_fields(args: FieldsArgs) -> Result<(), EnvsenseError> {
+    let registry = check::registry_json(&FieldRegistry::new());
+    if args.json {
+        return match serde_json::to_string_pretty(&registry) {
+            Ok(s) => {
+                println!("{}", s);
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        };
+    }
+    let fields = registry["fields"].as_array().cloned().unwrap_or_default();
+    let text = |field: &Value, key: &str| field[key].as_str().unwrap_or_default().to_string();
+    let width = fields
+        .iter()
+        .map(|f| text(f, "path").len())
+        .max()
+        .unwrap_or(0);
+    for field in &fields {
+        println!(
+            "{:<width$}  {}",
+            text(field, "path"),
+            text(field, "description")
+        );
+    }
+    Ok(())
+}
+
+/// Whether human output on stdout should be colored
+fn want_color(color: ColorChoice) -> bool {
+    match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => stdout().is_terminal(),
+    }
+}
+
+/// Colors for human output, from the `[theme]` config section
+fn theme(cli: &Cli, color: ColorChoice, config: &CliConfig) -> Theme {
+    Theme {
+        ascii: want_ascii(cli, config),
+        ..Theme::from_config(
+            &config.theme,
+            want_color(color),
+            config.output_formatting.rainbow_colors,
+        )
+    }
+}
+
+/// Print `tracing` spans to stderr as they close, with their busy time
+#[cfg(feature = "tracing")]
+fn init_tracing() {
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+/// Whether human output must be pure ASCII (`--ascii` or `output_formatting.ascii`)
+fn want_ascii(cli: &Cli, config: &CliConfig) -> bool {
+    cli.ascii || config.output_formatting.ascii
+}
+
+/// `text` transliterated to ASCII when `ascii` is set
+fn ascii_if(text: &str, ascii: bool) -> std::borrow::Cow<'_, str> {
+    if ascii {
+        ascii::transliterate(text)
+    } else {
+        text.into()
+    }
 }
 
-fn detect_color_choice() -> ColorChoice {
+fn detect_color_choice(config: &CliConfig) -> ColorChoice {
     // Scan args before clap so help/errors honor `--no-color`.
     // Mirror clap's parsing by stopping at `--` which terminates flags.
     let mut args = std::env::args_os();
@@ -772,67 +1628,552 @@ fn detect_color_choice() -> ColorChoice {
             break;
         }
     }
+    // `--no-color` and NO_COLOR always win over the configured color mode
     if flag || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
-        ColorChoice::Never
+        return ColorChoice::Never;
+    }
+    match config.output_formatting.color {
+        ColorMode::Auto => ColorChoice::Auto,
+        ColorMode::Always => ColorChoice::Always,
+        ColorMode::Never => ColorChoice::Never,
+    }
+}
+
+fn run_info(
+    args: InfoArgs,
+    cli: &Cli,
+    color: ColorChoice,
+    config: &CliConfig,
+) -> Result<(), EnvsenseError> {
+    let compat = match args.schema_version {
+        Some(SchemaVersion::Compat(version)) => {
+            if args.fields.is_some() || args.evidence_for.is_some() {
+                return Err(EnvsenseError::input(
+                    "an older --schema-version cannot be combined with --fields or --evidence-for",
+                ));
+            }
+            Some(version)
+        }
+        Some(SchemaVersion::Current) | None => args.compat,
+    };
+    let mut env = match &args.env_file {
+        Some(path) => detect_env_file(cli, config, path, args.merge_env, &[])?,
+        None => detect_env(cli, config, args.snapshot.as_deref())?,
+    };
+    let mut args = args;
+    if let Some(target) = &args.evidence_for {
+        let registry = FieldRegistry::new();
+        let group = format!("{}.", target);
+        let known = registry.has_context(target)
+            || registry
+                .list_all_fields()
+                .iter()
+                .any(|f| *f == target || f.starts_with(&group));
+        if !known {
+            return Err(EnvsenseError::input(format!(
+                "unknown context or field '{}'",
+                target
+            )));
+        }
+        let filter = EvidenceFilter {
+            context: Some(target.clone()),
+            ..EvidenceFilter::default()
+        };
+        env.evidence.retain(|e| filter.matches(e));
+        args.fields.get_or_insert_with(|| "evidence".to_string());
+    }
+    if args.porcelain {
+        print!("{}", report::render_porcelain(&env));
+        return Ok(());
+    }
+    if let Some(version) = compat {
+        let compat = Compat { env: &env, version };
+        return match serde_json::to_string_pretty(&compat) {
+            Ok(s) => {
+                println!("{}", s);
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        };
+    }
+    let mut warnings = Warnings::new(cli.no_deprecation_warnings);
+    for field in args.fields.iter().flat_map(|f| f.split(',')) {
+        let field = field.trim();
+        if !field.is_empty() {
+            warnings.check(DeprecationKind::Field, field);
+        }
+    }
+    warnings.emit();
+
+    // Explicit flags win over the configured default format
+    let format = if args.json {
+        OutputFormat::Json
+    } else if args.raw {
+        OutputFormat::Raw
     } else {
-        ColorChoice::Auto
+        args.format.unwrap_or(config.output_formatting.format)
+    };
+    if matches!(
+        format,
+        OutputFormat::Markdown | OutputFormat::Html | OutputFormat::Csv | OutputFormat::Tsv
+    ) {
+        let fields: Vec<&str> = match args.fields.as_deref() {
+            Some(f) => f
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect(),
+            None => report::DEFAULT_FIELDS.to_vec(),
+        };
+        let rendered = match format {
+            OutputFormat::Html => report::render_html(&env, &fields),
+            OutputFormat::Csv => report::render_delimited(&env, &fields, ','),
+            OutputFormat::Tsv => report::render_delimited(&env, &fields, '\t'),
+            _ => report::render_markdown(&env, &fields),
+        };
+        return match rendered {
+            Ok(rendered) => {
+                print!("{}", rendered);
+                Ok(())
+            }
+            Err(e) => Err(EnvsenseError::input(e)),
+        };
     }
+
+    if format != OutputFormat::Json {
+        let theme = theme(cli, color, config);
+        let rendered = match human::render_human(
+            &env,
+            args.fields.as_deref(),
+            &theme,
+            format == OutputFormat::Raw,
+            args.confidence,
+        ) {
+            Ok(r) => r,
+            Err(e) => return Err(EnvsenseError::input(e)),
+        };
+        pager::print(
+            &format!("{}\n", theme.text(&rendered)),
+            config.output_formatting.pager && !args.no_pager,
+        );
+        return Ok(());
+    }
+
+    let mut v = info_json(&env);
+    if let Some(f) = args.fields.as_deref() {
+        v = match filter_json_fields(v, f) {
+            Ok(v) => v,
+            Err(e) => return Err(EnvsenseError::input(e)),
+        };
+    }
+    if !warnings.is_empty() {
+        v["warnings"] = json!(warnings.as_slice());
+    }
+    match serde_json::to_string_pretty(&v) {
+        Ok(s) => println!("{}", s),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
 }
 
-fn run_info(args: InfoArgs, color: ColorChoice, _config: &CliConfig) -> Result<(), i32> {
-    let snapshot = collect_snapshot();
-    if args.json {
-        let mut v = json!({
-            "version": snapshot.meta["schema_version"],
-            "contexts": snapshot.contexts,
-            "traits": snapshot.traits,
-            "facets": snapshot.facets,
-            "meta": snapshot.meta,
-            "evidence": snapshot.evidence,
-        });
-        if let Some(f) = args.fields.as_deref() {
-            v = match filter_json_fields(v, f) {
-                Ok(v) => v,
-                Err(e) => {
-                    eprintln!("{}", e);
-                    return Err(2);
-                }
-            };
+fn run_capture(args: CaptureArgs) -> Result<(), EnvsenseError> {
+    let captured = CapturedSnapshot::capture();
+    let json = match serde_json::to_string_pretty(&captured) {
+        Ok(s) => s,
+        Err(e) => return Err(e.into()),
+    };
+    match args.output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, format!("{}\n", json)) {
+                return Err(EnvsenseError::failure(format!(
+                    "failed to write '{}': {}",
+                    path.display(),
+                    e
+                )));
+            }
         }
-        match serde_json::to_string_pretty(&v) {
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn run_explain(
+    args: ExplainArgs,
+    engine: &DetectionEngine,
+    ascii: bool,
+) -> Result<(), EnvsenseError> {
+    let snap = load_env_snapshot(args.snapshot.as_deref())?;
+    let explanation = match explain::explain(args.target.as_deref(), &snap, engine) {
+        Ok(e) => e,
+        Err(e) => return Err(EnvsenseError::input(e)),
+    };
+
+    if args.json {
+        match serde_json::to_string_pretty(&explanation) {
             Ok(s) => println!("{}", s),
-            Err(_) => return Err(3),
+            Err(e) => return Err(e.into()),
         }
     } else {
-        let want_color = stdout().is_terminal() && !matches!(color, ColorChoice::Never);
-        let rendered = match render_human(&snapshot, args.fields.as_deref(), want_color, args.raw) {
-            Ok(r) => r,
-            Err(e) => {
-                eprintln!("{}", e);
-                return Err(2);
+        print!(
+            "{}",
+            ascii_if(&explain::render_explanation(&explanation), ascii)
+        );
+    }
+    Ok(())
+}
+
+fn run_detectors(args: DetectorsArgs, engine: &DetectionEngine) -> Result<(), EnvsenseError> {
+    let snap = load_env_snapshot(args.snapshot.as_deref())?;
+    let report = engine.run(&snap);
+
+    // Listed in merge order: later entries win conflicting traits
+    let metadata: HashMap<_, _> = engine.detectors().into_iter().collect();
+    let entries: Vec<Value> = report
+        .runs
+        .iter()
+        .map(|run| {
+            let metadata = metadata.get(run.name).cloned().unwrap_or_default();
+            json!({
+                "name": run.name,
+                "priority": run.priority,
+                "description": metadata.description,
+                "contexts": metadata.contexts,
+                "traits": metadata.traits,
+                "ran": run.ran,
+                "contexts_added": run.contexts_added,
+            })
+        })
+        .collect();
+
+    if args.json {
+        match serde_json::to_string_pretty(&entries) {
+            Ok(s) => println!("{}", s),
+            Err(e) => return Err(e.into()),
+        }
+        return Ok(());
+    }
+
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let list = |key: &str| {
+            let items: Vec<String> = entry[key]
+                .as_array()
+                .map(|a| a.iter().map(value_to_string).collect())
+                .unwrap_or_default();
+            if items.is_empty() {
+                "-".to_string()
+            } else {
+                items.join(", ")
             }
         };
-        println!("{}", rendered);
+        let status = if entry["ran"] == true {
+            "ran"
+        } else {
+            "skipped"
+        };
+        println!(
+            "{} ({}, priority {}): {}",
+            value_to_string(&entry["name"]),
+            status,
+            entry["priority"],
+            value_to_string(&entry["description"])
+        );
+        println!("  contexts: {}", list("contexts"));
+        println!("  traits:   {}", list("traits"));
+        println!("  added:    {}", list("contexts_added"));
     }
     Ok(())
 }
 
-fn main() {
-    let config = CliConfig::load();
-    let color = detect_color_choice();
-    let matches = Cli::command().color(color).get_matches();
-    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
-    match cli.command {
-        Some(Commands::Info(args)) => {
-            if let Err(code) = run_info(args, color, &config) {
-                std::process::exit(code);
+fn run_doctor(
+    args: DoctorArgs,
+    engine: &DetectionEngine,
+    ascii: bool,
+) -> Result<(), EnvsenseError> {
+    let snap = load_env_snapshot(args.snapshot.as_deref())?;
+    let report = doctor::diagnose(&snap, engine.detect_from_snapshot(&snap));
+
+    if args.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(s) => println!("{}", s),
+            Err(e) => return Err(e.into()),
+        }
+    } else {
+        print!("{}", ascii_if(&doctor::render_report(&report), ascii));
+    }
+    Ok(())
+}
+
+fn run_mappings(command: MappingsCommand) -> Result<(), EnvsenseError> {
+    match command {
+        MappingsCommand::Lint(args) => run_mappings_lint(args),
+        MappingsCommand::Test(args) => run_mappings_test(args),
+    }
+}
+
+fn run_mappings_test(args: MappingsTestArgs) -> Result<(), EnvsenseError> {
+    let file = MappingFile::load(&args.file).map_err(EnvsenseError::input)?;
+
+    let snap = match &args.fixture {
+        Some(path) => mappings::load_fixture(path).map_err(EnvsenseError::input)?,
+        None => EnvSnapshot::with_mock_tty(Default::default(), false, false, false),
+    };
+    let mut vars = HashMap::new();
+    for var in &args.vars {
+        let Some((key, value)) = var.split_once('=') else {
+            return Err(EnvsenseError::input(format!(
+                "--env expects KEY=VALUE, got '{}'",
+                var
+            )));
+        };
+        vars.insert(key.to_string(), value.to_string());
+    }
+    let snap = snap.layered(vars);
+
+    // Detect exactly as if this file were the only one in the mappings directory
+    let user = UserMappings::from_files(vec![(args.file.clone(), file.clone())])
+        .map_err(EnvsenseError::input)?;
+    user_mappings::install(user);
+    let env = DetectionEngine::with_default_detectors().detect_from_snapshot(&snap);
+    let report = mappings::test_mappings(&file, &snap, env);
+
+    if args.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(s) => println!("{}", s),
+            Err(e) => return Err(e.into()),
+        }
+    } else {
+        print!("{}", mappings::render_test_report(&report));
+    }
+    Ok(())
+}
+
+fn run_mappings_lint(args: MappingsLintArgs) -> Result<(), EnvsenseError> {
+    let files = if args.files.is_empty() {
+        match CliConfig::mappings_dir().map(|dir| user_mappings::mapping_files(&dir)) {
+            Some(Ok(files)) => files,
+            Some(Err(e)) => {
+                return Err(EnvsenseError::failure(format!(
+                    "failed to read mappings directory: {}",
+                    e
+                )));
             }
+            None => Vec::new(),
+        }
+    } else {
+        args.files
+    };
+    let report = mappings::lint(&files);
+
+    if args.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(s) => println!("{}", s),
+            Err(e) => return Err(e.into()),
         }
-        Some(Commands::Check(args)) => {
-            if let Err(code) = run_check(args, &config) {
-                std::process::exit(code);
+    } else {
+        print!("{}", mappings::render_lint_report(&report));
+    }
+    if report.has_errors() {
+        Err(EnvsenseError::Reported(ErrorKind::Failure))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn run_daemon(args: DaemonArgs, cli: &Cli, config: &CliConfig) -> Result<(), EnvsenseError> {
+    let Some(socket) = args
+        .socket
+        .or_else(|| config.daemon.socket.clone())
+        .or_else(daemon::default_socket_path)
+    else {
+        return Err(EnvsenseError::input(
+            "no default socket location; pass --socket",
+        ));
+    };
+
+    if args.refresh || args.stop {
+        let request = if args.stop {
+            DaemonRequest::Stop
+        } else {
+            DaemonRequest::Refresh
+        };
+        return match daemon::send(&socket, &request) {
+            Ok(DaemonResponse::Ok) => Ok(()),
+            Ok(DaemonResponse::Error { message }) => {
+                Err(EnvsenseError::failure(format!("daemon error: {}", message)))
+            }
+            Ok(response) => Err(EnvsenseError::failure(format!(
+                "unexpected daemon response: {:?}",
+                response
+            ))),
+            Err(e) => Err(EnvsenseError::failure(e)),
+        };
+    }
+
+    // Assumptions depend on the caller's project, so clients apply their own
+    let engine = prepare_engine(cli, config)?.with_assumptions(Assumptions::default());
+    let server = daemon::Daemon::new(engine, effective_detectors(cli, config));
+    eprintln!("envsense daemon listening on {}", socket.display());
+    server.serve(&socket).map_err(EnvsenseError::failure)
+}
+
+fn run_prompt(args: PromptArgs, cli: &Cli, config: &CliConfig) -> Result<(), EnvsenseError> {
+    let segments = args
+        .segments
+        .unwrap_or_else(|| config.prompt.segments.clone());
+    let style = args.style.unwrap_or(config.prompt.style);
+    let max_width = args.max_width.or(config.prompt.max_width);
+    let ascii = want_ascii(cli, config);
+
+    let cache = DiskCache::user_dir("prompt")
+        .filter(|_| !cli.no_cache && config.prompt.cache_ttl > 0)
+        .map(|dir| DiskCache::new(dir, Duration::from_secs(config.prompt.cache_ttl)));
+    // Everything besides the environment that changes the rendered line
+    let settings = json!({
+        "segments": segments,
+        "style": style,
+        "max_width": max_width,
+        "glyphs": config.prompt.glyphs,
+        "detectors": effective_detectors(cli, config),
+        "assume": config.assume,
+        "forced": [&cli.assume, &cli.deny],
+        "ascii": ascii,
+    });
+    let key = DiskCache::key(&live_snapshot(), &settings.to_string());
+
+    let line = match cache.as_ref().and_then(|cache| cache.get(&key)) {
+        Some(line) => line,
+        None => {
+            let env = detect_env(cli, config, None)?;
+            let mut badges = prompt::badges(&env, &segments, &config.prompt.glyphs);
+            if ascii {
+                // Emoji glyphs have no ASCII look-alike; show the label alone
+                for badge in &mut badges {
+                    if !badge.glyph.is_ascii() {
+                        badge.glyph.clear();
+                    }
+                    badge.label = ascii::transliterate(&badge.label).into_owned();
+                }
+            }
+            if let Some(max_width) = max_width {
+                badges = prompt::fit(badges, max_width);
+            }
+            let line = prompt::render(&badges, style);
+            if let Some(cache) = &cache {
+                cache.put(&key, &line);
             }
+            line
         }
-        None => {}
+    };
+
+    // Nothing notable prints nothing, not even a newline
+    if !line.is_empty() {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn run_hook(command: HookCommand, cli: &Cli, config: &CliConfig) -> Result<(), EnvsenseError> {
+    match command {
+        HookCommand::Direnv(args) if args.exports => {
+            print!("{}", hook::direnv_exports(&detect_env(cli, config, None)?));
+        }
+        HookCommand::Direnv(_) => print!("{}", hook::DIRENV_STDLIB),
+    }
+    Ok(())
+}
+
+fn run_schema(args: SchemaArgs) -> Result<(), EnvsenseError> {
+    let schema = match json_schema::json_schema(args.document, args.version.as_deref()) {
+        Ok(schema) => schema,
+        Err(e) => return Err(EnvsenseError::input(e)),
+    };
+    match serde_json::to_string_pretty(&schema) {
+        Ok(s) => println!("{}", s),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+fn run_serve(_args: ServeArgs, engine: DetectionEngine) -> Result<(), EnvsenseError> {
+    let stdin = std::io::stdin().lock();
+    let stdout = std::io::stdout().lock();
+    Server::new(engine)
+        .serve(stdin, stdout)
+        .map_err(EnvsenseError::failure)
+}
+
+#[cfg(feature = "tui")]
+fn run_tui(args: TuiArgs, engine: DetectionEngine) -> Result<(), EnvsenseError> {
+    if !stdout().is_terminal() {
+        return Err(EnvsenseError::failure(
+            "envsense tui requires an interactive terminal",
+        ));
+    }
+    let interval = std::time::Duration::from_secs(args.interval.max(1));
+    envsense::tui::run(Box::new(move || engine.detect()), interval).map_err(EnvsenseError::failure)
+}
+
+/// Print an error returned by a command; its exit code is applied by `main`
+fn report_error(err: &EnvsenseError) {
+    match err {
+        EnvsenseError::Reported(_) => {}
+        EnvsenseError::Predicate { .. } => eprintln!("Error {}", err),
+        _ => eprintln!("Error: {}", err),
+    }
+}
+
+fn main() {
+    let config = CliConfig::load();
+    declare_contexts();
+    let color = detect_color_choice(&config);
+    match color {
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+        ColorChoice::Auto => {}
+    }
+    let matches = Cli::command().color(color).get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(Commands::Predicate(args)) = &cli.command {
+        cli = expand_predicate_shortcut(args, color);
+    }
+    logging::set_level(logging::Level::from_flags(cli.verbose, cli.debug));
+    #[cfg(feature = "tracing")]
+    if cli.debug {
+        init_tracing();
+    }
+    // `mappings` subcommands load mapping files themselves and report errors;
+    // `info` and `check` only build the engine when no daemon answers
+    let command = cli.command.take();
+    let engine = || prepare_engine(&cli, &config);
+    let ascii = want_ascii(&cli, &config);
+    let result = match command {
+        Some(Commands::Info(args)) => run_info(args, &cli, color, &config),
+        Some(Commands::Check(args)) => run_check(args, &cli, color, &config),
+        Some(Commands::Assert(args)) => run_assert(args, &cli, &config),
+        Some(Commands::Fields(args)) => run_fields(args),
+        Some(Commands::Predicate(_)) => unreachable!("expanded to check above"),
+        Some(Commands::Wait(args)) => run_wait(args, &cli, &config),
+        Some(Commands::Capture(args)) => run_capture(args),
+        Some(Commands::Detectors(args)) => engine().and_then(|e| run_detectors(args, &e)),
+        Some(Commands::Mappings(command)) => run_mappings(command),
+        Some(Commands::Doctor(args)) => engine().and_then(|e| run_doctor(args, &e, ascii)),
+        Some(Commands::Explain(args)) => engine().and_then(|e| run_explain(args, &e, ascii)),
+        #[cfg(unix)]
+        Some(Commands::Daemon(args)) => run_daemon(args, &cli, &config),
+        Some(Commands::Prompt(args)) => run_prompt(args, &cli, &config),
+        Some(Commands::Hook(command)) => run_hook(command, &cli, &config),
+        Some(Commands::Schema(args)) => run_schema(args),
+        Some(Commands::Serve(args)) => engine().and_then(|e| run_serve(args, e)),
+        #[cfg(feature = "tui")]
+        Some(Commands::Tui(args)) => engine().and_then(|e| run_tui(args, e)),
+        None => Ok(()),
+    };
+    if let Err(err) = result {
+        report_error(&err);
+        std::process::exit(err.exit_code());
     }
 }
@@ -0,0 +1,687 @@
+//! Tooling behind `envsense mappings`.
+//!
+//! [`lint`] checks built-in and user mapping files for problems that would
+//! otherwise only surface as warnings while detecting, reporting each one
+//! with the file and line it comes from. [`test_mappings`] shows how the
+//! mappings in one file fare against a fixture environment.
+
+use crate::detectors::EnvSnapshot;
+use crate::detectors::env_mapping::{
+    EnvMapping, builtin_agent_mappings, builtin_ci_mappings, builtin_ide_mappings,
+};
+use crate::detectors::user_mappings::{MappingFile, MappingKind};
+use crate::env_file;
+use crate::explain::{IndicatorTrace, trace_indicator};
+use crate::schema::EnvSense;
+use crate::snapshot::CapturedSnapshot;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Source label used for mappings compiled into envsense
+pub const BUILTIN_SOURCE: &str = "<built-in>";
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A single lint problem
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Problem {
+    pub severity: Severity,
+    /// File the mapping comes from, or [`BUILTIN_SOURCE`]
+    pub file: String,
+    /// 1-based line of the mapping (or parse error) when it can be located
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// `<kind>/<id>` of the offending mapping, if the problem is about one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mapping: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, ": {}: ", severity)?;
+        if let Some(mapping) = &self.mapping {
+            write!(f, "{}: ", mapping)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Result of linting a set of mapping files
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct LintReport {
+    /// Files that were checked, in order
+    pub files: Vec<String>,
+    /// Number of mappings checked, built-ins included
+    pub mappings: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.problems.iter().any(|p| p.severity == Severity::Error)
+    }
+}
+
+/// A mapping together with where it was defined
+struct Located<'a> {
+    kind: MappingKind,
+    mapping: &'a EnvMapping,
+    file: &'a str,
+    line: Option<usize>,
+}
+
+impl Located<'_> {
+    fn problem(&self, severity: Severity, message: impl Into<String>) -> Problem {
+        Problem {
+            severity,
+            file: self.file.to_string(),
+            line: self.line,
+            mapping: Some(format!("{}/{}", self.kind.name(), self.mapping.id)),
+            message: message.into(),
+        }
+    }
+}
+
+/// Lint the built-in mappings together with the given user mapping files
+///
+/// Unlike loading for detection, a file that fails to parse is reported and
+/// the remaining files are still checked.
+pub fn lint(paths: &[PathBuf]) -> LintReport {
+    let mut report = LintReport::default();
+
+    let mut parsed: Vec<(String, String, MappingFile)> = Vec::new();
+    for path in paths {
+        let file = path.display().to_string();
+        report.files.push(file.clone());
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                report
+                    .problems
+                    .push(file_problem(&file, None, e.to_string()));
+                continue;
+            }
+        };
+        match MappingFile::parse(&content, path) {
+            Ok(mappings) => parsed.push((file, content, mappings)),
+            Err(e) => {
+                let message = e.to_string();
+                let line = error_line(&message);
+                report.problems.push(file_problem(&file, line, message));
+            }
+        }
+    }
+
+    let builtins = [
+        (MappingKind::Agent, builtin_agent_mappings()),
+        (MappingKind::Ide, builtin_ide_mappings()),
+        (MappingKind::Ci, builtin_ci_mappings()),
+    ];
+
+    let mut located: Vec<Located> = Vec::new();
+    for (kind, mappings) in &builtins {
+        located.extend(mappings.iter().map(|mapping| Located {
+            kind: *kind,
+            mapping,
+            file: BUILTIN_SOURCE,
+            line: None,
+        }));
+    }
+    for (file, content, mappings) in &parsed {
+        for kind in MappingKind::ALL {
+            located.extend(mappings.mappings(kind).iter().map(|mapping| Located {
+                kind,
+                mapping,
+                file,
+                line: find_id_line(content, &mapping.id),
+            }));
+        }
+    }
+    report.mappings = located.len();
+
+    for (i, entry) in located.iter().enumerate() {
+        check_duplicates(entry, &located[..i], &mut report.problems);
+        check_mapping(entry, &mut report.problems);
+    }
+
+    report
+}
+
+fn file_problem(file: &str, line: Option<usize>, message: String) -> Problem {
+    Problem {
+        severity: Severity::Error,
+        file: file.to_string(),
+        line,
+        mapping: None,
+        message,
+    }
+}
+
+fn check_duplicates(entry: &Located, earlier: &[Located], problems: &mut Vec<Problem>) {
+    let Some(first) = earlier
+        .iter()
+        .find(|e| e.kind == entry.kind && e.mapping.id == entry.mapping.id)
+    else {
+        return;
+    };
+
+    if first.file == BUILTIN_SOURCE && entry.file != BUILTIN_SOURCE {
+        problems.push(entry.problem(
+            Severity::Warning,
+            "replaces the built-in mapping with the same id",
+        ));
+    } else {
+        let location = match first.line {
+            Some(line) => format!("{}:{}", first.file, line),
+            None => first.file.to_string(),
+        };
+        problems.push(entry.problem(
+            Severity::Error,
+            format!("duplicate id, already defined at {}", location),
+        ));
+    }
+}
+
+fn check_mapping(entry: &Located, problems: &mut Vec<Problem>) {
+    let mapping = entry.mapping;
+
+    if mapping.id.trim().is_empty() {
+        problems.push(entry.problem(Severity::Error, "id is empty"));
+    }
+    if !(0.0..=1.0).contains(&mapping.confidence) {
+        problems.push(entry.problem(
+            Severity::Error,
+            format!("confidence {} is outside 0.0..=1.0", mapping.confidence),
+        ));
+    }
+    if mapping.indicators.is_empty() {
+        problems.push(entry.problem(
+            Severity::Warning,
+            "has no indicators and matches every environment",
+        ));
+    }
+    // Only the agent detector filters candidates by context
+    if entry.kind == MappingKind::Agent && !mapping.contexts.iter().any(|c| c == "agent") {
+        problems.push(entry.problem(
+            Severity::Warning,
+            "does not add the 'agent' context, so the agent detector never selects it",
+        ));
+    }
+
+    for value_mapping in &mapping.value_mappings {
+        if let Err(e) = value_mapping.validate_config() {
+            problems.push(entry.problem(
+                Severity::Error,
+                format!("value mapping '{}': {}", value_mapping.target_key, e),
+            ));
+        }
+        if let Err(e) = value_mapping.check_circular_dependencies(&mapping.value_mappings) {
+            problems.push(entry.problem(
+                Severity::Error,
+                format!("value mapping '{}': {}", value_mapping.target_key, e),
+            ));
+        }
+    }
+}
+
+/// Line of the `id` key that defines `id`, in any supported format
+fn find_id_line(content: &str, id: &str) -> Option<usize> {
+    content
+        .lines()
+        .position(|line| defines_id(line, id))
+        .map(|index| index + 1)
+}
+
+/// Whether `line` sets an `id` key to `id`: `id = "x"` (toml),
+/// `- id: x` (yaml) or `{"id": "x",` (json)
+fn defines_id(line: &str, id: &str) -> bool {
+    line.match_indices("id").any(|(start, _)| {
+        // The key starts the line or follows a separator, optionally quoted
+        let before = &line[..start];
+        let before = before.strip_suffix('"').unwrap_or(before);
+        let separated = before
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || matches!(c, '{' | ',' | '-'));
+        if !separated {
+            return false;
+        }
+        let rest = &line[start + "id".len()..];
+        let rest = rest.strip_prefix('"').unwrap_or(rest).trim_start();
+        let Some(rest) = rest.strip_prefix([':', '=']) else {
+            return false;
+        };
+        let rest = rest.trim_start();
+        let rest = rest.strip_prefix(['"', '\'']).unwrap_or(rest);
+        let Some(rest) = rest.strip_prefix(id) else {
+            return false;
+        };
+        let rest = rest.strip_prefix(['"', '\'']).unwrap_or(rest).trim_start();
+        rest.is_empty() || rest.starts_with([',', '}'])
+    })
+}
+
+/// Pull a line number out of a toml, yaml or json parser message
+fn error_line(message: &str) -> Option<usize> {
+    let (_, rest) = message.split_once("line ")?;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// Render a report in `file:line: severity: message` form
+pub fn render_lint_report(report: &LintReport) -> String {
+    let mut out = String::new();
+    for problem in &report.problems {
+        let _ = writeln!(out, "{}", problem);
+    }
+
+    let errors = report
+        .problems
+        .iter()
+        .filter(|p| p.severity == Severity::Error)
+        .count();
+    let warnings = report.problems.len() - errors;
+    let _ = writeln!(
+        out,
+        "checked {} mappings in {} file(s) plus built-ins: {} error(s), {} warning(s)",
+        report.mappings,
+        report.files.len(),
+        errors,
+        warnings
+    );
+    out
+}
+
+/// How one mapping from the file under test fared
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MappingResult {
+    pub kind: &'static str,
+    pub id: String,
+    pub matched: bool,
+    /// Whether the detector picked this mapping for the final result
+    pub selected: bool,
+    pub indicators: Vec<IndicatorTrace>,
+    /// Values extracted by the mapping's value mappings
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<String, serde_json::Value>,
+}
+
+/// Result of `envsense mappings test`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MappingTestReport {
+    pub mappings: Vec<MappingResult>,
+    pub detected: EnvSense,
+}
+
+/// Trace every mapping in `file` against `snap`
+///
+/// `env` is the detection result for `snap` with `file` installed as the
+/// user mappings, and decides which mappings count as selected.
+pub fn test_mappings(file: &MappingFile, snap: &EnvSnapshot, env: EnvSense) -> MappingTestReport {
+    let mut mappings = Vec::new();
+    for kind in MappingKind::ALL {
+        let detected = match kind {
+            MappingKind::Agent => env.traits.agent.id.as_deref(),
+            MappingKind::Ide => env.traits.ide.id.as_deref(),
+            MappingKind::Ci => env.traits.ci.id.as_deref(),
+        };
+        for mapping in file.mappings(kind) {
+            let matched = mapping.matches(snap.env());
+            mappings.push(MappingResult {
+                kind: kind.name(),
+                id: mapping.id.clone(),
+                matched,
+                selected: matched && detected == Some(kind.detected_id(mapping).as_str()),
+                indicators: mapping
+                    .indicators
+                    .iter()
+                    .map(|i| trace_indicator(mapping, i, snap))
+                    .collect(),
+                values: if matched {
+                    mapping.extract_values(snap.env()).into_iter().collect()
+                } else {
+                    BTreeMap::new()
+                },
+            });
+        }
+    }
+
+    MappingTestReport {
+        mappings,
+        detected: env,
+    }
+}
+
+/// Load a fixture environment for `envsense mappings test`
+///
+/// Accepts a capture file from `envsense capture`, a JSON, TOML or YAML
+/// table of variables, or `.env` lines as read by `--env-file`. Streams are treated as non-TTYs unless the fixture is a
+/// capture.
+pub fn load_fixture(path: &Path) -> Result<EnvSnapshot, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read fixture '{}': {}", path.display(), e))?;
+    let invalid = |e: String| format!("invalid fixture '{}': {}", path.display(), e);
+
+    let vars: HashMap<String, String> = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => {
+            let value: serde_json::Value =
+                serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+            if value.get("format_version").is_some() {
+                return CapturedSnapshot::load(path)
+                    .map(|captured| captured.to_env_snapshot())
+                    .map_err(|e| e.to_string());
+            }
+            serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?
+        }
+        Some("toml") => toml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+        Some("yaml" | "yml") => {
+            serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?
+        }
+        _ => env_file::parse(&content)
+            .map_err(invalid)?
+            .into_iter()
+            .collect(),
+    };
+    Ok(EnvSnapshot::with_mock_tty(vars, false, false, false))
+}
+
+/// Render a test report as human-readable text
+pub fn render_test_report(report: &MappingTestReport) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "Mappings:");
+    if report.mappings.is_empty() {
+        let _ = writeln!(out, "  (none defined)");
+    }
+    for mapping in &report.mappings {
+        let marker = if mapping.selected {
+            "=>"
+        } else if mapping.matched {
+            "+ "
+        } else {
+            "- "
+        };
+        let _ = writeln!(out, "  {} {}/{}", marker, mapping.kind, mapping.id);
+        for indicator in &mapping.indicators {
+            let _ = write!(
+                out,
+                "       [{}] {}",
+                if indicator.matched { "x" } else { " " },
+                indicator.condition
+            );
+            match &indicator.actual {
+                Some(actual) => {
+                    let _ = writeln!(out, ", actual: {}", actual);
+                }
+                None => out.push('\n'),
+            }
+        }
+        for (key, value) in &mapping.values {
+            let _ = writeln!(out, "       {} = {}", key, value);
+        }
+    }
+
+    let env = &report.detected;
+    let mut contexts = env.contexts.clone();
+    contexts.sort();
+    let _ = writeln!(out, "\nDetected:");
+    let _ = writeln!(
+        out,
+        "  contexts: {}",
+        if contexts.is_empty() {
+            "none".to_string()
+        } else {
+            contexts.join(", ")
+        }
+    );
+    let traits = serde_json::to_value(&env.traits).unwrap_or_default();
+    for context in ["agent", "ide", "ci"] {
+        if let Some(serde_json::Value::Object(fields)) = traits.get(context) {
+            for (field, value) in fields {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                let _ = writeln!(out, "  {}.{}: {}", context, field, value);
+            }
+        }
+    }
+
+    let evidence: Vec<_> = env
+        .evidence
+        .iter()
+        .filter(|e| {
+            !e.key.starts_with("terminal.")
+                && e.supports.iter().all(|s| !s.starts_with("terminal."))
+        })
+        .collect();
+    if !evidence.is_empty() {
+        let _ = writeln!(out, "\nEvidence:");
+        for e in evidence {
+            let _ = write!(out, "  {}", e.key);
+            if let Some(value) = &e.value {
+                let _ = write!(out, "={}", value);
+            }
+            let _ = writeln!(
+                out,
+                " -> {} (confidence={:.2})",
+                e.supports.join(", "),
+                e.confidence
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn lint_files(files: &[(&str, &str)]) -> (TempDir, LintReport) {
+        let dir = TempDir::new().unwrap();
+        let paths: Vec<PathBuf> = files
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.path().join(name);
+                std::fs::write(&path, content).unwrap();
+                path
+            })
+            .collect();
+        let report = lint(&paths);
+        (dir, report)
+    }
+
+    #[test]
+    fn builtin_mappings_are_clean() {
+        let report = lint(&[]);
+        assert!(report.problems.is_empty(), "{:#?}", report.problems);
+        assert!(report.mappings > 0);
+    }
+
+    #[test]
+    fn reports_duplicates_with_locations() {
+        let (_dir, report) = lint_files(&[
+            (
+                "a.toml",
+                "[[ci]]\nid = \"acme\"\nconfidence = 0.9\nindicators = [{ key = \"ACME\" }]\n",
+            ),
+            (
+                "b.yaml",
+                "ci:\n  - id: acme\n    confidence: 0.9\n    indicators:\n      - key: ACME\n",
+            ),
+        ]);
+
+        assert_eq!(report.problems.len(), 1);
+        let problem = &report.problems[0];
+        assert_eq!(problem.severity, Severity::Error);
+        assert!(problem.file.ends_with("b.yaml"));
+        assert_eq!(problem.line, Some(2));
+        assert!(problem.message.contains("a.toml:2"), "{}", problem.message);
+    }
+
+    #[test]
+    fn finds_id_lines_in_every_format() {
+        assert!(defines_id(r#"id = "acme""#, "acme"));
+        assert!(defines_id("  - id: acme", "acme"));
+        assert!(defines_id(
+            r#"    {"id": "acme", "confidence": 0.9}"#,
+            "acme"
+        ));
+        assert!(defines_id("indicators = [{ id = 'acme' }]", "acme"));
+        assert!(!defines_id(r#"id = "acme-ci""#, "acme"));
+        assert!(!defines_id(r#"ide_id = "acme""#, "acme"));
+        assert!(!defines_id(r#"target_key = "id""#, "acme"));
+        assert_eq!(find_id_line("[[ci]]\n\nid = \"acme\"\n", "acme"), Some(3));
+        assert_eq!(find_id_line("[[ci]]\n", "acme"), None);
+    }
+
+    #[test]
+    fn warns_when_replacing_builtins() {
+        let (_dir, report) = lint_files(&[(
+            "cursor.json",
+            "{\n  \"agent\": [\n    {\"id\": \"cursor\", \"confidence\": 0.9, \"indicators\": [{\"key\": \"X\"}]}\n  ]\n}\n",
+        )]);
+
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(report.problems[0].severity, Severity::Warning);
+        assert_eq!(report.problems[0].line, Some(3));
+        assert!(!report.has_errors());
+    }
+
+    #[test]
+    fn reports_invalid_value_mappings_and_cycles() {
+        let (_dir, report) = lint_files(&[(
+            "ci.toml",
+            r#"
+[[ci]]
+id = "loop-ci"
+confidence = 1.5
+indicators = [{ key = "LOOP" }]
+
+[[ci.value_mappings]]
+target_key = "a"
+source_key = "A"
+condition = { Exists = "b" }
+
+[[ci.value_mappings]]
+target_key = "b"
+source_key = ""
+condition = { Exists = "a" }
+"#,
+        )]);
+
+        let messages: Vec<String> = report.problems.iter().map(|p| p.to_string()).collect();
+        assert!(report.has_errors());
+        assert!(
+            messages
+                .iter()
+                .all(|m| m.contains("ci.toml:3: error: ci/loop-ci"))
+        );
+        assert!(messages.iter().any(|m| m.contains("confidence 1.5")));
+        assert!(messages.iter().any(|m| m.contains("Circular dependency")));
+        assert!(messages.iter().any(|m| m.contains("Invalid source key")));
+    }
+
+    #[test]
+    fn parse_errors_do_not_stop_linting() {
+        let (_dir, report) = lint_files(&[
+            ("broken.toml", "[[ci]]\nid = \"x\"\nconfidence = \"high\"\n"),
+            (
+                "ok.toml",
+                "[[ide]]\nid = \"everything\"\nconfidence = 0.5\nindicators = []\n",
+            ),
+        ]);
+
+        assert_eq!(report.problems.len(), 2);
+        assert!(report.problems[0].file.ends_with("broken.toml"));
+        assert_eq!(report.problems[0].line, Some(3));
+        assert!(report.problems[1].message.contains("no indicators"));
+    }
+
+    fn acme_file() -> MappingFile {
+        MappingFile::parse(
+            r#"
+[[ci]]
+id = "acme-ci"
+confidence = 0.9
+indicators = [{ key = "ACME_BUILD_ID" }]
+
+[[ci.value_mappings]]
+target_key = "branch"
+source_key = "ACME_BRANCH"
+"#,
+            Path::new("acme.toml"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mappings_traces_matches() {
+        let file = acme_file();
+        let snap = crate::detectors::test_utils::create_env_snapshot(vec![
+            ("ACME_BUILD_ID", "7"),
+            ("ACME_BRANCH", "main"),
+        ]);
+        let mut env = EnvSense::default();
+        env.traits.ci.id = Some("acme-ci".to_string());
+
+        let report = test_mappings(&file, &snap, env);
+        let result = &report.mappings[0];
+        assert!(result.matched && result.selected);
+        assert_eq!(result.indicators[0].actual.as_deref(), Some("7"));
+        assert_eq!(result.values["branch"], "main");
+
+        let rendered = render_test_report(&report);
+        assert!(rendered.contains("=> ci/acme-ci"), "{}", rendered);
+        assert!(rendered.contains("ci.id: acme-ci"), "{}", rendered);
+    }
+
+    #[test]
+    fn unmatched_mappings_are_not_selected() {
+        let snap = crate::detectors::test_utils::create_env_snapshot(vec![]);
+        let report = test_mappings(&acme_file(), &snap, EnvSense::default());
+        assert!(!report.mappings[0].matched);
+        assert!(!report.mappings[0].selected);
+        assert!(render_test_report(&report).contains("-  ci/acme-ci"));
+    }
+
+    #[test]
+    fn loads_fixtures_in_each_format() {
+        let dir = TempDir::new().unwrap();
+        let fixtures = [
+            ("env", "# comment\nexport ACME_BUILD_ID=\"7\"\n\nCI=true\n"),
+            ("json", r#"{"ACME_BUILD_ID": "7", "CI": "true"}"#),
+            ("toml", "ACME_BUILD_ID = \"7\"\nCI = \"true\"\n"),
+            ("yaml", "ACME_BUILD_ID: \"7\"\nCI: \"true\"\n"),
+        ];
+        for (extension, content) in fixtures {
+            let path = dir.path().join(format!("fixture.{}", extension));
+            std::fs::write(&path, content).unwrap();
+            let snap = load_fixture(&path).unwrap();
+            assert_eq!(snap.get_env("ACME_BUILD_ID").as_deref(), Some("7"));
+            assert_eq!(snap.get_env("CI").as_deref(), Some("true"));
+            assert!(!snap.is_tty_stdout());
+        }
+
+        let bad = dir.path().join("bad.env");
+        std::fs::write(&bad, "NOT A PAIR\n").unwrap();
+        assert!(load_fixture(&bad).unwrap_err().contains("line 1"));
+    }
+}
@@ -0,0 +1,187 @@
+//! `ENVSENSE_OVERRIDE_<PATH>` variables that force a detection result.
+//!
+//! The path is a context or trait path upper-cased with dots as underscores:
+//! `ENVSENSE_OVERRIDE_AGENT_ID=cursor` sets `agent.id`, and
+//! `ENVSENSE_OVERRIDE_CI=false` removes the `ci` context and its traits.
+//! Overrides are applied after merging and after the `assume` setting, so
+//! they always win, and each one is recorded as `env` evidence naming the
+//! variable.
+
+use crate::assume::{add_context, remove_context, set_traits, trait_value};
+use crate::check::registry;
+use crate::detectors::EnvProvider;
+use crate::schema::{EnvSense, Evidence};
+use serde_json::Value;
+
+/// Prefix of the override variables
+pub const PREFIX: &str = "ENVSENSE_OVERRIDE_";
+
+/// Validated override variables, ordered by variable name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    entries: Vec<Override>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Override {
+    var: String,
+    raw: String,
+    target: Target,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// Add (`true`) or remove (`false`) a context
+    Context(String, bool),
+    Trait(String, Value),
+}
+
+impl Overrides {
+    /// Collect the override variables in `vars`
+    ///
+    /// Returns the valid overrides and one message per variable that names
+    /// no context or field, or has a value of the wrong type.
+    pub fn from_env(vars: &dyn EnvProvider) -> (Self, Vec<String>) {
+        let mut overrides = Self::default();
+        let mut errors = Vec::new();
+        for (var, value) in vars.with_prefix(PREFIX) {
+            let raw = value.trim();
+            let entry = target(&var[PREFIX.len()..], raw).map(|target| Override {
+                var: var.clone(),
+                raw: raw.to_string(),
+                target,
+            });
+            // Applying catches values the field type alone cannot, e.g. an
+            // unknown color level
+            let checked = entry.and_then(|entry| {
+                let single = Self {
+                    entries: vec![entry],
+                };
+                single.apply(&mut EnvSense::default()).map(|()| single)
+            });
+            match checked {
+                Ok(single) => overrides.entries.extend(single.entries),
+                Err(e) => errors.push(format!("{}: {}", var, e)),
+            }
+        }
+        (overrides, errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Force the overridden contexts and traits onto a detection result
+    pub fn apply(&self, env: &mut EnvSense) -> Result<(), String> {
+        let mut traits = Vec::new();
+        for entry in &self.entries {
+            let path = match &entry.target {
+                Target::Context(context, true) => {
+                    add_context(env, context);
+                    context
+                }
+                Target::Context(context, false) => {
+                    remove_context(env, context)?;
+                    context
+                }
+                Target::Trait(path, value) => {
+                    traits.push((path.clone(), value.clone()));
+                    path
+                }
+            };
+            env.evidence.push(
+                Evidence::env_var(&entry.var, &entry.raw)
+                    .with_supports(vec![path.clone()])
+                    .with_confidence(1.0),
+            );
+        }
+        set_traits(env, &traits)
+    }
+}
+
+/// Resolve the variable name `suffix`, e.g. `AGENT_ID`, and parse `raw`
+fn target(suffix: &str, raw: &str) -> Result<Target, String> {
+    let registry = registry();
+    let to_var = |path: &str| path.replace('.', "_").to_uppercase();
+
+    if let Some(context) = registry
+        .get_contexts()
+        .into_iter()
+        .find(|c| to_var(c) == suffix)
+    {
+        return match raw.parse::<bool>() {
+            Ok(present) => Ok(Target::Context(context.to_string(), present)),
+            Err(_) => Err(format!(
+                "context '{}' expects true or false, got '{}'",
+                context, raw
+            )),
+        };
+    }
+
+    let path = registry
+        .list_all_fields()
+        .into_iter()
+        .find(|path| to_var(path) == suffix)
+        .ok_or_else(|| "names no context or field".to_string())?;
+    let info = registry
+        .resolve_field(&path.split('.').map(str::to_string).collect::<Vec<_>>())
+        .ok_or_else(|| "names no context or field".to_string())?;
+    let value = trait_value(path, &info.field_type, raw)?;
+    Ok(Target::Trait(path.clone(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Signal;
+    use std::collections::HashMap;
+
+    fn vars(items: &[(&str, &str)]) -> HashMap<String, String> {
+        items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn forces_traits_and_contexts_with_evidence() {
+        let (overrides, errors) = Overrides::from_env(&vars(&[
+            ("ENVSENSE_OVERRIDE_AGENT_ID", "cursor"),
+            ("ENVSENSE_OVERRIDE_CI", "false"),
+            ("ENVSENSE_OVERRIDE_TERMINAL_STDOUT_TTY", "true"),
+            ("PATH", "/bin"),
+        ]));
+        assert!(errors.is_empty());
+
+        let mut env = EnvSense {
+            contexts: vec!["ci".to_string()],
+            ..EnvSense::default()
+        };
+        env.traits.ci.id = Some("github_actions".to_string());
+        overrides.apply(&mut env).unwrap();
+
+        assert_eq!(env.traits.agent.id.as_deref(), Some("cursor"));
+        assert!(env.traits.terminal.stdout.tty);
+        assert!(env.contexts.is_empty());
+        assert_eq!(env.traits.ci.id, None);
+        assert_eq!(env.confidence.traits["agent.id"], 1.0);
+        let evidence = &env.evidence[0];
+        assert_eq!(evidence.signal, Signal::Env);
+        assert_eq!(evidence.key, "ENVSENSE_OVERRIDE_AGENT_ID");
+        assert_eq!(evidence.value.as_deref(), Some("cursor"));
+        assert_eq!(evidence.supports, ["agent.id"]);
+    }
+
+    #[test]
+    fn reports_invalid_overrides() {
+        let (overrides, errors) = Overrides::from_env(&vars(&[
+            ("ENVSENSE_OVERRIDE_CLOUD", "aws"),
+            ("ENVSENSE_OVERRIDE_CI_IS_PR", "maybe"),
+            ("ENVSENSE_OVERRIDE_TERMINAL_COLOR_LEVEL", "millions"),
+            ("ENVSENSE_OVERRIDE_IDE_ID", "vscode"),
+        ]));
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("ENVSENSE_OVERRIDE_CI_IS_PR: "));
+        assert!(!overrides.is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+//! Paging long `info` output through `$PAGER`, like git does.
+
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Print `text`, through a pager when it is taller than the terminal
+///
+/// Only pages when `enabled` and stdout is a terminal. Falls back to printing
+/// directly when the pager is disabled with an empty `PAGER` or fails to start.
+pub fn print(text: &str, enabled: bool) {
+    if enabled
+        && std::io::stdout().is_terminal()
+        && let Some((_, terminal_size::Height(rows))) = terminal_size::terminal_size()
+        && needs_paging(text, rows)
+        && let Some(pager) = command(std::env::var("PAGER").ok().as_deref())
+        && spawn(&pager, text).is_ok()
+    {
+        return;
+    }
+    print!("{}", text);
+}
+
+/// Whether `text` plus the shell prompt overflows `rows` terminal lines
+fn needs_paging(text: &str, rows: u16) -> bool {
+    text.lines().count() >= usize::from(rows)
+}
+
+/// The pager command line: `$PAGER`, or `less` when unset
+fn command(pager: Option<&str>) -> Option<String> {
+    match pager.map(str::trim) {
+        None => Some("less".to_string()),
+        Some("" | "cat") => None,
+        Some(pager) => Some(pager.to_string()),
+    }
+}
+
+fn spawn(pager: &str, text: &str) -> std::io::Result<()> {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(pager).stdin(Stdio::piped());
+    // Keep colors and quit when the output fits, as git does
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe; that's not an error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_only_output_taller_than_the_terminal() {
+        assert!(!needs_paging("a\nb\n", 24));
+        assert!(needs_paging(&"line\n".repeat(24), 24));
+    }
+
+    #[test]
+    fn picks_pager_command() {
+        assert_eq!(command(None).as_deref(), Some("less"));
+        assert_eq!(command(Some("most -s")).as_deref(), Some("most -s"));
+        assert_eq!(command(Some("")), None);
+        assert_eq!(command(Some("cat")), None);
+    }
+}
@@ -0,0 +1,29 @@
+//! The stable library API in one import.
+//!
+//! ```
+//! use envsense::prelude::*;
+//!
+//! let env = EnvSense::detect();
+//! if env.contexts.iter().any(|c| c == "agent") {
+//!     println!("running under {:?}", env.traits.agent.id);
+//! }
+//! let ci = check::evaluate_predicate(&env, "ci").unwrap();
+//! println!("in CI: {}", ci.result.as_bool());
+//! ```
+
+pub use crate::cache::CachedEnvSense;
+pub use crate::check::{
+    self, CheckItem, CheckMode, CheckOutput, CheckResult, CheckValue, EvaluationResult, FieldInfo,
+    FieldRegistry, FieldType, ParseError, ParsedCheck,
+};
+pub use crate::detectors::{Detection, Detector, DetectorMetadata, EnvProvider, EnvSnapshot};
+pub use crate::engine::{DetectionEngine, DetectorSelection};
+pub use crate::error::{EnvsenseError, ErrorKind};
+pub use crate::schema::{
+    Confidence, DetectorTiming, Diagnostic, DiagnosticKind, EnvSense, Evidence, EvidenceFilter,
+    EvidenceSource, SCHEMA_VERSION, Signal, Timings, TraitProvenance,
+};
+pub use crate::traits::{
+    AgentTraits, CiTraits, ColorLevel, IdeTraits, NestedTraits, StdinInfo, StreamInfo,
+    TerminalTraits,
+};
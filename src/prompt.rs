@@ -0,0 +1,318 @@
+//! Compact badges for shell prompts and status lines (`envsense prompt`).
+//!
+//! Each [`Segment`] becomes a badge when its context is detected; segments
+//! that are not detected produce nothing, so an unremarkable environment
+//! renders as an empty string.
+//!
+//! Prompts run on every command, so rendered lines are cached on disk by
+//! [`DiskCache`](crate::disk_cache::DiskCache), keyed by the environment and
+//! the prompt settings.
+
+use crate::schema::EnvSense;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use unicode_width::UnicodeWidthStr;
+
+/// A part of the environment that can be shown as a badge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Segment {
+    Ci,
+    Agent,
+    Ide,
+    Container,
+    Remote,
+}
+
+impl Segment {
+    /// Every segment, in the default display order
+    pub const ALL: [Segment; 5] = [
+        Segment::Ci,
+        Segment::Agent,
+        Segment::Ide,
+        Segment::Container,
+        Segment::Remote,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Segment::Ci => "ci",
+            Segment::Agent => "agent",
+            Segment::Ide => "ide",
+            Segment::Container => "container",
+            Segment::Remote => "remote",
+        }
+    }
+
+    pub fn default_glyph(self) -> &'static str {
+        match self {
+            Segment::Ci => "⚙",
+            Segment::Agent => "🤖",
+            Segment::Ide => "📝",
+            Segment::Container => "📦",
+            Segment::Remote => "🌐",
+        }
+    }
+
+    /// tmux color name used by the `tmux` style
+    pub fn tmux_color(self) -> &'static str {
+        match self {
+            Segment::Ci => "yellow",
+            Segment::Agent => "green",
+            Segment::Ide => "blue",
+            Segment::Container => "cyan",
+            Segment::Remote => "magenta",
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Segment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Segment::ALL
+            .into_iter()
+            .find(|segment| segment.name() == s.trim())
+            .ok_or_else(|| {
+                let names: Vec<_> = Segment::ALL.iter().map(|s| s.name()).collect();
+                format!("unknown segment '{}' (available: {})", s, names.join(", "))
+            })
+    }
+}
+
+/// How badges are rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptStyle {
+    /// `⚙ ci:github 🤖 cursor`, plain text for starship custom modules
+    #[default]
+    Starship,
+    /// `#[fg=yellow]⚙ ci:github#[default]`, for the tmux status line; `#`
+    /// in badge text is escaped as `##`
+    Tmux,
+}
+
+impl FromStr for PromptStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "starship" => Ok(PromptStyle::Starship),
+            "tmux" => Ok(PromptStyle::Tmux),
+            other => Err(format!(
+                "unknown style '{}' (available: starship, tmux)",
+                other
+            )),
+        }
+    }
+}
+
+/// One detected segment ready for display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Badge {
+    pub segment: Segment,
+    pub glyph: String,
+    pub label: String,
+}
+
+/// Badges for the detected segments, in the order given
+///
+/// `glyphs` overrides [`Segment::default_glyph`]; an empty glyph shows the
+/// label alone.
+pub fn badges(
+    env: &EnvSense,
+    segments: &[Segment],
+    glyphs: &BTreeMap<Segment, String>,
+) -> Vec<Badge> {
+    segments
+        .iter()
+        .filter(|segment| env.contexts.iter().any(|c| c == segment.name()))
+        .map(|&segment| Badge {
+            segment,
+            glyph: glyphs
+                .get(&segment)
+                .cloned()
+                .unwrap_or_else(|| segment.default_glyph().to_string()),
+            label: label(env, segment),
+        })
+        .collect()
+}
+
+fn label(env: &EnvSense, segment: Segment) -> String {
+    match segment {
+        // `github_actions` reads as `ci:github`
+        Segment::Ci => match env.traits.ci.id.as_deref() {
+            Some(id) => format!("ci:{}", id.split('_').next().unwrap_or(id)),
+            None => "ci".to_string(),
+        },
+        Segment::Agent => env
+            .traits
+            .agent
+            .id
+            .clone()
+            .unwrap_or_else(|| "agent".into()),
+        Segment::Ide => env.traits.ide.id.clone().unwrap_or_else(|| "ide".into()),
+        Segment::Container | Segment::Remote => segment.name().to_string(),
+    }
+}
+
+impl Badge {
+    /// Visible text, without any style markup
+    pub fn text(&self) -> String {
+        if self.glyph.is_empty() {
+            self.label.clone()
+        } else {
+            format!("{} {}", self.glyph, self.label)
+        }
+    }
+
+    /// Terminal columns taken by [`Badge::text`]; most emoji take two
+    pub fn width(&self) -> usize {
+        self.text().width()
+    }
+}
+
+/// Keep the leading badges whose visible text fits in `max_width` columns
+///
+/// Whole badges are dropped from the end rather than cutting one in half.
+/// Style markup such as tmux color codes does not count toward the width.
+pub fn fit(badges: Vec<Badge>, max_width: usize) -> Vec<Badge> {
+    let mut width = 0;
+    badges
+        .into_iter()
+        .take_while(|badge| {
+            let separator = usize::from(width > 0);
+            width += separator + badge.width();
+            width <= max_width
+        })
+        .collect()
+}
+
+/// Render badges on a single line; empty when there are none
+pub fn render(badges: &[Badge], style: PromptStyle) -> String {
+    let parts: Vec<String> = match style {
+        PromptStyle::Starship => badges.iter().map(Badge::text).collect(),
+        PromptStyle::Tmux => badges
+            .iter()
+            .map(|b| {
+                format!(
+                    "#[fg={}]{}#[default]",
+                    b.segment.tmux_color(),
+                    b.text().replace('#', "##")
+                )
+            })
+            .collect(),
+    };
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(contexts: &[&str]) -> EnvSense {
+        EnvSense {
+            contexts: contexts.iter().map(|c| c.to_string()).collect(),
+            ..EnvSense::default()
+        }
+    }
+
+    #[test]
+    fn renders_detected_segments_in_order() {
+        let mut env = env(&["agent", "ci", "ide"]);
+        env.traits.ci.id = Some("github_actions".to_string());
+        env.traits.agent.id = Some("cursor".to_string());
+        env.traits.ide.id = Some("vscode".to_string());
+
+        let badges = badges(&env, &[Segment::Ci, Segment::Agent], &BTreeMap::new());
+        assert_eq!(
+            render(&badges, PromptStyle::Starship),
+            "⚙ ci:github 🤖 cursor"
+        );
+    }
+
+    #[test]
+    fn missing_ids_fall_back_to_segment_name() {
+        let badges = badges(&env(&["ci", "container"]), &Segment::ALL, &BTreeMap::new());
+        assert_eq!(render(&badges, PromptStyle::Starship), "⚙ ci 📦 container");
+    }
+
+    #[test]
+    fn nothing_detected_renders_empty() {
+        assert_eq!(
+            render(
+                &badges(&env(&[]), &Segment::ALL, &BTreeMap::new()),
+                PromptStyle::Starship
+            ),
+            ""
+        );
+    }
+
+    #[test]
+    fn renders_tmux_color_codes() {
+        let mut env = env(&["agent"]);
+        env.traits.agent.id = Some("cursor".to_string());
+        assert_eq!(
+            render(
+                &badges(&env, &Segment::ALL, &BTreeMap::new()),
+                PromptStyle::Tmux
+            ),
+            "#[fg=green]🤖 cursor#[default]"
+        );
+    }
+
+    #[test]
+    fn escapes_tmux_format_characters() {
+        let mut env = env(&["ci"]);
+        env.traits.ci.id = Some("build#7".to_string());
+        let glyphs = BTreeMap::from([(Segment::Ci, "#".to_string())]);
+        assert_eq!(
+            render(&badges(&env, &Segment::ALL, &glyphs), PromptStyle::Tmux),
+            "#[fg=yellow]## ci:build##7#[default]"
+        );
+    }
+
+    #[test]
+    fn fit_drops_trailing_badges() {
+        let badges = badges(
+            &env(&["ci", "agent", "ide"]),
+            &Segment::ALL,
+            &BTreeMap::new(),
+        );
+        // "⚙ ci" is 4 columns, "🤖 agent" 8 as the emoji is double-width,
+        // plus one separator
+        assert_eq!(fit(badges.clone(), 13).len(), 2);
+        assert_eq!(fit(badges.clone(), 12).len(), 1);
+        assert_eq!(fit(badges.clone(), 3).len(), 0);
+        assert_eq!(fit(badges, 100).len(), 3);
+    }
+
+    #[test]
+    fn glyphs_can_be_replaced_or_removed() {
+        let glyphs = BTreeMap::from([
+            (Segment::Ci, "CI".to_string()),
+            (Segment::Container, String::new()),
+        ]);
+        let badges = badges(&env(&["ci", "container"]), &Segment::ALL, &glyphs);
+        assert_eq!(render(&badges, PromptStyle::Starship), "CI ci container");
+    }
+
+    #[test]
+    fn parses_segment_names() {
+        assert_eq!("agent".parse::<Segment>(), Ok(Segment::Agent));
+        assert!(
+            "terminal"
+                .parse::<Segment>()
+                .unwrap_err()
+                .contains("available")
+        );
+    }
+}
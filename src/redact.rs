@@ -0,0 +1,138 @@
+//! Redaction of secret-looking values in evidence.
+//!
+//! Evidence records the value of each variable a detector matched, and
+//! prefix indicators such as `AIDER_*` can pull in API keys and tokens. A
+//! [`Redactor`] replaces those values with [`REDACTED`] before the result
+//! leaves the [`DetectionEngine`](crate::engine::DetectionEngine), so they
+//! never reach the cache, the daemon socket or any output format. The key
+//! itself is kept: it is often the only evidence a context was detected.
+
+use crate::schema::Evidence;
+
+/// Placeholder written in place of values that look like secrets
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Key fragments that mark a variable as sensitive, matched anywhere in the
+/// upper-cased name
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    "KEY",
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "CREDENTIAL",
+    "COOKIE",
+];
+
+/// Decides which evidence values to redact
+///
+/// A value is redacted when its key contains one of the patterns and is not
+/// on the allow list, or when it is a URL with embedded credentials.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redactor {
+    patterns: Vec<String>,
+    allow: Vec<String>,
+}
+
+impl Default for Redactor {
+    /// Redacts keys matching [`DEFAULT_PATTERNS`]
+    fn default() -> Self {
+        Self::new(DEFAULT_PATTERNS, &[] as &[&str])
+    }
+}
+
+impl Redactor {
+    /// Redact keys containing any of `patterns`, except the keys in `allow`
+    ///
+    /// Both are matched case-insensitively. Empty `patterns` still redact
+    /// URLs with credentials.
+    pub fn new<P: AsRef<str>, A: AsRef<str>>(patterns: &[P], allow: &[A]) -> Self {
+        let upper = |s: &str| s.to_ascii_uppercase();
+        Self {
+            patterns: patterns.iter().map(|p| upper(p.as_ref())).collect(),
+            allow: allow.iter().map(|a| upper(a.as_ref())).collect(),
+        }
+    }
+
+    /// Whether `key` names a variable whose value should be hidden
+    pub fn is_secret(&self, key: &str) -> bool {
+        let upper = key.to_ascii_uppercase();
+        !self.allow.contains(&upper) && self.patterns.iter().any(|p| upper.contains(p))
+    }
+
+    /// Whether the value of `key` should be hidden
+    pub fn hides(&self, key: &str, value: &str) -> bool {
+        self.is_secret(key) || has_url_credentials(value)
+    }
+
+    /// Replace secret values in `evidence` with [`REDACTED`]
+    pub fn redact(&self, evidence: &mut [Evidence]) {
+        for entry in evidence {
+            if let Some(value) = &mut entry.value
+                && self.hides(&entry.key, value)
+            {
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+}
+
+/// Whether an environment variable name looks like it holds a secret,
+/// going by [`DEFAULT_PATTERNS`]
+pub fn is_secret_key(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase();
+    DEFAULT_PATTERNS.iter().any(|p| upper.contains(p))
+}
+
+/// Whether `value` is a URL with a password in it
+pub fn has_url_credentials(value: &str) -> bool {
+    value.split_once("://").is_some_and(|(_, rest)| {
+        let authority = rest.split('/').next().unwrap_or("");
+        authority.contains('@') && authority.split('@').next().is_some_and(|u| u.contains(':'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(redactor: &Redactor, vars: &[(&str, &str)]) -> Vec<String> {
+        let mut evidence: Vec<Evidence> = vars
+            .iter()
+            .map(|(key, value)| Evidence::env_var(*key, *value))
+            .collect();
+        redactor.redact(&mut evidence);
+        evidence.into_iter().filter_map(|e| e.value).collect()
+    }
+
+    #[test]
+    fn redacts_default_patterns() {
+        let vars = [
+            ("AIDER_OPENAI_API_KEY", "sk-abc"),
+            ("GITHUB_TOKEN", "ghp_abc"),
+            ("ci_secret", "x"),
+            ("DB_PASSWORD", "hunter2"),
+            ("DATABASE_URL", "postgres://user:hunter2@db/app"),
+            ("TERM_PROGRAM", "vscode"),
+        ];
+        assert_eq!(
+            values(&Redactor::default(), &vars),
+            [REDACTED, REDACTED, REDACTED, REDACTED, REDACTED, "vscode"]
+        );
+    }
+
+    #[test]
+    fn configured_patterns_and_allow_list() {
+        let redactor = Redactor::new(&["token", "internal"], &["ci_job_token"]);
+        let vars = [
+            ("CI_JOB_TOKEN", "abc"),
+            ("NPM_TOKEN", "abc"),
+            ("INTERNAL_HOST", "db.local"),
+            ("AIDER_OPENAI_API_KEY", "sk-abc"),
+        ];
+        assert_eq!(
+            values(&redactor, &vars),
+            ["abc", REDACTED, REDACTED, "sk-abc"]
+        );
+    }
+}
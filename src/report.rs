@@ -0,0 +1,479 @@
+//! Shareable reports for `envsense info --format markdown|html|csv|tsv` and
+//! the script-oriented `envsense info --porcelain`.
+//!
+//! Markdown is meant for pasting into pull requests and issue reports, HTML
+//! for standalone CI artifacts, CSV and TSV for spreadsheets and awk. All of
+//! them redact evidence values of secret-looking variables like
+//! `envsense capture` does.
+
+use crate::redact::{REDACTED, is_secret_key};
+use crate::schema::{EnvSense, Evidence};
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Sections rendered when no fields are selected
+pub const DEFAULT_FIELDS: &[&str] = &["contexts", "traits", "evidence"];
+
+const FIELDS: &[&str] = &["contexts", "traits", "evidence", "facets", "meta"];
+
+/// Render the selected sections of a detection result as Markdown
+pub fn render_markdown(env: &EnvSense, fields: &[&str]) -> Result<String, String> {
+    validate_fields(fields)?;
+
+    let mut out = String::from("## envsense\n");
+    for field in fields {
+        out.push('\n');
+        match *field {
+            "contexts" => render_contexts(env, &mut out),
+            "traits" => render_traits(env, &mut out),
+            "evidence" => render_evidence(env, &mut out),
+            "meta" => render_meta(env, &mut out),
+            // Always empty in the current schema; kept for --fields parity
+            _ => out.push_str("### Facets\n\n_None_\n"),
+        }
+    }
+    Ok(out)
+}
+
+fn render_contexts(env: &EnvSense, out: &mut String) {
+    let contexts = sorted_contexts(env);
+    let list = if contexts.is_empty() {
+        "_none_".to_string()
+    } else {
+        contexts
+            .iter()
+            .map(|c| code(c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let _ = writeln!(out, "**Contexts:** {}", list);
+}
+
+fn render_traits(env: &EnvSense, out: &mut String) {
+    out.push_str("### Traits\n\n");
+    out.push_str("| Trait | Value |\n| --- | --- |\n");
+    for (path, value) in trait_rows(env) {
+        let _ = writeln!(out, "| {} | {} |", code(&path), code(&value));
+    }
+}
+
+fn render_evidence(env: &EnvSense, out: &mut String) {
+    out.push_str("### Evidence\n\n");
+    if env.evidence.is_empty() {
+        out.push_str("_None_\n");
+        return;
+    }
+    out.push_str("| Signal | Key | Value | Supports | Confidence |\n");
+    out.push_str("| --- | --- | --- | --- | --- |\n");
+    for evidence in &env.evidence {
+        let value = evidence_value(evidence).map(code).unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {:.2} |",
+            signal_name(evidence),
+            code(&evidence.key),
+            value,
+            escape(&evidence.supports.join(", ")),
+            evidence.confidence
+        );
+    }
+}
+
+fn render_meta(env: &EnvSense, out: &mut String) {
+    out.push_str("### Meta\n\n");
+    let _ = writeln!(out, "- schema_version: {}", code(&env.version));
+    let _ = writeln!(
+        out,
+        "- envsense_version: {}",
+        code(env!("CARGO_PKG_VERSION"))
+    );
+}
+
+const HTML_STYLE: &str = "\
+body { font-family: system-ui, sans-serif; margin: 2rem auto; max-width: 60rem; color: #1f2328; }
+h2 { margin-top: 2rem; border-bottom: 1px solid #d0d7de; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.3rem 0.6rem; border-bottom: 1px solid #eaeef2; }
+code { font-family: ui-monospace, monospace; }
+.context { display: inline-block; padding: 0.1rem 0.6rem; margin-right: 0.4rem; border-radius: 1rem; background: #ddf4ff; }
+.none, footer { color: #656d76; }
+";
+
+/// Render the selected sections as a standalone HTML document
+pub fn render_html(env: &EnvSense, fields: &[&str]) -> Result<String, String> {
+    validate_fields(fields)?;
+
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>envsense report</title>\n",
+    );
+    let _ = writeln!(out, "<style>\n{}</style>\n</head>\n<body>", HTML_STYLE);
+    out.push_str("<h1>envsense report</h1>\n");
+    for field in fields {
+        match *field {
+            "contexts" => html_contexts(env, &mut out),
+            "traits" => html_traits(env, &mut out),
+            "evidence" => html_evidence(env, &mut out),
+            "meta" => html_meta(env, &mut out),
+            _ => out.push_str("<h2>Facets</h2>\n<p class=\"none\">None</p>\n"),
+        }
+    }
+    let _ = writeln!(
+        out,
+        "<footer>Generated by envsense {}</footer>\n</body>\n</html>",
+        env!("CARGO_PKG_VERSION")
+    );
+    Ok(out)
+}
+
+fn html_contexts(env: &EnvSense, out: &mut String) {
+    out.push_str("<h2>Contexts</h2>\n<p>");
+    let contexts = sorted_contexts(env);
+    if contexts.is_empty() {
+        out.push_str("<span class=\"none\">None</span>");
+    }
+    for context in contexts {
+        let _ = write!(out, "<span class=\"context\">{}</span>", html(&context));
+    }
+    out.push_str("</p>\n");
+}
+
+fn html_traits(env: &EnvSense, out: &mut String) {
+    out.push_str("<h2>Traits</h2>\n<table>\n<tr><th>Trait</th><th>Value</th></tr>\n");
+    for (path, value) in trait_rows(env) {
+        let _ = writeln!(
+            out,
+            "<tr><td><code>{}</code></td><td><code>{}</code></td></tr>",
+            html(&path),
+            html(&value)
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn html_evidence(env: &EnvSense, out: &mut String) {
+    out.push_str("<h2>Evidence</h2>\n");
+    if env.evidence.is_empty() {
+        out.push_str("<p class=\"none\">None</p>\n");
+        return;
+    }
+    out.push_str(
+        "<table>\n<tr><th>Signal</th><th>Key</th><th>Value</th>\
+         <th>Supports</th><th>Confidence</th></tr>\n",
+    );
+    for evidence in &env.evidence {
+        let value = evidence_value(evidence)
+            .map(|v| format!("<code>{}</code>", html(v)))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{:.2}</td></tr>",
+            signal_name(evidence),
+            html(&evidence.key),
+            value,
+            html(&evidence.supports.join(", ")),
+            evidence.confidence
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn html_meta(env: &EnvSense, out: &mut String) {
+    out.push_str("<h2>Meta</h2>\n<table>\n");
+    let _ = writeln!(
+        out,
+        "<tr><td>schema_version</td><td><code>{}</code></td></tr>",
+        html(&env.version)
+    );
+    let _ = writeln!(
+        out,
+        "<tr><td>envsense_version</td><td><code>{}</code></td></tr>",
+        env!("CARGO_PKG_VERSION")
+    );
+    out.push_str("</table>\n");
+}
+
+/// Version announced on the first line of `--porcelain` output
+///
+/// Bumped only for incompatible changes; new keys may appear without a bump.
+pub const PORCELAIN_VERSION: u32 = 1;
+
+/// Render the line-oriented `key<TAB>value` format of `info --porcelain`
+///
+/// The first line is `porcelain<TAB>version`, followed by one `context` line
+/// per detected context and one `traits.<path>` line per set trait, each group
+/// sorted. Tabs, newlines and backslashes in values are backslash-escaped.
+pub fn render_porcelain(env: &EnvSense) -> String {
+    let mut out = format!("porcelain\t{}\n", PORCELAIN_VERSION);
+    for context in sorted_contexts(env) {
+        let _ = writeln!(out, "context\t{}", porcelain_escape(&context));
+    }
+    let mut rows = trait_rows(env);
+    rows.sort();
+    for (path, value) in rows {
+        let _ = writeln!(out, "traits.{}\t{}", path, porcelain_escape(&value));
+    }
+    out
+}
+
+/// Render the selected sections as `key,value` rows with a header line
+///
+/// `delimiter` is `,` for CSV or a tab for TSV. Contexts are `context` rows,
+/// set traits `traits.<path>` rows and evidence `evidence.<n>.<field>` rows.
+pub fn render_delimited(
+    env: &EnvSense,
+    fields: &[&str],
+    delimiter: char,
+) -> Result<String, String> {
+    validate_fields(fields)?;
+
+    let mut rows = vec![("key".to_string(), "value".to_string())];
+    for field in fields {
+        match *field {
+            "contexts" => rows.extend(
+                sorted_contexts(env)
+                    .into_iter()
+                    .map(|context| ("context".to_string(), context)),
+            ),
+            "traits" => rows.extend(
+                trait_rows(env)
+                    .into_iter()
+                    .map(|(path, value)| (format!("traits.{}", path), value)),
+            ),
+            "evidence" => {
+                for (i, evidence) in env.evidence.iter().enumerate() {
+                    let prefix = format!("evidence.{}", i);
+                    rows.push((format!("{}.signal", prefix), signal_name(evidence)));
+                    rows.push((format!("{}.key", prefix), evidence.key.clone()));
+                    if let Some(value) = evidence_value(evidence) {
+                        rows.push((format!("{}.value", prefix), value.to_string()));
+                    }
+                    rows.push((format!("{}.supports", prefix), evidence.supports.join(" ")));
+                    rows.push((
+                        format!("{}.confidence", prefix),
+                        evidence.confidence.to_string(),
+                    ));
+                }
+            }
+            "meta" => {
+                rows.push(("meta.schema_version".to_string(), env.version.clone()));
+                rows.push((
+                    "meta.envsense_version".to_string(),
+                    env!("CARGO_PKG_VERSION").to_string(),
+                ));
+            }
+            // Always empty in the current schema; kept for --fields parity
+            _ => {}
+        }
+    }
+    Ok(rows
+        .iter()
+        .map(|(key, value)| delimited_row(&[key, value], delimiter))
+        .collect())
+}
+
+/// One CSV (`,`) or TSV (tab) line, newline included
+///
+/// CSV fields are quoted as in RFC 4180 when needed; TSV fields escape tabs,
+/// newlines and backslashes like `--porcelain` does.
+pub fn delimited_row(fields: &[&str], delimiter: char) -> String {
+    let cells: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if delimiter == '\t' {
+                porcelain_escape(field)
+            } else if field.contains([delimiter, '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    format!("{}\n", cells.join(&delimiter.to_string()))
+}
+
+fn porcelain_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn validate_fields(fields: &[&str]) -> Result<(), String> {
+    match fields.iter().find(|f| !FIELDS.contains(f)) {
+        Some(unknown) => Err(format!("unknown field: {}", unknown)),
+        None => Ok(()),
+    }
+}
+
+fn sorted_contexts(env: &EnvSense) -> Vec<String> {
+    let mut contexts = env.contexts.clone();
+    contexts.sort();
+    contexts
+}
+
+fn trait_rows(env: &EnvSense) -> Vec<(String, String)> {
+    let mut rows = Vec::new();
+    if let Ok(traits) = serde_json::to_value(&env.traits) {
+        flatten("", &traits, &mut rows);
+    }
+    rows
+}
+
+fn signal_name(evidence: &Evidence) -> String {
+    serde_json::to_value(&evidence.signal)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// The evidence value to show, redacted for secret-looking keys
+fn evidence_value(evidence: &Evidence) -> Option<&str> {
+    match &evidence.value {
+        Some(_) if is_secret_key(&evidence.key) => Some(REDACTED),
+        value => value.as_deref(),
+    }
+}
+
+/// Collect `path = value` rows for every non-null leaf
+fn flatten(prefix: &str, value: &Value, rows: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&path, child, rows);
+            }
+        }
+        Value::Null => {}
+        Value::String(s) => rows.push((prefix.to_string(), s.clone())),
+        other => rows.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+/// Markdown inline code span, safe inside a table cell
+fn code(text: &str) -> String {
+    format!("`{}`", escape(&text.replace('`', "'")))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Signal;
+
+    fn env() -> EnvSense {
+        let mut env = EnvSense {
+            contexts: vec!["ci".to_string(), "agent".to_string()],
+            ..EnvSense::default()
+        };
+        env.traits.ci.id = Some("github_actions".to_string());
+        env.evidence.push(Evidence {
+            signal: Signal::Env,
+            key: "GITHUB_ACTIONS".to_string(),
+            value: Some("true".to_string()),
+            supports: vec!["ci".to_string(), "ci.id".to_string()],
+            confidence: 0.95,
+            sources: Vec::new(),
+        });
+        env
+    }
+
+    #[test]
+    fn renders_csv_and_tsv_rows() {
+        let out = render_delimited(&env(), DEFAULT_FIELDS, ',').unwrap();
+        assert!(out.starts_with("key,value\ncontext,agent\ncontext,ci\n"));
+        assert!(out.contains("traits.ci.id,github_actions\n"));
+        assert!(out.contains("evidence.0.key,GITHUB_ACTIONS\n"));
+        assert!(out.contains("evidence.0.supports,ci ci.id\n"));
+        assert!(!out.contains("ci.branch"), "unset traits are omitted");
+
+        let out = render_delimited(&env(), &["meta"], '\t').unwrap();
+        assert!(out.starts_with("key\tvalue\nmeta.schema_version\t"));
+        assert!(render_delimited(&env(), &["bogus"], ',').is_err());
+    }
+
+    #[test]
+    fn quotes_delimited_cells() {
+        assert_eq!(
+            delimited_row(&["a,b", "say \"hi\""], ','),
+            "\"a,b\",\"say \"\"hi\"\"\"\n"
+        );
+        assert_eq!(delimited_row(&["a\tb", "c,d"], '\t'), "a\\tb\tc,d\n");
+    }
+
+    #[test]
+    fn renders_default_sections() {
+        let out = render_markdown(&env(), DEFAULT_FIELDS).unwrap();
+        assert!(out.starts_with("## envsense\n"));
+        assert!(out.contains("**Contexts:** `agent`, `ci`\n"));
+        assert!(out.contains("| `ci.id` | `github_actions` |\n"));
+        assert!(out.contains("| env | `GITHUB_ACTIONS` | `true` | ci, ci.id | 0.95 |\n"));
+        assert!(!out.contains("ci.branch"), "unset traits are omitted");
+    }
+
+    #[test]
+    fn redacts_secret_evidence_and_escapes_cells() {
+        let mut env = env();
+        env.evidence[0].key = "CI_JOB_TOKEN".to_string();
+        env.traits.ci.branch = Some("feat|x".to_string());
+        let out = render_markdown(&env, &["traits", "evidence"]).unwrap();
+        assert!(out.contains("`[REDACTED]`"));
+        assert!(out.contains("`feat\\|x`"));
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert_eq!(
+            render_markdown(&env(), &["contexts", "nope"]).unwrap_err(),
+            "unknown field: nope"
+        );
+        assert!(render_html(&env(), &["nope"]).is_err());
+    }
+
+    #[test]
+    fn renders_sorted_porcelain_lines() {
+        let mut env = env();
+        env.traits.ci.branch = Some("feat\tx\\y".to_string());
+        let out = render_porcelain(&env);
+
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[..3],
+            ["porcelain\t1", "context\tagent", "context\tci"]
+        );
+        assert!(lines.contains(&"traits.ci.branch\tfeat\\tx\\\\y"));
+        assert!(lines.contains(&"traits.ci.id\tgithub_actions"));
+        assert!(lines.iter().all(|l| l.matches('\t').count() == 1));
+        assert!(!out.contains("evidence"));
+    }
+
+    #[test]
+    fn renders_standalone_html() {
+        let mut env = env();
+        env.traits.ci.branch = Some("<main>".to_string());
+        env.evidence[0].key = "NPM_TOKEN".to_string();
+        let out = render_html(&env, &["contexts", "traits", "evidence", "meta"]).unwrap();
+
+        assert!(out.starts_with("<!DOCTYPE html>"));
+        assert!(
+            out.contains("<span class=\"context\">agent</span><span class=\"context\">ci</span>")
+        );
+        assert!(out.contains("<code>&lt;main&gt;</code>"));
+        assert!(out.contains("<td><code>[REDACTED]</code></td>"));
+        assert!(out.contains("<td>0.95</td>"));
+        assert!(out.trim_end().ends_with("</html>"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Serialize results in older schema layouts for `envsense info
+//! --schema-version` (or `--compat`).
+//!
+//! This is the inverse of [`super::migrate`]: consumers that still parse the
+//! pre-0.3.0 layout can keep working while they migrate.
+
+use super::migrate::LEGACY_FIELDS;
+use super::{EnvSense, Evidence, SCHEMA_VERSION};
+use crate::traits::terminal::ColorLevel;
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
+use serde::{Serialize, Serializer};
+
+/// Older layouts that results can be serialized in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatVersion {
+    /// Flat traits, a `contexts` object and ids in `facets`
+    V0_2,
+}
+
+impl std::str::FromStr for CompatVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0.2" | "0.2.0" => Ok(Self::V0_2),
+            _ => Err(format!(
+                "unsupported compat version '{}' (available: 0.2)",
+                s
+            )),
+        }
+    }
+}
+
+/// Layouts that `envsense info --json --schema-version` can print
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaVersion {
+    /// An older layout, serialized by [`Compat`]
+    Compat(CompatVersion),
+    /// The current layout, [`SCHEMA_VERSION`]
+    Current,
+}
+
+impl SchemaVersion {
+    /// Versions that can be requested, oldest first
+    pub const AVAILABLE: &[&str] = &["0.2", "0.3"];
+}
+
+impl std::str::FromStr for SchemaVersion {
+    type Err = String;
+
+    /// Accepts `major.minor` or the full version, e.g. `0.3` or `0.3.0`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == SCHEMA_VERSION || SCHEMA_VERSION.strip_suffix(".0") == Some(s) {
+            return Ok(Self::Current);
+        }
+        s.parse().map(Self::Compat).map_err(|_| {
+            format!(
+                "unsupported schema version '{}' (available: {})",
+                s,
+                Self::AVAILABLE.join(", ")
+            )
+        })
+    }
+}
+
+/// Serializes `env` in the layout of `version`
+pub struct Compat<'a> {
+    pub env: &'a EnvSense,
+    pub version: CompatVersion,
+}
+
+impl Serialize for Compat<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.version {
+            CompatVersion::V0_2 => LegacyDocument::new(self.env).serialize(serializer),
+        }
+    }
+}
+
+/// Output of `envsense info --json --schema-version 0.2`
+#[derive(Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(title = "envsense info"))]
+pub(super) struct LegacyDocument<'a> {
+    contexts: LegacyContexts,
+    facets: LegacyFacets<'a>,
+    traits: LegacyTraits,
+    evidence: Vec<Evidence>,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+struct LegacyContexts {
+    agent: bool,
+    ide: bool,
+    ci: bool,
+    container: bool,
+    remote: bool,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+struct LegacyFacets<'a> {
+    agent_id: Option<&'a str>,
+    ide_id: Option<&'a str>,
+    ci_id: Option<&'a str>,
+    container_id: Option<&'a str>,
+    ci: LegacyCi<'a>,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+struct LegacyCi<'a> {
+    is_ci: bool,
+    vendor: Option<&'a str>,
+    name: Option<&'a str>,
+    pr: Option<bool>,
+    branch: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+struct LegacyTraits {
+    is_interactive: bool,
+    is_tty_stdin: bool,
+    is_tty_stdout: bool,
+    is_tty_stderr: bool,
+    is_piped_stdin: bool,
+    is_piped_stdout: bool,
+    color_level: ColorLevel,
+    supports_hyperlinks: bool,
+}
+
+impl<'a> LegacyDocument<'a> {
+    fn new(env: &'a EnvSense) -> Self {
+        let has = |context: &str| env.contexts.iter().any(|c| c == context);
+        let traits = &env.traits;
+        let terminal = &traits.terminal;
+
+        let evidence = env
+            .evidence
+            .iter()
+            .cloned()
+            .map(|mut evidence| {
+                for supported in &mut evidence.supports {
+                    if let Some((old, _)) = LEGACY_FIELDS.iter().find(|(_, new)| new == supported) {
+                        *supported = old.to_string();
+                    }
+                }
+                evidence
+            })
+            .collect();
+
+        Self {
+            contexts: LegacyContexts {
+                agent: has("agent"),
+                ide: has("ide"),
+                ci: has("ci"),
+                container: has("container"),
+                remote: has("remote"),
+            },
+            facets: LegacyFacets {
+                agent_id: traits.agent.id.as_deref(),
+                ide_id: traits.ide.id.as_deref(),
+                ci_id: traits.ci.id.as_deref(),
+                container_id: traits.container.id.as_deref(),
+                ci: LegacyCi {
+                    is_ci: has("ci"),
+                    vendor: traits.ci.vendor.as_deref(),
+                    name: traits.ci.name.as_deref(),
+                    pr: traits.ci.is_pr,
+                    branch: traits.ci.branch.as_deref(),
+                },
+            },
+            traits: LegacyTraits {
+                is_interactive: terminal.interactive,
+                is_tty_stdin: terminal.stdin.tty,
+                is_tty_stdout: terminal.stdout.tty,
+                is_tty_stderr: terminal.stderr.tty,
+                is_piped_stdin: terminal.stdin.piped,
+                is_piped_stdout: terminal.stdout.piped,
+                color_level: terminal.color_level.clone(),
+                supports_hyperlinks: terminal.supports_hyperlinks,
+            },
+            evidence,
+            version: "0.2.0",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::migrate;
+    use crate::schema::{Evidence, Signal};
+
+    #[test]
+    fn schema_versions_parse() {
+        assert_eq!("0.3".parse(), Ok(SchemaVersion::Current));
+        assert_eq!(SCHEMA_VERSION.parse(), Ok(SchemaVersion::Current));
+        assert_eq!(
+            "0.2.0".parse(),
+            Ok(SchemaVersion::Compat(CompatVersion::V0_2))
+        );
+        assert_eq!(
+            "0.9".parse::<SchemaVersion>(),
+            Err("unsupported schema version '0.9' (available: 0.2, 0.3)".to_string())
+        );
+        // The newest available version is the current one
+        assert_eq!(
+            SchemaVersion::AVAILABLE.last().unwrap().parse(),
+            Ok(SchemaVersion::Current)
+        );
+        for version in SchemaVersion::AVAILABLE {
+            assert!(version.parse::<SchemaVersion>().is_ok());
+        }
+    }
+
+    fn env() -> EnvSense {
+        let mut env = EnvSense {
+            contexts: vec!["agent".to_string(), "ci".to_string()],
+            ..EnvSense::default()
+        };
+        env.traits.agent.id = Some("cursor".to_string());
+        env.traits.ci.id = Some("github_actions".to_string());
+        env.traits.ci.branch = Some("main".to_string());
+        env.traits.terminal.interactive = true;
+        env.traits.terminal.stdout.tty = true;
+        env.evidence.push(Evidence {
+            signal: Signal::Env,
+            key: "CURSOR_AGENT".to_string(),
+            value: Some("1".to_string()),
+            supports: vec!["agent".to_string(), "agent.id".to_string()],
+            confidence: 1.0,
+            sources: Vec::new(),
+        });
+        env
+    }
+
+    fn legacy(env: &EnvSense) -> serde_json::Value {
+        serde_json::to_value(Compat {
+            env,
+            version: CompatVersion::V0_2,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn serializes_flat_legacy_layout() {
+        let value = legacy(&env());
+
+        assert_eq!(value["version"], "0.2.0");
+        assert_eq!(value["contexts"]["agent"], true);
+        assert_eq!(value["contexts"]["ide"], false);
+        assert_eq!(value["facets"]["agent_id"], "cursor");
+        assert_eq!(value["facets"]["ci"]["is_ci"], true);
+        assert_eq!(value["facets"]["ci"]["branch"], "main");
+        assert_eq!(value["traits"]["is_interactive"], true);
+        assert_eq!(value["traits"]["is_tty_stdout"], true);
+        assert_eq!(value["traits"]["color_level"], "none");
+        assert_eq!(
+            value["evidence"][0]["supports"],
+            serde_json::json!(["agent", "agent_id"])
+        );
+        assert!(value.get("confidence").is_none());
+    }
+
+    #[test]
+    fn round_trips_through_migration() {
+        let env = env();
+        let migrated = migrate::migrate(legacy(&env)).unwrap();
+        assert_eq!(migrated.contexts, env.contexts);
+        assert_eq!(migrated.traits, env.traits);
+        assert_eq!(migrated.evidence, env.evidence);
+    }
+
+    #[test]
+    fn parses_compat_versions() {
+        assert_eq!("0.2".parse(), Ok(CompatVersion::V0_2));
+        assert_eq!("0.2.0".parse(), Ok(CompatVersion::V0_2));
+        assert!("0.1".parse::<CompatVersion>().is_err());
+    }
+}
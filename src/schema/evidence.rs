@@ -1,17 +1,40 @@
 use crate::detectors::confidence::{HIGH, MEDIUM, TERMINAL};
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum Signal {
     Env,
     Tty,
     Proc,
     Fs,
+    /// A trait value that lost to a higher-confidence detection; `key` is the
+    /// trait path and `value` the rejected value
+    Conflict,
+    /// A context forced on or off for one run; `key` is the flag, e.g.
+    /// `--assume`, and `value` the context
+    Flag,
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+impl Signal {
+    /// The serialized name, e.g. `env`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Signal::Env => "env",
+            Signal::Tty => "tty",
+            Signal::Proc => "proc",
+            Signal::Fs => "fs",
+            Signal::Conflict => "conflict",
+            Signal::Flag => "flag",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct Evidence {
     pub signal: Signal,
     pub key: String,
@@ -20,6 +43,86 @@ pub struct Evidence {
     #[serde(default)]
     pub supports: Vec<String>,
     pub confidence: f32,
+    /// Detectors and mappings that reported this evidence
+    ///
+    /// Filled in by [`DetectionEngine`](crate::engine::DetectionEngine);
+    /// empty for evidence added by assumptions, overrides and flags.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<EvidenceSource>,
+}
+
+/// Where a piece of evidence came from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct EvidenceSource {
+    /// Detector name, as listed by `envsense detectors`
+    pub detector: String,
+    /// Id of the declarative mapping that matched, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mapping: Option<String>,
+}
+
+impl From<envsense_macros::TraitConflict> for Evidence {
+    /// `conflict` evidence for a trait value that lost during merging
+    fn from(conflict: envsense_macros::TraitConflict) -> Self {
+        Self {
+            signal: Signal::Conflict,
+            value: Some(conflict.value_string()),
+            key: conflict.path,
+            supports: Vec::new(),
+            confidence: conflict.confidence,
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for EvidenceSource {
+    /// `detector`, or `detector/mapping`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.mapping {
+            Some(mapping) => write!(f, "{}/{}", self.detector, mapping),
+            None => f.write_str(&self.detector),
+        }
+    }
+}
+
+/// Selects evidence by what it supports, which detector reported it and its
+/// signal; unset criteria match everything
+///
+/// ```
+/// use envsense::schema::{EnvSense, EvidenceFilter, Signal};
+///
+/// let env = EnvSense::detect();
+/// let filter = EvidenceFilter {
+///     context: Some("ci".to_string()),
+///     signal: Some(Signal::Env),
+///     ..EvidenceFilter::default()
+/// };
+/// for evidence in env.evidence_matching(&filter) {
+///     println!("{}", evidence.key);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvidenceFilter {
+    /// Context or dotted trait path the evidence supports, e.g. `ci` (which
+    /// includes `ci.id`) or `terminal.stdout.tty`
+    pub context: Option<String>,
+    /// Detector name, as listed by `envsense detectors`
+    pub detector: Option<String>,
+    pub signal: Option<Signal>,
+}
+
+impl EvidenceFilter {
+    pub fn matches(&self, evidence: &Evidence) -> bool {
+        self.context
+            .as_ref()
+            .is_none_or(|path| evidence.supports_path(path))
+            && self
+                .detector
+                .as_ref()
+                .is_none_or(|name| evidence.sources.iter().any(|s| &s.detector == name))
+            && self.signal.as_ref().is_none_or(|s| &evidence.signal == s)
+    }
 }
 
 impl Evidence {
@@ -34,6 +137,7 @@ impl Evidence {
             value: Some(value.into()),
             supports: Vec::new(),
             confidence: HIGH,
+            sources: Vec::new(),
         }
     }
 
@@ -48,6 +152,23 @@ impl Evidence {
             value: None,
             supports: Vec::new(),
             confidence: MEDIUM,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Create evidence from a file whose presence or contents say something
+    ///
+    /// Used for markers such as `/.dockerenv` or `/proc/self/cgroup`, with
+    /// the telling part of the contents as the value.
+    /// Confidence: MEDIUM (0.8) - Inferred from files other tools may create
+    pub fn file_marker(path: impl Into<String>, value: Option<String>) -> Self {
+        Self {
+            signal: Signal::Fs,
+            key: path.into(),
+            value,
+            supports: Vec::new(),
+            confidence: MEDIUM,
+            sources: Vec::new(),
         }
     }
 
@@ -62,6 +183,7 @@ impl Evidence {
             value: Some(is_tty.to_string()),
             supports: Vec::new(),
             confidence: TERMINAL,
+            sources: Vec::new(),
         }
     }
 
@@ -80,6 +202,25 @@ impl Evidence {
         self
     }
 
+    /// Whether this evidence supports `path` or a field under it
+    pub fn supports_path(&self, path: &str) -> bool {
+        self.supports.iter().any(|s| {
+            s.strip_prefix(path)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    }
+
+    /// Record the declarative mapping that produced this evidence
+    ///
+    /// The engine fills in the detector name.
+    pub fn with_mapping(mut self, mapping: impl Into<String>) -> Self {
+        self.sources.push(EvidenceSource {
+            detector: String::new(),
+            mapping: Some(mapping.into()),
+        });
+        self
+    }
+
     // Helper methods for common evidence patterns with nested field paths
 
     /// Create evidence for agent detection
@@ -161,6 +302,7 @@ impl Evidence {
             value: Some(color_level.into()),
             supports: vec!["terminal.color_level".into()],
             confidence: TERMINAL,
+            sources: Vec::new(),
         }
     }
 
@@ -172,4 +314,15 @@ impl Evidence {
         Self::tty_trait("terminal.supports_hyperlinks", supports_hyperlinks)
             .with_supports(vec!["terminal.supports_hyperlinks".into()])
     }
+
+    /// Create evidence for a terminal trait read from an environment variable
+    ///
+    /// Creates environment variable evidence supporting `terminal.<field>`,
+    /// such as `TERM_PROGRAM` for `terminal.program`.
+    /// Confidence: TERMINAL (1.0) - The emulator sets these itself
+    pub fn terminal_env(key: &str, value: impl Into<String>, field: &str) -> Self {
+        Self::env_var(key, value)
+            .with_supports(vec![format!("terminal.{}", field)])
+            .with_confidence(TERMINAL)
+    }
 }
@@ -0,0 +1,170 @@
+//! JSON Schema export for `envsense schema`.
+//!
+//! The schemas describe the documents printed by `envsense info --json` and
+//! `envsense check --json`, so downstream tools can validate them and generate
+//! typed bindings.
+
+use super::compat::{CompatVersion, LegacyDocument, SchemaVersion};
+use super::{Confidence, Diagnostic, Evidence, SCHEMA_VERSION, Timings, TraitProvenance};
+use crate::check::CheckOutput;
+use crate::deprecation::Warning;
+use crate::traits::NestedTraits;
+use schemars::JsonSchema;
+use schemars::schema::RootSchema;
+use std::collections::BTreeMap;
+
+/// Schema versions that can be exported, oldest first
+pub const SUPPORTED_VERSIONS: &[&str] = &["0.2.0", SCHEMA_VERSION];
+
+/// Which JSON output to describe
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Document {
+    #[default]
+    Info,
+    Check,
+}
+
+impl std::str::FromStr for Document {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(Self::Info),
+            "check" => Ok(Self::Check),
+            _ => Err(format!("unknown document '{}' (available: info, check)", s)),
+        }
+    }
+}
+
+/// Output of `envsense info --json`
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(title = "envsense info")]
+struct InfoDocument {
+    version: String,
+    contexts: Vec<String>,
+    traits: NestedTraits,
+    /// Always empty; kept for backward compatibility
+    facets: BTreeMap<String, serde_json::Value>,
+    meta: InfoMeta,
+    evidence: Vec<Evidence>,
+    confidence: Confidence,
+    /// Detectors that failed or warned during this run
+    diagnostics: Option<Vec<Diagnostic>>,
+    /// What set each trait, keyed by dotted trait path; only present with
+    /// `--provenance`
+    provenance: Option<BTreeMap<String, TraitProvenance>>,
+    /// Only present when deprecated fields were requested
+    warnings: Option<Vec<Warning>>,
+}
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+struct InfoMeta {
+    /// Version of this layout; older ones can be requested with
+    /// `--schema-version`
+    schema_version: String,
+    /// How long detection took; only present with `--timings`
+    timings: Option<Timings>,
+}
+
+/// Generate the JSON Schema of `document`
+///
+/// `version` defaults to the current schema version and may be any of
+/// [`SUPPORTED_VERSIONS`], with or without the patch number. Older versions
+/// describe the `info --json --schema-version` layout; `check --json` has
+/// only ever had one.
+pub fn json_schema(document: Document, version: Option<&str>) -> Result<RootSchema, String> {
+    let version = match version {
+        Some(version) => version.parse().map_err(|_| {
+            format!(
+                "unsupported schema version '{}' (available: {})",
+                version,
+                SUPPORTED_VERSIONS.join(", ")
+            )
+        })?,
+        None => SchemaVersion::Current,
+    };
+    Ok(match (document, version) {
+        (Document::Info, SchemaVersion::Current) => schemars::schema_for!(InfoDocument),
+        (Document::Info, SchemaVersion::Compat(CompatVersion::V0_2)) => {
+            schemars::schema_for!(LegacyDocument<'static>)
+        }
+        (Document::Check, _) => schemars::schema_for!(CheckOutput),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn schema(document: Document) -> Value {
+        serde_json::to_value(json_schema(document, None).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn info_schema_describes_info_output() {
+        let schema = schema(Document::Info);
+        assert_eq!(schema["title"], "envsense info");
+        let properties = schema["properties"].as_object().unwrap();
+        for key in [
+            "version",
+            "contexts",
+            "traits",
+            "facets",
+            "meta",
+            "evidence",
+            "confidence",
+        ] {
+            assert!(properties.contains_key(key), "missing {}", key);
+        }
+        assert!(schema["definitions"]["NestedTraits"].is_object());
+        assert!(!properties.contains_key("timings"));
+        assert!(
+            schema["definitions"]["InfoMeta"]["properties"]
+                .as_object()
+                .unwrap()
+                .contains_key("timings")
+        );
+    }
+
+    #[test]
+    fn info_schema_describes_legacy_layout() {
+        let schema =
+            serde_json::to_value(json_schema(Document::Info, Some("0.2")).unwrap()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        for key in ["contexts", "facets", "traits", "evidence", "version"] {
+            assert!(properties.contains_key(key), "missing {}", key);
+        }
+        assert!(schema["definitions"]["LegacyTraits"]["properties"]["is_interactive"].is_object());
+        assert_eq!(
+            json_schema(Document::Info, Some("0.2.0")).unwrap(),
+            json_schema(Document::Info, Some("0.2")).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_schema_describes_check_output() {
+        let schema = schema(Document::Check);
+        assert_eq!(schema["title"], "envsense check");
+        assert_eq!(
+            schema["definitions"]["CheckMode"]["enum"],
+            json!(["all", "any"])
+        );
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&json!("overall")));
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        assert!(json_schema(Document::Info, Some(SCHEMA_VERSION)).is_ok());
+        assert_eq!(
+            json_schema(Document::Info, Some("0.1.0")).unwrap_err(),
+            format!(
+                "unsupported schema version '0.1.0' (available: 0.2.0, {})",
+                SCHEMA_VERSION
+            )
+        );
+    }
+}
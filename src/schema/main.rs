@@ -1,40 +1,226 @@
 // Main schema structure - updated in task 1.3 to use the new nested structure
-use crate::detectors::DeclarativeAgentDetector;
-use crate::detectors::DeclarativeCiDetector;
-use crate::detectors::DeclarativeIdeDetector;
-use crate::detectors::terminal::TerminalDetector;
+use crate::assume::remove_context;
+use crate::detectors::EnvSnapshot;
 use crate::engine::DetectionEngine;
 use crate::traits::NestedTraits;
 use envsense_macros::{Detection, DetectionMerger, DetectionMergerDerive};
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
 
-use super::{Evidence, SCHEMA_VERSION};
+use super::{Evidence, EvidenceFilter, SCHEMA_VERSION};
 
 /// Main schema structure using the new nested structure
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, DetectionMergerDerive)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, DetectionMergerDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[detection_merge(strict)]
 pub struct EnvSense {
-    pub contexts: Vec<String>, // Simplified from Contexts struct
-    pub traits: NestedTraits,  // New nested structure
+    /// Detected contexts, such as `agent` or `ci`; `terminal` and `shell`
+    /// are also common outside any agent, IDE or CI
+    pub contexts: Vec<String>,
+    pub traits: NestedTraits, // New nested structure
     #[serde(default)]
     pub evidence: Vec<Evidence>,
+    #[detection_merge(ignore)]
     pub version: String,
+    #[serde(default, skip_serializing_if = "Confidence::is_empty")]
+    #[detection_merge(ignore)]
+    pub confidence: Confidence,
+    /// Detectors that failed during this run; their results are missing
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[detection_merge(ignore)]
+    pub diagnostics: Vec<Diagnostic>,
+    /// How long detection took; only recorded when
+    /// [`DetectionEngine::with_timings`] is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[detection_merge(ignore)]
+    pub timings: Option<Timings>,
+    /// What set each trait, keyed by dotted trait path; only recorded when
+    /// [`DetectionEngine::with_provenance`] is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[detection_merge(ignore)]
+    pub provenance: Option<BTreeMap<String, TraitProvenance>>,
 }
 
-fn detect_environment() -> EnvSense {
-    let engine = DetectionEngine::new()
-        .register(TerminalDetector::new())
-        .register(DeclarativeAgentDetector::new())
-        .register(DeclarativeCiDetector::new())
-        .register(DeclarativeIdeDetector::new());
+/// The detection that set a trait, and the evidence behind it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct TraitProvenance {
+    /// Detector whose value was kept; absent when `assume` or an
+    /// `ENVSENSE_OVERRIDE_*` variable set the trait
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detector: Option<String>,
+    /// Confidence of that detection
+    pub confidence: f32,
+    /// Keys of the evidence entries supporting the trait or its context
+    #[serde(default)]
+    pub evidence: Vec<String>,
+}
+
+/// Wall time of a detection run, in microseconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Timings {
+    /// The whole run, merging included
+    pub total_us: u64,
+    /// Detectors that ran, in registration order
+    pub detectors: Vec<DetectorTiming>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct DetectorTiming {
+    pub detector: String,
+    pub elapsed_us: u64,
+}
 
-    engine.detect()
+/// A detector that failed instead of returning a result, or something the
+/// run was asked to use that was ignored or worked around
+///
+/// The rest of the detection is still valid, but may be missing contexts or
+/// traits the failed detector would have set.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Diagnostic {
+    /// Name of the detector; for warnings, the plugin or setting ignored,
+    /// `mappings` for mapping files that failed to load, or the detector
+    /// whose mapping values were worked around
+    pub detector: String,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// A [`DiagnosticKind::Warning`] about `source`, a plugin or setting
+    pub fn warning(source: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            detector: source.into(),
+            kind: DiagnosticKind::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// The detector returned an error
+    Error,
+    /// The detector panicked
+    Panic,
+    /// A plugin or setting was ignored, or a mapping value failed validation
+    /// or a transform; detection went ahead regardless
+    Warning,
+}
+
+/// How confident detection is in each context and set trait
+///
+/// Carried over from the confidence of the detections that produced them;
+/// when several detections agree, the highest confidence wins.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Confidence {
+    /// Keyed by context name
+    #[serde(default, serialize_with = "serialize_scores")]
+    pub contexts: BTreeMap<String, f32>,
+    /// Keyed by dotted trait path, e.g. `terminal.interactive`
+    #[serde(default, serialize_with = "serialize_scores")]
+    pub traits: BTreeMap<String, f32>,
+}
+
+/// Serialize scores through their shortest decimal form, so 0.8 stays 0.8
+/// rather than widening to 0.800000011920929 in a `serde_json::Value`
+fn serialize_scores<S: Serializer>(
+    scores: &BTreeMap<String, f32>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(scores.iter().map(|(key, score)| {
+        let score: f64 = score.to_string().parse().unwrap_or(f64::from(*score));
+        (key, score)
+    }))
+}
+
+impl Confidence {
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty() && self.traits.is_empty()
+    }
+
+    /// Record `value` for `key`, keeping any higher confidence already there
+    pub fn raise(map: &mut BTreeMap<String, f32>, key: &str, value: f32) {
+        let entry = map.entry(key.to_string()).or_insert(value);
+        if value > *entry {
+            *entry = value;
+        }
+    }
+}
+
+fn detect_environment() -> EnvSense {
+    DetectionEngine::with_default_detectors().detect()
 }
 
 impl EnvSense {
+    /// Detect the current process environment with the built-in detectors
+    ///
+    /// ```
+    /// let env = envsense::prelude::EnvSense::detect();
+    /// assert_eq!(env.version, envsense::schema::SCHEMA_VERSION);
+    /// ```
     pub fn detect() -> Self {
         detect_environment()
     }
+
+    /// Run the built-in detectors against a caller-provided snapshot
+    ///
+    /// Nothing is read from the process: variables and TTY state all come
+    /// from `snapshot`, so captured, remote or synthetic environments detect
+    /// the same way everywhere. Use [`DetectionEngine`] to pick detectors or
+    /// add assumptions.
+    ///
+    /// ```
+    /// use envsense::prelude::*;
+    /// use std::collections::HashMap;
+    ///
+    /// let vars = HashMap::from([("GITHUB_ACTIONS".to_string(), "true".to_string())]);
+    /// let env = EnvSense::detect_from(&EnvSnapshot::with_mock_tty(vars, false, false, false));
+    /// # #[cfg(feature = "declarative-mappings")]
+    /// assert_eq!(env.traits.ci.id.as_deref(), Some("github_actions"));
+    /// assert!(!env.traits.terminal.interactive);
+    /// ```
+    pub fn detect_from(snapshot: &EnvSnapshot) -> Self {
+        DetectionEngine::with_default_detectors().detect_from_snapshot(snapshot)
+    }
+
+    #[deprecated(since = "0.6.0", note = "use `EnvSense::detect_from`")]
+    pub fn detect_from_snapshot(snapshot: &EnvSnapshot) -> Self {
+        Self::detect_from(snapshot)
+    }
+
+    /// Evidence entries selected by `filter`, in order
+    pub fn evidence_matching<'a>(
+        &'a self,
+        filter: &'a EvidenceFilter,
+    ) -> impl Iterator<Item = &'a Evidence> + 'a {
+        self.evidence.iter().filter(|e| filter.matches(e))
+    }
+
+    /// Remove every context detected with less than `min` confidence,
+    /// resetting the traits it owns; returns the removed contexts
+    ///
+    /// A context without a recorded confidence counts as 0.
+    pub fn drop_uncertain_contexts(&mut self, min: f32) -> Result<Vec<String>, String> {
+        let uncertain: Vec<String> = self
+            .contexts
+            .iter()
+            .filter(|c| self.confidence.contexts.get(*c).copied().unwrap_or(0.0) < min)
+            .cloned()
+            .collect();
+        for context in &uncertain {
+            remove_context(self, context)?;
+        }
+        Ok(uncertain)
+    }
 }
 
 impl Default for EnvSense {
@@ -44,6 +230,10 @@ impl Default for EnvSense {
             traits: NestedTraits::default(),
             evidence: Vec::new(),
             version: SCHEMA_VERSION.to_string(),
+            confidence: Confidence::default(),
+            diagnostics: Vec::new(),
+            timings: None,
+            provenance: None,
         }
     }
 }
@@ -52,6 +242,65 @@ impl Default for EnvSense {
 mod tests {
     use super::*;
 
+    #[test]
+    fn filters_evidence_by_context_detector_and_signal() {
+        use crate::schema::{EvidenceSource, Signal};
+
+        let from = |detector: &str, evidence: Evidence| Evidence {
+            sources: vec![EvidenceSource {
+                detector: detector.to_string(),
+                mapping: None,
+            }],
+            ..evidence
+        };
+        let env = EnvSense {
+            evidence: vec![
+                from(
+                    "agent",
+                    Evidence::env_var("CURSOR_AGENT", "1").with_supports(vec!["agent.id".into()]),
+                ),
+                from("terminal", Evidence::terminal_interactive(true)),
+                from(
+                    "ci",
+                    Evidence::env_var("CI", "true").with_supports(vec!["ci".into()]),
+                ),
+            ],
+            ..EnvSense::default()
+        };
+        let keys = |filter: EvidenceFilter| -> Vec<String> {
+            env.evidence_matching(&filter)
+                .map(|e| e.key.clone())
+                .collect()
+        };
+
+        let agent = EvidenceFilter {
+            context: Some("agent".to_string()),
+            ..EvidenceFilter::default()
+        };
+        assert_eq!(keys(agent), ["CURSOR_AGENT"]);
+        let agent_id = EvidenceFilter {
+            context: Some("agent.id".to_string()),
+            ..EvidenceFilter::default()
+        };
+        assert_eq!(keys(agent_id), ["CURSOR_AGENT"]);
+        let ag = EvidenceFilter {
+            context: Some("ag".to_string()),
+            ..EvidenceFilter::default()
+        };
+        assert!(keys(ag).is_empty());
+        let terminal = EvidenceFilter {
+            detector: Some("terminal".to_string()),
+            ..EvidenceFilter::default()
+        };
+        assert_eq!(keys(terminal), ["terminal.interactive"]);
+        let env_signal = EvidenceFilter {
+            signal: Some(Signal::Env),
+            ..EvidenceFilter::default()
+        };
+        assert_eq!(keys(env_signal), ["CURSOR_AGENT", "CI"]);
+        assert_eq!(keys(EvidenceFilter::default()).len(), 3);
+    }
+
     #[test]
     fn default_serializes_with_version() {
         let envsense = EnvSense::default();
@@ -59,6 +308,39 @@ mod tests {
         assert!(json.contains("\"version\":\"0.3.0\""));
     }
 
+    #[test]
+    fn confidence_serializes_shortest_scores() {
+        let mut env = EnvSense::default();
+        assert!(!serde_json::to_string(&env).unwrap().contains("confidence"));
+
+        Confidence::raise(&mut env.confidence.contexts, "agent", 0.6);
+        Confidence::raise(&mut env.confidence.contexts, "agent", 0.8);
+        Confidence::raise(&mut env.confidence.contexts, "agent", 0.6);
+        let value = serde_json::to_value(&env).unwrap();
+        assert_eq!(value["confidence"]["contexts"]["agent"].to_string(), "0.8");
+        assert!(
+            value["confidence"]["traits"]
+                .as_object()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "declarative-mappings")]
+    fn detect_from_ignores_process_state() {
+        let vars = [("CURSOR_AGENT", "1"), ("TERM_PROGRAM", "vscode")]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .into();
+        let env = EnvSense::detect_from(&EnvSnapshot::with_mock_tty(vars, true, true, true));
+
+        assert_eq!(env.contexts, ["agent", "ide", "terminal"]);
+        assert_eq!(env.traits.agent.id.as_deref(), Some("cursor"));
+        assert!(env.traits.terminal.interactive);
+        assert!(env.traits.ci.id.is_none());
+    }
+
+    #[cfg(feature = "schemars")]
     #[test]
     fn json_schema_generates() {
         let schema = schemars::schema_for!(EnvSense);
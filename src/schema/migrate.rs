@@ -0,0 +1,438 @@
+//! Upgrade envsense JSON written by older releases to the current schema.
+//!
+//! Before 0.3.0, `contexts` was an object of booleans, ids lived in `facets`
+//! and terminal traits were flat keys such as `is_interactive` and
+//! `is_tty_stdout`. Current documents, including `envsense info --json`
+//! output, are read as-is; keys they leave out take their default values, so
+//! hand-written fixtures only need the fields they care about.
+//!
+//! The `_strict` variants additionally reject keys the current schema does
+//! not have, so integrations notice a typo or a newer document early rather
+//! than reading defaults.
+
+use super::{
+    Confidence, DetectorTiming, Diagnostic, DiagnosticKind, EnvSense, Evidence, EvidenceSource,
+    SCHEMA_VERSION, Signal, Timings, TraitProvenance,
+};
+use crate::traits::{
+    AgentTraits, CiTraits, ColorLevel, ContainerTraits, IdeTraits, NestedTraits, ShellTraits,
+    StdinInfo, StreamInfo, TerminalTraits,
+};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Context names in the order the legacy `Contexts` struct declared them
+const LEGACY_CONTEXTS: &[&str] = &["agent", "ide", "ci", "container", "remote"];
+
+/// Legacy field names and the nested paths that replaced them
+pub const LEGACY_FIELDS: &[(&str, &str)] = &[
+    ("agent_id", "agent.id"),
+    ("ide_id", "ide.id"),
+    ("ci_id", "ci.id"),
+    ("container_id", "container.id"),
+    ("is_interactive", "terminal.interactive"),
+    ("is_tty_stdin", "terminal.stdin.tty"),
+    ("is_tty_stdout", "terminal.stdout.tty"),
+    ("is_tty_stderr", "terminal.stderr.tty"),
+    ("is_piped_stdin", "terminal.stdin.piped"),
+    ("is_piped_stdout", "terminal.stdout.piped"),
+    ("color_level", "terminal.color_level"),
+    ("supports_hyperlinks", "terminal.supports_hyperlinks"),
+];
+
+/// Whether `value` uses the pre-0.3.0 layout
+pub fn is_legacy(value: &Value) -> bool {
+    value["contexts"].is_object()
+        || value["traits"].as_object().is_some_and(|traits| {
+            LEGACY_FIELDS
+                .iter()
+                .any(|(old, _)| traits.contains_key(*old))
+        })
+}
+
+/// Read an envsense result from JSON text, upgrading older layouts
+pub fn from_json_str(json: &str) -> Result<EnvSense, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    migrate(value)
+}
+
+/// [`from_json_str`], rejecting keys the current schema does not have
+pub fn from_json_str_strict(json: &str) -> Result<EnvSense, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    migrate_strict(value)
+}
+
+/// [`migrate`], rejecting keys the current schema does not have
+///
+/// The error names the first unknown key by its path, e.g.
+/// `traits.ci.vendr`, and the keys expected there. Documents in the pre-0.3.0
+/// layout are upgraded as usual.
+pub fn migrate_strict(value: Value) -> Result<EnvSense, String> {
+    if value.is_object() && !is_legacy(&value) {
+        check_known_keys(&value, &known_layout(), "")?;
+    }
+    migrate(value)
+}
+
+/// Key standing for any key in the [`known_layout`] of open-ended maps
+const ANY_KEY: &str = "*";
+
+/// Every key a current document can hold
+///
+/// Maps keyed by name or path hold the layout of their values under
+/// [`ANY_KEY`]. Built from struct literals naming every field, so a new
+/// field cannot be left out.
+fn known_layout() -> Value {
+    let some = || Some(String::new());
+    let stream = || StreamInfo {
+        tty: false,
+        piped: false,
+    };
+    fn any<T>(value: T) -> BTreeMap<String, T> {
+        BTreeMap::from([(ANY_KEY.to_string(), value)])
+    }
+    let env = EnvSense {
+        contexts: Vec::new(),
+        traits: NestedTraits {
+            agent: AgentTraits {
+                id: some(),
+                version: some(),
+            },
+            ide: IdeTraits {
+                id: some(),
+                version: some(),
+                variant: some(),
+            },
+            terminal: TerminalTraits {
+                interactive: false,
+                color_level: ColorLevel::None,
+                stdin: StdinInfo {
+                    has_data: Some(false),
+                    ..stream().into()
+                },
+                stdout: stream(),
+                stderr: stream(),
+                supports_hyperlinks: false,
+                program: some(),
+                program_version: some(),
+                term: some(),
+                emulator: some(),
+                emulator_version: some(),
+            },
+            ci: CiTraits {
+                id: some(),
+                vendor: some(),
+                name: some(),
+                is_pr: Some(false),
+                branch: some(),
+            },
+            shell: ShellTraits { id: some() },
+            container: ContainerTraits {
+                runtime: some(),
+                image: some(),
+                id: some(),
+            },
+            extra: any(Value::Null),
+        },
+        evidence: vec![Evidence {
+            signal: Signal::Env,
+            key: String::new(),
+            value: some(),
+            supports: Vec::new(),
+            confidence: 0.0,
+            sources: vec![EvidenceSource {
+                detector: String::new(),
+                mapping: some(),
+            }],
+        }],
+        version: String::new(),
+        confidence: Confidence {
+            contexts: any(0.0),
+            traits: any(0.0),
+        },
+        diagnostics: vec![Diagnostic {
+            detector: String::new(),
+            kind: DiagnosticKind::Error,
+            message: String::new(),
+        }],
+        timings: Some(Timings {
+            total_us: 0,
+            detectors: vec![DetectorTiming {
+                detector: String::new(),
+                elapsed_us: 0,
+            }],
+        }),
+        provenance: Some(any(TraitProvenance {
+            detector: some(),
+            confidence: 0.0,
+            evidence: Vec::new(),
+        })),
+    };
+    serde_json::to_value(env).unwrap_or_default()
+}
+
+/// Fail on the first key of `value` that `layout` does not have
+fn check_known_keys(value: &Value, layout: &Value, path: &str) -> Result<(), String> {
+    let join = |key: &str| match path {
+        "" => key.to_string(),
+        _ => format!("{}.{}", path, key),
+    };
+    match (value, layout) {
+        (Value::Object(object), Value::Object(known)) => {
+            let any = known.get(ANY_KEY).filter(|_| known.len() == 1);
+            for (key, value) in object {
+                let Some(layout) = any.or_else(|| known.get(key)) else {
+                    let expected: Vec<&str> = known.keys().map(String::as_str).collect();
+                    return Err(format!(
+                        "unknown field `{}`, expected one of: {}",
+                        join(key),
+                        expected.join(", ")
+                    ));
+                };
+                check_known_keys(value, layout, &join(key))?;
+            }
+        }
+        (Value::Array(items), Value::Array(known)) => {
+            if let Some(layout) = known.first() {
+                for (i, item) in items.iter().enumerate() {
+                    check_known_keys(item, layout, &format!("{}[{}]", path, i))?;
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Read an envsense result, upgrading older layouts
+pub fn migrate(value: Value) -> Result<EnvSense, String> {
+    if !value.is_object() {
+        return Err("expected a JSON object".to_string());
+    }
+    if !is_legacy(&value) {
+        let mut full = serde_json::to_value(EnvSense::default()).map_err(|e| e.to_string())?;
+        merge(&mut full, value);
+        return serde_json::from_value(full).map_err(|e| e.to_string());
+    }
+
+    let mut env = EnvSense::default();
+
+    let contexts = &value["contexts"];
+    if let Some(flags) = contexts.as_object() {
+        let mut names: Vec<&String> = flags.keys().collect();
+        names.sort_by_key(|name| {
+            LEGACY_CONTEXTS
+                .iter()
+                .position(|c| c == name)
+                .unwrap_or(LEGACY_CONTEXTS.len())
+        });
+        env.contexts = names
+            .into_iter()
+            .filter(|name| flags[name.as_str()] == true)
+            .cloned()
+            .collect();
+    } else if let Some(list) = contexts.as_array() {
+        env.contexts = list
+            .iter()
+            .filter_map(|c| c.as_str().map(str::to_string))
+            .collect();
+    }
+
+    let facets = &value["facets"];
+    let string = |value: &Value| value.as_str().map(str::to_string);
+    env.traits.agent.id = string(&facets["agent_id"]);
+    env.traits.ide.id = string(&facets["ide_id"]);
+    env.traits.ci.id = string(&facets["ci_id"]);
+    env.traits.container.id = string(&facets["container_id"]);
+    env.traits.ci.vendor = string(&facets["ci"]["vendor"]);
+    env.traits.ci.name = string(&facets["ci"]["name"]);
+    env.traits.ci.is_pr = facets["ci"]["pr"].as_bool();
+    env.traits.ci.branch = string(&facets["ci"]["branch"]);
+
+    let traits = &value["traits"];
+    let flag = |key: &str| traits[key].as_bool().unwrap_or(false);
+    let terminal = &mut env.traits.terminal;
+    terminal.interactive = flag("is_interactive");
+    terminal.stdin.tty = flag("is_tty_stdin");
+    terminal.stdout.tty = flag("is_tty_stdout");
+    terminal.stderr.tty = flag("is_tty_stderr");
+    terminal.stdin.piped = flag("is_piped_stdin");
+    terminal.stdout.piped = flag("is_piped_stdout");
+    terminal.supports_hyperlinks = flag("supports_hyperlinks");
+    if let Some(level) = traits.get("color_level") {
+        terminal.color_level = serde_json::from_value::<ColorLevel>(level.clone())
+            .map_err(|e| format!("invalid color_level: {}", e))?;
+    }
+
+    if let Some(evidence) = value.get("evidence") {
+        let mut evidence: Vec<Evidence> =
+            serde_json::from_value(evidence.clone()).map_err(|e| e.to_string())?;
+        for item in &mut evidence {
+            for supported in &mut item.supports {
+                if let Some((_, new)) = LEGACY_FIELDS.iter().find(|(old, _)| old == supported) {
+                    *supported = new.to_string();
+                }
+            }
+        }
+        env.evidence = evidence;
+    }
+
+    env.version = SCHEMA_VERSION.to_string();
+    Ok(env)
+}
+
+/// Recursively overlay the objects in `value` onto `base`
+fn merge(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            for (key, value) in value {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn legacy() -> Value {
+        json!({
+            "contexts": {"agent": true, "ide": true, "ci": true, "container": false, "remote": false},
+            "facets": {
+                "agent_id": "cursor",
+                "ide_id": "cursor",
+                "ci_id": "github_actions",
+                "container_id": null,
+                "ci": {"is_ci": true, "vendor": "github_actions", "name": "GitHub Actions", "pr": true, "branch": "main"}
+            },
+            "traits": {
+                "is_interactive": true,
+                "is_tty_stdin": true,
+                "is_tty_stdout": true,
+                "is_tty_stderr": false,
+                "is_piped_stdin": false,
+                "is_piped_stdout": false,
+                "color_level": "truecolor",
+                "supports_hyperlinks": true
+            },
+            "evidence": [
+                {"signal": "env", "key": "CURSOR_TRACE_ID", "value": "x", "supports": ["agent", "agent_id"], "confidence": 0.95}
+            ],
+            "version": "0.2.0"
+        })
+    }
+
+    #[test]
+    fn upgrades_legacy_layout() {
+        let value = legacy();
+        assert!(is_legacy(&value));
+        let env = migrate(value).unwrap();
+
+        assert_eq!(env.contexts, ["agent", "ide", "ci"]);
+        assert_eq!(env.traits.agent.id.as_deref(), Some("cursor"));
+        assert_eq!(env.traits.ci.name.as_deref(), Some("GitHub Actions"));
+        assert_eq!(env.traits.ci.is_pr, Some(true));
+        assert_eq!(env.traits.ci.branch.as_deref(), Some("main"));
+        assert!(env.traits.terminal.interactive);
+        assert!(env.traits.terminal.stdout.tty);
+        assert!(!env.traits.terminal.stderr.tty);
+        assert_eq!(env.traits.terminal.color_level, ColorLevel::Truecolor);
+        assert!(env.traits.terminal.supports_hyperlinks);
+        assert_eq!(env.evidence[0].supports, ["agent", "agent.id"]);
+        assert_eq!(env.version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn reads_current_layout_unchanged() {
+        let mut env = EnvSense {
+            contexts: vec!["ci".to_string()],
+            ..EnvSense::default()
+        };
+        env.traits.ci.id = Some("gitlab".to_string());
+        let value = serde_json::to_value(&env).unwrap();
+
+        assert!(!is_legacy(&value));
+        assert_eq!(migrate(value).unwrap(), env);
+    }
+
+    #[test]
+    fn fills_in_missing_keys() {
+        let env = migrate(json!({
+            "contexts": ["ci"],
+            "traits": {"ci": {"id": "github_actions"}, "terminal": {"stdout": {"tty": true}}}
+        }))
+        .unwrap();
+
+        assert_eq!(env.contexts, ["ci"]);
+        assert_eq!(env.traits.ci.id.as_deref(), Some("github_actions"));
+        assert_eq!(env.traits.ci.name, None);
+        assert!(env.traits.terminal.stdout.tty);
+        assert!(!env.traits.terminal.interactive);
+        assert_eq!(env.traits.terminal.color_level, ColorLevel::None);
+        assert_eq!(env.version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn strict_mode_names_unknown_keys() {
+        let err = migrate_strict(json!({
+            "contexts": ["ci"],
+            "traits": {"ci": {"id": "github_actions", "vendr": "github"}}
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "unknown field `traits.ci.vendr`, expected one of: branch, id, is_pr, name, vendor"
+        );
+
+        let err = from_json_str_strict(
+            r#"{"contexts": [], "traits": {}, "evidence": [{"signal": "env", "key": "CI", "confidence": 1, "sorces": []}]}"#,
+        )
+        .unwrap_err();
+        assert!(
+            err.starts_with("unknown field `evidence[0].sorces`"),
+            "{}",
+            err
+        );
+
+        let err = migrate_strict(json!({"contexts": [], "trait": {}})).unwrap_err();
+        assert!(err.starts_with("unknown field `trait`"), "{}", err);
+    }
+
+    #[test]
+    fn strict_mode_accepts_everything_envsense_writes() {
+        let mut env = EnvSense {
+            contexts: vec!["ci".to_string()],
+            provenance: Some(BTreeMap::new()),
+            ..EnvSense::default()
+        };
+        env.traits.ci.vendor = Some("github".to_string());
+        env.traits
+            .extra
+            .insert("vendor".to_string(), json!({"region": "us"}));
+        Confidence::raise(&mut env.confidence.traits, "ci.vendor", 0.9);
+        env.evidence.push(Evidence::env_var("CI", "true"));
+
+        let value = serde_json::to_value(&env).unwrap();
+        assert_eq!(migrate_strict(value).unwrap(), env);
+        assert!(migrate_strict(serde_json::to_value(known_layout()).unwrap()).is_ok());
+        assert!(migrate_strict(legacy()).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(migrate(json!([])).is_err());
+        assert!(from_json_str("{").is_err());
+
+        let mut value = legacy();
+        value["traits"]["color_level"] = json!("millions");
+        assert!(migrate(value).unwrap_err().contains("color_level"));
+    }
+}
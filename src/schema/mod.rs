@@ -1,10 +1,16 @@
+pub mod compat;
 pub mod evidence;
+#[cfg(feature = "schemars")]
+pub mod json_schema;
 pub mod main;
+pub mod migrate;
 pub mod nested;
 
 // Re-export commonly used types
-pub use evidence::{Evidence, Signal};
-pub use main::EnvSense;
+pub use evidence::{Evidence, EvidenceFilter, EvidenceSource, Signal};
+pub use main::{
+    Confidence, DetectorTiming, Diagnostic, DiagnosticKind, EnvSense, Timings, TraitProvenance,
+};
 pub use nested::NewEnvSense;
 
 // Schema version constants
@@ -1,16 +1,19 @@
 use crate::traits::NestedTraits;
 use envsense_macros::{Detection, DetectionMerger, DetectionMergerDerive};
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::evidence::Evidence;
 
 /// New nested schema structure using the nested traits system
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, DetectionMergerDerive)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, DetectionMergerDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct NewEnvSense {
     pub contexts: Vec<String>, // Simplified from Contexts struct
     pub traits: NestedTraits,  // New nested structure
     pub evidence: Vec<Evidence>,
+    #[detection_merge(ignore)]
     pub version: String,
 }
 
@@ -0,0 +1,335 @@
+//! JSON-RPC 2.0 server for `envsense serve --stdio`.
+//!
+//! Messages are newline-delimited JSON objects. Detection runs once, on the
+//! first request that needs it, and is reused until `envsense/refresh`.
+//! [`info_json`] builds the `envsense/info` result and `envsense info --json`
+//! alike.
+//!
+//! | Method             | Params                                    | Result                           |
+//! | ------------------ | ----------------------------------------- | -------------------------------- |
+//! | `envsense/info`    | none                                      | same as `envsense info --json`   |
+//! | `envsense/check`   | `predicates`, optional `mode` / `explain` | same as `envsense check --json`  |
+//! | `envsense/refresh` | none                                      | `null`; re-detects on next query |
+
+use crate::cache::CachedEnvSense;
+use crate::check::{self, CheckMode, CheckOutput, FieldRegistry};
+use crate::engine::DetectionEngine;
+use crate::schema::EnvSense;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Absent for notifications, which get no response
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckParams {
+    predicates: Vec<String>,
+    #[serde(default)]
+    mode: CheckMode,
+    #[serde(default)]
+    explain: bool,
+}
+
+/// An error returned to the client as a JSON-RPC error object
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+pub struct Server {
+    /// Kept until `envsense/refresh`, however long that takes
+    cache: CachedEnvSense,
+    registry: FieldRegistry,
+}
+
+impl Server {
+    pub fn new(engine: DetectionEngine) -> Self {
+        Self {
+            cache: CachedEnvSense::with_engine(engine, Duration::MAX),
+            registry: FieldRegistry::new(),
+        }
+    }
+
+    /// Read requests from `input` until EOF, writing responses to `output`
+    pub fn serve(&mut self, input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_message(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer one raw message; `None` for notifications
+    pub fn handle_message(&mut self, message: &str) -> Option<Value> {
+        let value: Value = match serde_json::from_str(message) {
+            Ok(value) => value,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, e.to_string())),
+        };
+        if value.is_array() {
+            return Some(error_response(
+                Value::Null,
+                INVALID_REQUEST,
+                "batch requests are not supported",
+            ));
+        }
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        let request: Request = match serde_json::from_value(value) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(id, INVALID_REQUEST, e.to_string())),
+        };
+        if request.jsonrpc != "2.0" {
+            return Some(error_response(
+                id,
+                INVALID_REQUEST,
+                "jsonrpc must be \"2.0\"",
+            ));
+        }
+
+        let result = self.call(&request.method, request.params);
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(e) => error_response(id, e.code, e.message),
+        })
+    }
+
+    /// Dispatch a method call
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "envsense/info" => Ok(info_json(&self.cache.get())),
+            "envsense/check" => self.check(params),
+            "envsense/refresh" => {
+                self.cache.invalidate();
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method '{}'", method),
+            )),
+        }
+    }
+
+    fn check(&mut self, params: Value) -> Result<Value, RpcError> {
+        let params: CheckParams = serde_json::from_value(params)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+        if params.predicates.is_empty() {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                "predicates must not be empty",
+            ));
+        }
+
+        let mut parsed = Vec::with_capacity(params.predicates.len());
+        for predicate in &params.predicates {
+            let check = check::parse_predicate(predicate)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, format!("'{}': {}", predicate, e)))?;
+            if let check::Check::NestedField { ref path, .. } = check.check {
+                check::validate_field_path(path, &self.registry)
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+            }
+            parsed.push(check);
+        }
+
+        let env = self.cache.get();
+        let results: Vec<_> = parsed
+            .into_iter()
+            .map(|check| check::evaluate(&env, check, &self.registry))
+            .collect();
+        let overall = match params.mode {
+            CheckMode::Any => results.iter().any(|r| r.result.as_bool()),
+            CheckMode::All => results.iter().all(|r| r.result.as_bool()),
+        };
+        Ok(json!(CheckOutput::new(
+            &results,
+            &params.predicates,
+            overall,
+            params.mode,
+            params.explain,
+        )))
+    }
+}
+
+/// JSON document printed by `envsense info --json`
+///
+/// `diagnostics` and `provenance` are only present when there are any, and
+/// `meta.timings` only when timings were recorded.
+pub fn info_json(env: &EnvSense) -> Value {
+    let mut meta = json!({"schema_version": env.version});
+    if let Some(timings) = &env.timings {
+        meta["timings"] = json!(timings);
+    }
+    let mut document = json!({
+        "version": env.version,
+        "contexts": env.contexts,
+        "traits": env.traits,
+        "facets": {},
+        "meta": meta,
+        "evidence": env.evidence,
+        "confidence": env.confidence,
+    });
+    if !env.diagnostics.is_empty() {
+        document["diagnostics"] = json!(env.diagnostics);
+    }
+    if let Some(provenance) = &env.provenance {
+        document["provenance"] = json!(provenance);
+    }
+    document
+}
+
+fn error_response(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message.into()},
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        Server::new(DetectionEngine::with_default_detectors())
+    }
+
+    fn request(server: &mut Server, method: &str, params: Value) -> Value {
+        let message = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        server.handle_message(&message.to_string()).unwrap()
+    }
+
+    #[test]
+    fn info_returns_detection_document() {
+        let response = request(&mut server(), "envsense/info", Value::Null);
+        assert_eq!(response["id"], 1);
+        let result = &response["result"];
+        assert!(result["contexts"].is_array());
+        assert!(result["traits"]["terminal"].is_object());
+        assert_eq!(result["version"], result["meta"]["schema_version"]);
+    }
+
+    #[test]
+    fn info_carries_diagnostics_timings_and_provenance() {
+        let env = DetectionEngine::with_default_detectors().detect();
+        let document = info_json(&env);
+        assert!(document.get("provenance").is_none());
+        assert!(document["meta"].get("timings").is_none());
+
+        let mut env = DetectionEngine::with_default_detectors()
+            .with_timings(true)
+            .with_provenance(true)
+            .detect();
+        env.diagnostics.push(crate::schema::Diagnostic::warning(
+            "overrides",
+            "ignoring X",
+        ));
+        let document = info_json(&env);
+        assert_eq!(document["diagnostics"][0]["kind"], "warning");
+        assert!(document["meta"]["timings"]["total_us"].is_u64());
+        assert!(document["provenance"].is_object());
+    }
+
+    #[test]
+    fn check_evaluates_predicates() {
+        let mut server = server();
+        let response = request(
+            &mut server,
+            "envsense/check",
+            json!({"predicates": ["terminal.interactive", "!terminal.interactive"], "mode": "any"}),
+        );
+        let result = &response["result"];
+        assert_eq!(result["overall"], true);
+        assert_eq!(result["mode"], "any");
+        assert_eq!(result["checks"][1]["predicate"], "!terminal.interactive");
+    }
+
+    #[test]
+    fn reports_json_rpc_errors() {
+        let mut server = server();
+        assert_eq!(
+            server.handle_message("{not json").unwrap()["error"]["code"],
+            PARSE_ERROR
+        );
+        assert_eq!(
+            server.handle_message(r#"{"id": 1}"#).unwrap()["error"]["code"],
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            request(&mut server, "envsense/nope", Value::Null)["error"]["code"],
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            request(
+                &mut server,
+                "envsense/check",
+                json!({"predicates": ["nope.field"]})
+            )["error"]["code"],
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            request(&mut server, "envsense/check", json!({"predicates": []}))["error"]["code"],
+            INVALID_PARAMS
+        );
+    }
+
+    #[test]
+    fn notifications_get_no_response() {
+        let mut server = server();
+        let message = json!({"jsonrpc": "2.0", "method": "envsense/refresh"});
+        assert!(server.handle_message(&message.to_string()).is_none());
+    }
+
+    #[test]
+    fn serves_newline_delimited_messages() {
+        let input = concat!(
+            r#"{"jsonrpc": "2.0", "id": "a", "method": "envsense/info"}"#,
+            "\n\n",
+            r#"{"jsonrpc": "2.0", "method": "envsense/refresh"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": "b", "method": "envsense/check", "params": {"predicates": ["ci"]}}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        server().serve(input.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], "a");
+        assert_eq!(responses[1]["id"], "b");
+    }
+}
@@ -0,0 +1,254 @@
+//! Capture and replay of raw detection inputs.
+//!
+//! A [`CapturedSnapshot`] records everything the detectors look at — the
+//! environment variables, the TTY state of the standard streams, and the
+//! terminal capabilities resolved at capture time — so that `info` and
+//! `check` can later be evaluated against it on another machine.
+
+use crate::detectors::terminal::TerminalDetector;
+use crate::detectors::{Detector, EnvProvider, EnvSnapshot};
+use crate::redact::{REDACTED, has_url_credentials, is_secret_key};
+use crate::traits::terminal::{ColorLevel, TerminalTraits};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Version of the capture file format (independent of the schema version)
+pub const CAPTURE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("failed to read snapshot '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("invalid snapshot '{path}': {source}")]
+    Parse {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("unsupported snapshot format version {0} (expected {CAPTURE_FORMAT_VERSION})")]
+    UnsupportedVersion(u32),
+}
+
+/// TTY state of the standard streams at capture time
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TtyState {
+    pub stdin: bool,
+    pub stdout: bool,
+    pub stderr: bool,
+}
+
+/// Serializable form of an [`EnvSnapshot`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CapturedSnapshot {
+    pub format_version: u32,
+    pub envsense_version: String,
+    pub env: BTreeMap<String, String>,
+    pub tty: TtyState,
+    pub color_level: ColorLevel,
+    pub supports_hyperlinks: bool,
+    /// Whether piped stdin had data waiting; missing from older captures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdin_has_data: Option<bool>,
+}
+
+impl CapturedSnapshot {
+    /// Capture the current process environment with secrets scrubbed
+    pub fn capture() -> Self {
+        Self::from_env_snapshot(&EnvSnapshot::current())
+    }
+
+    /// Build a capture from an existing snapshot, scrubbing secret values
+    ///
+    /// Color level and hyperlink support are resolved here because the
+    /// terminal detector otherwise queries the live process on replay.
+    pub fn from_env_snapshot(snap: &EnvSnapshot) -> Self {
+        let terminal = resolve_terminal(snap);
+
+        Self {
+            format_version: CAPTURE_FORMAT_VERSION,
+            envsense_version: env!("CARGO_PKG_VERSION").to_string(),
+            env: scrub_env(snap.env()),
+            tty: TtyState {
+                stdin: snap.is_tty_stdin(),
+                stdout: snap.is_tty_stdout(),
+                stderr: snap.is_tty_stderr(),
+            },
+            color_level: terminal.color_level,
+            supports_hyperlinks: terminal.supports_hyperlinks,
+            stdin_has_data: terminal.stdin.has_data,
+        }
+    }
+
+    /// Load a capture file written by `envsense capture`
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        let content = std::fs::read_to_string(path).map_err(|source| SnapshotError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        let snapshot: Self =
+            serde_json::from_str(&content).map_err(|source| SnapshotError::Parse {
+                path: path.display().to_string(),
+                source,
+            })?;
+        if snapshot.format_version != CAPTURE_FORMAT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(snapshot.format_version));
+        }
+        Ok(snapshot)
+    }
+
+    /// Convert back into an [`EnvSnapshot`] that replays the captured state
+    ///
+    /// The resolved terminal capabilities are injected through the existing
+    /// `ENVSENSE_COLOR_LEVEL` / `ENVSENSE_SUPPORTS_HYPERLINKS` /
+    /// `ENVSENSE_STDIN_HAS_DATA` overrides so that detection never consults
+    /// the replaying process.
+    pub fn to_env_snapshot(&self) -> EnvSnapshot {
+        let mut env_vars: HashMap<String, String> = self
+            .env
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        let color_level = serde_json::to_value(&self.color_level)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_else(|| "none".to_string());
+        env_vars
+            .entry("ENVSENSE_COLOR_LEVEL".to_string())
+            .or_insert(color_level);
+        env_vars
+            .entry("ENVSENSE_SUPPORTS_HYPERLINKS".to_string())
+            .or_insert_with(|| self.supports_hyperlinks.to_string());
+        if let Some(has_data) = self.stdin_has_data {
+            env_vars
+                .entry("ENVSENSE_STDIN_HAS_DATA".to_string())
+                .or_insert_with(|| has_data.to_string());
+        }
+
+        EnvSnapshot::with_mock_tty(env_vars, self.tty.stdin, self.tty.stdout, self.tty.stderr)
+    }
+}
+
+fn resolve_terminal(snap: &EnvSnapshot) -> TerminalTraits {
+    TerminalDetector::new()
+        .detect(snap)
+        .traits_patch
+        .get("terminal")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Replace secret-looking values with [`REDACTED`]
+///
+/// Keys are always kept: many indicators only test for presence, so
+/// dropping them would change detection results on replay. URLs with
+/// embedded credentials are scrubbed regardless of the key name.
+pub fn scrub_env(env: &dyn EnvProvider) -> BTreeMap<String, String> {
+    env.iter()
+        .map(|(k, v)| {
+            let value = if is_secret_key(&k) || has_url_credentials(&v) {
+                REDACTED.to_string()
+            } else {
+                v
+            };
+            (k, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn scrubs_secret_keys_but_keeps_them() {
+        let scrubbed = scrub_env(&env(&[
+            ("GITHUB_TOKEN", "ghp_abc"),
+            ("AWS_SECRET_ACCESS_KEY", "xyz"),
+            ("TERM_PROGRAM", "vscode"),
+        ]));
+
+        assert_eq!(scrubbed["GITHUB_TOKEN"], REDACTED);
+        assert_eq!(scrubbed["AWS_SECRET_ACCESS_KEY"], REDACTED);
+        assert_eq!(scrubbed["TERM_PROGRAM"], "vscode");
+    }
+
+    #[test]
+    fn scrubs_urls_with_credentials() {
+        let scrubbed = scrub_env(&env(&[
+            ("DATABASE_URL", "postgres://user:hunter2@db:5432/app"),
+            ("HOMEPAGE", "https://example.com/a@b"),
+        ]));
+
+        assert_eq!(scrubbed["DATABASE_URL"], REDACTED);
+        assert_eq!(scrubbed["HOMEPAGE"], "https://example.com/a@b");
+    }
+
+    #[test]
+    fn round_trips_through_env_snapshot() {
+        let snap = EnvSnapshot::with_mock_tty(
+            env(&[
+                ("ENVSENSE_COLOR_LEVEL", "ansi256"),
+                ("ENVSENSE_SUPPORTS_HYPERLINKS", "true"),
+                ("CI", "true"),
+            ]),
+            true,
+            false,
+            true,
+        );
+
+        let captured = CapturedSnapshot::from_env_snapshot(&snap);
+        assert_eq!(captured.color_level, ColorLevel::Ansi256);
+        assert!(captured.supports_hyperlinks);
+
+        let json = serde_json::to_string(&captured).unwrap();
+        let restored: CapturedSnapshot = serde_json::from_str(&json).unwrap();
+        let replay = restored.to_env_snapshot();
+
+        assert!(replay.is_tty_stdin());
+        assert!(!replay.is_tty_stdout());
+        assert!(replay.is_tty_stderr());
+        assert_eq!(replay.get_env("CI"), Some("true".to_string()));
+        assert_eq!(resolve_terminal(&replay).color_level, ColorLevel::Ansi256);
+    }
+
+    #[test]
+    fn replay_pins_resolved_capabilities() {
+        let captured = CapturedSnapshot {
+            format_version: CAPTURE_FORMAT_VERSION,
+            envsense_version: "0.0.0".to_string(),
+            env: BTreeMap::new(),
+            tty: TtyState {
+                stdin: false,
+                stdout: false,
+                stderr: false,
+            },
+            color_level: ColorLevel::Truecolor,
+            supports_hyperlinks: false,
+            stdin_has_data: Some(true),
+        };
+
+        let replay = captured.to_env_snapshot();
+        assert_eq!(
+            replay.get_env("ENVSENSE_COLOR_LEVEL"),
+            Some("truecolor".to_string())
+        );
+        assert_eq!(
+            replay.get_env("ENVSENSE_SUPPORTS_HYPERLINKS"),
+            Some("false".to_string())
+        );
+        assert_eq!(replay.stdin_has_data(), Some(true));
+    }
+}
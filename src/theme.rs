@@ -0,0 +1,335 @@
+//! Colors for the human output of `info` and `check`.
+//!
+//! A [`Theme`] is built once per run from the `[theme]` config section and
+//! passed to every renderer, so both commands share the same colors and
+//! honor `--no-color`, `NO_COLOR` and the configured color mode the same way.
+
+use crate::ascii;
+use crate::config::{ThemeConfig, ThemeName};
+use colored::{Color, ColoredString, Colorize};
+
+/// How one element is drawn, parsed from e.g. `"bold cyan"`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub dimmed: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    const fn color(color: Color) -> Self {
+        Self {
+            color: Some(color),
+            bold: false,
+            dimmed: false,
+            underline: false,
+        }
+    }
+
+    const fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    const fn dimmed(mut self) -> Self {
+        self.dimmed = true;
+        self
+    }
+
+    fn paint(&self, text: &str) -> String {
+        let mut painted = ColoredString::from(text);
+        if let Some(color) = self.color {
+            painted = painted.color(color);
+        }
+        if self.bold {
+            painted = painted.bold();
+        }
+        if self.dimmed {
+            painted = painted.dimmed();
+        }
+        if self.underline {
+            painted = painted.underline();
+        }
+        painted.to_string()
+    }
+}
+
+const PLAIN: Style = Style {
+    color: None,
+    bold: false,
+    dimmed: false,
+    underline: false,
+};
+
+impl std::str::FromStr for Style {
+    type Err = String;
+
+    /// Space-separated color and attributes; `none` for no styling
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut style = PLAIN;
+        for word in s.split_whitespace() {
+            match word.to_lowercase().as_str() {
+                "none" => {}
+                "bold" => style.bold = true,
+                "dimmed" | "dim" => style.dimmed = true,
+                "underline" => style.underline = true,
+                name => {
+                    style.color = Some(parse_color(name).ok_or_else(|| {
+                        format!(
+                            "unknown style '{}' (expected a color such as red or bright_red, bold, dimmed, underline or none)",
+                            word
+                        )
+                    })?)
+                }
+            }
+        }
+        Ok(style)
+    }
+}
+
+/// A color name such as `red` or `bright_red`
+fn parse_color(name: &str) -> Option<Color> {
+    name.replace(['_', '-'], " ").parse().ok()
+}
+
+/// Resolved colors for every themed element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Section headings such as `Contexts:`
+    pub heading: Style,
+    /// `true` values and passing outcomes
+    pub true_value: Style,
+    /// `false`/`none` values and failing outcomes
+    pub false_value: Style,
+    /// De-emphasized text such as explanations
+    pub dim: Style,
+    /// Table column headers
+    pub header: Style,
+    /// Colors cycled through for `truecolor`; empty disables the rainbow
+    pub rainbow: Vec<Color>,
+    /// Whether to emit colors at all
+    pub enabled: bool,
+    /// Transliterate non-ASCII text, for `--ascii`
+    pub ascii: bool,
+}
+
+const DEFAULT_RAINBOW: [Color; 7] = [
+    Color::Red,
+    Color::BrightRed, // Orange approximation
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+];
+
+impl Theme {
+    /// One of the built-in themes
+    pub fn named(name: ThemeName) -> Self {
+        let (heading, true_value, false_value, dim) = match name {
+            ThemeName::Default => (
+                Style::color(Color::Cyan).bold(),
+                Style::color(Color::Green),
+                Style::color(Color::Red),
+                PLAIN.dimmed(),
+            ),
+            ThemeName::Bright => (
+                Style::color(Color::BrightCyan).bold(),
+                Style::color(Color::BrightGreen),
+                Style::color(Color::BrightRed),
+                Style::color(Color::BrightBlack),
+            ),
+            ThemeName::Mono => (PLAIN.bold(), PLAIN.bold(), PLAIN.dimmed(), PLAIN.dimmed()),
+        };
+        Self {
+            heading,
+            true_value,
+            false_value,
+            dim,
+            header: PLAIN.bold(),
+            rainbow: match name {
+                ThemeName::Mono => Vec::new(),
+                _ => DEFAULT_RAINBOW.to_vec(),
+            },
+            enabled: true,
+            ascii: false,
+        }
+    }
+
+    /// The configured theme with its overrides applied
+    ///
+    /// Invalid overrides are ignored, matching how config file errors are
+    /// handled. `rainbow` is `output_formatting.rainbow_colors`.
+    pub fn from_config(config: &ThemeConfig, enabled: bool, rainbow: bool) -> Self {
+        let mut theme = Self::named(config.name);
+        let overrides = [
+            (&config.heading, &mut theme.heading),
+            (&config.true_value, &mut theme.true_value),
+            (&config.false_value, &mut theme.false_value),
+            (&config.dim, &mut theme.dim),
+            (&config.header, &mut theme.header),
+        ];
+        for (value, style) in overrides {
+            if let Some(parsed) = value.as_deref().and_then(|v| v.parse().ok()) {
+                *style = parsed;
+            }
+        }
+        if let Some(colors) = &config.rainbow {
+            theme.rainbow = colors.iter().filter_map(|c| parse_color(c)).collect();
+        }
+        if !rainbow {
+            theme.rainbow.clear();
+        }
+        theme.enabled = enabled;
+        theme
+    }
+
+    /// The default theme without colors
+    pub fn plain() -> Self {
+        Self {
+            enabled: false,
+            ..Self::named(ThemeName::Default)
+        }
+    }
+
+    fn paint(&self, style: &Style, text: &str) -> String {
+        let text = self.text(text);
+        if self.enabled {
+            style.paint(&text)
+        } else {
+            text.into_owned()
+        }
+    }
+
+    /// `text` as-is, or transliterated to ASCII with `ascii`
+    pub fn text<'a>(&self, text: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.ascii {
+            ascii::transliterate(text)
+        } else {
+            text.into()
+        }
+    }
+
+    /// Color a rendered value: `true` and `false`/`none`, and `truecolor` in
+    /// the rainbow colors
+    pub fn value(&self, v: &str) -> String {
+        match v {
+            "truecolor" if self.enabled && !self.rainbow.is_empty() => self.rainbow_text(v),
+            "true" => self.paint(&self.true_value, v),
+            "false" | "none" => self.paint(&self.false_value, v),
+            _ => self.text(v).into_owned(),
+        }
+    }
+
+    /// [`Theme::value`] appended to `out`, copying plain values as they are
+    pub fn push_value(&self, out: &mut String, v: &str) {
+        match v {
+            "truecolor" | "true" | "false" | "none" => out.push_str(&self.value(v)),
+            _ => out.push_str(&self.text(v)),
+        }
+    }
+
+    pub fn heading(&self, text: &str) -> String {
+        self.paint(&self.heading, text)
+    }
+
+    pub fn dim(&self, text: &str) -> String {
+        self.paint(&self.dim, text)
+    }
+
+    pub fn header(&self, text: &str) -> String {
+        self.paint(&self.header, text)
+    }
+
+    /// Overall outcome in the `true` or `false` style, in bold
+    pub fn status(&self, text: &str, ok: bool) -> String {
+        let style = if ok {
+            self.true_value
+        } else {
+            self.false_value
+        };
+        self.paint(&style.bold(), text)
+    }
+
+    fn rainbow_text(&self, value: &str) -> String {
+        value
+            .chars()
+            .zip(self.rainbow.iter().cycle())
+            .map(|(c, color)| c.to_string().color(*color).to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> ThemeConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn plain_without_color() {
+        let theme = Theme::plain();
+        assert_eq!(theme.value("true"), "true");
+        assert_eq!(theme.value("truecolor"), "truecolor");
+        assert_eq!(theme.heading("Traits:"), "Traits:");
+        assert_eq!(theme.dim("# reason"), "# reason");
+    }
+
+    #[test]
+    fn ascii_transliterates_text() {
+        let theme = Theme {
+            ascii: true,
+            ..Theme::plain()
+        };
+        assert_eq!(theme.value("feature/🚀"), "feature/\\u{1f680}");
+        assert_eq!(theme.dim("# a → b"), "# a -> b");
+    }
+
+    #[test]
+    fn colors_booleans() {
+        colored::control::set_override(true);
+        let theme = Theme::from_config(&ThemeConfig::default(), true, false);
+        assert_eq!(theme.value("true"), "\x1b[32mtrue\x1b[0m");
+        assert_eq!(theme.value("false"), "\x1b[31mfalse\x1b[0m");
+        assert_eq!(theme.value("cursor"), "cursor");
+        assert_eq!(theme.value("truecolor"), "truecolor");
+
+        let theme = Theme::from_config(&ThemeConfig::default(), true, true);
+        assert!(theme.value("truecolor").starts_with("\x1b[31mt"));
+        colored::control::unset_override();
+    }
+
+    #[test]
+    fn parses_styles() {
+        assert_eq!("none".parse(), Ok(PLAIN));
+        assert_eq!("bold cyan".parse(), Ok(Style::color(Color::Cyan).bold()));
+        assert_eq!(
+            "bright_magenta".parse(),
+            Ok(Style::color(Color::BrightMagenta))
+        );
+        assert!("sparkly".parse::<Style>().is_err());
+    }
+
+    #[test]
+    fn applies_overrides_to_named_theme() {
+        let theme = Theme::from_config(
+            &config(
+                r#"
+name = "mono"
+true = "blue"
+heading = "not-a-color"
+rainbow = ["yellow", "green"]
+"#,
+            ),
+            true,
+            true,
+        );
+        assert_eq!(theme.true_value, Style::color(Color::Blue));
+        assert_eq!(theme.false_value, PLAIN.dimmed());
+        assert_eq!(theme.heading, Theme::named(ThemeName::Mono).heading);
+        assert_eq!(theme.rainbow, [Color::Yellow, Color::Green]);
+    }
+}
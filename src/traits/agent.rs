@@ -1,12 +1,18 @@
+use envsense_macros::MergeValueDerive;
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Traits specific to agent detection
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct AgentTraits {
     /// The detected agent ID (e.g., "cursor", "vscode", "intellij")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
+    /// The detected agent's version, when the agent exposes it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 #[cfg(test)]
@@ -23,6 +29,7 @@ mod tests {
     fn agent_traits_with_id() {
         let traits = AgentTraits {
             id: Some("cursor".to_string()),
+            ..Default::default()
         };
         assert_eq!(traits.id, Some("cursor".to_string()));
     }
@@ -31,6 +38,7 @@ mod tests {
     fn agent_traits_serialization() {
         let traits = AgentTraits {
             id: Some("vscode".to_string()),
+            ..Default::default()
         };
         let json = serde_json::to_string(&traits).unwrap();
         assert!(json.contains("\"id\":\"vscode\""));
@@ -45,7 +53,7 @@ mod tests {
 
     #[test]
     fn agent_traits_without_id_serialization() {
-        let traits = AgentTraits { id: None };
+        let traits = AgentTraits::default();
         let json = serde_json::to_string(&traits).unwrap();
         assert!(!json.contains("\"id\""));
     }
@@ -54,6 +62,7 @@ mod tests {
     fn agent_traits_empty_string_id() {
         let traits = AgentTraits {
             id: Some("".to_string()),
+            ..Default::default()
         };
         let json = serde_json::to_string(&traits).unwrap();
         assert!(json.contains("\"id\":\"\""));
@@ -63,6 +72,7 @@ mod tests {
     fn agent_traits_unicode_id() {
         let traits = AgentTraits {
             id: Some("cursor-🚀".to_string()),
+            ..Default::default()
         };
         let json = serde_json::to_string(&traits).unwrap();
         assert!(json.contains("cursor-🚀"));
@@ -82,6 +92,16 @@ mod tests {
         assert_eq!(traits.id, None);
     }
 
+    #[test]
+    fn agent_traits_version_serialization() {
+        let traits = AgentTraits {
+            id: Some("aider".to_string()),
+            version: Some("0.86.1".to_string()),
+        };
+        let json = serde_json::to_string(&traits).unwrap();
+        assert_eq!(json, r#"{"id":"aider","version":"0.86.1"}"#);
+    }
+
     #[test]
     fn agent_traits_deserialization_extra_field() {
         let json = r#"{"id":"cursor","unknown":"ignored"}"#;
@@ -1,8 +1,11 @@
+use envsense_macros::MergeValueDerive;
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Traits specific to CI environment detection
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct CiTraits {
     /// The detected CI system ID
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -0,0 +1,44 @@
+use envsense_macros::MergeValueDerive;
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Traits of the container envsense runs in, if any
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ContainerTraits {
+    /// The container runtime or orchestrator (e.g., "docker", "podman",
+    /// "kubernetes", "lxc", "systemd-nspawn")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+    /// The image the container was started from, when the runtime says
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// The container ID; may be the 12-character short form when it only
+    /// comes from the hostname
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn container_traits_serialization() {
+        let traits = ContainerTraits {
+            runtime: Some("podman".to_string()),
+            image: Some("docker.io/library/alpine:latest".to_string()),
+            id: None,
+        };
+        let json = serde_json::to_string(&traits).unwrap();
+        assert_eq!(
+            json,
+            r#"{"runtime":"podman","image":"docker.io/library/alpine:latest"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&ContainerTraits::default()).unwrap(),
+            "{}"
+        );
+    }
+}
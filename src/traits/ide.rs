@@ -1,12 +1,21 @@
+use envsense_macros::MergeValueDerive;
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Traits specific to IDE detection
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct IdeTraits {
     /// The detected IDE ID (e.g., "cursor", "vscode", "intellij")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
+    /// The IDE's version (e.g., "1.85.0"), from `TERM_PROGRAM_VERSION`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Release channel or session kind: "stable", "insiders" or "remote"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
 }
 
 #[cfg(test)]
@@ -23,6 +32,7 @@ mod tests {
     fn ide_traits_with_id() {
         let traits = IdeTraits {
             id: Some("cursor".to_string()),
+            ..Default::default()
         };
         assert_eq!(traits.id, Some("cursor".to_string()));
     }
@@ -31,6 +41,7 @@ mod tests {
     fn ide_traits_serialization() {
         let traits = IdeTraits {
             id: Some("vscode".to_string()),
+            ..Default::default()
         };
         let json = serde_json::to_string(&traits).unwrap();
         assert!(json.contains("\"id\":\"vscode\""));
@@ -45,7 +56,7 @@ mod tests {
 
     #[test]
     fn ide_traits_without_id_serialization() {
-        let traits = IdeTraits { id: None };
+        let traits = IdeTraits::default();
         let json = serde_json::to_string(&traits).unwrap();
         assert!(!json.contains("\"id\""));
     }
@@ -54,6 +65,7 @@ mod tests {
     fn ide_traits_empty_string_id() {
         let traits = IdeTraits {
             id: Some("".to_string()),
+            ..Default::default()
         };
         let json = serde_json::to_string(&traits).unwrap();
         assert!(json.contains("\"id\":\"\""));
@@ -63,6 +75,7 @@ mod tests {
     fn ide_traits_unicode_id() {
         let traits = IdeTraits {
             id: Some("vscode-🚀".to_string()),
+            ..Default::default()
         };
         let json = serde_json::to_string(&traits).unwrap();
         assert!(json.contains("vscode-🚀"));
@@ -1,15 +1,20 @@
 pub mod agent;
 pub mod ci;
+pub mod container;
 pub mod ide;
 pub mod nested;
+pub mod shell;
 pub mod stream;
 pub mod terminal;
+pub(crate) mod terminal_db;
 
 pub use agent::AgentTraits;
 pub use ci::CiTraits;
+pub use container::ContainerTraits;
 pub use ide::IdeTraits;
 pub use nested::NestedTraits;
-pub use stream::StreamInfo;
+pub use shell::ShellTraits;
+pub use stream::{StdinInfo, StreamInfo};
 pub use terminal::{ColorLevel, TerminalTraits};
 
 #[cfg(test)]
@@ -21,15 +26,20 @@ mod integration_tests {
         let nested = NestedTraits {
             agent: AgentTraits {
                 id: Some("cursor".to_string()),
+                ..Default::default()
             },
             ide: IdeTraits {
                 id: Some("cursor".to_string()),
+                ..Default::default()
             },
             terminal: TerminalTraits::detect(),
             ci: CiTraits {
                 id: Some("github".to_string()),
                 ..Default::default()
             },
+            shell: Default::default(),
+            container: Default::default(),
+            extra: Default::default(),
         };
 
         // Test that all components work together
@@ -46,6 +56,7 @@ mod integration_tests {
         assert_eq!(original, deserialized);
     }
 
+    #[cfg(feature = "schemars")]
     #[test]
     fn traits_json_schema_integration() {
         // Test that all trait types can generate JSON schemas
@@ -95,16 +106,19 @@ mod integration_tests {
         let nested = NestedTraits {
             agent: AgentTraits {
                 id: Some("".to_string()),
+                ..Default::default()
             },
             ide: IdeTraits {
                 id: Some("🚀".to_string()),
+                ..Default::default()
             },
             terminal: TerminalTraits {
                 interactive: false,
                 color_level: ColorLevel::None,
-                stdin: StreamInfo {
+                stdin: StdinInfo {
                     tty: false,
                     piped: true,
+                    has_data: None,
                 },
                 stdout: StreamInfo {
                     tty: false,
@@ -115,6 +129,7 @@ mod integration_tests {
                     piped: true,
                 },
                 supports_hyperlinks: false,
+                ..Default::default()
             },
             ci: CiTraits {
                 id: Some("".to_string()),
@@ -123,6 +138,9 @@ mod integration_tests {
                 is_pr: Some(false),
                 branch: Some("".to_string()),
             },
+            shell: Default::default(),
+            container: Default::default(),
+            extra: Default::default(),
         };
 
         // Test serialization with edge cases
@@ -1,13 +1,29 @@
+use envsense_macros::MergeValueDerive;
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 use super::agent::AgentTraits;
 use super::ci::CiTraits;
+use super::container::ContainerTraits;
 use super::ide::IdeTraits;
+use super::shell::ShellTraits;
 use super::terminal::TerminalTraits;
 
 /// Combined traits structure that organizes all environment traits by context
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[detection_merge(alias(
+    is_interactive = "terminal.interactive",
+    is_tty_stdin = "terminal.stdin.tty",
+    is_tty_stdout = "terminal.stdout.tty",
+    is_tty_stderr = "terminal.stderr.tty",
+    is_piped_stdin = "terminal.stdin.piped",
+    is_piped_stdout = "terminal.stdout.piped",
+    color_level = "terminal.color_level",
+    supports_hyperlinks = "terminal.supports_hyperlinks",
+))]
 pub struct NestedTraits {
     /// Agent-related traits (e.g., cursor, vscode, intellij)
     pub agent: AgentTraits,
@@ -17,6 +33,16 @@ pub struct NestedTraits {
     pub terminal: TerminalTraits,
     /// CI environment traits (vendor, name, PR status, branch)
     pub ci: CiTraits,
+    /// Shell traits (which shell, e.g. bash, pwsh or cmd)
+    #[serde(default)]
+    pub shell: ShellTraits,
+    /// Container traits (runtime, image and ID)
+    #[serde(default)]
+    pub container: ContainerTraits,
+    /// Traits no group models yet, from plugins, user mappings or vendors
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[detection_merge(extensions)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 impl NestedTraits {
@@ -27,6 +53,9 @@ impl NestedTraits {
             ide: IdeTraits::default(),     // Will be populated by detection engine
             terminal: TerminalTraits::detect(),
             ci: CiTraits::default(), // Will be populated by detection engine
+            shell: ShellTraits::default(),
+            container: ContainerTraits::default(),
+            extra: BTreeMap::new(),
         }
     }
 
@@ -53,7 +82,7 @@ impl NestedTraits {
 
 #[cfg(test)]
 mod tests {
-    use super::super::stream::StreamInfo;
+    use super::super::stream::{StdinInfo, StreamInfo};
     use super::super::terminal::ColorLevel;
     use super::*;
 
@@ -71,16 +100,19 @@ mod tests {
         let traits = NestedTraits {
             agent: AgentTraits {
                 id: Some("cursor".to_string()),
+                ..Default::default()
             },
             ide: IdeTraits {
                 id: Some("cursor".to_string()),
+                ..Default::default()
             },
             terminal: TerminalTraits {
                 interactive: true,
                 color_level: ColorLevel::Truecolor,
-                stdin: StreamInfo {
+                stdin: StdinInfo {
                     tty: true,
                     piped: false,
+                    has_data: None,
                 },
                 stdout: StreamInfo {
                     tty: true,
@@ -91,6 +123,7 @@ mod tests {
                     piped: false,
                 },
                 supports_hyperlinks: true,
+                ..Default::default()
             },
             ci: CiTraits {
                 id: Some("github".to_string()),
@@ -99,6 +132,9 @@ mod tests {
                 is_pr: Some(true),
                 branch: Some("main".to_string()),
             },
+            shell: Default::default(),
+            container: Default::default(),
+            extra: Default::default(),
         };
 
         let json = serde_json::to_string(&traits).unwrap();
@@ -246,6 +282,7 @@ mod tests {
         assert_eq!(traits.ci.id, None);
     }
 
+    #[cfg(feature = "schemars")]
     #[test]
     fn nested_traits_json_schema_generation() {
         // Ensure JSON schema can be generated
@@ -271,16 +308,19 @@ mod tests {
         let traits = NestedTraits {
             agent: AgentTraits {
                 id: Some("".to_string()),
+                ..Default::default()
             },
             ide: IdeTraits {
                 id: Some("".to_string()),
+                ..Default::default()
             },
             terminal: TerminalTraits {
                 interactive: false,
                 color_level: ColorLevel::None,
-                stdin: StreamInfo {
+                stdin: StdinInfo {
                     tty: false,
                     piped: true,
+                    has_data: None,
                 },
                 stdout: StreamInfo {
                     tty: false,
@@ -291,6 +331,7 @@ mod tests {
                     piped: true,
                 },
                 supports_hyperlinks: false,
+                ..Default::default()
             },
             ci: CiTraits {
                 id: Some("".to_string()),
@@ -299,6 +340,9 @@ mod tests {
                 is_pr: Some(false),
                 branch: Some("".to_string()),
             },
+            shell: Default::default(),
+            container: Default::default(),
+            extra: Default::default(),
         };
 
         let json = serde_json::to_string(&traits).unwrap();
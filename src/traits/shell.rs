@@ -0,0 +1,32 @@
+use envsense_macros::MergeValueDerive;
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Traits of the shell envsense runs under
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ShellTraits {
+    /// The detected shell (e.g., "bash", "zsh", "pwsh", "powershell", "cmd",
+    /// "git-bash")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_traits_serialization() {
+        let traits = ShellTraits {
+            id: Some("pwsh".to_string()),
+        };
+        let json = serde_json::to_string(&traits).unwrap();
+        assert_eq!(json, r#"{"id":"pwsh"}"#);
+        assert_eq!(
+            serde_json::to_string(&ShellTraits::default()).unwrap(),
+            "{}"
+        );
+    }
+}
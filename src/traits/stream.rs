@@ -1,9 +1,12 @@
+use envsense_macros::MergeValueDerive;
 use is_terminal::IsTerminal;
+#[cfg(feature = "schemars")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Information about a stream (stdin, stdout, stderr)
-#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct StreamInfo {
     /// Whether the stream is connected to a TTY
     pub tty: bool,
@@ -20,6 +23,36 @@ impl Default for StreamInfo {
     }
 }
 
+/// Information about stdin, which unlike the output streams can be checked
+/// for input that is already waiting
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct StdinInfo {
+    /// Whether the stream is connected to a TTY
+    pub tty: bool,
+    /// Whether the stream is piped (not connected to a TTY)
+    pub piped: bool,
+    /// Whether bytes are already waiting to be read; only checked when piped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_data: Option<bool>,
+}
+
+impl Default for StdinInfo {
+    fn default() -> Self {
+        StreamInfo::default().into()
+    }
+}
+
+impl From<StreamInfo> for StdinInfo {
+    fn from(stream: StreamInfo) -> Self {
+        Self {
+            tty: stream.tty,
+            piped: stream.piped,
+            has_data: None,
+        }
+    }
+}
+
 impl StreamInfo {
     /// Create stream info from TTY status
     pub fn from_tty(is_tty: bool) -> Self {
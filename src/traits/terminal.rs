@@ -1,8 +1,9 @@
-use super::stream::StreamInfo;
+use super::stream::{StdinInfo, StreamInfo};
+use super::terminal_db::{emulator, emulator_color, hyperlink_support};
+use envsense_macros::{MergeValue, MergeValueDerive, TraitSchema, ValueKind};
 
-#[derive(
-    Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq,
-)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum ColorLevel {
     None,
@@ -11,25 +12,81 @@ pub enum ColorLevel {
     Truecolor,
 }
 
+impl std::str::FromStr for ColorLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "ansi16" => Ok(Self::Ansi16),
+            "ansi256" => Ok(Self::Ansi256),
+            "truecolor" => Ok(Self::Truecolor),
+            _ => Err(format!(
+                "unknown color level '{}' (available: none, ansi16, ansi256, truecolor)",
+                s
+            )),
+        }
+    }
+}
+
+impl MergeValue for ColorLevel {
+    const SCHEMA: TraitSchema = TraitSchema::leaf(ValueKind::Enum);
+
+    fn merge_value(&mut self, value: &serde_json::Value) -> bool {
+        match value.as_str().map(str::parse) {
+            Some(Ok(level)) => {
+                *self = level;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
 /// Traits specific to terminal capabilities and stream information
-#[derive(
-    Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq,
-)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq, MergeValueDerive)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[detection_merge(alias(
+    is_interactive = "interactive",
+    is_tty_stdin = "stdin.tty",
+    is_tty_stdout = "stdout.tty",
+    is_tty_stderr = "stderr.tty",
+    is_piped_stdin = "stdin.piped",
+    is_piped_stdout = "stdout.piped",
+))]
 pub struct TerminalTraits {
     /// Whether the terminal is interactive (both stdin and stdout are TTYs)
     pub interactive: bool,
     /// The color support level of the terminal
     pub color_level: ColorLevel,
     /// Information about the stdin stream
-    pub stdin: StreamInfo,
+    pub stdin: StdinInfo,
     /// Information about the stdout stream
     pub stdout: StreamInfo,
     /// Information about the stderr stream
     pub stderr: StreamInfo,
     /// Whether the terminal supports hyperlinks
     pub supports_hyperlinks: bool,
+    /// The terminal emulator as it names itself in `TERM_PROGRAM` (e.g., "iTerm.app")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program: Option<String>,
+    /// The emulator's version from `TERM_PROGRAM_VERSION`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_version: Option<String>,
+    /// The terminal type from `TERM` (e.g., "xterm-256color")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub term: Option<String>,
+    /// The terminal emulator envsense recognizes, whether or not it sets
+    /// `TERM_PROGRAM` (e.g., "windows-terminal", "iterm2", "kitty")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emulator: Option<String>,
+    /// The recognized emulator's version, which unlike `program_version`
+    /// survives tmux (e.g., iTerm2's `LC_TERMINAL_VERSION`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emulator_version: Option<String>,
 }
 
+#[cfg(any(test, feature = "terminal-detection"))]
 fn level_from_flags(has_basic: bool, has_256: bool, has_16m: bool) -> ColorLevel {
     if has_16m {
         ColorLevel::Truecolor
@@ -42,11 +99,90 @@ fn level_from_flags(has_basic: bool, has_256: bool, has_16m: bool) -> ColorLevel
     }
 }
 
-fn map_color_level(level: Option<supports_color::ColorLevel>) -> ColorLevel {
-    match level {
-        Some(l) => level_from_flags(l.has_basic, l.has_256, l.has_16m),
-        None => ColorLevel::None,
+/// Color level supported on stdout
+///
+/// Without the `terminal-detection` feature nothing is probed and this is
+/// always [`ColorLevel::None`].
+pub(crate) fn probe_color_level() -> ColorLevel {
+    #[cfg(feature = "terminal-detection")]
+    {
+        match supports_color::on(supports_color::Stream::Stdout) {
+            Some(l) => level_from_flags(l.has_basic, l.has_256, l.has_16m),
+            None => ColorLevel::None,
+        }
     }
+    #[cfg(not(feature = "terminal-detection"))]
+    ColorLevel::None
+}
+
+/// CI services whose log viewers render ANSI color, by the variable that
+/// marks them, with the level they render
+pub(crate) const ANSI_CI_LOGS: &[(&str, ColorLevel)] = &[
+    ("GITHUB_ACTIONS", ColorLevel::Ansi256),
+    ("GITLAB_CI", ColorLevel::Ansi256),
+    ("BUILDKITE", ColorLevel::Ansi256),
+];
+
+/// Color level after the color environment variables, with the variable
+/// and value that decided it
+///
+/// Follows the de-facto precedence: a non-empty `NO_COLOR` disables color,
+/// then `FORCE_COLOR` (`0`/`false` disable, `2` and `3` ask for 256 colors
+/// and truecolor) or `CLICOLOR_FORCE` raise `probe`'s level to at least
+/// ANSI 16, then `CLICOLOR=0` disables color. CI services whose log viewer
+/// renders ANSI color ([`ANSI_CI_LOGS`]) come next, although their output
+/// is not a TTY. Otherwise `probe`, the TTY and terminfo check, decides,
+/// corrected to what a known emulator renders when it found a terminal at
+/// all: Windows Terminal renders truecolor without saying so, Terminal.app
+/// before macOS 26 at most 256 colors whatever `TERM` says.
+pub(crate) fn resolve_color_level(
+    var: impl Fn(&str) -> Option<String>,
+    probe: impl FnOnce() -> ColorLevel,
+) -> (ColorLevel, Option<(&'static str, String)>) {
+    if let Some(value) = var("NO_COLOR").filter(|v| !v.is_empty()) {
+        return (ColorLevel::None, Some(("NO_COLOR", value)));
+    }
+    if let Some(value) = var("FORCE_COLOR") {
+        let level = match value.as_str() {
+            "0" | "false" => ColorLevel::None,
+            "2" => probe().max(ColorLevel::Ansi256),
+            "3" => ColorLevel::Truecolor,
+            _ => probe().max(ColorLevel::Ansi16),
+        };
+        return (level, Some(("FORCE_COLOR", value)));
+    }
+    if let Some(value) = var("CLICOLOR_FORCE").filter(|v| !v.is_empty() && v != "0") {
+        return (
+            probe().max(ColorLevel::Ansi16),
+            Some(("CLICOLOR_FORCE", value)),
+        );
+    }
+    if let Some(value) = var("CLICOLOR").filter(|v| v == "0") {
+        return (ColorLevel::None, Some(("CLICOLOR", value)));
+    }
+    for (key, level) in ANSI_CI_LOGS {
+        if let Some(value) = var(key).filter(|v| v == "true") {
+            return (probe().max(level.clone()), Some((key, value)));
+        }
+    }
+    let level = probe();
+    if level != ColorLevel::None
+        && let Some((level, decided_by)) = emulator_color(&var, &level)
+    {
+        return (level, Some(decided_by));
+    }
+    (level, None)
+}
+
+/// Whether stdout supports OSC 8 hyperlinks, going by the terminal
+/// capability database
+pub(crate) fn probe_hyperlinks() -> bool {
+    use std::io::IsTerminal;
+    hyperlink_support(
+        |key| std::env::var(key).ok(),
+        std::io::stdout().is_terminal(),
+    )
+    .0
 }
 
 impl Default for TerminalTraits {
@@ -54,10 +190,15 @@ impl Default for TerminalTraits {
         Self {
             interactive: false,
             color_level: ColorLevel::None,
-            stdin: StreamInfo::default(),
+            stdin: StdinInfo::default(),
             stdout: StreamInfo::default(),
             stderr: StreamInfo::default(),
             supports_hyperlinks: false,
+            program: None,
+            program_version: None,
+            term: None,
+            emulator: None,
+            emulator_version: None,
         }
     }
 }
@@ -65,12 +206,15 @@ impl Default for TerminalTraits {
 impl TerminalTraits {
     /// Detect terminal traits from the current environment
     pub fn detect() -> Self {
-        let stdin = StreamInfo::stdin();
+        let stdin = StdinInfo::from(StreamInfo::stdin());
         let stdout = StreamInfo::stdout();
         let stderr = StreamInfo::stderr();
         let interactive = stdin.tty && stdout.tty;
-        let color_level = map_color_level(supports_color::on(supports_color::Stream::Stdout));
-        let supports_hyperlinks = supports_hyperlinks::on(supports_hyperlinks::Stream::Stdout);
+        let (color_level, _) =
+            resolve_color_level(|key| std::env::var(key).ok(), probe_color_level);
+        let supports_hyperlinks = probe_hyperlinks();
+        let var = |key| std::env::var(key).ok().filter(|v| !v.is_empty());
+        let emulator = emulator(|key| std::env::var(key).ok());
 
         Self {
             interactive,
@@ -79,6 +223,11 @@ impl TerminalTraits {
             stdout,
             stderr,
             supports_hyperlinks,
+            program: var("TERM_PROGRAM"),
+            program_version: var("TERM_PROGRAM_VERSION"),
+            term: var("TERM"),
+            emulator: emulator.as_ref().map(|e| e.id.to_string()),
+            emulator_version: emulator.and_then(|e| e.version).map(|(_, version)| version),
         }
     }
 
@@ -126,6 +275,113 @@ mod tests {
         assert_eq!(level_from_flags(false, false, false), ColorLevel::None);
     }
 
+    #[test]
+    fn color_variables_take_precedence_over_probe() {
+        let resolve = |vars: &[(&str, &str)], probed| {
+            let var = |key: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            };
+            let (level, decided_by) = resolve_color_level(var, || probed);
+            (level, decided_by.map(|(key, _)| key))
+        };
+
+        assert_eq!(
+            resolve(&[], ColorLevel::Ansi256),
+            (ColorLevel::Ansi256, None)
+        );
+        assert_eq!(
+            resolve(
+                &[("NO_COLOR", "1"), ("FORCE_COLOR", "3")],
+                ColorLevel::Truecolor
+            ),
+            (ColorLevel::None, Some("NO_COLOR"))
+        );
+        // An empty NO_COLOR is ignored
+        assert_eq!(
+            resolve(&[("NO_COLOR", "")], ColorLevel::Ansi16),
+            (ColorLevel::Ansi16, None)
+        );
+        assert_eq!(
+            resolve(&[("FORCE_COLOR", "1")], ColorLevel::None),
+            (ColorLevel::Ansi16, Some("FORCE_COLOR"))
+        );
+        assert_eq!(
+            resolve(&[("FORCE_COLOR", "")], ColorLevel::Truecolor),
+            (ColorLevel::Truecolor, Some("FORCE_COLOR"))
+        );
+        assert_eq!(
+            resolve(&[("FORCE_COLOR", "2")], ColorLevel::None),
+            (ColorLevel::Ansi256, Some("FORCE_COLOR"))
+        );
+        assert_eq!(
+            resolve(
+                &[("FORCE_COLOR", "false"), ("CLICOLOR_FORCE", "1")],
+                ColorLevel::Ansi256
+            ),
+            (ColorLevel::None, Some("FORCE_COLOR"))
+        );
+        assert_eq!(
+            resolve(
+                &[("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0")],
+                ColorLevel::None
+            ),
+            (ColorLevel::Ansi16, Some("CLICOLOR_FORCE"))
+        );
+        assert_eq!(
+            resolve(
+                &[("CLICOLOR_FORCE", "0"), ("CLICOLOR", "0")],
+                ColorLevel::Ansi256
+            ),
+            (ColorLevel::None, Some("CLICOLOR"))
+        );
+        assert_eq!(
+            resolve(&[("GITHUB_ACTIONS", "true")], ColorLevel::None),
+            (ColorLevel::Ansi256, Some("GITHUB_ACTIONS"))
+        );
+        assert_eq!(
+            resolve(
+                &[("GITLAB_CI", "true"), ("NO_COLOR", "1")],
+                ColorLevel::None
+            ),
+            (ColorLevel::None, Some("NO_COLOR"))
+        );
+        // CLICOLOR=1 only allows color on a terminal, which the probe checks
+        assert_eq!(
+            resolve(&[("CLICOLOR", "1")], ColorLevel::None),
+            (ColorLevel::None, None)
+        );
+        // Windows Terminal renders truecolor where the probe guesses ANSI 16,
+        // but only on a terminal
+        assert_eq!(
+            resolve(&[("WT_SESSION", "abc")], ColorLevel::Ansi16),
+            (ColorLevel::Truecolor, Some("WT_SESSION"))
+        );
+        assert_eq!(
+            resolve(&[("WT_SESSION", "abc")], ColorLevel::None),
+            (ColorLevel::None, None)
+        );
+        // Terminal.app before macOS 26 stops at 256 colors unless truecolor
+        // is forced
+        let apple_terminal = [
+            ("TERM_PROGRAM", "Apple_Terminal"),
+            ("TERM_PROGRAM_VERSION", "455.1"),
+            ("COLORTERM", "truecolor"),
+        ];
+        assert_eq!(
+            resolve(&apple_terminal, ColorLevel::Truecolor),
+            (ColorLevel::Ansi256, Some("TERM_PROGRAM"))
+        );
+        assert_eq!(
+            resolve(
+                &[("TERM_PROGRAM", "Apple_Terminal"), ("FORCE_COLOR", "3")],
+                ColorLevel::Ansi256
+            ),
+            (ColorLevel::Truecolor, Some("FORCE_COLOR"))
+        );
+    }
+
     #[test]
     fn default_terminal_traits() {
         let traits = TerminalTraits::default();
@@ -145,9 +401,10 @@ mod tests {
         let traits = TerminalTraits {
             interactive: true,
             color_level: ColorLevel::Truecolor,
-            stdin: StreamInfo {
+            stdin: StdinInfo {
                 tty: true,
                 piped: false,
+                has_data: None,
             },
             stdout: StreamInfo {
                 tty: true,
@@ -158,6 +415,11 @@ mod tests {
                 piped: false,
             },
             supports_hyperlinks: true,
+            program: Some("WezTerm".to_string()),
+            program_version: Some("20240203".to_string()),
+            term: None,
+            emulator: Some("wezterm".to_string()),
+            emulator_version: None,
         };
         let json = serde_json::to_string(&traits).unwrap();
         assert!(json.contains("\"interactive\":true"));
@@ -166,6 +428,9 @@ mod tests {
         assert!(json.contains("\"stdout\":{\"tty\":true,\"piped\":false}"));
         assert!(json.contains("\"stderr\":{\"tty\":true,\"piped\":false}"));
         assert!(json.contains("\"supports_hyperlinks\":true"));
+        assert!(json.contains("\"program\":\"WezTerm\",\"program_version\":\"20240203\""));
+        assert!(json.contains("\"emulator\":\"wezterm\""));
+        assert!(!json.contains("\"term\""));
     }
 
     #[test]
@@ -173,9 +438,10 @@ mod tests {
         let traits = TerminalTraits {
             interactive: true,
             color_level: ColorLevel::Ansi256,
-            stdin: StreamInfo {
+            stdin: StdinInfo {
                 tty: true,
                 piped: false,
+                has_data: None,
             },
             stdout: StreamInfo {
                 tty: true,
@@ -186,6 +452,7 @@ mod tests {
                 piped: true,
             },
             supports_hyperlinks: false,
+            ..Default::default()
         };
 
         assert!(traits.interactive);
@@ -0,0 +1,480 @@
+//! Terminal emulators and multiplexers envsense knows capabilities for.
+//!
+//! Emulators are recognized by the variables they set, such as
+//! `TERM_PROGRAM=iTerm.app`, `VTE_VERSION` or Windows Terminal's
+//! `WT_SESSION`, and support a capability from a minimum version on.
+//! Multiplexers sit between the program and the emulator and have to pass a
+//! capability through as well: tmux only forwards OSC 8 hyperlinks from 3.4
+//! on, and GNU screen never does.
+
+use super::terminal::ColorLevel;
+use crate::log_event;
+
+/// Which versions of a terminal support a capability
+#[derive(Clone, Copy)]
+enum Since {
+    Always,
+    /// This version and later, compared as dotted numbers
+    Version(&'static str),
+    Never,
+}
+
+/// A terminal emulator or multiplexer and what it supports
+struct Terminal {
+    /// Identifier reported as `terminal.emulator`
+    id: &'static str,
+    /// Variable that identifies the terminal
+    key: &'static str,
+    /// Value `key` must have; any value when `None`
+    value: Option<&'static str>,
+    /// Variable holding the terminal's version
+    version_key: Option<&'static str>,
+    /// Versions with OSC 8 hyperlinks
+    hyperlinks: Since,
+    /// Color level the terminal renders at least, for terminals the color
+    /// probe underestimates
+    min_color: Option<ColorLevel>,
+    /// Color level the terminal renders at most, for terminals whose `TERM`
+    /// or `COLORTERM` promise more, and the versions that lift the cap
+    max_color: Option<(ColorLevel, Since)>,
+}
+
+/// A terminal without hyperlinks or a known color level
+const fn terminal(id: &'static str, key: &'static str, value: Option<&'static str>) -> Terminal {
+    Terminal {
+        id,
+        key,
+        value,
+        version_key: None,
+        hyperlinks: Since::Never,
+        min_color: None,
+        max_color: None,
+    }
+}
+
+impl Terminal {
+    const fn version_from(self, key: &'static str) -> Self {
+        Self {
+            version_key: Some(key),
+            ..self
+        }
+    }
+
+    const fn hyperlinks(self, since: Since) -> Self {
+        Self {
+            hyperlinks: since,
+            ..self
+        }
+    }
+
+    const fn colors_at_least(self, level: ColorLevel) -> Self {
+        Self {
+            min_color: Some(level),
+            ..self
+        }
+    }
+
+    const fn colors_at_most(self, level: ColorLevel, until: Since) -> Self {
+        Self {
+            max_color: Some((level, until)),
+            ..self
+        }
+    }
+}
+
+/// Multiplexers, checked before emulators: inside one, `TERM_PROGRAM`
+/// names the multiplexer and only variables the emulator exported before it
+/// started identify the emulator
+const MULTIPLEXERS: &[Terminal] = &[
+    terminal("tmux", "TERM_PROGRAM", Some("tmux"))
+        .version_from("TERM_PROGRAM_VERSION")
+        .hyperlinks(Since::Version("3.4")),
+    // tmux before 3.2 does not set TERM_PROGRAM, so it is too old
+    terminal("tmux", "TMUX", None),
+    terminal("screen", "STY", None),
+];
+
+/// Emulators, most specific marker first
+const EMULATORS: &[Terminal] = &[
+    terminal("kitty", "TERM", Some("xterm-kitty")).hyperlinks(Since::Always),
+    terminal("kitty", "KITTY_WINDOW_ID", None).hyperlinks(Since::Always),
+    terminal("iterm2", "TERM_PROGRAM", Some("iTerm.app"))
+        .version_from("TERM_PROGRAM_VERSION")
+        .hyperlinks(Since::Version("3.1"))
+        .colors_at_least(ColorLevel::Truecolor),
+    // Inside tmux iTerm2 is only known by the variables it exports itself
+    terminal("iterm2", "LC_TERMINAL", Some("iTerm2"))
+        .version_from("LC_TERMINAL_VERSION")
+        .hyperlinks(Since::Version("3.1"))
+        .colors_at_least(ColorLevel::Truecolor),
+    terminal("iterm2", "ITERM_SESSION_ID", None)
+        .version_from("LC_TERMINAL_VERSION")
+        .hyperlinks(Since::Version("3.1"))
+        .colors_at_least(ColorLevel::Truecolor),
+    // Terminal.app renders no hyperlinks, and only 256 colors whatever TERM
+    // says before the truecolor support of macOS 26 (Terminal 460; macOS 15
+    // ships 455)
+    terminal("apple-terminal", "TERM_PROGRAM", Some("Apple_Terminal"))
+        .version_from("TERM_PROGRAM_VERSION")
+        .colors_at_most(ColorLevel::Ansi256, Since::Version("460")),
+    terminal("wezterm", "TERM_PROGRAM", Some("WezTerm"))
+        .version_from("TERM_PROGRAM_VERSION")
+        .hyperlinks(Since::Always),
+    terminal("wezterm", "WEZTERM_EXECUTABLE", None).hyperlinks(Since::Always),
+    terminal("ghostty", "TERM_PROGRAM", Some("ghostty"))
+        .version_from("TERM_PROGRAM_VERSION")
+        .hyperlinks(Since::Always),
+    terminal("vscode", "TERM_PROGRAM", Some("vscode"))
+        .version_from("TERM_PROGRAM_VERSION")
+        .hyperlinks(Since::Version("1.72")),
+    terminal("hyper", "TERM_PROGRAM", Some("Hyper"))
+        .version_from("TERM_PROGRAM_VERSION")
+        .hyperlinks(Since::Always),
+    terminal("terminology", "TERM_PROGRAM", Some("terminology")).hyperlinks(Since::Always),
+    // Windows Terminal renders truecolor through ConPTY, where the probe
+    // finds no TERM and assumes ANSI 16
+    terminal("windows-terminal", "WT_SESSION", None)
+        .hyperlinks(Since::Always)
+        .colors_at_least(ColorLevel::Truecolor),
+    terminal("conemu", "ConEmuANSI", Some("ON")).colors_at_least(ColorLevel::Ansi256),
+    terminal("ansicon", "ANSICON", None).colors_at_least(ColorLevel::Ansi16),
+    // VTE_VERSION is 100 * minor + micro, so 5000 is VTE 0.50
+    terminal("vte", "VTE_VERSION", None)
+        .version_from("VTE_VERSION")
+        .hyperlinks(Since::Version("5000")),
+    terminal("konsole", "KONSOLE_VERSION", None)
+        .version_from("KONSOLE_VERSION")
+        .hyperlinks(Since::Always),
+    terminal("domterm", "DOMTERM", None).hyperlinks(Since::Always),
+    terminal("alacritty", "TERM", Some("alacritty")).hyperlinks(Since::Always),
+    terminal("alacritty", "TERM", Some("alacritty-direct")).hyperlinks(Since::Always),
+    terminal("xfce4-terminal", "COLORTERM", Some("xfce4-terminal")).hyperlinks(Since::Always),
+];
+
+impl Terminal {
+    /// The marker variable and its value when the environment matches
+    fn matches(&self, var: &impl Fn(&str) -> Option<String>) -> Option<String> {
+        var(self.key).filter(|v| self.value.is_none_or(|expected| v == expected))
+    }
+
+    /// Whether the version in the environment is one of `since`; an unknown
+    /// version is assumed to be recent
+    fn is_version(&self, since: Since, var: &impl Fn(&str) -> Option<String>) -> bool {
+        match since {
+            Since::Always => true,
+            Since::Never => false,
+            Since::Version(since) => self
+                .version_key
+                .and_then(var)
+                .is_none_or(|version| version_at_least(&version, since)),
+        }
+    }
+
+    fn has_hyperlinks(&self, var: &impl Fn(&str) -> Option<String>) -> bool {
+        self.is_version(self.hyperlinks, var)
+    }
+}
+
+/// Whether a terminal at stdout renders OSC 8 hyperlinks, with the variable
+/// and value that decided it
+///
+/// `FORCE_HYPERLINK` decides first (`0` disables), then stdout has to be a
+/// TTY. A multiplexer that cannot pass hyperlinks through disables them;
+/// otherwise the first known emulator decides. Unknown emulators have no
+/// hyperlinks and no deciding variable.
+pub(crate) fn hyperlink_support(
+    var: impl Fn(&str) -> Option<String>,
+    stdout_tty: bool,
+) -> (bool, Option<(&'static str, String)>) {
+    if let Some(value) = var("FORCE_HYPERLINK") {
+        return (value.trim() != "0", Some(("FORCE_HYPERLINK", value)));
+    }
+    if !stdout_tty {
+        return (false, None);
+    }
+    for multiplexer in MULTIPLEXERS {
+        if let Some(value) = multiplexer.matches(&var) {
+            let supported = multiplexer.has_hyperlinks(&var);
+            log_event!(
+                Debug,
+                "hyperlinks.terminal",
+                terminal = multiplexer.id,
+                supported = supported
+            );
+            if !supported {
+                return (false, Some((multiplexer.key, value)));
+            }
+            break;
+        }
+    }
+    let Some((emulator, value)) = find_emulator(&var) else {
+        return (false, None);
+    };
+    let supported = emulator.has_hyperlinks(&var);
+    log_event!(
+        Debug,
+        "hyperlinks.terminal",
+        terminal = emulator.id,
+        supported = supported
+    );
+    (supported, Some((emulator.key, value)))
+}
+
+/// An emulator recognized from the environment
+pub(crate) struct Emulator {
+    /// Identifier such as `iterm2` or `windows-terminal`
+    pub id: &'static str,
+    /// The variable holding the emulator's version and its value
+    pub version: Option<(&'static str, String)>,
+    /// The variable and value that identify the emulator
+    pub marker: (&'static str, String),
+}
+
+/// The emulator the environment names
+///
+/// Multiplexers are skipped: inside tmux this is the emulator tmux runs in,
+/// if it exported a marker.
+pub(crate) fn emulator(var: impl Fn(&str) -> Option<String>) -> Option<Emulator> {
+    let (emulator, value) = find_emulator(&var)?;
+    Some(Emulator {
+        id: emulator.id,
+        version: emulator
+            .version_key
+            .and_then(|key| Some((key, var(key).filter(|v| !v.is_empty())?))),
+        marker: (emulator.key, value),
+    })
+}
+
+/// `probed` corrected to what the emulator renders, with the variable and
+/// value that identify the emulator; `None` when the emulator leaves the
+/// level as it is
+pub(crate) fn emulator_color(
+    var: impl Fn(&str) -> Option<String>,
+    probed: &ColorLevel,
+) -> Option<(ColorLevel, (&'static str, String))> {
+    let (emulator, value) = find_emulator(&var)?;
+    let mut level = probed.clone();
+    if let Some(min) = &emulator.min_color {
+        level = level.max(min.clone());
+    }
+    if let Some((max, until)) = &emulator.max_color
+        && !emulator.is_version(*until, &var)
+    {
+        level = level.min(max.clone());
+    }
+    (level != *probed).then_some((level, (emulator.key, value)))
+}
+
+fn find_emulator(var: &impl Fn(&str) -> Option<String>) -> Option<(&'static Terminal, String)> {
+    EMULATORS
+        .iter()
+        .find_map(|emulator| Some((emulator, emulator.matches(var)?)))
+}
+
+/// Compare dotted versions number by number, ignoring suffixes such as
+/// `-insider`; `3.4a` counts as `3.4`
+fn version_at_least(version: &str, minimum: &str) -> bool {
+    let numbers = |v: &str| -> Vec<u64> {
+        v.split(['.', '-', '+'])
+            .map_while(|part| {
+                let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+                digits.parse().ok()
+            })
+            .collect()
+    };
+    numbers(version) >= numbers(minimum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn support(vars: &[(&str, &str)], tty: bool) -> (bool, Option<&'static str>) {
+        let var = |key: &str| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        };
+        let (supported, decided_by) = hyperlink_support(var, tty);
+        (supported, decided_by.map(|(key, _)| key))
+    }
+
+    #[test]
+    fn known_emulators_by_version() {
+        assert_eq!(
+            support(&[("TERM", "xterm-kitty")], true),
+            (true, Some("TERM"))
+        );
+        assert_eq!(
+            support(
+                &[
+                    ("TERM_PROGRAM", "iTerm.app"),
+                    ("TERM_PROGRAM_VERSION", "3.0.15")
+                ],
+                true
+            ),
+            (false, Some("TERM_PROGRAM"))
+        );
+        assert_eq!(
+            support(
+                &[
+                    ("TERM_PROGRAM", "iTerm.app"),
+                    ("TERM_PROGRAM_VERSION", "3.5.0")
+                ],
+                true
+            ),
+            (true, Some("TERM_PROGRAM"))
+        );
+        assert_eq!(
+            support(&[("VTE_VERSION", "4803")], true),
+            (false, Some("VTE_VERSION"))
+        );
+        assert_eq!(
+            support(&[("VTE_VERSION", "7600")], true),
+            (true, Some("VTE_VERSION"))
+        );
+        assert_eq!(
+            support(&[("WT_SESSION", "abc")], true),
+            (true, Some("WT_SESSION"))
+        );
+        assert_eq!(
+            support(&[("TERM_PROGRAM", "Apple_Terminal")], true),
+            (false, Some("TERM_PROGRAM"))
+        );
+        assert_eq!(support(&[("TERM", "xterm")], true), (false, None));
+        // Piped output never renders hyperlinks
+        assert_eq!(support(&[("TERM", "xterm-kitty")], false), (false, None));
+    }
+
+    #[test]
+    fn multiplexers_must_pass_hyperlinks_through() {
+        let old_tmux = [
+            ("TERM_PROGRAM", "tmux"),
+            ("TERM_PROGRAM_VERSION", "3.3a"),
+            ("TMUX", "/tmp/tmux-1000/default,1,0"),
+            ("WT_SESSION", "abc"),
+        ];
+        assert_eq!(support(&old_tmux, true), (false, Some("TERM_PROGRAM")));
+
+        let new_tmux = [
+            ("TERM_PROGRAM", "tmux"),
+            ("TERM_PROGRAM_VERSION", "3.4"),
+            ("KITTY_WINDOW_ID", "1"),
+        ];
+        assert_eq!(support(&new_tmux, true), (true, Some("KITTY_WINDOW_ID")));
+
+        let screen = [("STY", "1234.pts-0.host"), ("VTE_VERSION", "7600")];
+        assert_eq!(support(&screen, true), (false, Some("STY")));
+    }
+
+    #[test]
+    fn force_hyperlink_wins() {
+        assert_eq!(
+            support(&[("FORCE_HYPERLINK", "1")], false),
+            (true, Some("FORCE_HYPERLINK"))
+        );
+        assert_eq!(
+            support(&[("FORCE_HYPERLINK", "0"), ("TERM", "xterm-kitty")], true),
+            (false, Some("FORCE_HYPERLINK"))
+        );
+    }
+
+    #[test]
+    fn names_the_emulator_under_a_multiplexer() {
+        let var = |vars: &'static [(&str, &str)]| {
+            move |key: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        let id = |vars| emulator(var(vars)).map(|emulator| emulator.id);
+
+        assert_eq!(id(&[("WT_SESSION", "abc")]), Some("windows-terminal"));
+        assert_eq!(
+            id(&[("TERM_PROGRAM", "tmux"), ("KITTY_WINDOW_ID", "1")]),
+            Some("kitty")
+        );
+        assert_eq!(id(&[("TERM_PROGRAM", "tmux")]), None);
+
+        let iterm = emulator(var(&[
+            ("TERM_PROGRAM", "tmux"),
+            ("TERM_PROGRAM_VERSION", "3.4"),
+            ("ITERM_SESSION_ID", "w0t0p0:1234"),
+            ("LC_TERMINAL_VERSION", "3.5.2"),
+        ]))
+        .unwrap();
+        assert_eq!(iterm.id, "iterm2");
+        assert_eq!(
+            iterm.version,
+            Some(("LC_TERMINAL_VERSION", "3.5.2".to_string()))
+        );
+    }
+
+    #[test]
+    fn emulators_correct_the_probed_color_level() {
+        let var = |vars: &'static [(&str, &str)]| {
+            move |key: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        let level = |vars, probed| emulator_color(var(vars), &probed).map(|(level, _)| level);
+
+        assert_eq!(
+            level(&[("ANSICON", "80x300 (80x25)")], ColorLevel::None),
+            Some(ColorLevel::Ansi16)
+        );
+        // Terminal.app before macOS 26 lacks truecolor even when COLORTERM
+        // claims it
+        assert_eq!(
+            level(
+                &[
+                    ("TERM_PROGRAM", "Apple_Terminal"),
+                    ("TERM_PROGRAM_VERSION", "455.1")
+                ],
+                ColorLevel::Truecolor
+            ),
+            Some(ColorLevel::Ansi256)
+        );
+        assert_eq!(
+            level(
+                &[
+                    ("TERM_PROGRAM", "Apple_Terminal"),
+                    ("TERM_PROGRAM_VERSION", "455.1")
+                ],
+                ColorLevel::Ansi256
+            ),
+            None
+        );
+        assert_eq!(
+            level(
+                &[
+                    ("TERM_PROGRAM", "Apple_Terminal"),
+                    ("TERM_PROGRAM_VERSION", "465")
+                ],
+                ColorLevel::Truecolor
+            ),
+            None
+        );
+        // Without a version it is assumed to be recent
+        assert_eq!(
+            level(&[("TERM_PROGRAM", "Apple_Terminal")], ColorLevel::Truecolor),
+            None
+        );
+        assert_eq!(
+            level(&[("TERM", "xterm-kitty")], ColorLevel::Truecolor),
+            None
+        );
+    }
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert!(version_at_least("1.85.0", "1.72"));
+        assert!(version_at_least("1.86.0-insider", "1.72"));
+        assert!(!version_at_least("1.9.0", "1.72"));
+        assert!(version_at_least("3.4", "3.4"));
+        assert!(!version_at_least("3.3a", "3.4"));
+    }
+}
@@ -0,0 +1,395 @@
+//! Interactive browser for `envsense tui`.
+//!
+//! Shows contexts, traits and evidence as a collapsible tree with a detail
+//! pane that displays the `check` predicate for the selected field, and
+//! re-runs detection on an interval so changes show up live.
+
+use crate::schema::EnvSense;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// A node in the browsable tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeNode {
+    /// Unique id used to track expansion state across refreshes
+    pub id: String,
+    pub label: String,
+    pub value: Option<String>,
+    /// Predicate that can be passed to `envsense check`, if any
+    pub predicate: Option<String>,
+    pub children: Vec<TreeNode>,
+}
+
+/// A tree node flattened for display
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row<'a> {
+    pub depth: usize,
+    pub node: &'a TreeNode,
+}
+
+/// Build the tree shown by the browser
+pub fn build_tree(env: &EnvSense) -> Vec<TreeNode> {
+    let mut contexts = env.contexts.clone();
+    contexts.sort();
+    let contexts_node = TreeNode {
+        id: "contexts".to_string(),
+        label: "contexts".to_string(),
+        value: None,
+        predicate: None,
+        children: contexts
+            .iter()
+            .map(|c| TreeNode {
+                id: format!("contexts.{}", c),
+                label: c.clone(),
+                value: None,
+                predicate: Some(c.clone()),
+                children: Vec::new(),
+            })
+            .collect(),
+    };
+
+    let traits = serde_json::to_value(&env.traits).unwrap_or(Value::Null);
+    let traits_node = TreeNode {
+        id: "traits".to_string(),
+        label: "traits".to_string(),
+        value: None,
+        predicate: None,
+        children: value_children(&traits, ""),
+    };
+
+    let evidence_node = TreeNode {
+        id: "evidence".to_string(),
+        label: "evidence".to_string(),
+        value: None,
+        predicate: None,
+        children: env
+            .evidence
+            .iter()
+            .enumerate()
+            .map(|(i, e)| TreeNode {
+                id: format!("evidence.{}", i),
+                label: e.key.clone(),
+                value: e.value.clone(),
+                predicate: None,
+                children: vec![
+                    leaf(
+                        format!("evidence.{}.signal", i),
+                        "signal",
+                        format!("{:?}", e.signal).to_lowercase(),
+                    ),
+                    leaf(
+                        format!("evidence.{}.supports", i),
+                        "supports",
+                        e.supports.join(", "),
+                    ),
+                    leaf(
+                        format!("evidence.{}.confidence", i),
+                        "confidence",
+                        format!("{:.2}", e.confidence),
+                    ),
+                ],
+            })
+            .collect(),
+    };
+
+    vec![contexts_node, traits_node, evidence_node]
+}
+
+fn leaf(id: String, label: &str, value: String) -> TreeNode {
+    TreeNode {
+        id,
+        label: label.to_string(),
+        value: Some(value),
+        predicate: None,
+        children: Vec::new(),
+    }
+}
+
+fn value_children(value: &Value, prefix: &str) -> Vec<TreeNode> {
+    let Value::Object(map) = value else {
+        return Vec::new();
+    };
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            let child = &map[key];
+            let (value, children) = match child {
+                Value::Object(_) => (None, value_children(child, &path)),
+                Value::Null => (Some("null".to_string()), Vec::new()),
+                Value::String(s) => (Some(s.clone()), Vec::new()),
+                other => (Some(other.to_string()), Vec::new()),
+            };
+            TreeNode {
+                id: format!("traits.{}", path),
+                label: key.clone(),
+                value,
+                predicate: Some(path),
+                children,
+            }
+        })
+        .collect()
+}
+
+/// Flatten the tree into the rows currently visible given the expanded set
+pub fn visible_rows<'a>(nodes: &'a [TreeNode], expanded: &HashSet<String>) -> Vec<Row<'a>> {
+    fn walk<'a>(
+        nodes: &'a [TreeNode],
+        depth: usize,
+        expanded: &HashSet<String>,
+        out: &mut Vec<Row<'a>>,
+    ) {
+        for node in nodes {
+            out.push(Row { depth, node });
+            if expanded.contains(&node.id) {
+                walk(&node.children, depth + 1, expanded, out);
+            }
+        }
+    }
+
+    let mut rows = Vec::new();
+    walk(nodes, 0, expanded, &mut rows);
+    rows
+}
+
+struct App {
+    detect: Box<dyn Fn() -> EnvSense>,
+    tree: Vec<TreeNode>,
+    expanded: HashSet<String>,
+    list_state: ListState,
+    last_refresh: Instant,
+}
+
+impl App {
+    fn new(detect: Box<dyn Fn() -> EnvSense>) -> Self {
+        let tree = build_tree(&detect());
+        let expanded = ["contexts", "traits"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        Self {
+            detect,
+            tree,
+            expanded,
+            list_state: ListState::default().with_selected(Some(0)),
+            last_refresh: Instant::now(),
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.tree = build_tree(&(self.detect)());
+        self.last_refresh = Instant::now();
+        let len = visible_rows(&self.tree, &self.expanded).len();
+        if self.list_state.selected().is_some_and(|i| i >= len) {
+            self.list_state.select(Some(len.saturating_sub(1)));
+        }
+    }
+
+    fn selected_node(&self) -> Option<&TreeNode> {
+        let rows = visible_rows(&self.tree, &self.expanded);
+        self.list_state
+            .selected()
+            .and_then(|i| rows.get(i).map(|r| r.node))
+    }
+
+    fn set_expanded(&mut self, expand: bool) {
+        if let Some(id) = self
+            .selected_node()
+            .filter(|n| !n.children.is_empty())
+            .map(|n| n.id.clone())
+        {
+            if expand {
+                self.expanded.insert(id);
+            } else {
+                self.expanded.remove(&id);
+            }
+        }
+    }
+
+    fn toggle(&mut self) {
+        if let Some(id) = self.selected_node().map(|n| n.id.clone()) {
+            let expand = !self.expanded.contains(&id);
+            self.set_expanded(expand);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [tree_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main);
+
+        let items: Vec<ListItem> = visible_rows(&self.tree, &self.expanded)
+            .into_iter()
+            .map(|row| {
+                let marker = if row.node.children.is_empty() {
+                    "  "
+                } else if self.expanded.contains(&row.node.id) {
+                    "▾ "
+                } else {
+                    "▸ "
+                };
+                let mut spans = vec![
+                    Span::raw("  ".repeat(row.depth)),
+                    Span::raw(marker),
+                    Span::raw(row.node.label.clone()).bold(),
+                ];
+                if let Some(value) = &row.node.value {
+                    spans.push(Span::raw(" = "));
+                    spans.push(Span::raw(value.clone()).cyan());
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::bordered().title(" envsense "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, tree_area, &mut self.list_state);
+
+        let detail = match self.selected_node() {
+            Some(node) => {
+                let mut lines = vec![Line::from(vec![
+                    Span::raw("name: ").bold(),
+                    Span::raw(node.label.clone()),
+                ])];
+                if let Some(value) = &node.value {
+                    lines.push(Line::from(vec![
+                        Span::raw("value: ").bold(),
+                        Span::raw(value.clone()),
+                    ]));
+                }
+                if let Some(predicate) = &node.predicate {
+                    lines.push(Line::raw(""));
+                    lines.push(Line::raw("check with:").bold());
+                    lines.push(Line::raw(format!("  envsense check {}", predicate)).green());
+                }
+                lines
+            }
+            None => Vec::new(),
+        };
+        frame.render_widget(
+            Paragraph::new(detail)
+                .block(Block::bordered().title(" details "))
+                .wrap(Wrap { trim: false }),
+            detail_area,
+        );
+
+        frame.render_widget(
+            Paragraph::new("↑/↓ move  →/enter expand  ← collapse  r refresh  q quit".dim()),
+            help,
+        );
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal, interval: Duration) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let timeout = interval.saturating_sub(self.last_refresh.elapsed());
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()?
+                    && key.kind == KeyEventKind::Press
+                {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Down | KeyCode::Char('j') => self.list_state.select_next(),
+                        KeyCode::Up | KeyCode::Char('k') => self.list_state.select_previous(),
+                        KeyCode::Right | KeyCode::Char('l') => self.set_expanded(true),
+                        KeyCode::Left | KeyCode::Char('h') => self.set_expanded(false),
+                        KeyCode::Enter | KeyCode::Char(' ') => self.toggle(),
+                        KeyCode::Char('r') => self.refresh(),
+                        _ => {}
+                    }
+                }
+            } else {
+                self.refresh();
+            }
+        }
+    }
+}
+
+/// Run the interactive browser until the user quits
+///
+/// `detect` is called on startup and again every `interval`.
+pub fn run(detect: Box<dyn Fn() -> EnvSense>, interval: Duration) -> std::io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::new(detect).run(&mut terminal, interval);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Evidence;
+
+    fn sample_env() -> EnvSense {
+        let mut env = EnvSense {
+            contexts: vec!["ide".to_string(), "agent".to_string()],
+            ..Default::default()
+        };
+        env.traits.agent.id = Some("cursor".to_string());
+        env.evidence = vec![Evidence::env_var("CURSOR_AGENT", "1")];
+        env
+    }
+
+    #[test]
+    fn builds_predicates_for_fields() {
+        let tree = build_tree(&sample_env());
+        let labels: Vec<_> = tree.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(labels, ["contexts", "traits", "evidence"]);
+
+        let contexts: Vec<_> = tree[0].children.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(contexts, ["agent", "ide"]);
+
+        let agent = tree[1]
+            .children
+            .iter()
+            .find(|n| n.label == "agent")
+            .unwrap();
+        assert_eq!(agent.predicate.as_deref(), Some("agent"));
+        assert_eq!(agent.children[0].predicate.as_deref(), Some("agent.id"));
+        assert_eq!(agent.children[0].value.as_deref(), Some("cursor"));
+
+        let terminal = tree[1]
+            .children
+            .iter()
+            .find(|n| n.label == "terminal")
+            .unwrap();
+        let stdout = terminal
+            .children
+            .iter()
+            .find(|n| n.label == "stdout")
+            .unwrap();
+        assert_eq!(
+            stdout.children[1].predicate.as_deref(),
+            Some("terminal.stdout.tty")
+        );
+    }
+
+    #[test]
+    fn visible_rows_follow_expansion() {
+        let tree = build_tree(&sample_env());
+        let mut expanded = HashSet::new();
+        assert_eq!(visible_rows(&tree, &expanded).len(), 3);
+
+        expanded.insert("contexts".to_string());
+        let rows = visible_rows(&tree, &expanded);
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[1].depth, 1);
+        assert_eq!(rows[1].node.label, "agent");
+    }
+}
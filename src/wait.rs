@@ -0,0 +1,106 @@
+//! Polling support for `envsense wait`.
+
+use std::time::{Duration, Instant};
+
+/// Parse a duration such as `30s`, `500ms`, `2m` or `1h`
+///
+/// A bare number is taken as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let invalid = || {
+        format!(
+            "invalid duration '{}' (expected e.g. 500ms, 30s, 2m or 1h)",
+            input
+        )
+    };
+    let amount: u64 = number.parse().map_err(|_| invalid())?;
+    let millis = match unit {
+        "ms" => 1,
+        "" | "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return Err(invalid()),
+    };
+    amount
+        .checked_mul(millis)
+        .map(Duration::from_millis)
+        .ok_or_else(invalid)
+}
+
+/// Call `check` every `interval` until it returns `Ok(true)`
+///
+/// Returns `Ok(false)` once `timeout` has passed without success; without a
+/// timeout, polls forever. Errors from `check` stop polling immediately.
+pub fn poll<E>(
+    timeout: Option<Duration>,
+    interval: Duration,
+    mut check: impl FnMut() -> Result<bool, E>,
+) -> Result<bool, E> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        if check()? {
+            return Ok(true);
+        }
+        let sleep = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Ok(false);
+                }
+                interval.min(remaining)
+            }
+            None => interval,
+        };
+        std::thread::sleep(sleep);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("1.5s").is_err());
+        assert!(parse_duration("10d").is_err());
+    }
+
+    #[test]
+    fn poll_stops_when_check_succeeds() {
+        let mut calls = 0;
+        let held = poll::<()>(None, Duration::from_millis(1), || {
+            calls += 1;
+            Ok(calls == 3)
+        });
+        assert_eq!(held, Ok(true));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn poll_times_out() {
+        let start = Instant::now();
+        let held = poll::<()>(
+            Some(Duration::from_millis(30)),
+            Duration::from_millis(10),
+            || Ok(false),
+        );
+        assert_eq!(held, Ok(false));
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn poll_propagates_errors() {
+        assert_eq!(poll(None, Duration::ZERO, || Err::<bool, _>(2)), Err(2));
+    }
+}
@@ -69,6 +69,362 @@ fn fields_limit_output() {
         .stdout(contains("\"contexts\"").and(contains("\"traits\"").not()));
 }
 
+#[test]
+fn nested_field_paths_limit_json_output() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args([
+            "--no-daemon",
+            "info",
+            "--json",
+            "--fields=traits.terminal.interactive,traits.ci.id,traits.ide.id",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "traits": {
+                "terminal": {"interactive": false},
+                "ci": {"id": "github_actions"},
+                "ide": {"id": null},
+            }
+        })
+    );
+}
+
+#[test]
+fn nested_field_paths_limit_human_output() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args([
+            "--no-daemon",
+            "info",
+            "--fields=traits.terminal.stdin,contexts",
+            "--no-color",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("Traits:\n  terminal:\n    stdin:\n"))
+        .stdout(contains("      piped:"))
+        .stdout(contains("color_level").not())
+        .stdout(contains("Contexts:"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.args(["info", "--fields=traits.terminal.bogus"])
+        .assert()
+        .code(2)
+        .stderr(contains("unknown field: traits.terminal.bogus"));
+}
+
+#[test]
+fn confidence_shown_next_to_contexts() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .env("ENVSENSE_CONTAINER", "none")
+        .args([
+            "--no-daemon",
+            "info",
+            "--confidence",
+            "--fields=contexts",
+            "--no-color",
+        ])
+        .assert()
+        .success()
+        .stdout("Contexts:\n  - ci (1.0)\n\n");
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-daemon", "info", "--json", "--fields=confidence"])
+        .assert()
+        .success()
+        .stdout(contains("\"ci\": 1.0").and(contains("\"ci.id\": 1.0")));
+}
+
+#[test]
+fn timings_flag_prints_table_and_meta() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .args(["--timings", "info", "--json", "--fields=meta"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("detector"));
+    assert!(
+        stderr
+            .lines()
+            .any(|l| l.starts_with("agent") && l.ends_with(" ms"))
+    );
+    assert!(stderr.lines().last().unwrap().starts_with("total"));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let timings = &json["meta"]["timings"];
+    assert!(timings["total_us"].is_u64());
+    let detectors: Vec<&str> = timings["detectors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["detector"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        detectors,
+        ["terminal", "shell", "container", "agent", "ci", "ide"]
+    );
+}
+
+#[test]
+fn check_runs_only_targeted_detectors() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-daemon", "-v", "check", "ci.id=github_actions"])
+        .assert()
+        .success()
+        .stderr(contains("detector.run name=ci"))
+        .stderr(contains(
+            r#"detector.skipped name=agent reason="not targeted""#,
+        ));
+}
+
+#[test]
+fn quiet_check_stops_at_deciding_predicate() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-daemon", "-v", "check", "-q", "--any", "ci", "agent"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(contains("detector.run name=ci"))
+        .stderr(contains("detector.run name=agent").not());
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-daemon", "-v", "check", "-q", "agent", "ci"])
+        .assert()
+        .failure()
+        .stderr(contains("detector.run name=agent"))
+        .stderr(contains("detector.run name=ci").not());
+
+    // Each detector runs once, however many predicates need it
+    let output = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args([
+            "--no-daemon",
+            "-v",
+            "check",
+            "-q",
+            "ci",
+            "ci.id=github_actions",
+            "!agent",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("detector.run name=ci ").count(), 1);
+    assert_eq!(stderr.matches("detector.run name=agent ").count(), 1);
+
+    // Predicates after the deciding one are still validated
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-daemon", "check", "-q", "--any", "ci", "ci.nope"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn override_variables_force_results() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .env("ENVSENSE_OVERRIDE_CI", "false")
+        .env("ENVSENSE_OVERRIDE_AGENT", "true")
+        .env("ENVSENSE_OVERRIDE_AGENT_ID", "cursor")
+        .args(["--no-daemon", "check", "agent.id=cursor", "!ci", "agent"])
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("ENVSENSE_OVERRIDE_TERMINAL_INTERACTIVE", "maybe")
+        .args(["--no-daemon", "info", "--json", "--fields=evidence"])
+        .assert()
+        .success()
+        .stderr(contains(
+            "Warning: ignoring ENVSENSE_OVERRIDE_TERMINAL_INTERACTIVE: \
+             'terminal.interactive' expects true or false",
+        ))
+        .stdout(contains("ENVSENSE_OVERRIDE").not());
+}
+
+#[test]
+fn min_confidence_drops_heuristic_contexts() {
+    let check = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("envsense").unwrap();
+        cmd.env_clear()
+            .env("IS_CODE_AGENT", "1")
+            .arg("--no-daemon")
+            .args(args)
+            .args(["check", "agent"])
+            .assert()
+    };
+    check(&[]).success();
+    check(&["--min-confidence", "0.8"]).code(1);
+    check(&["--min-confidence", "0.6"]).success();
+    check(&["--min-confidence", "1.5"])
+        .code(2)
+        .stderr(contains("expected a number from 0 to 1"));
+}
+
+#[test]
+fn assume_and_deny_force_contexts() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args([
+            "--no-daemon",
+            "check",
+            "--assume",
+            "ci",
+            "--deny",
+            "agent",
+            "--explain",
+            "ci",
+            "!agent",
+            "agent.id",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("context 'ci' assumed by --assume"))
+        .stdout(contains("context 'agent' denied by --deny"))
+        .stdout(contains("agent.id=null"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "--assume", "cloud", "check", "ci"])
+        .assert()
+        .code(2)
+        .stderr(contains("unknown context 'cloud'"));
+}
+
+#[test]
+fn explain_names_evidence_sources() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "check", "--explain", "agent", "ci"])
+        .assert()
+        .code(1)
+        .stdout(contains(
+            "agent=true  # reason: context 'agent' detected; evidence: CURSOR_AGENT=1 (agent/cursor)",
+        ))
+        .stdout(contains("ci=false  # reason: context 'ci' not detected\n"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "check", "--explain", "--json", "agent.id"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["checks"][0]["signals"],
+        serde_json::json!({"CURSOR_AGENT=1": "agent/cursor"})
+    );
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args([
+            "--no-daemon",
+            "check",
+            "--explain",
+            "--format",
+            "ndjson",
+            "agent",
+        ])
+        .assert()
+        .success()
+        .stdout(contains(r#""signals":{"CURSOR_AGENT=1":"agent/cursor"}"#));
+}
+
+#[test]
+fn info_evidence_for_filters_evidence() {
+    let info = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("envsense").unwrap();
+        cmd.env_clear()
+            .env("AIDER_MODEL", "gpt-4")
+            .env("ENVSENSE_CONTAINER", "none")
+            .args(["--no-daemon", "info", "--evidence-for"])
+            .args(args)
+            .assert()
+    };
+
+    info(&["agent"])
+        .success()
+        .stdout(contains(
+            "Evidence:\n  - AIDER_MODEL=gpt-4 (env, 0.8, agent/aider)\n",
+        ))
+        .stdout(contains("terminal").not())
+        .stdout(contains("Traits:").not());
+    info(&["terminal.stdout", "--raw", "--fields", "contexts,evidence"])
+        .success()
+        .stdout("agent\nterminal.stdout.tty=false\n");
+
+    let output = info(&["agent.id", "--json"]).success().get_output().clone();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let keys: Vec<&str> = json["evidence"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["key"].as_str().unwrap())
+        .collect();
+    assert_eq!(keys, ["AIDER_MODEL"]);
+    assert_eq!(json.as_object().unwrap().len(), 1);
+
+    info(&["cloud"])
+        .code(2)
+        .stderr(contains("unknown context or field 'cloud'"));
+}
+
+#[test]
+fn provenance_is_opt_in() {
+    let info = |extra: &[&str]| {
+        let mut cmd = Command::cargo_bin("envsense").unwrap();
+        let output = cmd
+            .env_clear()
+            .env("CURSOR_AGENT", "1")
+            .args(extra)
+            .args(["info", "--json"])
+            .output()
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    assert!(info(&["--no-daemon"]).get("provenance").is_none());
+    let json = info(&["--provenance"]);
+    assert_eq!(
+        json["provenance"]["agent.id"],
+        serde_json::json!({"detector": "agent", "confidence": 1.0, "evidence": ["CURSOR_AGENT"]})
+    );
+}
+
 #[test]
 fn human_info_multiline() {
     let mut cmd = Command::cargo_bin("envsense").unwrap();
@@ -88,6 +444,7 @@ fn raw_contexts_without_headings_or_color() {
     cmd.env_clear()
         .env("TERM_PROGRAM", "vscode")
         .env("TERM_PROGRAM_VERSION", "1.75.0")
+        .env("ENVSENSE_CONTAINER", "none")
         .args(["info", "--raw", "--fields=contexts"])
         .assert()
         .success()
@@ -414,3 +771,459 @@ fn check_new_syntax_comprehensive() {
         .assert()
         .success();
 }
+
+#[test]
+fn verbose_logs_detection_to_stderr() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "-v", "check", "agent"])
+        .assert()
+        .success()
+        .stdout("true\n")
+        .stderr(contains(
+            "envsense[verbose] detector.run name=agent contexts=agent",
+        ))
+        .stderr(contains("detect.done contexts=agent"))
+        .stderr(contains("mapping.checked").not());
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "--debug", "check", "agent"])
+        .assert()
+        .success()
+        .stderr(contains(
+            "envsense[debug] mapping.checked context=agent id=cursor matched=true vars=CURSOR_AGENT",
+        ))
+        .stderr(contains("mapping.selected context=agent id=cursor"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "check", "agent"])
+        .assert()
+        .stderr("");
+}
+
+#[test]
+fn info_prints_directly_when_not_a_terminal() {
+    // A pager that would swallow the output must not run when piped
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("PAGER", "false")
+        .args(["--no-daemon", "info", "--fields", "contexts"])
+        .assert()
+        .success()
+        .stdout(contains("Contexts:"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "info", "--no-pager", "--fields", "contexts"])
+        .assert()
+        .success()
+        .stdout(contains("Contexts:"));
+}
+
+#[test]
+fn info_json_compat_uses_legacy_layout() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "info", "--json", "--compat", "0.2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["version"], "0.2.0");
+    assert_eq!(json["contexts"]["agent"], true);
+    assert_eq!(json["contexts"]["ci"], false);
+    assert_eq!(json["facets"]["agent_id"], "cursor");
+    assert_eq!(json["traits"]["is_interactive"], false);
+    assert!(json["traits"]["is_tty_stdout"].is_boolean());
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "info", "--json", "--compat", "0.1"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unsupported compat version"));
+}
+
+#[test]
+fn info_json_schema_version_negotiation() {
+    let info = |version: &str| {
+        let mut cmd = Command::cargo_bin("envsense").unwrap();
+        cmd.env_clear()
+            .env("CURSOR_AGENT", "1")
+            .args(["--no-daemon", "info", "--json", "--schema-version", version])
+            .output()
+            .unwrap()
+    };
+
+    let output = info("0.3");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["meta"]["schema_version"],
+        envsense::schema::SCHEMA_VERSION
+    );
+    assert_eq!(json["traits"]["agent"]["id"], "cursor");
+
+    let output = info("0.2");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["version"], "0.2.0");
+    assert_eq!(json["facets"]["agent_id"], "cursor");
+
+    let output = info("0.9");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unsupported schema version '0.9' (available: 0.2, 0.3)"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args([
+            "--no-daemon",
+            "info",
+            "--json",
+            "--schema-version",
+            "0.2",
+            "--fields",
+            "contexts",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--fields"));
+}
+
+#[test]
+fn info_warns_about_deprecated_fields() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .args([
+            "--no-daemon",
+            "info",
+            "--json",
+            "--fields",
+            "contexts,facets",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("warning: field 'facets' is deprecated"));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["warnings"][0]["name"], "facets");
+    assert_eq!(json["warnings"][0]["replacement"], "traits");
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .args([
+            "--no-daemon",
+            "--no-deprecation-warnings",
+            "info",
+            "--json",
+            "--fields",
+            "facets",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json.get("warnings").is_none());
+}
+
+#[test]
+fn check_reads_predicates_from_stdin() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "check", "-"])
+        .write_stdin("# policy\nagent\n\nagent.id=cursor\n!ci\n")
+        .assert()
+        .success()
+        .stdout("overall=true\nagent=true\nagent.id=cursor=true\n!ci=true\n");
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "check", "--any", "--stdin", "ide"])
+        .write_stdin("ci\n")
+        .assert()
+        .failure()
+        .stdout(contains("ide=false").and(contains("ci=false")));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "check", "-"])
+        .write_stdin("# nothing\n")
+        .assert()
+        .code(2)
+        .stderr(contains("no predicates read from stdin"));
+}
+
+#[test]
+fn check_report_prints_table() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "check", "--report", "agent", "ci"])
+        .assert()
+        .failure()
+        .stdout(
+            "PREDICATE  RESULT  REASON\n\
+             agent      true    context 'agent' detected\n\
+             ci         false   context 'ci' not detected\n\
+             \n\
+             overall: fail (1 of 2 matched, mode: all)\n",
+        );
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "check", "--report", "--json", "agent"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn assert_prints_message_and_hints_on_failure() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args([
+            "--no-daemon",
+            "assert",
+            "agent",
+            "ci",
+            "--message",
+            "this script must run in CI",
+            "--hint",
+            "push a branch instead",
+        ])
+        .assert()
+        .code(1)
+        .stdout(predicates::str::is_empty())
+        .stderr(
+            "error: this script must run in CI\n  \
+             failed: ci (context 'ci' not detected)\n\
+             hint: push a branch instead\n",
+        );
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "assert", "agent", "agent.id=cursor"])
+        .assert()
+        .success()
+        .stdout(predicates::str::is_empty())
+        .stderr(predicates::str::is_empty());
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "assert", "--any", "ci", "ide"])
+        .assert()
+        .code(1)
+        .stderr(contains("error: assertion failed: ci ide"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "assert", "agent.bogus"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn wait_returns_once_predicates_hold() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "wait", "agent", "--timeout", "5s"])
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args([
+            "--no-daemon",
+            "wait",
+            "ci",
+            "--timeout",
+            "300ms",
+            "--interval",
+            "100ms",
+        ])
+        .assert()
+        .code(1)
+        .stderr(contains("Timed out after 300ms waiting for: ci"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "wait", "ci", "--timeout", "soon"])
+        .assert()
+        .code(2)
+        .stderr(contains("invalid duration 'soon'"));
+}
+
+#[test]
+fn wait_rereads_env_file() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("app.env");
+    std::fs::write(&path, "# not provisioned yet\n").unwrap();
+
+    let mut child = std::process::Command::new(cargo_bin("envsense"))
+        .env_clear()
+        .env("ENVSENSE_CONTAINER", "none")
+        .args(["wait", "ci", "--timeout", "10s", "--interval", "50ms"])
+        .arg("--env-file")
+        .arg(&path)
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    std::fs::write(&path, "GITHUB_ACTIONS=true\n").unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn fields_json_dumps_registry() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd.env_clear().args(["fields", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let fields = json["fields"].as_array().unwrap();
+    let ci_id = fields.iter().find(|f| f["path"] == "ci.id").unwrap();
+    assert_eq!(ci_id["context"], "ci");
+    assert_eq!(ci_id["type"], "optional_string");
+    assert!(
+        json["contexts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|c| c["name"] == "terminal")
+    );
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .arg("fields")
+        .assert()
+        .success()
+        .stdout(contains("terminal.interactive"));
+}
+
+#[test]
+fn bare_predicate_routes_to_check() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "agent.id=cursor"])
+        .assert()
+        .success()
+        .stdout("true\n");
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "!agent", "ide", "--any", "--json"])
+        .assert()
+        .failure()
+        .stdout(contains(r#""mode":"any""#));
+
+    // Global flags may follow the predicates, as they may follow `check`
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["agent.id=cursor", "--no-daemon", "--no-cache"])
+        .assert()
+        .success()
+        .stdout("true\n");
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "inof"])
+        .assert()
+        .code(2)
+        .stderr(contains("unrecognized subcommand 'inof'"))
+        .stderr(contains("a similar subcommand exists: 'info'"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["chek", "agent"])
+        .assert()
+        .code(2)
+        .stderr(contains("similar subcommands exist: 'check', 'schema'"));
+}
+
+#[test]
+fn check_ndjson_prints_one_object_per_line() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "check", "--format", "ndjson", "agent", "ci"])
+        .assert()
+        .failure()
+        .stdout(
+            "{\"predicate\":\"agent\",\"result\":true}\n\
+             {\"predicate\":\"ci\",\"result\":false}\n\
+             {\"mode\":\"all\",\"overall\":false}\n",
+        );
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--no-daemon", "check", "--format", "ndjson", "ci"])
+        .assert()
+        .failure()
+        .stdout(contains("\"predicate\":\"ci\""));
+}
+
+#[test]
+fn check_csv_prints_predicate_result_reason_rows() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "check", "--format", "csv", "agent", "ci"])
+        .assert()
+        .failure()
+        .stdout(contains("predicate,result,reason\nagent,true,"))
+        .stdout(contains("\nci,false,"));
+}
+
+#[test]
+fn check_reports_container_runtime() {
+    // Mocked TTYs keep the host's marker files out of detection
+    let envsense = || {
+        let mut cmd = Command::cargo_bin("envsense").unwrap();
+        cmd.env_clear()
+            .env("container", "podman")
+            .env("HOSTNAME", "3f4e1b2c5d6a")
+            .env("ENVSENSE_TTY_STDIN", "false")
+            .env("ENVSENSE_TTY_STDOUT", "false")
+            .env("ENVSENSE_TTY_STDERR", "false")
+            .arg("--no-daemon");
+        cmd
+    };
+
+    envsense()
+        .args([
+            "check",
+            "--all",
+            "container",
+            "container.runtime=podman",
+            "container.id=3f4e1b2c5d6a",
+        ])
+        .assert()
+        .success();
+    let output = envsense()
+        .args(["info", "--json", "--compat", "0.2"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["contexts"]["container"], true);
+    assert_eq!(json["facets"]["container_id"], "3f4e1b2c5d6a");
+    envsense()
+        .env("ENVSENSE_CONTAINER", "none")
+        .args(["check", "-q", "container"])
+        .assert()
+        .code(1);
+}
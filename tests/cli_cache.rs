@@ -0,0 +1,91 @@
+#![cfg(unix)]
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use tempfile::TempDir;
+
+// Tests for the opt-in on-disk detection cache
+
+fn envsense(dir: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env("XDG_CACHE_HOME", dir.path().join("cache"))
+        // The counting plugin below tracks how often detection runs
+        .env("ENVSENSE_PLUGINS", "true")
+        .env("PATH", format!("{}:/usr/bin:/bin", dir.path().display()))
+        .arg("--no-daemon");
+    cmd
+}
+
+/// Install a plugin that counts its runs; returns a reader for the count
+fn counting_plugin(dir: &TempDir) -> impl Fn() -> usize {
+    let count = dir.path().join("count");
+    let plugin = dir.path().join("envsense-detector-counter");
+    fs::write(
+        &plugin,
+        format!(
+            "#!/bin/sh\necho x >> '{}'\nprintf '{{}}'\n",
+            count.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+    move || fs::read_to_string(&count).map_or(0, |s| s.lines().count())
+}
+
+#[test]
+fn test_cache_is_opt_in() {
+    let dir = TempDir::new().unwrap();
+    let invocations = counting_plugin(&dir);
+
+    for _ in 0..2 {
+        envsense(&dir).args(["check", "-q", "ci"]).assert().code(1);
+    }
+    assert_eq!(invocations(), 2);
+    assert!(!dir.path().join("cache/envsense/detect").exists());
+}
+
+#[test]
+fn test_cache_reuses_results_until_environment_changes() {
+    let dir = TempDir::new().unwrap();
+    let invocations = counting_plugin(&dir);
+    let cached = || {
+        let mut cmd = envsense(&dir);
+        cmd.env("ENVSENSE_CACHE", "true");
+        cmd
+    };
+
+    for pwd in ["/a", "/b"] {
+        cached()
+            .env("PWD", pwd)
+            .env("CURSOR_AGENT", "1")
+            .args(["check", "agent.id=cursor"])
+            .assert()
+            .success();
+    }
+    assert_eq!(invocations(), 1, "a changed PWD should still hit the cache");
+
+    // A cached result answers predicates the first call did not ask about
+    cached()
+        .env("CURSOR_AGENT", "1")
+        .args(["-v", "info", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"agent\""))
+        .stderr(predicate::str::contains("cache.hit"));
+    assert_eq!(invocations(), 1);
+
+    cached()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-cache", "check", "agent"])
+        .assert()
+        .success();
+    assert_eq!(invocations(), 2);
+
+    // A different environment hashes to a different entry
+    cached().args(["check", "-q", "agent"]).assert().code(1);
+    assert_eq!(invocations(), 3);
+}
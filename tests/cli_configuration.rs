@@ -234,3 +234,219 @@ fn test_output_formatting_still_works() {
             "Integrated development environment",
         ));
 }
+
+fn write_config(dir: &TempDir, content: &str) {
+    let config_dir = dir.path().join("envsense");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("config.toml"), content).unwrap();
+}
+
+fn envsense_with_config(dir: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear().env("XDG_CONFIG_HOME", dir.path());
+    cmd
+}
+
+#[test]
+fn test_config_file_sets_default_format() {
+    let dir = TempDir::new().unwrap();
+    write_config(&dir, "[output_formatting]\nformat = \"json\"\n");
+
+    envsense_with_config(&dir)
+        .args(["info"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{"));
+}
+
+#[test]
+fn test_env_var_overrides_config_file() {
+    let dir = TempDir::new().unwrap();
+    write_config(&dir, "[output_formatting]\nformat = \"json\"\n");
+
+    envsense_with_config(&dir)
+        .env("ENVSENSE_FORMAT", "raw")
+        .args(["info", "--fields", "contexts"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("{").not());
+}
+
+#[test]
+fn test_cli_flag_overrides_env_var() {
+    let dir = TempDir::new().unwrap();
+
+    envsense_with_config(&dir)
+        .env("ENVSENSE_FORMAT", "raw")
+        .args(["info", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{"));
+}
+
+#[test]
+fn test_config_check_mode_any() {
+    let dir = TempDir::new().unwrap();
+    write_config(&dir, "[check]\nmode = \"any\"\n");
+
+    // Only `ci` matches, so this passes in ANY mode and fails in ALL mode
+    envsense_with_config(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .args(["check", "-q", "ci", "agent"])
+        .assert()
+        .success();
+
+    envsense_with_config(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .args(["check", "-q", "--all", "ci", "agent"])
+        .assert()
+        .code(1);
+
+    envsense_with_config(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("ENVSENSE_CHECK_MODE", "all")
+        .args(["check", "-q", "ci", "agent"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_config_color_always_and_no_color_precedence() {
+    let dir = TempDir::new().unwrap();
+    write_config(&dir, "[output_formatting]\ncolor = \"always\"\n");
+
+    envsense_with_config(&dir)
+        .args(["info"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\u{1b}["));
+
+    envsense_with_config(&dir)
+        .args(["--no-color", "info"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\u{1b}[").not());
+}
+
+#[test]
+fn test_config_color_applies_to_check() {
+    let dir = TempDir::new().unwrap();
+    write_config(&dir, "[output_formatting]\ncolor = \"always\"\n");
+
+    envsense_with_config(&dir)
+        .env("CURSOR_AGENT", "1")
+        .args(["check", "--explain", "agent", "ci"])
+        .assert()
+        .code(1)
+        .stdout(
+            predicate::str::contains("agent=\u{1b}[32mtrue\u{1b}[0m")
+                .and(predicate::str::contains("ci=\u{1b}[31mfalse\u{1b}[0m"))
+                .and(predicate::str::contains("\u{1b}[2m# reason:")),
+        );
+
+    envsense_with_config(&dir)
+        .env("NO_COLOR", "1")
+        .args(["check", "agent"])
+        .assert()
+        .stdout(predicate::str::contains("\u{1b}[").not());
+
+    envsense_with_config(&dir)
+        .args(["--no-color", "check", "--report", "agent"])
+        .assert()
+        .stdout(predicate::str::contains("\u{1b}[").not());
+}
+
+#[test]
+fn test_config_theme_overrides_colors() {
+    let dir = TempDir::new().unwrap();
+    write_config(
+        &dir,
+        "[output_formatting]\ncolor = \"always\"\n\n[theme]\nname = \"mono\"\ntrue = \"blue\"\n",
+    );
+
+    envsense_with_config(&dir)
+        .env("CURSOR_AGENT", "1")
+        .args(["check", "agent", "ci"])
+        .assert()
+        .code(1)
+        .stdout(
+            predicate::str::contains("agent=\u{1b}[34mtrue\u{1b}[0m")
+                .and(predicate::str::contains("ci=\u{1b}[2mfalse\u{1b}[0m")),
+        );
+
+    envsense_with_config(&dir)
+        .args(["info", "--fields", "contexts"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("\u{1b}[1mContexts:\u{1b}[0m"));
+}
+
+#[test]
+fn test_env_var_disables_detector() {
+    let dir = TempDir::new().unwrap();
+
+    envsense_with_config(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("ENVSENSE_DISABLE_DETECTORS", "ci")
+        .args(["check", "-q", "ci"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_project_file_assumes_contexts() {
+    let dir = TempDir::new().unwrap();
+    let nested = dir.path().join("packages/app");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(
+        dir.path().join(".envsense.toml"),
+        "assume = [\"ci\", \"ci.id=github_actions\"]\n",
+    )
+    .unwrap();
+
+    envsense_with_config(&dir)
+        .current_dir(&nested)
+        .args(["check", "-q", "ci", "ci.id=github_actions"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_project_file_invalid_assume_errors() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join(".envsense.toml"), "assume = [\"cloud\"]\n").unwrap();
+
+    envsense_with_config(&dir)
+        .current_dir(dir.path())
+        .args(["info"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown context 'cloud'"));
+}
+
+#[test]
+fn test_evidence_values_are_redacted() {
+    let dir = TempDir::new().unwrap();
+    let info = |dir: &TempDir| {
+        envsense_with_config(dir)
+            .env("AIDER_MODEL", "gpt-4")
+            .env("AIDER_OPENAI_API_KEY", "sk-live-123")
+            .env("AIDER_INTERNAL_HOST", "db.local")
+            .args(["--no-daemon", "info", "--json", "--fields=evidence"])
+            .assert()
+            .success()
+    };
+
+    info(&dir)
+        .stdout(predicate::str::contains("sk-live-123").not())
+        .stdout(predicate::str::contains("[REDACTED]"))
+        .stdout(predicate::str::contains("db.local"));
+
+    write_config(
+        &dir,
+        "[detectors.redact]\npatterns = [\"internal\"]\nallow = [\"AIDER_OPENAI_API_KEY\"]\n",
+    );
+    info(&dir)
+        .stdout(predicate::str::contains("sk-live-123"))
+        .stdout(predicate::str::contains("db.local").not());
+}
@@ -0,0 +1,177 @@
+#![cfg(unix)]
+
+use assert_cmd::Command;
+use serde_json::json;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+// Tests for `envsense daemon` and transparent use of it by info/check
+
+/// Kills the daemon if a test fails before stopping it
+struct DaemonProcess(Child);
+
+impl Drop for DaemonProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn socket(dir: &TempDir) -> PathBuf {
+    dir.path().join("envsense.sock")
+}
+
+/// A plugin that records each invocation so cache hits are observable
+fn write_counting_plugin(dir: &TempDir) -> PathBuf {
+    let count = dir.path().join("count");
+    let plugin = dir.path().join("envsense-detector-counter");
+    fs::write(
+        &plugin,
+        format!(
+            "#!/bin/sh\necho x >> '{}'\nprintf '{{}}'\n",
+            count.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+    count
+}
+
+fn invocations(count: &Path) -> usize {
+    fs::read_to_string(count).map_or(0, |s| s.lines().count())
+}
+
+fn configure(cmd: &mut std::process::Command, dir: &TempDir) {
+    cmd.env_clear()
+        .env("PATH", format!("{}:/usr/bin:/bin", dir.path().display()))
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env("ENVSENSE_PLUGINS", "true")
+        .env("ENVSENSE_DAEMON_SOCKET", socket(dir))
+        .current_dir(dir.path());
+}
+
+fn envsense(dir: &TempDir) -> Command {
+    let mut cmd = std::process::Command::new(assert_cmd::cargo::cargo_bin("envsense"));
+    configure(&mut cmd, dir);
+    Command::from_std(cmd)
+}
+
+fn start_daemon(dir: &TempDir, args: &[&str]) -> DaemonProcess {
+    let mut cmd = std::process::Command::new(assert_cmd::cargo::cargo_bin("envsense"));
+    configure(&mut cmd, dir);
+    let child = cmd
+        .args(args)
+        .arg("daemon")
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let daemon = DaemonProcess(child);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !socket(dir).exists() {
+        assert!(Instant::now() < deadline, "daemon did not start");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    daemon
+}
+
+#[test]
+fn test_daemon_caches_detection_per_environment() {
+    let dir = TempDir::new().unwrap();
+    let count = write_counting_plugin(&dir);
+    let mut daemon = start_daemon(&dir, &[]);
+
+    envsense(&dir).args(["info", "--json"]).assert().success();
+    envsense(&dir).args(["info", "--json"]).assert().success();
+    assert_eq!(invocations(&count), 1, "second query should be cached");
+
+    // The daemon answers for the caller's environment, not its own
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .args(["check", "ci.id=github_actions"])
+        .assert()
+        .success();
+    assert_eq!(invocations(&count), 2);
+
+    envsense(&dir)
+        .args(["--no-daemon", "check", "ci"])
+        .assert()
+        .failure();
+    assert_eq!(invocations(&count), 3, "--no-daemon detects in-process");
+
+    envsense(&dir)
+        .args(["daemon", "--refresh"])
+        .assert()
+        .success();
+    envsense(&dir).args(["check", "ci"]).assert().failure();
+    assert_eq!(invocations(&count), 4, "refresh drops cached results");
+
+    envsense(&dir).args(["daemon", "--stop"]).assert().success();
+    assert!(daemon.0.wait().unwrap().success());
+    assert!(!socket(&dir).exists());
+}
+
+fn info(cmd: &mut Command) -> serde_json::Value {
+    let output = cmd.args(["info", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    json!({ "contexts": json["contexts"], "traits": json["traits"] })
+}
+
+#[test]
+fn test_daemon_answers_like_local_detection() {
+    let dir = TempDir::new().unwrap();
+    let _daemon = start_daemon(&dir, &[]);
+
+    // Container detection reads marker files, which the daemon may read too
+    let local = info(envsense(&dir).arg("--no-daemon"));
+    assert_eq!(info(&mut envsense(&dir)), local);
+
+    let local = info(envsense(&dir).env("container", "podman").arg("--no-daemon"));
+    assert_eq!(local["traits"]["container"]["runtime"], "podman");
+    assert_eq!(info(envsense(&dir).env("container", "podman")), local);
+}
+
+#[test]
+fn test_client_applies_its_own_assumptions() {
+    let dir = TempDir::new().unwrap();
+    let _daemon = start_daemon(&dir, &[]);
+
+    let project = dir.path().join("project");
+    fs::create_dir(&project).unwrap();
+    fs::write(project.join(".envsense.toml"), "assume = [\"ci\"]\n").unwrap();
+
+    envsense(&dir).args(["check", "ci"]).assert().failure();
+    envsense(&dir)
+        .current_dir(&project)
+        .args(["check", "ci"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_different_detector_selection_falls_back() {
+    let dir = TempDir::new().unwrap();
+    let _daemon = start_daemon(&dir, &["--only", "terminal"]);
+
+    // The daemon would not run the CI detector, so detection happens locally
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .args(["check", "ci"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_control_commands_fail_without_daemon() {
+    let dir = TempDir::new().unwrap();
+    envsense(&dir)
+        .args(["daemon", "--stop"])
+        .assert()
+        .failure()
+        .code(1);
+}
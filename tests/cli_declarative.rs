@@ -197,3 +197,41 @@ fn cli_declarative_evidence_in_json() {
         .success()
         .stdout(contains("\"evidence\"").and(contains("REPL_ID")));
 }
+
+#[test]
+fn cli_declarative_agent_version() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("AIDER_MODEL", "gpt-4o-mini")
+        .env("AIDER_VERSION", "0.86.1")
+        .args(["check", "agent.version"])
+        .assert()
+        .success()
+        .stdout("0.86.1\n");
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("AIDER_MODEL", "gpt-4o-mini")
+        .env("AIDER_VERSION", "0.86.1")
+        .args(["check", "agent.version=0.86.1"])
+        .assert()
+        .success()
+        .stdout("true\n");
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("AIDER_MODEL", "gpt-4o-mini")
+        .env("AIDER_VERSION", "0.86.1")
+        .args(["check", "agent.version>=0.86", "agent.version<0.100"])
+        .assert()
+        .success()
+        .stdout(contains("overall=true"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["check", "agent.version>=0.86"])
+        .assert()
+        .failure()
+        .stdout("false\n");
+}
@@ -0,0 +1,109 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::Value;
+
+// Tests for the diagnostic commands: `envsense explain` and `envsense doctor`
+
+#[test]
+fn test_explain_shows_decision_chain() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["explain", "agent.id=cursor"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("agent.id=cursor = true"))
+        .stdout(predicate::str::contains("=> cursor"))
+        .stdout(predicate::str::contains("[x] CURSOR_AGENT set"));
+}
+
+#[test]
+fn test_explain_json_output() {
+    let output = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["explain", "ci", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["result"], "true");
+    let ci = &json["contexts"][0];
+    assert_eq!(ci["context"], "ci");
+    assert_eq!(ci["winner"], "github_actions");
+    assert!(
+        ci["mappings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|m| m["selected"] == true && m["id"] == "github-actions")
+    );
+}
+
+#[test]
+fn test_explain_contexts_without_mappings() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("SHELL", "/bin/zsh")
+        .args(["explain", "shell.id=zsh"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("shell: detected"))
+        .stdout(predicate::str::contains("SHELL=/bin/zsh (env)"));
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("ENVSENSE_CONTAINER", "none")
+        .args(["explain", "container"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("container: not detected"));
+}
+
+#[test]
+fn test_explain_invalid_predicate() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .args(["explain", "agent.bogus"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Error:"));
+}
+
+#[test]
+fn test_doctor_reports_findings() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("CI", "true")
+        .env("NO_COLOR", "1")
+        .env("FORCE_COLOR", "1")
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("contexts: ci"))
+        .stdout(predicate::str::contains(
+            "[warning] NO_COLOR and FORCE_COLOR",
+        ))
+        .stdout(predicate::str::contains("no CI vendor mapping matched"));
+}
+
+#[test]
+fn test_doctor_json() {
+    let output = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .args(["doctor", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["findings"].as_array().unwrap().is_empty());
+    assert!(json["detected"]["contexts"].is_array());
+}
@@ -121,8 +121,6 @@ fn test_predicate_syntax_validation_invalid_characters() {
         ("test/value", "/"),
         ("test:value", ":"),
         ("test;value", ";"),
-        ("test<value", "<"),
-        ("test>value", ">"),
         ("test?value", "?"),
         ("test\"value", "\""),
         ("test'value", "'"),
@@ -138,7 +136,7 @@ fn test_predicate_syntax_validation_invalid_characters() {
             .failure()
             .code(2)
             .stderr(predicate::str::contains(format!("Error parsing '{}': invalid predicate syntax", invalid_predicate)))
-            .stderr(predicate::str::contains("Valid predicate syntax: letters, numbers, dots (.), equals (=), underscores (_), and hyphens (-) only"));
+            .stderr(predicate::str::contains("Valid predicate syntax: letters, numbers, dots (.), comparisons (=, <, >), underscores (_), and hyphens (-) only"));
     }
 }
 
@@ -160,6 +158,7 @@ fn test_predicate_syntax_validation_valid_characters() {
         "field_name=test_value",
         "!agent",
         "!agent.id=test",
+        "agent.version>=1.7",
     ];
 
     for valid_predicate in valid_cases {
@@ -190,6 +189,25 @@ fn test_field_path_validation_invalid_field() {
         .stderr(predicate::str::contains("Error: invalid field path 'agent.invalid_field': available fields for 'agent': agent.id"));
 }
 
+#[test]
+fn test_field_path_validation_suggests_close_field() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.args(["check", "terminal.interactiv"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "; did you mean 'terminal.interactive'?",
+        ));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.args(["check", "ci.bogus"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("did you mean").not());
+}
+
 #[test]
 fn test_field_path_validation_unknown_context() {
     let mut cmd = Command::cargo_bin("envsense").unwrap();
@@ -261,7 +279,7 @@ fn test_negated_invalid_syntax() {
         .failure()
         .code(2)
         .stderr(predicate::str::contains("Error parsing '!invalid@syntax': invalid predicate syntax"))
-        .stderr(predicate::str::contains("Valid predicate syntax: letters, numbers, dots (.), equals (=), underscores (_), and hyphens (-) only"));
+        .stderr(predicate::str::contains("Valid predicate syntax: letters, numbers, dots (.), comparisons (=, <, >), underscores (_), and hyphens (-) only"));
 }
 
 #[test]
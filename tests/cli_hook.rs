@@ -0,0 +1,66 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+// Tests for `envsense hook` integration snippets
+
+fn envsense() -> Command {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        // Keep the host's container out of the detected contexts
+        .env("ENVSENSE_CONTAINER", "none")
+        .arg("--no-daemon");
+    cmd
+}
+
+#[test]
+fn test_direnv_snippet_defines_functions() {
+    envsense()
+        .args(["hook", "direnv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("use_envsense()"))
+        .stdout(predicate::str::contains("envsense_check()"));
+}
+
+#[test]
+fn test_direnv_exports() {
+    envsense()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["hook", "direnv", "--exports"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "export ENVSENSE_DETECTED_CONTEXTS='ci'",
+        ))
+        .stdout(predicate::str::contains(
+            "export ENVSENSE_DETECTED_CI='github_actions'",
+        ))
+        .stdout(predicate::str::contains(
+            "export ENVSENSE_DETECTED_AGENT=''",
+        ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_direnv_snippet_runs_in_bash() {
+    let bin = assert_cmd::cargo::cargo_bin("envsense");
+    let bin_dir = bin.parent().unwrap();
+    let script = r#"
+        watch_file() { :; }
+        eval "$(envsense hook direnv)"
+        use_envsense
+        echo "ci=$ENVSENSE_DETECTED_CI"
+        envsense_check ci && echo "check=yes"
+        envsense_check agent || echo "agent=no"
+        envsense_check_any agent ci && echo "any=yes"
+    "#;
+
+    Command::new("bash")
+        .env_clear()
+        .env("PATH", format!("{}:/usr/bin:/bin", bin_dir.display()))
+        .env("GITHUB_ACTIONS", "true")
+        .args(["-c", script])
+        .assert()
+        .success()
+        .stdout("ci=github_actions\ncheck=yes\nagent=no\nany=yes\n");
+}
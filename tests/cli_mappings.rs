@@ -0,0 +1,281 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+// Tests for user-defined mapping files in the config directory
+
+fn write_mapping(dir: &TempDir, name: &str, content: &str) {
+    let mappings = dir.path().join("envsense/mappings");
+    fs::create_dir_all(&mappings).unwrap();
+    fs::write(mappings.join(name), content).unwrap();
+}
+
+fn envsense(dir: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear().env("XDG_CONFIG_HOME", dir.path());
+    cmd
+}
+
+#[test]
+fn test_user_ci_mapping_is_detected() {
+    let dir = TempDir::new().unwrap();
+    write_mapping(
+        &dir,
+        "acme.toml",
+        r#"
+[[ci]]
+id = "acme-ci"
+confidence = 0.9
+indicators = [{ key = "ACME_BUILD_ID" }]
+"#,
+    );
+
+    envsense(&dir)
+        .env("ACME_BUILD_ID", "42")
+        .args(["check", "-q", "ci", "ci.id=acme-ci"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_user_agent_mapping_from_yaml() {
+    let dir = TempDir::new().unwrap();
+    write_mapping(
+        &dir,
+        "agents.yaml",
+        "agent:\n  - id: in-house-bot\n    confidence: 0.9\n    indicators:\n      - key: IN_HOUSE_BOT\n",
+    );
+
+    envsense(&dir)
+        .env("IN_HOUSE_BOT", "1")
+        .args(["check", "agent.id=in-house-bot"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_user_mapping_overrides_builtin_id() {
+    let dir = TempDir::new().unwrap();
+    write_mapping(
+        &dir,
+        "cursor.json",
+        r#"{"agent": [{"id": "cursor", "confidence": 0.9, "indicators": [{"key": "MY_CURSOR"}]}]}"#,
+    );
+
+    // The built-in CURSOR_AGENT indicator no longer applies
+    envsense(&dir)
+        .env("CURSOR_AGENT", "1")
+        .args(["check", "-q", "agent"])
+        .assert()
+        .code(1);
+
+    envsense(&dir)
+        .env("MY_CURSOR", "1")
+        .args(["check", "-q", "agent.id=cursor"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_user_mapping_declares_contexts() {
+    let dir = TempDir::new().unwrap();
+    write_mapping(
+        &dir,
+        "sandbox.toml",
+        r#"
+[contexts]
+sandbox = "Sandboxed execution environment"
+
+[[agent]]
+id = "sandboxed-agent"
+confidence = 0.9
+indicators = [{ key = "SANDBOX_AGENT" }]
+contexts = ["agent", "sandbox", "k8s"]
+"#,
+    );
+
+    envsense(&dir)
+        .env("SANDBOX_AGENT", "1")
+        .args(["check", "-q", "sandbox", "k8s", "agent.id=sandboxed-agent"])
+        .assert()
+        .success();
+
+    envsense(&dir)
+        .env("SANDBOX_AGENT", "1")
+        .args(["info", "--json", "--fields", "contexts"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""sandbox""#));
+
+    envsense(&dir)
+        .args(["check", "--list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "- sandbox: Sandboxed execution environment",
+        ))
+        .stdout(predicate::str::contains(
+            "- k8s: Added by agent mapping 'sandboxed-agent'",
+        ));
+
+    // Declared contexts can be assumed and denied like built-in ones
+    envsense(&dir)
+        .args(["check", "-q", "--assume", "sandbox", "sandbox"])
+        .assert()
+        .success();
+    envsense(&dir)
+        .args(["check", "--assume", "sandboxx", "agent"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "available: agent, ide, terminal, ci, shell, container, remote, sandbox, k8s",
+        ));
+}
+
+#[test]
+fn test_invalid_mapping_file_warns() {
+    let dir = TempDir::new().unwrap();
+    write_mapping(&dir, "broken.toml", "[[ci]]\nid = \"x\"\n");
+
+    envsense(&dir)
+        .args(["check", "terminal.interactive"])
+        .assert()
+        .code(predicate::in_iter([0, 1]))
+        .stderr(predicate::str::contains(
+            "Warning: ignoring mapping files: invalid mapping file",
+        ))
+        .stderr(predicate::str::contains("broken.toml"));
+
+    let output = envsense(&dir).args(["info", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["diagnostics"][0]["detector"], "mappings");
+
+    envsense(&dir)
+        .args(["mappings", "lint"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("broken.toml"));
+}
+
+#[test]
+fn test_mappings_lint_reports_problems_with_locations() {
+    let dir = TempDir::new().unwrap();
+    write_mapping(
+        &dir,
+        "a.toml",
+        "[[ci]]\nid = \"acme\"\nconfidence = 0.9\nindicators = [{ key = \"ACME\" }]\n",
+    );
+    write_mapping(
+        &dir,
+        "b.yaml",
+        "ci:\n  - id: acme\n    confidence: 0.9\n    indicators:\n      - key: ACME\n",
+    );
+
+    // Broken files must not prevent the lint command itself from running
+    envsense(&dir)
+        .args(["mappings", "lint"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "b.yaml:2: error: ci/acme: duplicate id",
+        ))
+        .stdout(predicate::str::contains("1 error(s)"));
+}
+
+#[test]
+fn test_mappings_lint_explicit_files_json() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("ok.toml");
+    fs::write(
+        &path,
+        "[[ide]]\nid = \"helix\"\nconfidence = 0.8\nindicators = [{ key = \"HELIX\" }]\n",
+    )
+    .unwrap();
+
+    let output = envsense(&dir)
+        .args(["mappings", "lint", "--json"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["problems"].as_array().unwrap().len(), 0);
+    assert_eq!(json["files"][0], path.display().to_string());
+}
+
+#[test]
+fn test_mappings_lint_runs_with_unparsable_file() {
+    let dir = TempDir::new().unwrap();
+    write_mapping(&dir, "broken.toml", "[[ci]]\nid = \"x\"\n");
+
+    envsense(&dir)
+        .args(["mappings", "lint"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("broken.toml"))
+        .stdout(predicate::str::contains("missing field"));
+}
+
+#[test]
+fn test_mappings_test_with_fixture() {
+    let dir = TempDir::new().unwrap();
+    let mapping = dir.path().join("acme.toml");
+    fs::write(
+        &mapping,
+        "[[ci]]\nid = \"acme-ci\"\nconfidence = 0.9\nindicators = [{ key = \"ACME_BUILD_ID\" }]\n",
+    )
+    .unwrap();
+    let fixture = dir.path().join("acme.env");
+    fs::write(&fixture, "ACME_BUILD_ID=7\n").unwrap();
+
+    envsense(&dir)
+        .args(["mappings", "test"])
+        .arg(&mapping)
+        .arg("--fixture")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=> ci/acme-ci"))
+        .stdout(predicate::str::contains("[x] ACME_BUILD_ID set, actual: 7"))
+        .stdout(predicate::str::contains("ci.id: acme-ci"));
+}
+
+#[test]
+fn test_mappings_test_json_with_inline_env() {
+    let dir = TempDir::new().unwrap();
+    let mapping = dir.path().join("bot.yaml");
+    fs::write(
+        &mapping,
+        "agent:\n  - id: in-house-bot\n    confidence: 0.9\n    indicators:\n      - key: BOT\n        value: \"1\"\n",
+    )
+    .unwrap();
+
+    let output = envsense(&dir)
+        .args(["mappings", "test", "--json", "--env", "BOT=2"])
+        .arg(&mapping)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["mappings"][0]["id"], "in-house-bot");
+    assert_eq!(json["mappings"][0]["matched"], false);
+    assert!(json["detected"]["contexts"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_mappings_test_rejects_bad_env() {
+    let dir = TempDir::new().unwrap();
+    let mapping = dir.path().join("empty.toml");
+    fs::write(&mapping, "").unwrap();
+
+    envsense(&dir)
+        .args(["mappings", "test", "--env", "NOPE"])
+        .arg(&mapping)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("KEY=VALUE"));
+}
@@ -19,7 +19,7 @@ fn test_list_checks_context_descriptions() {
             "- ci: Continuous integration environment",
         ))
         .stdout(predicate::str::contains(
-            "- terminal: Terminal characteristics",
+            "- terminal: Interactive terminal (stdin and stdout are TTYs)",
         ));
 }
 
@@ -33,10 +33,12 @@ fn test_list_checks_field_formatting() {
         .stdout(predicate::str::contains("Available fields:"))
         .stdout(predicate::str::contains("agent fields:"))
         .stdout(predicate::str::contains("agent.id"))
-        .stdout(predicate::str::contains("# Agent identifier"))
+        .stdout(predicate::str::contains("# The detected agent ID"))
         .stdout(predicate::str::contains("terminal fields:"))
         .stdout(predicate::str::contains("terminal.color_level"))
-        .stdout(predicate::str::contains("# Color support level"));
+        .stdout(predicate::str::contains(
+            "# The color support level of the terminal",
+        ));
 }
 
 #[test]
@@ -201,3 +203,112 @@ fn test_json_output_format() {
     assert!(output_str.contains("}"));
     assert!(output_str.contains("\"traits\""));
 }
+
+#[test]
+fn test_markdown_output_format() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear().env("GITHUB_ACTIONS", "true").args([
+        "--no-daemon",
+        "info",
+        "--format",
+        "markdown",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("## envsense\n"))
+        .stdout(predicate::str::contains("**Contexts:** `ci`"))
+        .stdout(predicate::str::contains("| `ci.id` | `github_actions` |"))
+        .stdout(predicate::str::contains("### Evidence"));
+}
+
+#[test]
+fn test_markdown_output_respects_fields() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear().env("ENVSENSE_CONTAINER", "none").args([
+        "--no-daemon",
+        "info",
+        "--format",
+        "markdown",
+        "--fields",
+        "contexts",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout("## envsense\n\n**Contexts:** _none_\n");
+}
+
+#[test]
+fn test_csv_and_tsv_output_formats() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .env("ENVSENSE_CONTAINER", "none")
+        .args(["--no-daemon", "info", "--format", "csv"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("key,value\ncontext,ci\n"))
+        .stdout(predicate::str::contains("traits.ci.id,github_actions\n"))
+        .stdout(predicate::str::contains("evidence.0.signal,tty\n"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear().env("ENVSENSE_CONTAINER", "none").args([
+        "--no-daemon",
+        "info",
+        "--format",
+        "tsv",
+        "--fields",
+        "contexts",
+    ]);
+
+    cmd.assert().success().stdout("key\tvalue\n");
+}
+
+#[test]
+fn test_html_output_format() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-daemon", "info", "--format", "html"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("<!DOCTYPE html>"))
+        .stdout(predicate::str::contains(
+            "<span class=\"context\">ci</span>",
+        ))
+        .stdout(predicate::str::contains("<h2>Evidence</h2>"))
+        .stdout(predicate::str::contains("</html>"));
+}
+
+#[test]
+fn test_porcelain_output() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-daemon", "info", "--porcelain"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("porcelain\t1\ncontext\tci\n"))
+        .stdout(predicate::str::contains("\ntraits.ci.id\tgithub_actions\n"))
+        .stdout(predicate::str::contains("\x1b").not());
+}
+
+#[test]
+fn test_porcelain_conflicts_with_json_flag() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.args(["info", "--porcelain", "--json"]);
+
+    cmd.assert().failure().code(2);
+}
+
+#[test]
+fn test_format_conflicts_with_json_flag() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.args(["info", "--json", "--format", "markdown"]);
+
+    cmd.assert().failure().code(2);
+}
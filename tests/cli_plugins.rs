@@ -0,0 +1,135 @@
+#![cfg(unix)]
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use tempfile::TempDir;
+
+// Tests for envsense-detector-* plugins discovered on PATH
+
+const ACME_PLUGIN: &str = r#"#!/bin/sh
+if grep -q '"ACME_BUILD_ID"'; then
+  printf '{"contexts_add":["ci"],"traits_patch":{"ci":{"id":"acme-ci"}},"confidence":0.9}'
+else
+  printf '{}'
+fi
+"#;
+
+fn write_plugin(dir: &TempDir, name: &str, script: &str) {
+    let path = dir.path().join(format!("envsense-detector-{}", name));
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn envsense(dir: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    // The plugins themselves need sh and grep from the system PATH
+    let path = format!("{}:/usr/bin:/bin", dir.path().display());
+    cmd.env_clear()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env("ENVSENSE_PLUGINS", "true")
+        .env("PATH", path);
+    cmd
+}
+
+#[test]
+fn test_plugin_detection_is_merged() {
+    let dir = TempDir::new().unwrap();
+    write_plugin(&dir, "acme", ACME_PLUGIN);
+
+    envsense(&dir)
+        .env("ACME_BUILD_ID", "42")
+        .args(["check", "ci.id=acme-ci"])
+        .assert()
+        .success();
+
+    envsense(&dir).args(["check", "ci"]).assert().failure();
+}
+
+#[test]
+fn test_plugin_is_listed_and_selectable() {
+    let dir = TempDir::new().unwrap();
+    write_plugin(&dir, "acme", ACME_PLUGIN);
+
+    envsense(&dir)
+        .arg("detectors")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("acme"))
+        .stdout(predicate::str::contains("External detector plugin"));
+
+    envsense(&dir)
+        .env("ACME_BUILD_ID", "42")
+        .args(["--disable-detector", "acme", "check", "ci"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_plugins_are_opt_in() {
+    let dir = TempDir::new().unwrap();
+    write_plugin(&dir, "acme", ACME_PLUGIN);
+
+    envsense(&dir)
+        .env("ACME_BUILD_ID", "42")
+        .env_remove("ENVSENSE_PLUGINS")
+        .args(["check", "ci"])
+        .assert()
+        .failure();
+
+    envsense(&dir)
+        .env("ACME_BUILD_ID", "42")
+        .env("ENVSENSE_PLUGINS", "false")
+        .args(["check", "ci"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_plugin_named_like_a_builtin_is_a_warning() {
+    let dir = TempDir::new().unwrap();
+    write_plugin(&dir, "ci", ACME_PLUGIN);
+
+    let output = envsense(&dir)
+        .env("ACME_BUILD_ID", "42")
+        .args(["info", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("name already in use"));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["diagnostics"][0]["detector"], "ci");
+    assert_eq!(json["diagnostics"][0]["kind"], "warning");
+    assert!(json["traits"]["ci"]["id"].is_null());
+}
+
+#[test]
+fn test_failing_plugin_warns_and_is_ignored() {
+    let dir = TempDir::new().unwrap();
+    write_plugin(&dir, "broken", "#!/bin/sh\nexit 1\n");
+
+    envsense(&dir)
+        .args(["check", "ci"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("detector 'broken' failed"));
+}
+
+#[test]
+fn test_failing_plugin_is_reported_in_diagnostics() {
+    let dir = TempDir::new().unwrap();
+    write_plugin(&dir, "broken", "#!/bin/sh\nexit 1\n");
+    write_plugin(&dir, "acme", ACME_PLUGIN);
+
+    let output = envsense(&dir)
+        .env("ACME_BUILD_ID", "42")
+        .args(["info", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["traits"]["ci"]["id"], "acme-ci");
+    assert_eq!(json["diagnostics"][0]["detector"], "broken");
+    assert_eq!(json["diagnostics"][0]["kind"], "error");
+}
@@ -0,0 +1,182 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+// Tests for `envsense prompt` badge output
+
+fn envsense(dir: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env("XDG_CACHE_HOME", dir.path().join("cache"))
+        // Keep the host's container out of the badges
+        .env("ENVSENSE_CONTAINER", "none")
+        .arg("--no-daemon");
+    cmd
+}
+
+#[test]
+fn test_prompt_starship_badges() {
+    let dir = TempDir::new().unwrap();
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("CURSOR_AGENT", "1")
+        .args(["prompt", "--style", "starship"])
+        .assert()
+        .success()
+        .stdout("⚙ ci:github 🤖 cursor\n");
+}
+
+#[test]
+fn test_prompt_ascii_drops_emoji_glyphs() {
+    let dir = TempDir::new().unwrap();
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("CURSOR_AGENT", "1")
+        .args(["prompt", "--ascii"])
+        .assert()
+        .success()
+        .stdout("ci:github cursor\n");
+}
+
+#[test]
+fn test_prompt_segments_select_and_order() {
+    let dir = TempDir::new().unwrap();
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("CURSOR_AGENT", "1")
+        .args(["prompt", "--segments", "agent,ci"])
+        .assert()
+        .success()
+        .stdout("🤖 cursor ⚙ ci:github\n");
+}
+
+#[test]
+fn test_prompt_segments_from_config() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("envsense")).unwrap();
+    fs::write(
+        dir.path().join("envsense/config.toml"),
+        "[prompt]\nsegments = [\"agent\"]\n",
+    )
+    .unwrap();
+
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("CURSOR_AGENT", "1")
+        .arg("prompt")
+        .assert()
+        .success()
+        .stdout("🤖 cursor\n");
+}
+
+#[test]
+fn test_prompt_is_empty_when_nothing_detected() {
+    let dir = TempDir::new().unwrap();
+    envsense(&dir).arg("prompt").assert().success().stdout("");
+}
+
+#[test]
+fn test_prompt_rejects_unknown_segment() {
+    let dir = TempDir::new().unwrap();
+    envsense(&dir)
+        .args(["prompt", "--segments", "shell"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("unknown segment 'shell'"));
+}
+
+#[test]
+fn test_prompt_tmux_style_with_width_budget() {
+    let dir = TempDir::new().unwrap();
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("CURSOR_AGENT", "1")
+        .args(["prompt", "--style", "tmux"])
+        .assert()
+        .success()
+        .stdout("#[fg=yellow]⚙ ci:github#[default] #[fg=green]🤖 cursor#[default]\n");
+
+    // "⚙ ci:github" alone is 11 characters; the agent badge no longer fits
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("CURSOR_AGENT", "1")
+        .args(["prompt", "--style", "tmux", "--max-width", "15"])
+        .assert()
+        .success()
+        .stdout("#[fg=yellow]⚙ ci:github#[default]\n");
+}
+
+#[test]
+fn test_prompt_glyphs_from_config() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("envsense")).unwrap();
+    fs::write(
+        dir.path().join("envsense/config.toml"),
+        "[prompt.glyphs]\nci = \"CI\"\nagent = \"\"\n",
+    )
+    .unwrap();
+
+    envsense(&dir)
+        .env("GITHUB_ACTIONS", "true")
+        .env("CURSOR_AGENT", "1")
+        .arg("prompt")
+        .assert()
+        .success()
+        .stdout("CI ci:github cursor\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_prompt_reuses_cached_line() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let count = dir.path().join("count");
+    let plugin = dir.path().join("envsense-detector-counter");
+    fs::write(
+        &plugin,
+        format!(
+            "#!/bin/sh\necho x >> '{}'\nprintf '{{}}'\n",
+            count.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+    let invocations = || fs::read_to_string(&count).map_or(0, |s| s.lines().count());
+    let path = format!("{}:/usr/bin:/bin", dir.path().display());
+
+    for pwd in ["/a", "/b"] {
+        envsense(&dir)
+            .env("PATH", &path)
+            .env("ENVSENSE_PLUGINS", "true")
+            .env("PWD", pwd)
+            .env("CURSOR_AGENT", "1")
+            .arg("prompt")
+            .assert()
+            .success()
+            .stdout("🤖 cursor\n");
+    }
+    assert_eq!(invocations(), 1, "a changed PWD should still hit the cache");
+
+    envsense(&dir)
+        .env("PATH", &path)
+        .env("ENVSENSE_PLUGINS", "true")
+        .env("CURSOR_AGENT", "1")
+        .args(["prompt", "--no-cache"])
+        .assert()
+        .success();
+    assert_eq!(invocations(), 2);
+
+    // A different environment is a different entry
+    envsense(&dir)
+        .env("PATH", &path)
+        .env("ENVSENSE_PLUGINS", "true")
+        .args(["prompt"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(invocations(), 3);
+}
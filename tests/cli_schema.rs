@@ -0,0 +1,68 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::Value;
+
+// Tests for `envsense schema` JSON Schema export
+
+fn schema(args: &[&str]) -> Value {
+    let output = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .arg("schema")
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    serde_json::from_slice(&output).unwrap()
+}
+
+#[test]
+fn test_schema_defaults_to_info_output() {
+    let schema = schema(&[]);
+    assert_eq!(schema["title"], "envsense info");
+    assert!(schema["properties"]["traits"].is_object());
+
+    // Every top-level key of `info --json` is described
+    let output = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .args(["--no-daemon", "info", "--json"])
+        .output()
+        .unwrap();
+    let info: Value = serde_json::from_slice(&output.stdout).unwrap();
+    for key in info.as_object().unwrap().keys() {
+        assert!(
+            schema["properties"].get(key).is_some(),
+            "{} is not in the schema",
+            key
+        );
+    }
+}
+
+#[test]
+fn test_schema_for_check_output() {
+    let schema = schema(&["check", "--version", "0.3.0"]);
+    assert_eq!(schema["title"], "envsense check");
+    assert!(schema["properties"]["checks"].is_object());
+}
+
+#[test]
+fn test_schema_rejects_unknown_version_and_document() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .args(["schema", "--version", "0.1.0"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "unsupported schema version '0.1.0' (available: 0.2.0, 0.3.0)",
+        ));
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .args(["schema", "detectors"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown document 'detectors'"));
+}
@@ -0,0 +1,73 @@
+use assert_cmd::Command;
+use serde_json::Value;
+
+// Tests for the `envsense serve --stdio` JSON-RPC server
+
+fn responses(input: &str, env: &[(&str, &str)]) -> Vec<Value> {
+    let output = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .envs(env.iter().copied())
+        .args(["serve", "--stdio"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_serve_answers_info_and_check() {
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"envsense/info"}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":2,"method":"envsense/check","params":{"predicates":["ci","ci.id=github_actions"]}}"#,
+        "\n",
+    );
+    let responses = responses(input, &[("GITHUB_ACTIONS", "true")]);
+
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0]["id"], 1);
+    assert!(
+        responses[0]["result"]["contexts"]
+            .as_array()
+            .unwrap()
+            .contains(&Value::from("ci"))
+    );
+    assert_eq!(responses[1]["id"], 2);
+    assert_eq!(responses[1]["result"]["overall"], true);
+    assert_eq!(responses[1]["result"]["mode"], "all");
+}
+
+#[test]
+fn test_serve_reports_errors_and_keeps_running() {
+    let input = concat!(
+        "garbage\n",
+        r#"{"jsonrpc":"2.0","id":"x","method":"envsense/unknown"}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":"y","method":"envsense/check","params":{"predicates":["agent"]}}"#,
+        "\n",
+    );
+    let responses = responses(input, &[]);
+
+    assert_eq!(responses[0]["error"]["code"], -32700);
+    assert_eq!(responses[1]["error"]["code"], -32601);
+    assert_eq!(responses[2]["result"]["overall"], false);
+}
+
+#[test]
+fn test_serve_requires_a_transport() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .arg("serve")
+        .assert()
+        .failure()
+        .code(2);
+}
@@ -0,0 +1,357 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::Value;
+use tempfile::TempDir;
+
+// Tests for `envsense capture` and `--snapshot` replay
+
+fn capture_to(path: &std::path::Path, envs: &[(&str, &str)]) {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear();
+    for (k, v) in envs {
+        cmd.env(k, v);
+    }
+    cmd.args(["capture", "--output"])
+        .arg(path)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_capture_scrubs_secrets() {
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .env("GITHUB_TOKEN", "ghp_supersecret")
+        .env("TERM_PROGRAM", "vscode")
+        .arg("capture")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["format_version"], 1);
+    assert_eq!(json["env"]["GITHUB_TOKEN"], "[REDACTED]");
+    assert_eq!(json["env"]["TERM_PROGRAM"], "vscode");
+    assert!(json["tty"]["stdin"].is_boolean());
+}
+
+#[test]
+fn test_info_replays_snapshot() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("snap.json");
+    capture_to(
+        &path,
+        &[
+            ("CURSOR_AGENT", "1"),
+            ("ENVSENSE_TTY_STDIN", "true"),
+            ("ENVSENSE_TTY_STDOUT", "true"),
+            ("ENVSENSE_TTY_STDERR", "true"),
+        ],
+    );
+
+    // Run with a clean environment: everything must come from the snapshot
+    let output = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .args(["info", "--json", "--snapshot"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["traits"]["agent"]["id"], "cursor");
+    assert_eq!(json["traits"]["terminal"]["interactive"], true);
+}
+
+#[test]
+fn test_check_replays_snapshot() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("snap.json");
+    capture_to(&path, &[("GITHUB_ACTIONS", "true")]);
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .args(["check", "ci.id=github_actions", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .success();
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["check", "agent", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_ascii_escapes_non_ascii_values() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("result.json");
+    std::fs::write(
+        &path,
+        r#"{"version":"0.3.0","contexts":["ci"],"traits":{"ci":{"branch":"feat/🚀"}}}"#,
+    )
+    .unwrap();
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .args(["--ascii", "info", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("branch: feat/\\u{1f680}"))
+        .stdout(predicate::function(|out: &str| out.is_ascii()));
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .args(["check", "--ascii", "ci.branch", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("feat/\\u{1f680}\n");
+}
+
+#[test]
+fn test_missing_snapshot_file_errors() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .args(["info", "--snapshot", "/nonexistent/snap.json"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("failed to read snapshot"));
+}
+
+#[test]
+fn test_snapshot_accepts_legacy_info_output() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("legacy.json");
+    std::fs::write(
+        &path,
+        r#"{
+  "contexts": {"agent": false, "ide": true, "ci": true, "container": false, "remote": false},
+  "facets": {"ide_id": "vscode", "ci_id": "github_actions", "ci": {"is_ci": true, "vendor": "github_actions", "name": "GitHub Actions", "pr": false, "branch": "main"}},
+  "traits": {"is_interactive": true, "is_tty_stdin": true, "is_tty_stdout": true, "is_tty_stderr": true, "is_piped_stdin": false, "is_piped_stdout": false, "color_level": "ansi256", "supports_hyperlinks": false},
+  "evidence": [],
+  "version": "0.2.0"
+}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .args(["info", "--json", "--snapshot"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["version"], "0.3.0");
+    assert_eq!(json["contexts"], serde_json::json!(["ide", "ci"]));
+    assert_eq!(json["traits"]["ci"]["branch"], "main");
+    assert_eq!(json["traits"]["terminal"]["color_level"], "ansi256");
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args([
+            "check",
+            "ide.id=vscode",
+            "terminal.interactive",
+            "--snapshot",
+        ])
+        .arg(&path)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_snapshot_accepts_current_info_output() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("info.json");
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "info", "--json"])
+        .output()
+        .unwrap();
+    std::fs::write(&path, &output.stdout).unwrap();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["check", "agent.id=cursor", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("true"));
+}
+
+#[test]
+fn test_snapshot_accepts_partial_result_fixture() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("ci.json");
+    std::fs::write(
+        &path,
+        r#"{"contexts": ["ci"], "traits": {"ci": {"id": "github_actions", "is_pr": true}}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args([
+            "check",
+            "ci.id=github_actions",
+            "ci.is_pr",
+            "!agent",
+            "!terminal.interactive",
+            "--snapshot",
+        ])
+        .arg(&path)
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["check", "ide", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_strict_snapshot_rejects_unknown_fields() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("ci.json");
+    std::fs::write(
+        &path,
+        r#"{"contexts": ["ci"], "traits": {"ci": {"id": "github_actions", "is_pull_request": true}}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["check", "ci", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["--strict-snapshot", "check", "ci", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "unknown field `traits.ci.is_pull_request`",
+        ));
+}
+
+#[test]
+fn test_check_queries_extra_traits() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("extra.json");
+    std::fs::write(
+        &path,
+        r#"{"contexts": [], "traits": {"extra": {"my_key": "value", "sandbox": {"enabled": true, "level": 2}}}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args([
+            "check",
+            "--all",
+            "extra.my_key=value",
+            "extra.sandbox.enabled",
+            "extra.sandbox.level=2",
+            "!extra.missing",
+            "--snapshot",
+        ])
+        .arg(&path)
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["check", "extra.my_key=other", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .code(1);
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["check", "extra.my_key", "--snapshot"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("value"));
+}
+
+#[test]
+fn test_env_file_replaces_environment() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("ci.env");
+    std::fs::write(
+        &path,
+        "# CI container\nexport GITHUB_ACTIONS=true\nGITHUB_REPOSITORY='acme/app'\n",
+    )
+    .unwrap();
+
+    // Neither the process's variables nor the host's container files count
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args([
+            "check",
+            "ci.id=github_actions",
+            "!agent",
+            "!container",
+            "--env-file",
+        ])
+        .arg(&path)
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    let output = cmd
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .env("ENVSENSE_CONTAINER", "none")
+        .args(["info", "--json", "--merge-env", "--env-file"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["contexts"], serde_json::json!(["agent", "ci"]));
+}
+
+#[test]
+fn test_env_file_errors() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("bad.env");
+    std::fs::write(&path, "CI=true\nnot a variable\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["check", "ci", "--env-file"])
+        .arg(&path)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("line 2: expected KEY=value"));
+
+    let mut cmd = Command::cargo_bin("envsense").unwrap();
+    cmd.env_clear()
+        .args(["check", "ci", "--merge-env"])
+        .assert()
+        .code(2);
+}
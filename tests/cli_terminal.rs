@@ -1,4 +1,5 @@
 use assert_cmd::Command;
+use assert_cmd::prelude::*;
 
 #[test]
 fn info_reports_piped_stdout() {
@@ -13,3 +14,114 @@ fn info_reports_piped_stdout() {
         text.contains("stdout:") && text.contains("piped: true") && text.contains("tty: false")
     );
 }
+
+#[test]
+fn check_reports_terminal_program() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("TERM", "xterm-256color")
+        .env("TERM_PROGRAM", "WezTerm")
+        .env("TERM_PROGRAM_VERSION", "20240203-110809-5046fc22")
+        .args(["--no-daemon", "check", "terminal.program=WezTerm"])
+        .assert()
+        .success()
+        .stdout("true\n");
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("TERM", "xterm-256color")
+        .args(["--no-daemon", "check", "terminal.term"])
+        .assert()
+        .success()
+        .stdout("xterm-256color\n");
+}
+
+#[test]
+fn ci_logs_render_color_without_a_tty() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-daemon", "check", "terminal.color_level"])
+        .assert()
+        .success()
+        .stdout("ansi256\n");
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .env("NO_COLOR", "1")
+        .args(["--no-daemon", "check", "terminal.color_level"])
+        .assert()
+        .success()
+        .stdout("none\n");
+}
+
+#[test]
+fn check_reports_pending_stdin() {
+    let input = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(input.path(), "piped input\n").unwrap();
+    // Redirected from the file itself, so the data is there before envsense
+    // starts rather than written by a thread racing the check
+    std::process::Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .stdin(std::fs::File::open(input.path()).unwrap())
+        .args(["--no-daemon", "check", "terminal.stdin.has_data"])
+        .assert()
+        .success()
+        .stdout("true\n");
+
+    // Redirected from /dev/null: piped, but nothing to read
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .args(["--no-daemon", "check", "terminal.stdin.has_data"])
+        .assert()
+        .failure()
+        .stdout("false\n");
+}
+
+#[test]
+fn check_reports_windows_terminal() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("WT_SESSION", "3e5f2a4c-1d2b-4c8e-9f3a-7b6d5e4c3b2a")
+        .env("ENVSENSE_TTY_STDIN", "true")
+        .env("ENVSENSE_TTY_STDOUT", "true")
+        .env("ENVSENSE_TTY_STDERR", "true")
+        .args([
+            "--no-daemon",
+            "check",
+            "--all",
+            "terminal.emulator=windows-terminal",
+            "terminal.supports_hyperlinks",
+        ])
+        .assert()
+        .success();
+}
+
+#[test]
+fn check_reports_iterm2_inside_tmux() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("TERM_PROGRAM", "tmux")
+        .env("TERM_PROGRAM_VERSION", "3.4")
+        .env("ITERM_SESSION_ID", "w0t0p0:6A1B1C1D")
+        .env("LC_TERMINAL", "iTerm2")
+        .env("LC_TERMINAL_VERSION", "3.5.2")
+        .args([
+            "--no-daemon",
+            "check",
+            "--all",
+            "terminal.emulator=iterm2",
+            "terminal.emulator_version=3.5.2",
+        ])
+        .assert()
+        .success();
+}
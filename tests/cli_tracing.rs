@@ -0,0 +1,19 @@
+#![cfg(feature = "tracing")]
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+#[test]
+fn test_debug_prints_tracing_spans() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .env("ENVSENSE_CONTAINER", "none")
+        .args(["--no-daemon", "--debug", "info", "--json"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(r#"detector{name="agent"}"#))
+        .stderr(predicate::str::contains("mapping.selected id=cursor"))
+        .stderr(predicate::str::contains("merge{detections=6}"));
+}
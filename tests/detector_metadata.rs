@@ -0,0 +1,193 @@
+use assert_cmd::Command;
+use envsense::check::FieldRegistry;
+use envsense::detectors::EnvSnapshot;
+use envsense::detectors::env_mapping::{get_agent_mappings, get_ci_mappings, get_ide_mappings};
+use envsense::engine::DetectionEngine;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// Tests for detector metadata and `envsense detectors`
+
+#[test]
+fn test_default_detector_metadata_matches_field_registry() {
+    let engine = DetectionEngine::with_default_detectors();
+    let registry = FieldRegistry::new();
+
+    let names: Vec<_> = engine.detectors().iter().map(|(name, _)| *name).collect();
+    assert_eq!(
+        names,
+        ["terminal", "shell", "container", "agent", "ci", "ide"]
+    );
+
+    for (name, metadata) in engine.detectors() {
+        assert!(
+            !metadata.description.is_empty(),
+            "{} lacks a description",
+            name
+        );
+        for context in &metadata.contexts {
+            assert!(
+                registry.has_context(context),
+                "{}: unknown context {}",
+                name,
+                context
+            );
+        }
+        for path in &metadata.traits {
+            assert!(registry.has_field(path), "{}: unknown trait {}", name, path);
+        }
+    }
+}
+
+#[test]
+fn test_contexts_match_what_detectors_add() {
+    let engine = DetectionEngine::with_default_detectors();
+    let declared: Vec<&str> = engine
+        .detectors()
+        .into_iter()
+        .flat_map(|(_, metadata)| metadata.contexts)
+        .collect();
+
+    // Every context predicates accept is one some detector adds
+    for context in FieldRegistry::new().get_contexts() {
+        assert!(
+            declared.contains(&context),
+            "no detector adds context {}",
+            context
+        );
+    }
+
+    // And every context a built-in mapping adds is declared by its detector
+    let contexts_of = |name: &str| {
+        engine
+            .detectors()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .unwrap()
+            .1
+            .contexts
+    };
+    for (detector, mappings) in [
+        ("agent", get_agent_mappings()),
+        ("ide", get_ide_mappings()),
+        ("ci", get_ci_mappings()),
+    ] {
+        for mapping in mappings {
+            for context in &mapping.contexts {
+                assert!(
+                    contexts_of(detector).contains(&context.as_str()),
+                    "{}: mapping {} adds undeclared context {}",
+                    detector,
+                    mapping.id,
+                    context
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_run_reports_contexts_added() {
+    let mut env = HashMap::new();
+    env.insert("GITHUB_ACTIONS".to_string(), "true".to_string());
+    let snap = EnvSnapshot::with_mock_tty(env, false, false, false);
+
+    let report = DetectionEngine::with_default_detectors().run(&snap);
+    assert!(report.runs.iter().all(|r| r.ran));
+
+    let ci = report.runs.iter().find(|r| r.name == "ci").unwrap();
+    assert_eq!(ci.contexts_added, vec!["ci"]);
+    let agent = report.runs.iter().find(|r| r.name == "agent").unwrap();
+    assert!(agent.contexts_added.is_empty());
+    assert!(report.env.contexts.contains(&"ci".to_string()));
+}
+
+#[test]
+fn test_detectors_command_json() {
+    let output = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["detectors", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let agent = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["name"] == "agent")
+        .unwrap();
+    assert_eq!(agent["ran"], true);
+    assert_eq!(agent["priority"], 0);
+    assert_eq!(agent["contexts"][0], "agent");
+    assert_eq!(agent["contexts_added"][0], "agent");
+}
+
+#[test]
+fn test_selection_skips_detectors() {
+    use envsense::engine::DetectorSelection;
+
+    let mut env = HashMap::new();
+    env.insert("GITHUB_ACTIONS".to_string(), "true".to_string());
+    env.insert("CURSOR_AGENT".to_string(), "1".to_string());
+    let snap = EnvSnapshot::with_mock_tty(env, false, false, false);
+
+    let engine = DetectionEngine::with_default_detectors()
+        .with_selection(DetectorSelection {
+            only: Some(vec!["terminal".to_string(), "ci".to_string()]),
+            disabled: vec!["ci".to_string()],
+        })
+        .unwrap();
+    let report = engine.run(&snap);
+
+    let ran: Vec<_> = report
+        .runs
+        .iter()
+        .filter(|r| r.ran)
+        .map(|r| r.name)
+        .collect();
+    assert_eq!(ran, ["terminal"]);
+    assert!(report.env.contexts.is_empty());
+}
+
+#[test]
+fn test_selection_rejects_unknown_detectors() {
+    use envsense::engine::DetectorSelection;
+
+    let err = DetectionEngine::with_default_detectors()
+        .with_selection(DetectorSelection {
+            only: None,
+            disabled: vec!["bogus".to_string()],
+        })
+        .err()
+        .unwrap();
+    assert!(err.contains("unknown detector(s): bogus"));
+}
+
+#[test]
+fn test_disable_detector_flag() {
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["check", "ci", "--disable-detector", "ci"])
+        .assert()
+        .code(1);
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--only", "terminal,ide", "check", "agent"])
+        .assert()
+        .code(1);
+
+    Command::cargo_bin("envsense")
+        .unwrap()
+        .args(["info", "--only", "nope"])
+        .assert()
+        .code(2);
+}
@@ -0,0 +1,167 @@
+use assert_cmd::Command;
+use envsense::detectors::{Detection, Detector, EnvSnapshot};
+use envsense::engine::DetectionEngine;
+use envsense::schema::{EnvSense, Signal};
+use serde_json::json;
+use std::collections::HashMap;
+
+// Every HashMap gets its own random seed, so detecting a fresh snapshot
+// several times exposes any output that follows HashMap iteration order.
+const RUNS: usize = 10;
+
+fn snapshot(vars: &[(&str, &str)]) -> EnvSnapshot {
+    let env_vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    EnvSnapshot::with_mock_tty(env_vars, false, false, false)
+}
+
+fn serialize_runs(detect: impl Fn() -> EnvSense) -> Vec<String> {
+    (0..RUNS)
+        .map(|_| serde_json::to_string(&detect()).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_prefix_evidence_is_sorted_by_variable() {
+    let vars = [
+        ("AIDER_MODEL", "gpt-4"),
+        ("AIDER_AUTO_COMMITS", "false"),
+        ("AIDER_DARK_MODE", "true"),
+        ("AIDER_CHAT_HISTORY_FILE", ".aider.chat"),
+        ("GITHUB_ACTIONS", "true"),
+    ];
+    let runs = serialize_runs(|| EnvSense::detect_from(&snapshot(&vars)));
+    assert!(runs.iter().all(|run| run == &runs[0]));
+
+    let env = EnvSense::detect_from(&snapshot(&vars));
+    let aider: Vec<&str> = env
+        .evidence
+        .iter()
+        .filter(|e| e.key.starts_with("AIDER_"))
+        .map(|e| e.key.as_str())
+        .collect();
+    assert_eq!(
+        aider,
+        [
+            "AIDER_AUTO_COMMITS",
+            "AIDER_CHAT_HISTORY_FILE",
+            "AIDER_DARK_MODE",
+            "AIDER_MODEL",
+        ]
+    );
+}
+
+/// Patches several trait groups at once, each with its own values
+struct Patcher {
+    name: &'static str,
+    label: &'static str,
+    context: &'static str,
+}
+
+impl Detector for Patcher {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn detect(&self, _snap: &EnvSnapshot) -> Detection {
+        Detection {
+            contexts_add: vec![self.context.to_string()],
+            traits_patch: HashMap::from([
+                ("agent".to_string(), json!({"id": self.label})),
+                (
+                    "ci".to_string(),
+                    json!({"id": self.label, "branch": self.label}),
+                ),
+                ("ide".to_string(), json!({"id": self.label})),
+            ]),
+            confidence: 0.9,
+            ..Detection::default()
+        }
+    }
+}
+
+#[test]
+fn test_conflicts_and_contexts_have_a_fixed_order() {
+    let engine = || {
+        DetectionEngine::new()
+            .register(Patcher {
+                name: "first",
+                label: "one",
+                context: "ide",
+            })
+            .register(Patcher {
+                name: "second",
+                label: "two",
+                context: "agent",
+            })
+    };
+    let runs = serialize_runs(|| engine().detect_from_snapshot(&snapshot(&[])));
+    assert!(runs.iter().all(|run| run == &runs[0]));
+
+    let env = engine().detect_from_snapshot(&snapshot(&[]));
+    assert_eq!(env.contexts, ["agent", "ide"]);
+    let conflicts: Vec<&str> = env
+        .evidence
+        .iter()
+        .filter(|e| e.signal == Signal::Conflict)
+        .map(|e| e.key.as_str())
+        .collect();
+    assert_eq!(conflicts, ["agent.id", "ci.branch", "ci.id", "ide.id"]);
+}
+
+#[test]
+fn test_every_output_format_is_stable() {
+    for format in ["human", "json", "raw", "markdown", "html", "csv", "tsv"] {
+        let output = || {
+            let assert = Command::cargo_bin("envsense")
+                .unwrap()
+                .env_clear()
+                .env("GITHUB_ACTIONS", "true")
+                .env("GITHUB_REF", "refs/heads/main")
+                .env("AIDER_MODEL", "gpt-4")
+                .env("AIDER_DARK_MODE", "true")
+                .env("TERM_PROGRAM", "vscode")
+                .args(["--no-daemon", "info", "--format", format])
+                .assert()
+                .success();
+            assert.get_output().stdout.clone()
+        };
+        let first = output();
+        for _ in 0..3 {
+            assert_eq!(output(), first, "{} output changed between runs", format);
+        }
+    }
+}
+
+#[test]
+fn test_check_json_keys_are_sorted() {
+    let assert = Command::cargo_bin("envsense")
+        .unwrap()
+        .env_clear()
+        .env("CURSOR_AGENT", "1")
+        .args(["--no-daemon", "check", "--json", "--explain", "agent"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+
+    let keys: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix('"'))
+        .filter_map(|line| line.split_once("\":").map(|(key, _)| key))
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            "checks",
+            "predicate",
+            "reason",
+            "result",
+            "signals",
+            "CURSOR_AGENT=1",
+            "mode",
+            "overall",
+        ]
+    );
+}
@@ -14,6 +14,8 @@ fn parse_json(bytes: &[u8]) -> Value {
 fn run_info_json(envs: &[(&str, &str)]) -> Value {
     let mut cmd = Command::cargo_bin("envsense").unwrap();
     cmd.env_clear();
+    // Keep the host's container out of the snapshots
+    cmd.env("ENVSENSE_CONTAINER", "none");
     cmd.args(["info", "--json"]);
     for (k, v) in envs {
         cmd.env(k, v);
@@ -33,6 +35,8 @@ fn run_info_json_tty(envs: &[(&str, &str)]) -> Value {
         .arg("info")
         .arg("--json");
     cmd.env_clear();
+    // Keep the host's container out of the snapshots
+    cmd.env("ENVSENSE_CONTAINER", "none");
     for (k, v) in envs {
         cmd.env(k, v);
     }
@@ -49,6 +53,8 @@ fn run_info_json_tty(envs: &[(&str, &str)]) -> Value {
         .arg(format!("{} info --json", bin.display()))
         .arg("/dev/null");
     cmd.env_clear();
+    // Keep the host's container out of the snapshots
+    cmd.env("ENVSENSE_CONTAINER", "none");
     for (k, v) in envs {
         cmd.env(k, v);
     }
@@ -303,10 +309,10 @@ fn snapshot_nested_structure_comprehensive() {
     let has_nested_evidence = evidence_array.iter().any(|e| {
         e.get("supports")
             .and_then(|s| s.as_array())
-            .map_or(false, |supports| {
+            .is_some_and(|supports| {
                 supports
                     .iter()
-                    .any(|support| support.as_str().map_or(false, |s| s.contains(".")))
+                    .any(|support| support.as_str().is_some_and(|s| s.contains(".")))
             })
     });
     assert!(
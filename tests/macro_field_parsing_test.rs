@@ -1,6 +1,6 @@
 //! Test for macro field parsing and annotation handling
 
-use envsense::schema::EnvSense;
+use envsense::schema::{EnvSense, Evidence};
 use envsense_macros::{Detection, DetectionMerger};
 
 #[test]
@@ -34,8 +34,8 @@ fn test_evidence_merging_works() {
             traits_patch: std::collections::HashMap::new(),
             facets_patch: std::collections::HashMap::new(),
             evidence: vec![
-                serde_json::Value::String("evidence1".to_string()),
-                serde_json::Value::String("evidence2".to_string()),
+                Evidence::env_var("EVIDENCE_ONE", "1"),
+                Evidence::env_var("EVIDENCE_TWO", "2"),
             ],
             confidence: 1.0,
         },
@@ -43,15 +43,171 @@ fn test_evidence_merging_works() {
             contexts_add: vec![],
             traits_patch: std::collections::HashMap::new(),
             facets_patch: std::collections::HashMap::new(),
-            evidence: vec![serde_json::Value::String("evidence3".to_string())],
+            evidence: vec![Evidence::env_var("EVIDENCE_THREE", "3")],
             confidence: 0.8,
         },
     ];
 
     envsense.merge_detections(&detections);
 
-    // Evidence merging should work since we implemented it
-    // Note: The macro converts serde_json::Value back to Evidence, so we can't easily test the content
-    // but we can verify that the merging process completed without errors
-    // Evidence length is always >= 0, so this assertion is always true
+    // Typed evidence is merged without a JSON round-trip
+    let keys: Vec<&str> = envsense.evidence.iter().map(|e| e.key.as_str()).collect();
+    assert_eq!(keys, ["EVIDENCE_ONE", "EVIDENCE_TWO", "EVIDENCE_THREE"]);
+}
+
+#[derive(envsense_macros::DetectionMergerDerive, Default, Debug)]
+#[detection_merge(schema = "envsense::traits::NestedTraits")]
+struct AnnotatedStruct {
+    #[detection_merge(key = "contexts")]
+    pub active: Vec<String>,
+    #[detection_merge(key = "ci.branch")]
+    pub branch: Option<String>,
+    #[detection_merge(key = "host")]
+    pub host: Option<String>,
+    #[detection_merge(key = "ci.is_pr")]
+    pub is_pr: bool,
+    #[detection_merge(ignore)]
+    pub contexts: Vec<String>,
+    #[detection_merge(flatten)]
+    pub inner: InnerStruct,
+}
+
+#[derive(envsense_macros::DetectionMergerDerive, Default, Debug)]
+struct InnerStruct {
+    pub contexts: Vec<String>,
+}
+
+#[test]
+fn test_detection_merge_attribute_options() {
+    let mut annotated = AnnotatedStruct::default();
+    let detections = vec![Detection {
+        contexts_add: vec!["ci".to_string()],
+        traits_patch: std::collections::HashMap::from([(
+            "ci".to_string(),
+            serde_json::json!({"branch": "main", "is_pr": true}),
+        )]),
+        facets_patch: std::collections::HashMap::from([(
+            "host".to_string(),
+            serde_json::json!("github"),
+        )]),
+        evidence: vec![],
+        confidence: 1.0,
+    }];
+
+    annotated.merge_detections(&detections);
+
+    assert_eq!(annotated.active, ["ci"]);
+    assert_eq!(annotated.branch.as_deref(), Some("main"));
+    assert_eq!(annotated.host.as_deref(), Some("github"));
+    assert!(annotated.is_pr);
+    assert!(annotated.contexts.is_empty());
+    assert_eq!(annotated.inner.contexts, ["ci"]);
+}
+
+#[derive(envsense_macros::DetectionMergerDerive, Default, Debug)]
+#[detection_merge(schema = "envsense::traits::NestedTraits")]
+struct StrategyStruct {
+    #[detection_merge(key = "agent.id", strategy = "first_wins")]
+    pub first: Option<String>,
+    #[detection_merge(key = "agent.id", strategy = "last_wins")]
+    pub last: Option<String>,
+    #[detection_merge(key = "agent.id", strategy = "highest_confidence")]
+    pub confident: Option<String>,
+    #[detection_merge(key = "agent.id", strategy = "append")]
+    pub all: Vec<String>,
+}
+
+#[test]
+fn test_detection_merge_strategies() {
+    let agent = |id: &str, confidence: f32| Detection {
+        contexts_add: vec![],
+        traits_patch: std::collections::HashMap::from([(
+            "agent".to_string(),
+            serde_json::json!({"id": id}),
+        )]),
+        facets_patch: std::collections::HashMap::new(),
+        evidence: vec![],
+        confidence,
+    };
+    let mut merged = StrategyStruct::default();
+    merged.merge_detections(&[
+        agent("cursor", 0.6),
+        agent("aider", 0.9),
+        agent("replit", 0.9),
+        agent("goose", 0.4),
+    ]);
+
+    assert_eq!(merged.first.as_deref(), Some("cursor"));
+    assert_eq!(merged.last.as_deref(), Some("goose"));
+    assert_eq!(merged.confident.as_deref(), Some("replit"));
+    assert_eq!(merged.all, ["cursor", "aider", "replit", "goose"]);
+}
+
+/// A newtype flattens into the struct it wraps
+#[derive(envsense_macros::DetectionMergerDerive, Default, Debug)]
+struct Merged(EnvSense);
+
+#[derive(envsense_macros::DetectionMergerDerive, Default, Debug)]
+struct Branch(
+    #[detection_merge(key = "ci.branch")] Option<String>,
+    #[detection_merge(ignore)] bool,
+);
+
+#[derive(envsense_macros::DetectionMergerDerive, Debug, PartialEq)]
+enum Runtime {
+    #[detection_merge(context = "ci")]
+    Ci {
+        #[detection_merge(key = "ci.branch")]
+        branch: Option<String>,
+        #[detection_merge(key = "contexts")]
+        contexts: Vec<String>,
+    },
+    #[detection_merge(context = "agent")]
+    Agent(#[detection_merge(key = "agent.id")] Option<String>),
+    #[detection_merge(fallback)]
+    Local,
+}
+
+fn detected<E>(context: &str, traits: serde_json::Value, evidence: Vec<E>) -> Detection<E> {
+    Detection {
+        contexts_add: vec![context.to_string()],
+        traits_patch: std::collections::HashMap::from([(context.to_string(), traits)]),
+        facets_patch: std::collections::HashMap::new(),
+        evidence,
+        confidence: 1.0,
+    }
+}
+
+#[test]
+fn test_tuple_structs_and_enums() {
+    let ci = || detected("ci", serde_json::json!({"branch": "main"}), vec![]);
+    let agent = || detected("agent", serde_json::json!({"id": "cursor"}), vec![]);
+
+    let mut merged = Merged::default();
+    merged.merge_detections(&[detected(
+        "ci",
+        serde_json::json!({}),
+        vec![Evidence::env_var("CI", "true")],
+    )]);
+    assert_eq!(merged.0.contexts, ["ci"]);
+    assert_eq!(merged.0.evidence.len(), 1);
+
+    let mut branch = Branch::default();
+    branch.merge_detections(&[ci()]);
+    assert_eq!(branch.0.as_deref(), Some("main"));
+    assert!(!branch.1);
+
+    let mut runtime = Runtime::Local;
+    runtime.merge_detections(&[agent()]);
+    assert_eq!(runtime, Runtime::Agent(Some("cursor".to_string())));
+    runtime.merge_detections(&[agent(), ci()]);
+    assert_eq!(
+        runtime,
+        Runtime::Ci {
+            branch: Some("main".to_string()),
+            contexts: vec!["agent".to_string(), "ci".to_string()],
+        }
+    );
+    runtime.merge_detections(&[]);
+    assert_eq!(runtime, Runtime::Local);
 }
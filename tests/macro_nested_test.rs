@@ -27,6 +27,28 @@ pub struct TestLegacyStruct {
     pub evidence: Vec<serde_json::Value>,
 }
 
+/// A trait group the macro knows nothing about
+#[derive(envsense_macros::MergeValueDerive, Default, Debug, PartialEq, Clone)]
+pub struct ShellTraits {
+    pub id: Option<String>,
+    #[detection_merge(key = "login")]
+    pub is_login: bool,
+}
+
+#[derive(envsense_macros::MergeValueDerive, Default, Debug, PartialEq, Clone)]
+#[detection_merge(alias(shell_id = "shell.id"))]
+pub struct ExtendedTraits {
+    pub shell: ShellTraits,
+    pub terminal: TerminalTraits,
+    #[detection_merge(extensions)]
+    pub extra: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(DetectionMergerDerive, Default, Debug, PartialEq, Clone)]
+pub struct TestExtendedStruct {
+    pub traits: ExtendedTraits,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,14 +116,14 @@ mod tests {
 
         test_struct.merge_detections(&detections);
 
-        assert_eq!(test_struct.traits.terminal.interactive, true);
-        assert_eq!(test_struct.traits.terminal.stdin.tty, true);
-        assert_eq!(test_struct.traits.terminal.stdout.piped, false);
+        assert!(test_struct.traits.terminal.interactive);
+        assert!(test_struct.traits.terminal.stdin.tty);
+        assert!(!test_struct.traits.terminal.stdout.piped);
         assert_eq!(
             test_struct.traits.terminal.color_level,
             ColorLevel::Truecolor
         );
-        assert_eq!(test_struct.traits.terminal.supports_hyperlinks, true);
+        assert!(test_struct.traits.terminal.supports_hyperlinks);
     }
 
     #[test]
@@ -158,7 +180,7 @@ mod tests {
 
         assert_eq!(test_struct.traits.agent.id, Some("cursor".to_string()));
         assert_eq!(test_struct.traits.ide.id, Some("cursor".to_string()));
-        assert_eq!(test_struct.traits.terminal.interactive, true);
+        assert!(test_struct.traits.terminal.interactive);
         assert_eq!(test_struct.traits.ci.id, Some("github".to_string()));
         assert_eq!(test_struct.contexts, vec!["agent", "ide", "ci"]);
     }
@@ -185,11 +207,11 @@ mod tests {
 
         test_struct.merge_detections(&detections);
 
-        assert_eq!(test_struct.traits.terminal.interactive, true);
-        assert_eq!(test_struct.traits.terminal.stdin.tty, true);
-        assert_eq!(test_struct.traits.terminal.stdout.tty, false);
+        assert!(test_struct.traits.terminal.interactive);
+        assert!(test_struct.traits.terminal.stdin.tty);
+        assert!(!test_struct.traits.terminal.stdout.tty);
         assert_eq!(test_struct.traits.terminal.color_level, ColorLevel::Ansi256);
-        assert_eq!(test_struct.traits.terminal.supports_hyperlinks, false);
+        assert!(!test_struct.traits.terminal.supports_hyperlinks);
     }
 
     #[test]
@@ -212,7 +234,7 @@ mod tests {
         test_struct.merge_detections(&detections);
 
         // Nested key should take precedence (processed after flat key)
-        assert_eq!(test_struct.traits.terminal.interactive, true);
+        assert!(test_struct.traits.terminal.interactive);
     }
 
     #[test]
@@ -233,7 +255,7 @@ mod tests {
 
         test_struct.merge_detections(&detections);
 
-        assert_eq!(test_struct.traits.interactive, true);
+        assert!(test_struct.traits.interactive);
         assert_eq!(test_struct.traits.color_level, ColorLevel::Truecolor);
     }
 
@@ -336,7 +358,56 @@ mod tests {
 
         assert_eq!(test_struct.traits.agent.id, Some("cursor".to_string()));
         assert_eq!(test_struct.traits.ci.id, Some("github".to_string()));
-        assert_eq!(test_struct.traits.terminal.interactive, false);
+        assert!(!test_struct.traits.terminal.interactive);
         assert_eq!(test_struct.contexts, vec!["agent", "ci"]);
     }
+
+    #[test]
+    fn test_new_trait_group_merging() {
+        let mut test_struct = TestExtendedStruct::default();
+
+        let mut traits_patch = HashMap::new();
+        traits_patch.insert("shell".to_string(), json!({"login": true}));
+        traits_patch.insert("shell_id".to_string(), json!("zsh"));
+        traits_patch.insert("terminal.stdout.tty".to_string(), json!(true));
+
+        let detections = vec![Detection {
+            contexts_add: vec![],
+            traits_patch,
+            facets_patch: HashMap::new(),
+            evidence: vec![],
+            confidence: 1.0,
+        }];
+
+        test_struct.merge_detections(&detections);
+
+        assert_eq!(test_struct.traits.shell.id.as_deref(), Some("zsh"));
+        assert!(test_struct.traits.shell.is_login);
+        assert!(test_struct.traits.terminal.stdout.tty);
+    }
+
+    #[test]
+    fn test_unknown_traits_merge_into_extensions() {
+        let mut test_struct = TestExtendedStruct::default();
+
+        let mut traits_patch = HashMap::new();
+        traits_patch.insert("sandbox".to_string(), json!({"id": "firejail"}));
+        traits_patch.insert("sandbox.profile".to_string(), json!("strict"));
+        traits_patch.insert("extra".to_string(), json!({"vendor": "acme"}));
+        traits_patch.insert("shell".to_string(), json!({"id": "fish"}));
+
+        test_struct.merge_detections(&[Detection {
+            contexts_add: vec![],
+            traits_patch,
+            facets_patch: HashMap::new(),
+            evidence: vec![],
+            confidence: 1.0,
+        }]);
+
+        assert_eq!(test_struct.traits.shell.id.as_deref(), Some("fish"));
+        assert_eq!(
+            serde_json::to_value(&test_struct.traits.extra).unwrap(),
+            json!({"sandbox": {"id": "firejail", "profile": "strict"}, "vendor": "acme"})
+        );
+    }
 }
@@ -8,7 +8,7 @@ use std::time::Instant;
 #[test]
 fn benchmark_macro_merging_performance() {
     // Create a large number of detections to test performance
-    let detections: Vec<Detection> = (0..1000)
+    let detections: Vec<Detection<Evidence>> = (0..1000)
         .map(|i| Detection {
             contexts_add: vec![
                 if i % 2 == 0 {
@@ -46,16 +46,14 @@ fn benchmark_macro_merging_performance() {
                     serde_json::Value::String(format!("ide-{}", i)),
                 ),
             ]),
-            evidence: vec![
-                serde_json::to_value(Evidence {
-                    signal: Signal::Env,
-                    key: format!("test-key-{}", i),
-                    value: Some(format!("test-value-{}", i)),
-                    supports: vec![],
-                    confidence: 0.8,
-                })
-                .unwrap(),
-            ],
+            evidence: vec![Evidence {
+                signal: Signal::Env,
+                key: format!("test-key-{}", i),
+                value: Some(format!("test-value-{}", i)),
+                supports: vec![],
+                confidence: 0.8,
+                sources: Vec::new(),
+            }],
             confidence: 0.8 + (i as f32 * 0.001),
         })
         .collect();
@@ -120,16 +118,14 @@ fn benchmark_macro_vs_manual_approach() {
                 serde_json::Value::String("cursor".to_string()),
             ),
         ]),
-        evidence: vec![
-            serde_json::to_value(Evidence {
-                signal: Signal::Env,
-                key: "test-key".to_string(),
-                value: Some("test-value".to_string()),
-                supports: vec![],
-                confidence: 1.0,
-            })
-            .unwrap(),
-        ],
+        evidence: vec![Evidence {
+            signal: Signal::Env,
+            key: "test-key".to_string(),
+            value: Some("test-value".to_string()),
+            supports: vec![],
+            confidence: 1.0,
+            sources: Vec::new(),
+        }],
         confidence: 1.0,
     }];
 
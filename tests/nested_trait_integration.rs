@@ -10,7 +10,7 @@ use envsense::detectors::{
     DeclarativeAgentDetector, DeclarativeCiDetector, DeclarativeIdeDetector, EnvSnapshot,
 };
 use envsense::engine::DetectionEngine;
-use envsense::schema::EnvSense;
+use envsense::schema::{EnvSense, Evidence, Signal};
 use envsense_macros::{Detection, DetectionMerger};
 use serde_json::{Value, json};
 use std::collections::HashMap;
@@ -41,6 +41,11 @@ fn is_valid_terminal_path(path: &str) -> bool {
             | "terminal.stderr.tty"
             | "terminal.stderr.piped"
             | "terminal.supports_hyperlinks"
+            | "terminal.program"
+            | "terminal.program_version"
+            | "terminal.term"
+            | "terminal.emulator"
+            | "terminal.emulator_version"
     )
 }
 
@@ -291,7 +296,7 @@ fn test_complete_nested_pipeline() {
     // 3. Verify CLI-relevant data is accessible
     // This simulates what the CLI field registry would access
     assert!(result.traits.agent.id.is_some() || result.traits.agent.id.is_none()); // Field exists
-    assert!(result.traits.terminal.interactive || !result.traits.terminal.interactive); // Field exists
+    let _ = result.traits.terminal.interactive; // Field exists
 
     // Verify specific expected detections from comprehensive environment
     assert!(result.contexts.contains(&"agent".to_string()));
@@ -328,9 +333,13 @@ fn test_nested_object_merging_with_conflicts() {
     let mut result = EnvSense::default();
     result.merge_detections(&detections);
 
-    // The macro should handle this gracefully (last one wins in current implementation)
-    // This documents current behavior - could be enhanced with confidence-based merging
-    assert!(result.traits.agent.id.is_some());
+    // The more confident detection wins; the rejected value is kept as evidence
+    assert_eq!(result.traits.agent.id.as_deref(), Some("cursor"));
+    assert_eq!(result.evidence.len(), 1);
+    assert_eq!(result.evidence[0].signal, Signal::Conflict);
+    assert_eq!(result.evidence[0].key, "agent.id");
+    assert_eq!(result.evidence[0].value.as_deref(), Some("other-agent"));
+    assert_eq!(result.evidence[0].confidence, 0.8);
 }
 
 #[test]
@@ -385,7 +394,7 @@ fn test_backward_compatibility_during_transition() {
 #[test]
 fn test_nested_merging_performance() {
     // Test that nested object merging doesn't significantly impact performance
-    let detections: Vec<Detection> = (0..100)
+    let detections: Vec<Detection<Evidence>> = (0..100)
         .map(|i| Detection {
             contexts_add: vec![],
             traits_patch: HashMap::from([